@@ -0,0 +1,399 @@
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use crate::{
+    custom_types::IpNetwork,
+    models::{
+        device::{Device, MTU},
+        ethernet::Ethernet,
+        network::Network,
+        route::Route,
+    },
+};
+
+/// Prefix shared by all the environment variables used to seed the initial configuration.
+pub const ENVIRONMENT_PREFIX: &str = "OPENSIGHT_NET_";
+
+/// How the addresses of a seeded interface are obtained.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressingMode {
+    Dhcp,
+    Static,
+}
+
+/// Configuration of a single interface, as seeded from the environment.
+#[derive(Debug, Default)]
+pub struct InterfaceSpec {
+    pub mode: Option<AddressingMode>,
+    pub addresses: Vec<IpNetwork>,
+    pub gateway: Option<IpAddr>,
+    pub dns: Vec<IpAddr>,
+    pub search: Vec<String>,
+    pub mtu: Option<MTU>,
+}
+
+/// Initial network configuration seeded from `OPENSIGHT_NET_*` environment variables.
+///
+/// The supported variables are:
+/// - `OPENSIGHT_NET_DNS` and `OPENSIGHT_NET_SEARCH`: comma separated nameserver addresses and
+///   search domains, used for every interface that doesn't define its own.
+/// - `OPENSIGHT_NET_<IFACE>_MODE`: either `dhcp` or `static`.
+/// - `OPENSIGHT_NET_<IFACE>_ADDRESS`: comma separated addresses in CIDR notation (`10.0.0.5/24`).
+/// - `OPENSIGHT_NET_<IFACE>_GATEWAY`: the default gateway of the interface.
+/// - `OPENSIGHT_NET_<IFACE>_DNS` and `OPENSIGHT_NET_<IFACE>_SEARCH`: per interface nameservers.
+/// - `OPENSIGHT_NET_<IFACE>_MTU`: the MTU of the interface.
+///
+/// `<IFACE>` is the interface name in upper case (`ETH0` configures `eth0`).
+#[derive(Debug, Default)]
+pub struct EnvironmentSpec {
+    pub dns: Vec<IpAddr>,
+    pub search: Vec<String>,
+    pub interfaces: BTreeMap<String, InterfaceSpec>,
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+}
+
+fn parse_ip_list(variable: &str, value: &str) -> Result<Vec<IpAddr>, String> {
+    split_list(value)
+        .map(|entry| {
+            entry
+                .parse::<IpAddr>()
+                .map_err(|err| format!("{variable}: invalid address '{entry}': {err}"))
+        })
+        .collect()
+}
+
+fn parse_cidr(variable: &str, value: &str) -> Result<IpNetwork, String> {
+    value.parse().map_err(|err| format!("{variable}: {err}"))
+}
+
+impl InterfaceSpec {
+    fn set(&mut self, variable: &str, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "MODE" => {
+                self.mode = Some(match value.trim().to_lowercase().as_str() {
+                    "dhcp" => AddressingMode::Dhcp,
+                    "static" => AddressingMode::Static,
+                    other => return Err(format!("{variable}: unknown mode '{other}'")),
+                })
+            }
+            "ADDRESS" => {
+                self.addresses = split_list(value)
+                    .map(|entry| parse_cidr(variable, entry))
+                    .collect::<Result<_, _>>()?
+            }
+            "GATEWAY" => {
+                self.gateway = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|err| format!("{variable}: invalid gateway: {err}"))?,
+                )
+            }
+            "DNS" => self.dns = parse_ip_list(variable, value)?,
+            "SEARCH" => self.search = split_list(value).map(str::to_string).collect(),
+            "MTU" => {
+                self.mtu = Some(
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .ok()
+                        .and_then(MTU::new)
+                        .ok_or_else(|| format!("{variable}: invalid MTU '{value}'"))?,
+                )
+            }
+            other => return Err(format!("{variable}: unknown setting '{other}'")),
+        }
+        Ok(())
+    }
+
+    fn validate(&self, name: &str) -> Result<(), String> {
+        let mode = self.mode.unwrap_or(if self.addresses.is_empty() {
+            AddressingMode::Dhcp
+        } else {
+            AddressingMode::Static
+        });
+        if mode == AddressingMode::Static && self.addresses.is_empty() {
+            return Err(format!(
+                "Interface {name} is configured as static, but has no addresses."
+            ));
+        }
+        if let Some(gateway) = self.gateway {
+            if mode == AddressingMode::Static
+                && !self
+                    .addresses
+                    .iter()
+                    .any(|address| address.ip().is_ipv4() == gateway.is_ipv4())
+            {
+                return Err(format!(
+                    "Interface {name} has gateway {gateway}, but no address of the same family."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_ethernet(&self, name: &str, dns: &[IpAddr], search: &[String]) -> Ethernet {
+        let mut ethernet = Ethernet::new(name.to_string());
+        ethernet.set_dhcp4(match self.mode {
+            Some(mode) => mode == AddressingMode::Dhcp,
+            None => self.addresses.is_empty(),
+        });
+        ethernet.set_mtu(self.mtu);
+        self.addresses
+            .iter()
+            .for_each(|address| ethernet.add_address(address));
+        if let Some(gateway) = self.gateway {
+            let default = if gateway.is_ipv4() {
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+            } else {
+                IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+            };
+            ethernet.add_route(&Route::new(default, Some(gateway), None));
+        }
+        // Interface specific nameservers take precedence over the global ones
+        let dns = if self.dns.is_empty() { dns } else { &self.dns };
+        let search = if self.search.is_empty() {
+            search
+        } else {
+            &self.search
+        };
+        dns.iter()
+            .for_each(|address| ethernet.add_nameservers_address(address));
+        search
+            .iter()
+            .for_each(|domain| ethernet.add_nameservers_search(domain));
+        ethernet
+    }
+}
+
+impl EnvironmentSpec {
+    /// Builds the spec from the given variables, ignoring those without the `OPENSIGHT_NET_` prefix.
+    ///
+    /// # Returns
+    /// - `Ok(None)` if none of the variables is meant for seeding.
+    /// - `Ok(Some(spec))` with the parsed and validated spec.
+    /// - `Err` with every problem found in the variables, one per line.
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Option<Self>, String> {
+        let mut spec = Self::default();
+        let mut seeded = false;
+        let mut errors = vec![];
+        for (variable, value) in vars {
+            let Some(setting) = variable.strip_prefix(ENVIRONMENT_PREFIX) else {
+                continue;
+            };
+            seeded = true;
+            let result = match setting {
+                "DNS" => parse_ip_list(&variable, &value).map(|dns| spec.dns = dns),
+                "SEARCH" => {
+                    spec.search = split_list(&value).map(str::to_string).collect();
+                    Ok(())
+                }
+                _ => match setting.rsplit_once('_') {
+                    Some((interface, key)) if !interface.is_empty() => spec
+                        .interfaces
+                        .entry(interface.to_lowercase())
+                        .or_default()
+                        .set(&variable, key, &value),
+                    _ => Err(format!("{variable}: missing interface name")),
+                },
+            };
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+        if !seeded {
+            return Ok(None);
+        }
+        for (name, interface) in spec.interfaces.iter() {
+            if let Err(err) = interface.validate(name) {
+                errors.push(err);
+            }
+        }
+        if spec.interfaces.is_empty() {
+            errors.push("No interface was configured through the environment.".to_string());
+        }
+        if errors.is_empty() {
+            Ok(Some(spec))
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Builds the spec from the process environment.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Builds the `Network` described by this spec.
+    pub fn to_network(&self) -> Network {
+        let mut network = Network::new();
+        for (name, interface) in self.interfaces.iter() {
+            network.add_ethernet(&interface.to_ethernet(name, &self.dns, &self.search));
+        }
+        network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(variable, value)| (variable.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_environment_grammar() {
+        // The seeded interfaces, or part of the error
+        type Case<'a> = (&'a [(&'a str, &'a str)], Result<&'a [&'a str], &'a str>);
+        let cases: &[Case] = &[
+            (&[("OPENSIGHT_NET_ETH0_MODE", "dhcp")], Ok(&["eth0"])),
+            (&[("OPENSIGHT_NET_ETH0_MODE", " DHCP ")], Ok(&["eth0"])),
+            (
+                &[("OPENSIGHT_NET_ETH0_ADDRESS", "10.0.0.5/24, fd00::5/64,")],
+                Ok(&["eth0"]),
+            ),
+            (
+                &[
+                    ("OPENSIGHT_NET_ETH0_MODE", "static"),
+                    ("OPENSIGHT_NET_ETH0_ADDRESS", "10.0.0.5/24"),
+                    ("OPENSIGHT_NET_ETH0_GATEWAY", "10.0.0.1"),
+                    ("OPENSIGHT_NET_ENP1S0_MTU", "9000"),
+                    ("OPENSIGHT_NET_DNS", "10.0.0.1,fd00::1"),
+                    ("OPENSIGHT_NET_SEARCH", "example.com"),
+                    ("PATH", "/usr/bin"),
+                ],
+                Ok(&["enp1s0", "eth0"]),
+            ),
+            // Underscores belong to the interface name, only the last one separates the setting
+            (&[("OPENSIGHT_NET_BR_LAN_MODE", "dhcp")], Ok(&["br_lan"])),
+            (
+                &[("OPENSIGHT_NET_ETH0_MODE", "bootp")],
+                Err("unknown mode 'bootp'"),
+            ),
+            (
+                &[("OPENSIGHT_NET_ETH0_SPEED", "1000")],
+                Err("unknown setting 'SPEED'"),
+            ),
+            (
+                &[("OPENSIGHT_NET__MODE", "dhcp")],
+                Err("missing interface name"),
+            ),
+            (
+                &[("OPENSIGHT_NET_MODE", "dhcp")],
+                Err("missing interface name"),
+            ),
+            (
+                &[("OPENSIGHT_NET_ETH0_ADDRESS", "10.0.0.5")],
+                Err("OPENSIGHT_NET_ETH0_ADDRESS"),
+            ),
+            (
+                &[("OPENSIGHT_NET_ETH0_GATEWAY", "gw")],
+                Err("invalid gateway"),
+            ),
+            (&[("OPENSIGHT_NET_ETH0_MTU", "12")], Err("invalid MTU '12'")),
+            (
+                &[("OPENSIGHT_NET_ETH0_DNS", "10.0.0.256")],
+                Err("invalid address '10.0.0.256'"),
+            ),
+            (
+                &[("OPENSIGHT_NET_ETH0_MODE", "static")],
+                Err("configured as static, but has no addresses"),
+            ),
+            (
+                &[
+                    ("OPENSIGHT_NET_ETH0_ADDRESS", "fd00::5/64"),
+                    ("OPENSIGHT_NET_ETH0_GATEWAY", "10.0.0.1"),
+                ],
+                Err("no address of the same family"),
+            ),
+            (
+                &[("OPENSIGHT_NET_DNS", "10.0.0.1")],
+                Err("No interface was configured"),
+            ),
+        ];
+        for (entries, expected) in cases {
+            let result = EnvironmentSpec::from_vars(vars(entries));
+            match (result, expected) {
+                (Ok(Some(spec)), Ok(interfaces)) => {
+                    let names: Vec<&str> = spec.interfaces.keys().map(String::as_str).collect();
+                    assert_eq!(&names, interfaces, "{entries:?}");
+                }
+                (Err(err), Err(expected)) => {
+                    assert!(err.contains(expected), "{entries:?}: {err}")
+                }
+                (result, expected) => panic!("{entries:?}: {result:?} instead of {expected:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_every_error_at_once() {
+        let err = EnvironmentSpec::from_vars(vars(&[
+            ("OPENSIGHT_NET_ETH0_MODE", "bootp"),
+            ("OPENSIGHT_NET_ETH1_MTU", "1"),
+        ]))
+        .unwrap_err();
+        assert_eq!(err.lines().count(), 2, "{err}");
+    }
+
+    #[test]
+    fn nothing_is_seeded_without_the_prefix() {
+        let spec = EnvironmentSpec::from_vars(vars(&[("OPENSIGHT_NETWORK", "x"), ("HOME", "/")]));
+        assert!(spec.unwrap().is_none());
+    }
+
+    #[test]
+    fn builds_the_network() {
+        let spec = EnvironmentSpec::from_vars(vars(&[
+            ("OPENSIGHT_NET_DNS", "10.0.0.1"),
+            ("OPENSIGHT_NET_SEARCH", "example.com"),
+            ("OPENSIGHT_NET_ETH0_ADDRESS", "10.0.0.5/24"),
+            ("OPENSIGHT_NET_ETH0_GATEWAY", "10.0.0.1"),
+            ("OPENSIGHT_NET_ETH1_MODE", "dhcp"),
+            ("OPENSIGHT_NET_ETH1_DNS", "fd00::1"),
+        ]))
+        .unwrap()
+        .unwrap();
+        let network = spec.to_network();
+        let eth0 = &network.get_ethernets()["eth0"];
+        assert!(!eth0.get_dhcp4());
+        assert_eq!(
+            eth0.get_addresses(),
+            ["10.0.0.5/24".parse().unwrap()].into()
+        );
+        assert_eq!(eth0.get_routes().len(), 1);
+        assert_eq!(
+            eth0.get_nameservers().addresses,
+            ["10.0.0.1".parse().unwrap()].into()
+        );
+        assert_eq!(
+            eth0.get_nameservers().search,
+            ["example.com".to_string()].into()
+        );
+        let eth1 = &network.get_ethernets()["eth1"];
+        assert!(eth1.get_dhcp4());
+        // Its own nameservers take precedence, but not over the search domains it lacks
+        assert_eq!(
+            eth1.get_nameservers().addresses,
+            ["fd00::1".parse().unwrap()].into()
+        );
+        assert_eq!(
+            eth1.get_nameservers().search,
+            ["example.com".to_string()].into()
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::netplan::{NetplanError, DEFAULT_OPERATION_TIMEOUT};
+use std::cell::Cell;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often a running command is checked for having exited.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// The deadline of the operation running on this thread, if any.
+    static OPERATION_DEADLINE: Cell<Option<Deadline>> = const { Cell::new(None) };
+}
+
+/// When an operation, with all the commands it runs, must be done by.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: Instant,
+    /// The time the operation was given, to report it.
+    budget: Duration,
+}
+
+impl Deadline {
+    /// A deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+            budget,
+        }
+    }
+
+    /// The deadline of the operation running on this thread, or one `DEFAULT_OPERATION_TIMEOUT`
+    /// from now for a command run on its own.
+    pub fn current() -> Self {
+        OPERATION_DEADLINE
+            .get()
+            .unwrap_or_else(|| Self::after(DEFAULT_OPERATION_TIMEOUT))
+    }
+
+    /// Runs `operation` with this deadline as the current one of the thread.
+    pub fn enforce<T>(self, operation: impl FnOnce() -> T) -> T {
+        let previous = OPERATION_DEADLINE.replace(Some(self));
+        let result = operation();
+        OPERATION_DEADLINE.set(previous);
+        result
+    }
+
+    /// The time left until the deadline, zero once it passed.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_past(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// The `Timeout` error of `command`, stopped at the deadline.
+    pub fn timeout(&self, command: &str) -> NetplanError {
+        NetplanError::Timeout {
+            command: command.to_string(),
+            timeout: self.budget,
+        }
+    }
+}
+
+/// Runs the external commands the system is managed with, like `netplan` and `systemctl`.
+///
+/// `SystemRunner` runs them for real, while tests script their outputs instead.
+pub trait CommandRunner {
+    /// Runs `program` with `args` and collects its output, whatever its exit status.
+    ///
+    /// The command is killed at `deadline`, and `Timeout` is returned.
+    fn output(
+        &self,
+        program: &str,
+        args: &[&str],
+        deadline: Deadline,
+    ) -> Result<Output, NetplanError>;
+
+    /// Starts `program` with `args` without waiting for it, e.g. `netplan try` which runs until
+    /// it is signaled or times out.
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, NetplanError>;
+}
+
+/// Runs the commands as child processes, without input.
+#[derive(Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(
+        &self,
+        program: &str,
+        args: &[&str],
+        deadline: Deadline,
+    ) -> Result<Output, NetplanError> {
+        // Past the deadline, the command would only be killed right away
+        if deadline.is_past() {
+            return Err(deadline.timeout(&command_line(program, args)));
+        }
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| NetplanError::spawn(program, err))?;
+        wait_until(child, &command_line(program, args), deadline)
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, NetplanError> {
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|err| NetplanError::spawn(program, err))
+    }
+}
+
+/// The command as it would be typed, to report it.
+pub fn command_line(program: &str, args: &[&str]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Waits for `child` to exit and collects its output, killing it at `deadline`.
+fn wait_until(mut child: Child, command: &str, deadline: Deadline) -> Result<Output, NetplanError> {
+    // The pipes are drained meanwhile, so a verbose command doesn't block on a full pipe
+    let stdout = child.stdout.take().map(read_to_end_in_background);
+    let stderr = child.stderr.take().map(read_to_end_in_background);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_past() {
+            let _ = child.kill();
+            let _ = child.wait();
+            let err = deadline.timeout(command);
+            log::error!("{err}");
+            return Err(err);
+        }
+        thread::sleep(COMMAND_POLL_INTERVAL);
+    };
+    let collect = |reader: Option<JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+/// Reads `pipe` until it is closed in a thread of its own, returning what was read, or what was
+/// read until an error.
+fn read_to_end_in_background(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut content = vec![];
+        let _ = pipe.read_to_end(&mut content);
+        content
+    })
+}
@@ -1,6 +1,10 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use utoipa::ToSchema;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, ToSchema)]
+/// An unsigned integer between `MIN` and `MAX` included, whose bounds are checked when deserializing.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
 pub struct BoundedU32<const MIN: u32, const MAX: u32>(u32);
 
 impl<const MIN: u32, const MAX: u32> BoundedU32<MIN, MAX> {
@@ -16,3 +20,207 @@ impl<const MIN: u32, const MAX: u32> BoundedU32<MIN, MAX> {
         self.0
     }
 }
+
+impl<'de, const MIN: u32, const MAX: u32> Deserialize<'de> for BoundedU32<MIN, MAX> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+        Self::new(value)
+            .ok_or_else(|| de::Error::custom(format!("{value} is not between {MIN} and {MAX}")))
+    }
+}
+
+/// An IP address together with the prefix length of its network, in CIDR notation (`10.0.0.5/24`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    /// Creates the network, validating the prefix length against the address family.
+    pub fn new(address: IpAddr, prefix: u8) -> Result<Self, String> {
+        let max_prefix = Self::max_prefix_for(&address);
+        if prefix > max_prefix {
+            return Err(format!(
+                "Prefix length {prefix} is out of range for {address} (expected 0-{max_prefix})"
+            ));
+        }
+        Ok(Self { address, prefix })
+    }
+
+    fn max_prefix_for(address: &IpAddr) -> u8 {
+        if address.is_ipv4() {
+            32
+        } else {
+            128
+        }
+    }
+
+    pub fn ip(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Whether this is a host address (`/32` for IPv4 and `/128` for IPv6).
+    pub fn is_host(&self) -> bool {
+        self.prefix == Self::max_prefix_for(&self.address)
+    }
+
+    /// Checks whether `address` belongs to this network.
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match (address, self.address) {
+            (IpAddr::V4(address), IpAddr::V4(network)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(*address) & mask == u32::from(network) & mask
+            }
+            (IpAddr::V6(address), IpAddr::V6(network)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(*address) & mask == u128::from(network) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = value
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| format!("Address '{value}' is missing a prefix length (e.g. /24)"))?;
+        let address: IpAddr = address
+            .parse()
+            .map_err(|err| format!("Invalid address '{value}': {err}"))?;
+        let prefix: u8 = prefix.parse().map_err(|_| {
+            format!(
+                "Invalid prefix length in '{value}' (expected 0-{})",
+                Self::max_prefix_for(&address)
+            )
+        })?;
+        Self::new(address, prefix)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A MAC address, written as six colon separated hexadecimal octets (`aa:bb:cc:dd:ee:ff`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[schema(value_type = String, example = "aa:bb:cc:dd:ee:ff")]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid MAC address '{value}' (expected aa:bb:cc:dd:ee:ff)");
+        let octets: Vec<&str> = value.trim().split(':').collect();
+        if octets.len() != 6 {
+            return Err(invalid());
+        }
+        let mut result = [0u8; 6];
+        for (octet, parsed) in octets.iter().zip(result.iter_mut()) {
+            if octet.len() != 2 || !octet.chars().all(|char| char.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            *parsed = u8::from_str_radix(octet, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(result))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_addresses_are_parsed_in_any_case() {
+        let octets = [0xaa, 0xbb, 0xcc, 0x0d, 0xee, 0xff];
+        for value in [
+            "aa:bb:cc:0d:ee:ff",
+            "AA:BB:CC:0D:EE:FF",
+            "Aa:bB:cc:0d:Ee:fF",
+        ] {
+            let mac: MacAddress = value.parse().unwrap();
+            assert_eq!(mac.octets(), octets, "{value}");
+            assert_eq!(mac.to_string(), "aa:bb:cc:0d:ee:ff");
+        }
+    }
+
+    #[test]
+    fn malformed_mac_addresses_are_refused() {
+        for value in [
+            "",
+            "aa:bb:cc:dd:ee",
+            "aa:bb:cc:dd:ee:ff:00",
+            "aa:bb:cc:dd:ee:f",
+            "aa:bb:cc:dd:ee:fff",
+            "aa-bb-cc-dd-ee-ff",
+            "aa:bb:cc:dd:ee:gg",
+            "+a:bb:cc:dd:ee:ff",
+        ] {
+            let err = value.parse::<MacAddress>().unwrap_err();
+            assert!(err.starts_with("Invalid MAC address"), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn mac_addresses_are_validated_on_deserialization() {
+        let mac: MacAddress = serde_yml::from_str("AA:BB:CC:DD:EE:FF").unwrap();
+        assert_eq!(serde_yml::to_string(&mac).unwrap(), "aa:bb:cc:dd:ee:ff\n");
+        assert!(serde_yml::from_str::<MacAddress>("aa:bb:cc:dd:ee").is_err());
+    }
+}
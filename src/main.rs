@@ -1,16 +1,39 @@
 // #![warn(missing_docs)]
 // #![warn(clippy::missing_ docs_in_private_items)]
 
+pub mod bootstrap;
+pub mod command;
 pub mod custom_types;
+pub mod method_handling;
 pub mod misc;
+pub mod mock_netplan;
 pub mod models;
 pub mod netplan;
 pub mod opensight_os_api_lib;
+#[cfg(test)]
+mod round_trip;
 pub mod routes;
-use crate::routes::{ethernet, host_info};
-use actix_web::{middleware::Logger, web::Data, App, HttpServer};
+pub mod system;
+#[cfg(test)]
+mod test_support;
+use crate::routes::{
+    admin, bonds, bridges, captures, devices, dummy_devices, ethernet, health, host_info,
+    netplan as netplan_routes, network, tunnels, vlans, vrfs, wifis,
+};
+use actix_web::{
+    dev::Extensions,
+    middleware::{from_fn, Logger, NormalizePath},
+    rt::net::TcpStream,
+    web::Data,
+    App, HttpServer,
+};
+use method_handling::AllowedMethods;
+use models::{
+    capture::{CaptureStore, CAPTURE_SUPERVISION_PERIOD},
+    storage::{StorageBudget, StorageCategory},
+};
 use opensight_os_api_lib::OpenSightOSApiLib;
-use std::net::Ipv4Addr;
+use std::{any::Any, net::Ipv4Addr, path::PathBuf, time::Duration};
 use utoipa::{openapi::Info, OpenApi};
 use utoipa_actix_web::AppExt;
 use utoipa_rapidoc::RapiDoc;
@@ -36,6 +59,138 @@ fn config_api() -> Info {
     base_app.build_info()
 }
 
+/// Number of backups of the configuration to keep, from the `OPENSIGHT_NETPLAN_BACKUPS`
+/// environment variable.
+///
+/// # Returns
+///
+/// * `usize` - The number of backups, `DEFAULT_BACKUP_RETENTION` if the variable is unset or invalid.
+fn backup_retention() -> usize {
+    let Ok(value) = std::env::var("OPENSIGHT_NETPLAN_BACKUPS") else {
+        return netplan::DEFAULT_BACKUP_RETENTION;
+    };
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => count,
+        _ => {
+            log::warn!(
+                "Ignoring OPENSIGHT_NETPLAN_BACKUPS={value}, which is not a positive number of backups."
+            );
+            netplan::DEFAULT_BACKUP_RETENTION
+        }
+    }
+}
+
+/// How long a request may use netplan, with all the commands it runs, before it is stopped, from
+/// the `OPENSIGHT_NETPLAN_TIMEOUT` environment variable, in seconds.
+///
+/// # Returns
+///
+/// * `Duration` - The timeout, `DEFAULT_OPERATION_TIMEOUT` if the variable is unset or invalid.
+fn operation_timeout() -> Duration {
+    let Ok(value) = std::env::var("OPENSIGHT_NETPLAN_TIMEOUT") else {
+        return netplan::DEFAULT_OPERATION_TIMEOUT;
+    };
+    match value.parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+        _ => {
+            log::warn!(
+                "Ignoring OPENSIGHT_NETPLAN_TIMEOUT={value}, which is not a positive number of seconds."
+            );
+            netplan::DEFAULT_OPERATION_TIMEOUT
+        }
+    }
+}
+
+/// Whether changes which would expand the YAML anchors, aliases or merge keys of a hand-written
+/// configuration file are refused, from the `OPENSIGHT_NETPLAN_STRICT_YAML` environment variable.
+///
+/// # Returns
+///
+/// * `bool` - `true` if the variable is `1` or `true`, `false` if it is unset or anything else.
+fn strict_yaml() -> bool {
+    std::env::var("OPENSIGHT_NETPLAN_STRICT_YAML")
+        .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+}
+
+/// Whether packets may be captured on the interfaces, from the `OPENSIGHT_PACKET_CAPTURE`
+/// environment variable. Capturing is off by default: the captures hold the traffic of the host.
+///
+/// # Returns
+///
+/// * `bool` - `true` if the variable is `1` or `true`, `false` if it is unset or anything else.
+fn packet_capture() -> bool {
+    std::env::var("OPENSIGHT_PACKET_CAPTURE")
+        .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+}
+
+/// Path of the configuration file to manage, from the `OPENSIGHT_NETPLAN_CONFIG` environment
+/// variable.
+///
+/// # Returns
+///
+/// * `PathBuf` - The path of the file, `NETPLAN_CONFIG_PATH` if the variable is unset or empty.
+fn config_path() -> PathBuf {
+    match std::env::var_os("OPENSIGHT_NETPLAN_CONFIG") {
+        Some(value) if !value.is_empty() => PathBuf::from(value),
+        _ => PathBuf::from(netplan::NETPLAN_CONFIG_PATH),
+    }
+}
+
+/// A budget in bytes, from the environment variable `name`.
+///
+/// # Returns
+///
+/// * `Option<u64>` - The number of bytes, `None` for no budget if the variable is unset or invalid.
+fn byte_budget(name: &str) -> Option<u64> {
+    let value = std::env::var(name).ok()?;
+    match value.parse::<u64>() {
+        Ok(bytes) => Some(bytes),
+        Err(_) => {
+            log::warn!("Ignoring {name}={value}, which is not a number of bytes.");
+            None
+        }
+    }
+}
+
+/// How much disk the backups and the metadata of the configuration may take, from the
+/// `OPENSIGHT_STORAGE_BUDGET` environment variable for the total, and the
+/// `OPENSIGHT_STORAGE_BUDGET_<CATEGORY>` ones for each category, in bytes.
+///
+/// # Returns
+///
+/// * `StorageBudget` - The budget, unlimited where the variables are unset or invalid.
+fn storage_budget() -> StorageBudget {
+    StorageBudget {
+        total: byte_budget("OPENSIGHT_STORAGE_BUDGET"),
+        categories: [
+            (StorageCategory::Backups, "OPENSIGHT_STORAGE_BUDGET_BACKUPS"),
+            (
+                StorageCategory::Metadata,
+                "OPENSIGHT_STORAGE_BUDGET_METADATA",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(category, name)| Some((category, byte_budget(name)?)))
+        .collect(),
+    }
+}
+
+/// Records the local address each connection was accepted on, so that the handlers can tell which
+/// address of the host the API is being served on.
+///
+/// # Arguments
+///
+/// * `connection` - The accepted connection, a `TcpStream`.
+/// * `data` - The extensions of the connection, given to the `SocketAddr` of its local end.
+fn record_local_address(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(address) = connection
+        .downcast_ref::<TcpStream>()
+        .and_then(|stream| stream.local_addr().ok())
+    {
+        data.insert(address);
+    }
+}
+
 #[actix_web::main]
 async fn main() -> Result<(), std::io::Error> {
     // Adjust logging
@@ -48,8 +203,21 @@ async fn main() -> Result<(), std::io::Error> {
         // Nesting allows for grouping of routes in the documentation at different levels
         nest(
             // Each path has its own documentation (<Path>Api)
+            (path = "/admin", api = admin::AdminApi),
+            (path = "/bonds", api = bonds::BondsApi),
+            (path = "/bridges", api = bridges::BridgesApi),
+            (path = "/captures", api = captures::CapturesApi),
+            (path = "/devices", api = devices::DevicesApi),
+            (path = "/dummy-devices", api = dummy_devices::DummyDevicesApi),
             (path = "/ethernets", api = ethernet::EthernetsApi),
-            (path = "/host-info", api = host_info::HostInfoApi)
+            (path = "/healthz", api = health::HealthApi),
+            (path = "/host-info", api = host_info::HostInfoApi),
+            (path = "/netplan", api = netplan_routes::NetplanApi),
+            (path = "/network", api = network::NetworkApi),
+            (path = "/tunnels", api = tunnels::TunnelsApi),
+            (path = "/vlans", api = vlans::VlansApi),
+            (path = "/vrfs", api = vrfs::VrfsApi),
+            (path = "/wifis", api = wifis::WifisApi)
         ),
     )]
     pub struct ApiDoc;
@@ -60,32 +228,149 @@ async fn main() -> Result<(), std::io::Error> {
     openapi.info = api_info;
 
     // Each route has its own store to hold the data (many routes can share the same store)
-    let ethernet_routes_store = Data::new(netplan::NetplanStore::default());
+    // Without netplan, e.g. to develop a client, the configuration can be kept in memory instead
+    let backend: Box<dyn netplan::NetplanBackend + Send + Sync> = match std::env::var(
+        "NETPLAN_BACKEND",
+    )
+    .as_deref()
+    {
+        Ok("memory") => Box::new(mock_netplan::MockNetplan::default()),
+        _ => {
+            let netplan = netplan::Netplan::with_config_path(config_path())
+                .with_backup_retention(backup_retention())
+                .with_storage_budget(storage_budget())
+                .with_strict_yaml(strict_yaml());
+            // The single backup of earlier versions joins the rotation before anything is served
+            if let Err(err) = netplan.migrate_legacy_backup() {
+                log::error!("Could not import the legacy backup: {err}");
+            }
+            for sidecar in netplan.corrupt_sidecars() {
+                log::error!(
+                        "The {sidecar} file can't be read. Rebuild it from what can still be read with POST /admin/rebuild-state?targets={sidecar}."
+                    );
+            }
+            Box::new(netplan)
+        }
+    };
+    let ethernet_routes_store =
+        Data::new(netplan::NetplanStore::new(backend).with_operation_timeout(operation_timeout()));
+    // The bookkeeping of the storage budget drifts when files change behind the back of the API
+    let reconciled_store = ethernet_routes_store.clone();
+    actix_web::rt::spawn(async move {
+        reconciled_store
+            .reconcile_storage_every(netplan::STORAGE_RECONCILIATION_PERIOD)
+            .await
+    });
+    // After a reboot, the changes which were waiting for it are checked once up
+    let checked_store = ethernet_routes_store.clone();
+    actix_web::rt::spawn(async move {
+        match checked_store.lock().await.pending_actions().await {
+            Ok(actions) if !actions.is_empty() => {
+                log::info!("{} changes are waiting for a reboot.", actions.len())
+            }
+            Ok(_) => (),
+            Err(err) => log::error!("Could not list the changes waiting for a reboot: {err}"),
+        }
+    });
     let host_info_routes_store = Data::new(models::host_info::HostInfoStore::default());
+    let capture_store = Data::new(CaptureStore::default().with_enabled(packet_capture()));
+    // The captures of a previous run can't be followed anymore: they are stopped and deleted
+    match capture_store.recover() {
+        Ok(captures) if !captures.is_empty() => {
+            log::info!("Deleted {} captures of a previous run.", captures.len())
+        }
+        Ok(_) => (),
+        Err(err) => log::error!("Could not clean up the captures of a previous run: {err}"),
+    }
+    let supervised_store = capture_store.clone();
+    actix_web::rt::spawn(async move {
+        supervised_store
+            .supervise_every(CAPTURE_SUPERVISION_PERIOD)
+            .await
+    });
     HttpServer::new(move || {
         // The server's application must be started and configured from within this closure
-        App::new()
+        let (app, api) = App::new()
             .into_utoipa_app()
             .openapi(openapi.clone())
             // Add some logging if wanted, so we can see what's happening
             .map(|app| app.wrap(Logger::default()))
+            // Captures are started from the interfaces' scopes, and followed from their own
+            .map(|app| app.app_data(capture_store.clone()))
             // The application's routes/scopes are configured here independently
+            .service(
+                utoipa_actix_web::scope("/admin")
+                    .configure(routes::admin::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/bonds")
+                    .configure(routes::bonds::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/bridges")
+                    .configure(routes::bridges::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/captures")
+                    .configure(routes::captures::configure(capture_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/devices")
+                    .configure(routes::devices::configure(ethernet_routes_store.clone())),
+            )
+            .service(utoipa_actix_web::scope("/dummy-devices").configure(
+                routes::dummy_devices::configure(ethernet_routes_store.clone()),
+            ))
             .service(
                 utoipa_actix_web::scope("/ethernets")
                     .configure(routes::ethernet::configure(ethernet_routes_store.clone())),
             )
             .service(
-                utoipa_actix_web::scope("/host-info")
-                    .configure(routes::host_info::configure(host_info_routes_store.clone())),
+                utoipa_actix_web::scope("/healthz")
+                    .configure(routes::health::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/host-info").configure(routes::host_info::configure(
+                    host_info_routes_store.clone(),
+                    ethernet_routes_store.clone(),
+                )),
+            )
+            .service(
+                utoipa_actix_web::scope("/netplan")
+                    .configure(routes::netplan::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/network")
+                    .configure(routes::network::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/tunnels")
+                    .configure(routes::tunnels::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/vlans")
+                    .configure(routes::vlans::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/vrfs")
+                    .configure(routes::vrfs::configure(ethernet_routes_store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/wifis")
+                    .configure(routes::wifis::configure(ethernet_routes_store.clone())),
             )
-            .openapi_service(|api| {
-                SwaggerUi::new("/docs/{_:.*}").url("/api-docs/openapi.json", api)
-            })
-            .openapi_service(|api| Redoc::with_url("/redoc", api))
-            .map(|app| app.service(RapiDoc::new("api-docs/openapi.json").path("/rapidoc")))
-            .openapi_service(|api| Scalar::with_url("/scalar", api))
-            .into_app()
+            .split_for_parts();
+        // The allowed methods of every resource are taken from the registered routes
+        app.app_data(Data::new(AllowedMethods::from_openapi(&api)))
+            .wrap(from_fn(method_handling::handle_methods))
+            // Canonical paths have no trailing slash; duplicate slashes are merged as well
+            .wrap(NormalizePath::trim())
+            .service(SwaggerUi::new("/docs/{_:.*}").url("/api-docs/openapi.json", api.clone()))
+            .service(Redoc::with_url("/redoc", api.clone()))
+            .service(RapiDoc::new("api-docs/openapi.json").path("/rapidoc"))
+            .service(Scalar::with_url("/scalar", api))
     })
+    .on_connect(record_local_address)
     .bind((Ipv4Addr::UNSPECIFIED, 8080))?
     .run()
     .await
@@ -0,0 +1,209 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web::Data,
+    Error, HttpResponse,
+};
+use utoipa::openapi::OpenApi;
+
+/// Methods allowed on each of the registered resources.
+///
+/// The table is built from the OpenAPI document assembled while registering the
+/// routes, so it always reflects the services actually mounted in the application.
+#[derive(Debug, Clone, Default)]
+pub struct AllowedMethods {
+    resources: Vec<(Vec<String>, Vec<Method>)>,
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+impl AllowedMethods {
+    /// Builds the table from the paths of the given OpenAPI document.
+    ///
+    /// `HEAD` is allowed wherever `GET` is, and `OPTIONS` on every resource.
+    pub fn from_openapi(api: &OpenApi) -> Self {
+        let resources = api
+            .paths
+            .paths
+            .iter()
+            .map(|(path, item)| {
+                let operations = [
+                    (Method::GET, item.get.is_some()),
+                    (Method::HEAD, item.get.is_some() || item.head.is_some()),
+                    (Method::POST, item.post.is_some()),
+                    (Method::PUT, item.put.is_some()),
+                    (Method::PATCH, item.patch.is_some()),
+                    (Method::DELETE, item.delete.is_some()),
+                    (Method::OPTIONS, true),
+                ];
+                let methods = operations
+                    .into_iter()
+                    .filter_map(|(method, allowed)| allowed.then_some(method))
+                    .collect();
+                (segments(path).map(str::to_string).collect(), methods)
+            })
+            .collect();
+        Self { resources }
+    }
+
+    /// Returns the methods allowed on the resource matching the given request path, if any.
+    ///
+    /// Path parameters (`{name}`) in the registered paths match any single segment.
+    pub fn for_path(&self, path: &str) -> Option<&[Method]> {
+        let requested: Vec<&str> = segments(path).collect();
+        self.resources
+            .iter()
+            .find(|(template, _)| {
+                template.len() == requested.len()
+                    && template
+                        .iter()
+                        .zip(requested.iter())
+                        .all(|(expected, actual)| {
+                            (expected.starts_with('{') && expected.ends_with('}'))
+                                || expected == actual
+                        })
+            })
+            .map(|(_, methods)| methods.as_slice())
+    }
+}
+
+/// Middleware answering `OPTIONS` requests and serving `HEAD` requests through the `GET` handlers.
+///
+/// `OPTIONS` on a registered resource is answered with `204 No Content` and an `Allow`
+/// header listing its methods. `HEAD` requests are routed as `GET`, so their headers are
+/// identical to the ones of `GET`; the body is dropped by the HTTP layer.
+pub async fn handle_methods(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if req.method() == Method::OPTIONS {
+        let allowed = req
+            .app_data::<Data<AllowedMethods>>()
+            .and_then(|table| table.for_path(req.path()))
+            .map(|methods| {
+                methods
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+        if let Some(allowed) = allowed {
+            let response = HttpResponse::NoContent()
+                .insert_header((header::ALLOW, allowed))
+                .finish();
+            return Ok(req.into_response(response));
+        }
+    } else if req.method() == Method::HEAD {
+        req.head_mut().method = Method::GET;
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        routes::{ethernet, netplan},
+        test_support::mock_store,
+    };
+    use actix_web::{
+        http::StatusCode,
+        middleware::{from_fn, NormalizePath},
+        test, App,
+    };
+    use serde_json::json;
+    use utoipa_actix_web::AppExt;
+
+    /// Methods advertised by `OPTIONS` on each resource, as registered by the routes.
+    const ALLOWED: [(&str, &str); 5] = [
+        ("/ethernets", "GET, HEAD, PATCH, OPTIONS"),
+        ("/ethernets/eth0", "GET, HEAD, PUT, PATCH, DELETE, OPTIONS"),
+        ("/ethernets/eth0/addresses", "GET, HEAD, POST, PUT, OPTIONS"),
+        ("/netplan/backups", "GET, HEAD, OPTIONS"),
+        ("/netplan/backups/1700000000000/restore", "POST, OPTIONS"),
+    ];
+
+    #[actix_web::test]
+    async fn every_route_answers_slashes_head_and_options() {
+        #[derive(utoipa::OpenApi)]
+        #[openapi(nest(
+            (path = "/ethernets", api = ethernet::EthernetsApi),
+            (path = "/netplan", api = netplan::NetplanApi)
+        ))]
+        struct ApiDoc;
+        let store = mock_store(&["eth0"]);
+        // Wrapped like in `main`
+        let (app, api) = App::new()
+            .into_utoipa_app()
+            .openapi(<ApiDoc as utoipa::OpenApi>::openapi())
+            .service(
+                utoipa_actix_web::scope("/ethernets").configure(ethernet::configure(store.clone())),
+            )
+            .service(
+                utoipa_actix_web::scope("/netplan").configure(netplan::configure(store.clone())),
+            )
+            .split_for_parts();
+        let app = test::init_service(
+            app.app_data(Data::new(AllowedMethods::from_openapi(&api)))
+                .wrap(from_fn(handle_methods))
+                .wrap(NormalizePath::trim()),
+        )
+        .await;
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        for (path, allowed) in ALLOWED {
+            let req = test::TestRequest::default()
+                .method(Method::OPTIONS)
+                .uri(&format!("{path}/"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT, "{path}");
+            assert_eq!(
+                resp.headers().get(header::ALLOW).unwrap(),
+                allowed,
+                "{path}"
+            );
+        }
+        let req = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/ethernets/eth0/unknown")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(!resp.headers().contains_key(header::ALLOW));
+
+        for path in ["/ethernets", "/ethernets/eth0", "/ethernets/eth0/addresses"] {
+            let req = test::TestRequest::get().uri(path).to_request();
+            let get = test::call_service(&app, req).await;
+            assert_eq!(get.status(), StatusCode::OK, "{path}");
+            for variant in [format!("{path}/"), path.replace('/', "//")] {
+                let req = test::TestRequest::get().uri(&variant).to_request();
+                let resp = test::call_service(&app, req).await;
+                assert_eq!(resp.status(), StatusCode::OK, "{variant}");
+            }
+            let req = test::TestRequest::default()
+                .method(Method::HEAD)
+                .uri(path)
+                .to_request();
+            let head = test::call_service(&app, req).await;
+            assert_eq!(head.status(), StatusCode::OK, "{path}");
+            assert_eq!(
+                head.headers().get(header::ETAG),
+                get.headers().get(header::ETAG),
+                "{path}"
+            );
+            assert_eq!(
+                head.headers().get(header::CONTENT_TYPE),
+                get.headers().get(header::CONTENT_TYPE),
+                "{path}"
+            );
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     net::{self, AddrParseError, IpAddr},
 };
 
-use serde::{Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yml::libyml::parser::{Anchor, Event, Parser, ScalarStyle};
 
 struct IpAddrVisitor;
 
@@ -14,8 +16,7 @@ pub fn serialize_hash_set_from_ip_addr_as_yaml_sequence<S>(
 where
     S: serde::Serializer,
 {
-    let addresses_vec: Vec<&IpAddr> = addresses.iter().collect();
-    addresses_vec.serialize(serializer)
+    serialize_hash_set_as_sorted_yaml_sequence(addresses, serializer)
 }
 
 pub fn serialize_hash_set_from_string_as_yaml_sequence<S>(
@@ -25,8 +26,48 @@ pub fn serialize_hash_set_from_string_as_yaml_sequence<S>(
 where
     S: serde::Serializer,
 {
-    let addresses_vec: Vec<&String> = addresses.iter().collect();
-    addresses_vec.serialize(serializer)
+    serialize_hash_set_as_sorted_yaml_sequence(addresses, serializer)
+}
+
+/// Serializes the set as a sequence, sorted so that equal sets are always written the same way.
+pub fn serialize_hash_set_as_sorted_yaml_sequence<S, T>(
+    set: &HashSet<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    T: Serialize + Ord,
+{
+    let mut values: Vec<&T> = set.iter().collect();
+    values.sort();
+    values.serialize(serializer)
+}
+
+/// Serializes the map as a mapping sorted by key, so that equal maps are always written the same
+/// way.
+pub fn serialize_hash_map_sorted<S, T>(
+    map: &HashMap<String, T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    T: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+pub fn serialize_hash_map_values_as_yaml_sequence<S, T>(
+    map: &HashMap<String, T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    T: Serialize,
+{
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    let values: Vec<&T> = keys.into_iter().map(|key| &map[key]).collect();
+    values.serialize(serializer)
 }
 
 pub fn serialize_ip_option<S>(origin: &Option<IpAddr>, serializer: S) -> Result<S::Ok, S::Error>
@@ -51,7 +92,7 @@ where
     }
 }
 
-impl serde::de::Visitor<'_> for IpAddrVisitor {
+impl<'de> serde::de::Visitor<'de> for IpAddrVisitor {
     type Value = Option<IpAddr>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -82,6 +123,13 @@ impl serde::de::Visitor<'_> for IpAddrVisitor {
     {
         Ok(None)
     }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
 }
 
 pub fn deserialize_ip_option<'de, D>(deserializer: D) -> Result<Option<IpAddr>, D::Error>
@@ -91,6 +139,18 @@ where
     deserializer.deserialize_option(IpAddrVisitor)
 }
 
+/// Deserializes a field which can be absent, `null` or set, telling the first two apart.
+///
+/// Along with `#[serde(default)]`, an absent field is `None`, a `null` one `Some(None)`, and a
+/// set one `Some(Some(value))`.
+pub fn deserialize_double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 pub fn deserialize_ip<'de, D>(deserializer: D) -> Result<IpAddr, D::Error>
 where
     D: Deserializer<'de>,
@@ -98,3 +158,160 @@ where
     let result = deserializer.deserialize_str(IpAddrVisitor)?.unwrap();
     Ok(result)
 }
+
+/// Finds the YAML anchors (`&name`), aliases (`*name`) and merge keys (`<<`) used in each
+/// document of a YAML stream, sorted, one set by document.
+///
+/// The parsed value model loses these constructs, so the events of the parser are looked at
+/// instead, where quoted and block scalars can't be mistaken for them. A merge key is a plain
+/// `<<` scalar in the key position of a mapping. Scanning stops at the first syntax error,
+/// leaving the incomplete document out, as parsing the value reports it anyway.
+pub fn find_yaml_references(content: &str) -> Vec<BTreeSet<String>> {
+    let mut parser = Parser::new(Cow::Borrowed(content.as_bytes()));
+    let mut documents = vec![];
+    let mut references = BTreeSet::new();
+    // For each open collection, whether it is a mapping whose next node is a key
+    let mut collections: Vec<Option<bool>> = vec![];
+    while let Ok((event, _)) = parser.parse_next_event() {
+        let is_key = matches!(collections.last(), Some(Some(true)));
+        let (anchor, complete) = match event {
+            Event::StreamEnd => break,
+            Event::StreamStart | Event::DocumentStart => continue,
+            Event::DocumentEnd => {
+                documents.push(std::mem::take(&mut references));
+                continue;
+            }
+            Event::Alias(anchor) => {
+                references.insert(format!("*{}", anchor_name(&anchor)));
+                (None, true)
+            }
+            Event::Scalar(scalar) => {
+                if is_key && scalar.style == ScalarStyle::Plain && &*scalar.value == b"<<" {
+                    references.insert("<<".to_string());
+                }
+                (scalar.anchor, true)
+            }
+            Event::SequenceStart(start) => {
+                collections.push(None);
+                (start.anchor, false)
+            }
+            Event::MappingStart(start) => {
+                collections.push(Some(true));
+                (start.anchor, false)
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                collections.pop();
+                (None, true)
+            }
+        };
+        if let Some(anchor) = anchor {
+            references.insert(format!("&{}", anchor_name(&anchor)));
+        }
+        if let (true, Some(Some(is_key))) = (complete, collections.last_mut()) {
+            *is_key = !*is_key;
+        }
+    }
+    documents
+}
+
+/// The name of an anchor, which the parser only exposes through its quoted `Debug` output.
+fn anchor_name(anchor: &Anchor) -> String {
+    let name = format!("{anchor:?}");
+    name.trim_matches('"').to_string()
+}
+
+/// Converts a YAML value into one that can be serialized as JSON.
+///
+/// JSON objects only have string keys, so non-string keys are rendered as YAML scalars, and
+/// tags (e.g. `!!binary`) are dropped in favour of the tagged value.
+pub fn yaml_to_json_compatible(value: &serde_yml::Value) -> serde_yml::Value {
+    match value {
+        serde_yml::Value::Mapping(mapping) => {
+            serde_yml::Value::Mapping(yaml_mapping_to_json_compatible(mapping))
+        }
+        serde_yml::Value::Sequence(sequence) => {
+            serde_yml::Value::Sequence(sequence.iter().map(yaml_to_json_compatible).collect())
+        }
+        serde_yml::Value::Tagged(tagged) => yaml_to_json_compatible(&tagged.value),
+        other => other.clone(),
+    }
+}
+
+/// Converts a YAML mapping into one that can be serialized as a JSON object.
+///
+/// See `yaml_to_json_compatible`.
+pub fn yaml_mapping_to_json_compatible(mapping: &serde_yml::Mapping) -> serde_yml::Mapping {
+    mapping
+        .iter()
+        .map(|(key, value)| {
+            let key = match key {
+                serde_yml::Value::String(key) => key.clone(),
+                other => serde_yml::to_string(other)
+                    .map(|key| key.trim().to_string())
+                    .unwrap_or_default(),
+            };
+            (
+                serde_yml::Value::String(key),
+                yaml_to_json_compatible(value),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn references_in_fixture(name: &str) -> Vec<BTreeSet<String>> {
+        let content = std::fs::read_to_string(format!(
+            "{}/tests/fixtures/yaml-references/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        find_yaml_references(&content)
+    }
+
+    fn set(references: &[&str]) -> BTreeSet<String> {
+        references
+            .iter()
+            .map(|reference| reference.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn anchors_aliases_and_merge_keys_are_found() {
+        for (fixture, expected) in [
+            (
+                "anchored-nameservers.yaml",
+                set(&["&office-dns", "*office-dns"]),
+            ),
+            (
+                "merge-key-routes.yaml",
+                set(&["&via-office-gateway", "*via-office-gateway", "<<"]),
+            ),
+            ("flow-style.yaml", set(&["&base", "*base", "<<"])),
+            ("anchored-nameservers.expanded.yaml", set(&[])),
+        ] {
+            assert_eq!(references_in_fixture(fixture), [expected], "{fixture}");
+        }
+    }
+
+    #[test]
+    fn quoted_and_block_scalars_are_not_references() {
+        assert_eq!(references_in_fixture("quoted.yaml"), [set(&[])]);
+    }
+
+    #[test]
+    fn references_are_found_by_document() {
+        let content = "a: &x 1\nb: *x\n---\nc: {<<: {d: 2}}\n---\ne: 3\n";
+        assert_eq!(
+            find_yaml_references(content),
+            [set(&["&x", "*x"]), set(&["<<"]), set(&[])]
+        );
+        // The incomplete document is left to the parsing of the value
+        assert_eq!(
+            find_yaml_references("a: &x [1\n"),
+            Vec::<BTreeSet<String>>::new()
+        );
+    }
+}
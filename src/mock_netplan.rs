@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, ErrorKind},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    models::{
+        dhcp_probe::DhcpProbeResult,
+        network::{Network, NetworkRenderer},
+        pending_action::PendingAction,
+        resource_usage::StructureUsage,
+        sidecar::{RebuildReport, Sidecar},
+        storage::StorageReport,
+        wait_online::{WaitOnlineParticipation, WaitOnlineReport},
+    },
+    netplan::{
+        without_metadata, Netplan, NetplanBackend, NetplanError, PendingTry,
+        DEFAULT_BACKUP_RETENTION,
+    },
+};
+
+/// Netplan backend keeping the configuration in memory, without touching the system.
+///
+/// Applying always succeeds and the system always matches the configuration, so the API can be
+/// run where netplan isn't available, e.g. to develop a client against it.
+pub struct MockNetplan {
+    network: Mutex<Network>,
+    /// Previous configurations by timestamp, oldest first.
+    backups: Mutex<Vec<(u64, Network)>>,
+    /// Ethernet interfaces the simulated system has.
+    interfaces: Vec<String>,
+}
+
+impl MockNetplan {
+    pub fn new(interfaces: Vec<String>) -> Self {
+        Self {
+            network: Mutex::new(Network::new()),
+            backups: Mutex::new(Vec::new()),
+            interfaces,
+        }
+    }
+}
+
+impl MockNetplan {
+    /// Keeps the current configuration as a backup, like `Netplan` does on save.
+    fn backup_config(&self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let mut backups = self.backups.lock().unwrap();
+        backups.push((timestamp, self.network.lock().unwrap().clone()));
+        let excess = backups.len().saturating_sub(DEFAULT_BACKUP_RETENTION);
+        backups.drain(..excess);
+    }
+}
+
+impl Default for MockNetplan {
+    /// A system with a single `eth0` interface, and an empty configuration.
+    fn default() -> Self {
+        Self::new(vec!["eth0".to_string()])
+    }
+}
+
+impl NetplanBackend for MockNetplan {
+    fn load_config(&self) -> Result<Network, NetplanError> {
+        Ok(self.network.lock().unwrap().clone())
+    }
+
+    fn load_stored_config(&self) -> Result<Network, NetplanError> {
+        self.load_config()
+    }
+
+    /// The configuration of `network` is the same, so it is kept as a whole.
+    fn save_metadata(&self, network: &Network) -> io::Result<()> {
+        *self.network.lock().unwrap() = network.clone();
+        Ok(())
+    }
+
+    fn save_config(&self, network: &Network) -> io::Result<()> {
+        self.backup_config();
+        *self.network.lock().unwrap() = network.clone();
+        Ok(())
+    }
+
+    fn apply(&self) -> Result<(), NetplanError> {
+        Ok(())
+    }
+
+    fn get_diff(&self) -> Result<HashMap<String, serde_yml::Mapping>, NetplanError> {
+        Ok(HashMap::new())
+    }
+
+    fn list_system_interfaces(&self) -> io::Result<Vec<String>> {
+        Ok(self.interfaces.clone())
+    }
+
+    /// The labels are left out, like `Netplan` keeps them out of the configuration file.
+    fn config_etag(&self) -> io::Result<String> {
+        let content = serde_yml::to_string(&without_metadata(&self.network.lock().unwrap()))
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(format!("\"{:016x}\"", hasher.finish()))
+    }
+
+    fn metadata_revision(&self) -> io::Result<String> {
+        let content = Netplan::metadata_content(&self.network.lock().unwrap());
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(format!("\"{:016x}\"", hasher.finish()))
+    }
+
+    fn restore_config(&self) -> io::Result<()> {
+        let Some((_, backup)) = self.backups.lock().unwrap().last().cloned() else {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                "There is no backup of the configuration to restore.",
+            ));
+        };
+        *self.network.lock().unwrap() = backup;
+        Ok(())
+    }
+
+    fn list_backups(&self) -> io::Result<Vec<u64>> {
+        Ok(self
+            .backups
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .collect())
+    }
+
+    fn restore_backup(&self, timestamp: u64) -> io::Result<()> {
+        let Some((_, backup)) = self
+            .backups
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(backup_timestamp, _)| *backup_timestamp == timestamp)
+            .cloned()
+        else {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Backup {timestamp} was not found."),
+            ));
+        };
+        self.save_config(&backup)
+    }
+
+    /// Nothing was kept before the upgrade.
+    fn is_pre_upgrade_backup(&self, _timestamp: u64) -> bool {
+        false
+    }
+
+    fn tryout(&self, _timeout: u32) -> Result<PendingTry, NetplanError> {
+        Err(NetplanError::NotInstalled("netplan".to_string()))
+    }
+
+    fn generate(&self, _network: &Network) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn check_renderer_service(&self, _renderer: &NetworkRenderer) -> Result<(), NetplanError> {
+        Ok(())
+    }
+
+    /// The simulated segments have no DHCP server.
+    fn probe_dhcp(
+        &self,
+        interface: &str,
+        _timeout: Duration,
+    ) -> Result<DhcpProbeResult, NetplanError> {
+        if !self.interfaces.iter().any(|name| name == interface) {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Interface {interface} does not exist in the system."),
+            )
+            .into());
+        }
+        Ok(DhcpProbeResult::from_networkctl_status(interface, ""))
+    }
+
+    /// The simulated system takes every change on apply.
+    fn pending_actions(&self) -> io::Result<Vec<PendingAction>> {
+        Ok(vec![])
+    }
+
+    /// The configured Ethernets are the only interfaces networkd is configured for, and the
+    /// service always runs.
+    fn wait_online(&self) -> io::Result<WaitOnlineReport> {
+        let interfaces = self
+            .network
+            .lock()
+            .unwrap()
+            .get_ethernets()
+            .iter()
+            .map(|(name, ethernet)| WaitOnlineParticipation {
+                interface: name.clone(),
+                required_for_online: ethernet.wait_online_participation(),
+            })
+            .collect();
+        Ok(WaitOnlineReport::new("active".to_string(), interfaces))
+    }
+
+    /// The configuration is never written by hand.
+    fn yaml_references(&self) -> io::Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn check_yaml_references(&self) -> Result<(), NetplanError> {
+        Ok(())
+    }
+
+    /// Nothing is kept besides the configuration and its backups.
+    fn resource_usage(&self) -> io::Result<Vec<StructureUsage>> {
+        Ok(vec![])
+    }
+
+    /// Nothing is written to disk, so nothing can be corrupt.
+    fn rebuild_state(&self, targets: &[Sidecar]) -> io::Result<Vec<RebuildReport>> {
+        Ok(targets
+            .iter()
+            .map(|target| RebuildReport::intact(*target))
+            .collect())
+    }
+
+    /// Nothing is written to disk.
+    fn storage_report(&self) -> io::Result<StorageReport> {
+        Ok(StorageReport::default())
+    }
+
+    fn reconcile_storage(&self) -> io::Result<StorageReport> {
+        self.storage_report()
+    }
+}
@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Value the passwords of access points are replaced with in responses.
+pub const MASKED_PASSWORD: &str = "***";
+
+/// Frequency band of a wireless network.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum WifiBand {
+    #[serde(rename = "2.4GHz")]
+    Band2_4GHz,
+    #[serde(rename = "5GHz")]
+    Band5GHz,
+}
+
+/// Key management scheme of a network, WPA2/WPA3 enterprise networks using EAP.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyManagement {
+    None,
+    /// WPA2 personal, with a pre-shared key.
+    Psk,
+    /// WPA2 enterprise.
+    Eap,
+    /// WPA2 enterprise with SHA-256, as required by WPA3 enterprise.
+    #[serde(rename = "eap-sha256")]
+    EapSha256,
+    /// WPA3 enterprise 192-bit mode.
+    #[serde(rename = "eap-suite-b-192")]
+    EapSuiteB192,
+    /// WPA3 personal.
+    Sae,
+    /// IEEE 802.1X, e.g. for wired networks.
+    #[serde(rename = "802.1x")]
+    Ieee8021x,
+}
+
+/// EAP method used to authenticate on enterprise networks.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMethod {
+    Tls,
+    Peap,
+    Ttls,
+    Leap,
+    Pwd,
+}
+
+/// Authentication settings of an access point, written under its `auth` key.
+///
+/// Certificates and keys are paths to files on the host.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct Auth {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_management: Option<KeyManagement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<AuthMethod>,
+    /// Identity to authenticate with, required by PEAP and TTLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Identity sent in the clear before the tunnel is established, by PEAP and TTLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous_identity: Option<String>,
+    /// Password of the identity, or pre-shared key. It is write-only, and returned as `***`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(write_only)]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_certificate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_certificate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+    /// Password the client key is encrypted with. It is write-only, and returned as `***`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(write_only)]
+    pub client_key_password: Option<String>,
+    /// Phase 2 authentication of PEAP and TTLS, e.g. `mschapv2`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase2_auth: Option<String>,
+}
+
+impl Auth {
+    /// Validates that the settings the method requires are present.
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.method, Some(AuthMethod::Peap | AuthMethod::Ttls))
+            && self.identity.is_none()
+        {
+            return Err("An identity is required to authenticate with PEAP or TTLS.".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the settings with their secrets masked.
+    pub fn redacted(&self) -> Self {
+        Self {
+            password: mask(&self.password),
+            client_key_password: mask(&self.client_key_password),
+            ..self.clone()
+        }
+    }
+
+    /// Keeps the secrets of `current` that were sent back masked.
+    pub fn unmask(&mut self, current: Option<&Auth>) {
+        unmask(
+            &mut self.password,
+            current.and_then(|current| current.password.clone()),
+        );
+        unmask(
+            &mut self.client_key_password,
+            current.and_then(|current| current.client_key_password.clone()),
+        );
+    }
+}
+
+/// Replaces a secret, if set, with `MASKED_PASSWORD`.
+fn mask(secret: &Option<String>) -> Option<String> {
+    secret.as_ref().map(|_| MASKED_PASSWORD.to_string())
+}
+
+/// Restores the `current` value of a secret that was sent back masked.
+fn unmask(secret: &mut Option<String>, current: Option<String>) {
+    if secret.as_deref() == Some(MASKED_PASSWORD) {
+        *secret = current;
+    }
+}
+
+/// A wireless network a wifi device connects to, keyed by its SSID.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct AccessPoint {
+    /// Pre-shared key of the network, left out for open networks. It is write-only, and
+    /// returned as `***`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(write_only)]
+    pub password: Option<String>,
+    /// Whether the network does not broadcast its SSID, so it has to be probed for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub band: Option<WifiBand>,
+    /// Channel to use, which requires the band to be set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u32>,
+    /// Authentication settings beyond a pre-shared key, e.g. for WPA2/WPA3 enterprise networks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+}
+
+impl AccessPoint {
+    /// Validates that the settings are consistent with each other.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.channel.is_some() && self.band.is_none() {
+            return Err(
+                "The channel of an access point can only be set along with its band.".to_string(),
+            );
+        }
+        if let Some(auth) = &self.auth {
+            auth.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the access point with its secrets masked.
+    pub fn redacted(&self) -> Self {
+        Self {
+            password: mask(&self.password),
+            auth: self.auth.as_ref().map(Auth::redacted),
+            ..self.clone()
+        }
+    }
+
+    /// Keeps the secrets of `current` that were sent back still masked, as read from a response.
+    pub fn unmask(&mut self, current: Option<&AccessPoint>) {
+        unmask(
+            &mut self.password,
+            current.and_then(|current| current.password.clone()),
+        );
+        if let Some(auth) = &mut self.auth {
+            auth.unmask(current.and_then(|current| current.auth.as_ref()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peap() -> Auth {
+        Auth {
+            key_management: Some(KeyManagement::Eap),
+            method: Some(AuthMethod::Peap),
+            identity: Some("capture@example.com".to_string()),
+            password: Some("hunter2".to_string()),
+            ca_certificate: Some("/etc/ssl/certs/ca.pem".to_string()),
+            phase2_auth: Some("mschapv2".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn auth_is_nested_under_its_key() {
+        let access_point = AccessPoint {
+            auth: Some(peap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_yml::to_string(&access_point).unwrap(),
+            "auth:\n  key-management: eap\n  method: peap\n  identity: capture@example.com\n  \
+             password: hunter2\n  ca-certificate: /etc/ssl/certs/ca.pem\n  phase2-auth: mschapv2\n"
+        );
+        let read: AccessPoint = serde_yml::from_str(
+            "auth: {key-management: eap-suite-b-192, method: tls, client-key: /etc/key.pem}",
+        )
+        .unwrap();
+        let auth = read.auth.unwrap();
+        assert_eq!(auth.key_management, Some(KeyManagement::EapSuiteB192));
+        assert_eq!(auth.method, Some(AuthMethod::Tls));
+        assert_eq!(auth.client_key.as_deref(), Some("/etc/key.pem"));
+    }
+
+    #[test]
+    fn peap_and_ttls_require_an_identity() {
+        for method in [AuthMethod::Peap, AuthMethod::Ttls] {
+            let access_point = AccessPoint {
+                auth: Some(Auth {
+                    method: Some(method),
+                    identity: None,
+                    ..peap()
+                }),
+                ..Default::default()
+            };
+            assert!(access_point.validate().is_err());
+        }
+        let tls = Auth {
+            method: Some(AuthMethod::Tls),
+            identity: None,
+            ..peap()
+        };
+        assert_eq!(tls.validate(), Ok(()));
+        assert_eq!(peap().validate(), Ok(()));
+    }
+
+    #[test]
+    fn secrets_are_masked_and_kept_when_sent_back() {
+        let current = AccessPoint {
+            password: Some("psk-secret".to_string()),
+            auth: Some(Auth {
+                client_key_password: Some("key-secret".to_string()),
+                ..peap()
+            }),
+            ..Default::default()
+        };
+        let redacted = current.redacted();
+        let auth = redacted.auth.as_ref().unwrap();
+        assert_eq!(redacted.password.as_deref(), Some(MASKED_PASSWORD));
+        assert_eq!(auth.password.as_deref(), Some(MASKED_PASSWORD));
+        assert_eq!(auth.client_key_password.as_deref(), Some(MASKED_PASSWORD));
+        assert_eq!(auth.identity, peap().identity);
+
+        let mut sent_back = redacted.clone();
+        sent_back.unmask(Some(&current));
+        assert_eq!(sent_back, current);
+
+        let mut changed = redacted;
+        changed.auth.as_mut().unwrap().password = Some("new-secret".to_string());
+        changed.unmask(Some(&current));
+        assert_eq!(
+            changed.auth.unwrap().password.as_deref(),
+            Some("new-secret")
+        );
+    }
+}
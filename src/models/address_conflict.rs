@@ -0,0 +1,60 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::custom_types::IpNetwork;
+
+use super::{loopback::is_loopback, network::Network};
+
+/// How an address conflicts with one configured on another interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictKind {
+    /// The very same IP address is configured on the other interface.
+    Identical,
+    /// The subnets of both addresses overlap.
+    SameSubnet,
+}
+
+/// An address of another interface conflicting with the one being configured.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AddressConflict {
+    pub interface: String,
+    #[schema(value_type = String)]
+    pub address: IpNetwork,
+    pub kind: ConflictKind,
+}
+
+/// Finds the addresses of interfaces other than `interface` conflicting with `address`.
+///
+/// Host addresses on loopback interfaces (VIPs) usually lie within the subnet of a physical
+/// interface on purpose, so they only conflict when identical.
+pub fn find_address_conflicts(
+    network: &Network,
+    interface: &str,
+    address: &IpNetwork,
+) -> Vec<AddressConflict> {
+    let mut result = vec![];
+    for (name, (addresses, _)) in network.interfaces() {
+        if name == interface {
+            continue;
+        }
+        let loopback = is_loopback(&name) || is_loopback(interface);
+        for other in addresses {
+            let kind = if other.ip() == address.ip() {
+                ConflictKind::Identical
+            } else if !loopback && (other.contains(&address.ip()) || address.contains(&other.ip()))
+            {
+                ConflictKind::SameSubnet
+            } else {
+                continue;
+            };
+            result.push(AddressConflict {
+                interface: name.clone(),
+                address: other,
+                kind,
+            });
+        }
+    }
+    result.sort_by(|a, b| (&a.interface, a.address).cmp(&(&b.interface, b.address)));
+    result
+}
@@ -0,0 +1,88 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::custom_types::IpNetwork;
+
+use super::device::Device;
+
+/// Where an address of an interface comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressSource {
+    /// Configured in the netplan configuration.
+    Static,
+    /// Leased from a DHCPv4 server.
+    Dhcp4,
+    /// Leased from a DHCPv6 server.
+    Dhcp6,
+    /// Autoconfigured from router advertisements.
+    Ra,
+    /// Link-local address, generated by the kernel.
+    LinkLocal,
+}
+
+/// An address of an interface, together with where it comes from.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AnnotatedAddress {
+    /// The address in CIDR notation, e.g. `10.0.0.5/24`.
+    pub address: String,
+    pub source: AddressSource,
+}
+
+impl AnnotatedAddress {
+    /// Annotates a dynamic address as reported by `netplan status`, e.g. `10.0.0.5/24 (dhcp)`.
+    ///
+    /// Addresses without a flag telling their source are skipped.
+    fn from_dynamic(dynamic_address: &str) -> Option<Self> {
+        let (address, flags) = dynamic_address
+            .split_once(" (")
+            .map(|(address, flags)| (address, flags.trim_end_matches(')')))
+            .unwrap_or((dynamic_address, ""));
+        let flags: Vec<&str> = flags.split(", ").collect();
+        let source = if flags.contains(&"dhcp") {
+            // netplan uses the same flag for both versions of DHCP
+            if address.contains(':') {
+                AddressSource::Dhcp6
+            } else {
+                AddressSource::Dhcp4
+            }
+        } else if flags.contains(&"ra") {
+            AddressSource::Ra
+        } else if flags.contains(&"link") {
+            AddressSource::LinkLocal
+        } else {
+            return None;
+        };
+        Some(Self {
+            address: address.to_string(),
+            source,
+        })
+    }
+}
+
+/// Lists the static and dynamic addresses of an interface, static ones first.
+pub fn annotate_addresses(device: &impl Device) -> Vec<AnnotatedAddress> {
+    let mut static_addresses: Vec<IpNetwork> = device.get_addresses().into_iter().collect();
+    static_addresses.sort();
+    let mut result: Vec<AnnotatedAddress> = static_addresses
+        .into_iter()
+        .map(|address| AnnotatedAddress {
+            address: address.to_string(),
+            source: AddressSource::Static,
+        })
+        .collect();
+    for dynamic_address in device.get_dynamic_addresses() {
+        let Some(annotated) = AnnotatedAddress::from_dynamic(&dynamic_address) else {
+            continue;
+        };
+        // Static addresses are reported by netplan as well, flagged or not
+        if result
+            .iter()
+            .any(|existing| existing.address == annotated.address)
+        {
+            continue;
+        }
+        result.push(annotated);
+    }
+    result
+}
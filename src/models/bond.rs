@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bond_parameters::BondParameters,
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputBond,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bond {
+    #[serde(skip_serializing)]
+    name: String,
+    /// Names of the Ethernets aggregated by the bond.
+    #[serde(default)]
+    interfaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "BondParameters::is_empty")]
+    parameters: BondParameters,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl Bond {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            interfaces: Vec::new(),
+            parameters: BondParameters::default(),
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    pub fn from_input_bond(name: &str, input_bond: &InputBond) -> Self {
+        Self {
+            name: name.to_string(),
+            interfaces: input_bond.interfaces.clone(),
+            parameters: BondParameters::default(),
+            settings: DeviceSettings::from_input_device(&input_bond.device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn interfaces(&self) -> Vec<String> {
+        self.interfaces.clone()
+    }
+
+    /// Replaces the members of the bond, dropping the primary one if it is not a member anymore.
+    pub fn set_interfaces(&mut self, interfaces: Vec<String>) {
+        if self
+            .parameters
+            .primary
+            .as_ref()
+            .is_some_and(|primary| !interfaces.contains(primary))
+        {
+            self.parameters.primary = None;
+        }
+        self.interfaces = interfaces;
+    }
+
+    pub fn parameters(&self) -> BondParameters {
+        self.parameters.clone()
+    }
+
+    pub fn set_parameters(&mut self, parameters: BondParameters) {
+        self.parameters = parameters;
+    }
+}
+
+impl WithDeviceSettings for Bond {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
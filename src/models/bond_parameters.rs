@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Policy the bond follows to spread traffic over its members.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum BondMode {
+    /// Round-robin over the members, the kernel's default.
+    BalanceRr,
+    /// Only one member is active, another taking over when it fails.
+    ActiveBackup,
+    /// The member is picked by hashing, see `transmit-hash-policy`.
+    BalanceXor,
+    /// Everything is sent on every member.
+    Broadcast,
+    /// IEEE 802.3ad dynamic link aggregation (LACP), which the switch has to support.
+    #[serde(rename = "802.3ad")]
+    Lacp,
+    /// Outgoing traffic is balanced according to the load of each member.
+    BalanceTlb,
+    /// Like `balance-tlb`, incoming IPv4 traffic being balanced as well.
+    BalanceAlb,
+}
+
+/// Rate at which LACPDUs are requested from the link partner in `802.3ad` mode.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LacpRate {
+    /// Every 30 seconds, the default.
+    Slow,
+    /// Every second.
+    Fast,
+}
+
+/// Packet fields hashed to pick the member traffic is sent on.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum TransmitHashPolicy {
+    #[serde(rename = "layer2")]
+    Layer2,
+    #[serde(rename = "layer2+3")]
+    Layer2And3,
+    #[serde(rename = "layer3+4")]
+    Layer3And4,
+    #[serde(rename = "encap2+3")]
+    Encap2And3,
+    #[serde(rename = "encap3+4")]
+    Encap3And4,
+}
+
+/// Aggregation and monitoring settings of a bond, written under its `parameters` key.
+///
+/// Unset fields are left out of the configuration, so netplan's defaults apply. Intervals and
+/// delays are in milliseconds.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct BondParameters {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<BondMode>,
+    /// Only accepted in `802.3ad` mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lacp_rate: Option<LacpRate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transmit_hash_policy: Option<TransmitHashPolicy>,
+    /// Interval at which the link state of the members is checked, 0 disabling the monitoring.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mii_monitor_interval: Option<u32>,
+    /// Number of members that have to be up for the bond to be up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_links: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_delay: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down_delay: Option<u32>,
+    /// Member preferred as the active one, which has to be a member of the bond.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+}
+
+impl BondParameters {
+    /// Whether no parameter is set at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Validates that the parameters are consistent with each other.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lacp_rate.is_some() && self.mode != Some(BondMode::Lacp) {
+            return Err("The LACP rate can only be set in 802.3ad mode.".to_string());
+        }
+        Ok(())
+    }
+
+    /// Overlays the parameters set in `other` on these ones.
+    pub fn merge(&mut self, other: &BondParameters) {
+        self.mode = other.mode.or(self.mode);
+        self.lacp_rate = other.lacp_rate.or(self.lacp_rate);
+        self.transmit_hash_policy = other.transmit_hash_policy.or(self.transmit_hash_policy);
+        self.mii_monitor_interval = other.mii_monitor_interval.or(self.mii_monitor_interval);
+        self.min_links = other.min_links.or(self.min_links);
+        self.up_delay = other.up_delay.or(self.up_delay);
+        self.down_delay = other.down_delay.or(self.down_delay);
+        self.primary = other.primary.clone().or(self.primary.take());
+    }
+}
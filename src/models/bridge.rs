@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bridge_parameters::BridgeParameters,
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputBridge,
+    openvswitch::OpenVSwitch,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bridge {
+    #[serde(skip_serializing)]
+    name: String,
+    /// Names of the interfaces enslaved to the bridge, its ports.
+    #[serde(default)]
+    interfaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "BridgeParameters::is_empty")]
+    parameters: BridgeParameters,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    openvswitch: Option<OpenVSwitch>,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl Bridge {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            interfaces: Vec::new(),
+            parameters: BridgeParameters::default(),
+            openvswitch: None,
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    pub fn from_input_bridge(name: &str, input_bridge: &InputBridge) -> Self {
+        Self {
+            name: name.to_string(),
+            interfaces: input_bridge.interfaces.clone(),
+            parameters: BridgeParameters::default(),
+            openvswitch: None,
+            settings: DeviceSettings::from_input_device(&input_bridge.device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn interfaces(&self) -> Vec<String> {
+        self.interfaces.clone()
+    }
+
+    /// Replaces the members of the bridge, dropping the path costs of the former ones.
+    pub fn set_interfaces(&mut self, interfaces: Vec<String>) {
+        self.parameters
+            .path_cost
+            .retain(|port, _| interfaces.contains(port));
+        self.interfaces = interfaces;
+    }
+
+    pub fn parameters(&self) -> BridgeParameters {
+        self.parameters.clone()
+    }
+
+    pub fn set_parameters(&mut self, parameters: BridgeParameters) {
+        self.parameters = parameters;
+    }
+
+    pub fn openvswitch(&self) -> Option<OpenVSwitch> {
+        self.openvswitch.clone()
+    }
+
+    /// Sets the Open vSwitch settings, empty ones removing the `openvswitch` key.
+    pub fn set_openvswitch(&mut self, openvswitch: OpenVSwitch) {
+        self.openvswitch = Some(openvswitch).filter(|openvswitch| !openvswitch.is_empty());
+    }
+}
+
+impl WithDeviceSettings for Bridge {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::misc::serialize_hash_map_sorted;
+
+/// Highest bridge priority, lower values being preferred as root bridge.
+pub const MAX_BRIDGE_PRIORITY: u32 = 65535;
+
+/// Spanning tree settings of a bridge, written under its `parameters` key.
+///
+/// Unset fields are left out of the configuration, so netplan's defaults apply. Times are in seconds.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct BridgeParameters {
+    /// Whether the spanning tree protocol is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stp: Option<bool>,
+    /// Priority of the bridge, between 0 and 65535.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_delay: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hello_time: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+    /// Time after which learned MAC addresses are forgotten.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ageing_time: Option<u32>,
+    /// Cost of the path through each port, keyed by member interface.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    pub path_cost: HashMap<String, u32>,
+}
+
+impl BridgeParameters {
+    /// Whether no parameter is set at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Validates the bounds of the parameters.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(priority) = self.priority {
+            if priority > MAX_BRIDGE_PRIORITY {
+                return Err(format!(
+                    "The bridge priority must be between 0 and {MAX_BRIDGE_PRIORITY}, got {priority}."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Overlays the parameters set in `other` on these ones. Path costs are merged per port.
+    pub fn merge(&mut self, other: &BridgeParameters) {
+        self.stp = other.stp.or(self.stp);
+        self.priority = other.priority.or(self.priority);
+        self.forward_delay = other.forward_delay.or(self.forward_delay);
+        self.hello_time = other.hello_time.or(self.hello_time);
+        self.max_age = other.max_age.or(self.max_age);
+        self.ageing_time = other.ageing_time.or(self.ageing_time);
+        self.path_cost.extend(other.path_cost.clone());
+    }
+}
@@ -0,0 +1,840 @@
+use std::{
+    collections::HashMap,
+    fs::{self, DirBuilder},
+    io::{self, ErrorKind},
+    net::IpAddr,
+    os::unix::fs::DirBuilderExt,
+    path::{Path, PathBuf},
+    process::Child,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    command::{command_line, CommandRunner, SystemRunner},
+    netplan::{write_atomically, NetplanError},
+};
+
+use super::input_models::InputCapture;
+
+/// Where the captures are written, only readable by root.
+pub const CAPTURE_SPOOL_PATH: &str = "/var/spool/opensight-network/captures";
+/// How long a finished capture can be downloaded before it is deleted.
+pub const DEFAULT_CAPTURE_RETENTION: Duration = Duration::from_secs(15 * 60);
+/// How often the running captures are checked for having ended.
+pub const CAPTURE_SUPERVISION_PERIOD: Duration = Duration::from_secs(1);
+
+/// Longest capture, in seconds.
+pub const MAX_CAPTURE_DURATION: u32 = 60;
+const DEFAULT_CAPTURE_DURATION: u32 = 30;
+/// Most packets a capture keeps.
+pub const MAX_CAPTURE_PACKETS: u32 = 10_000;
+const DEFAULT_CAPTURE_PACKETS: u32 = 1_000;
+/// Most bytes kept of each packet, enough for a whole Ethernet frame.
+pub const MAX_CAPTURE_SNAPLEN: u32 = 1518;
+const MIN_CAPTURE_SNAPLEN: u32 = 64;
+
+/// How long tcpdump is given to flush its file once interrupted, before it is killed.
+const STOP_GRACE: Duration = Duration::from_secs(2);
+/// File the captures are journaled in, within the spool.
+const JOURNAL_FILE: &str = "journal.yaml";
+
+/// Protocols a capture can be limited to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureProtocol {
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6,
+    Arp,
+    /// DHCPv4, on UDP ports 67 and 68.
+    Dhcp,
+    /// DHCPv6, on UDP ports 546 and 547.
+    Dhcp6,
+}
+
+/// Which packets are captured, every one if no field is set.
+///
+/// Only these structured fields are accepted, and translated to a BPF expression, so that no
+/// filter string is ever handed to tcpdump as is.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct CaptureFilter {
+    pub protocol: Option<CaptureProtocol>,
+    /// Source or destination port, for `tcp` and `udp`, or for both if there is no protocol.
+    pub port: Option<u16>,
+    /// Source or destination address.
+    #[schema(value_type = Option<String>)]
+    pub host: Option<IpAddr>,
+}
+
+impl CaptureFilter {
+    /// Checks that the fields can be combined, e.g. that a port is only given to a protocol
+    /// having ports, and that the address family of the host matches the protocol.
+    pub fn validate(&self) -> Result<(), String> {
+        use CaptureProtocol::*;
+        if let (Some(protocol), Some(_)) = (self.protocol, self.port) {
+            if !matches!(protocol, Tcp | Udp) {
+                return Err(format!(
+                    "A port can only be captured for tcp or udp, not {}.",
+                    protocol.keyword()
+                ));
+            }
+        }
+        match (self.protocol, self.host) {
+            (Some(protocol @ (Icmp | Arp | Dhcp)), Some(host @ IpAddr::V6(_)))
+            | (Some(protocol @ (Icmp6 | Dhcp6)), Some(host @ IpAddr::V4(_))) => Err(format!(
+                "{host} is not of the address family of {}.",
+                protocol.keyword()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The BPF expression of the filter, as the separate arguments of tcpdump.
+    pub fn to_bpf(&self) -> Vec<String> {
+        let mut terms: Vec<Vec<String>> = vec![];
+        if let Some(protocol) = self.protocol {
+            terms.push(protocol.to_bpf());
+        }
+        if let Some(port) = self.port {
+            terms.push(vec!["port".to_string(), port.to_string()]);
+        }
+        if let Some(host) = self.host {
+            terms.push(vec!["host".to_string(), host.to_string()]);
+        }
+        terms.join(&"and".to_string())
+    }
+}
+
+impl CaptureProtocol {
+    /// Name of the protocol in the API and in BPF.
+    fn keyword(&self) -> &'static str {
+        match self {
+            CaptureProtocol::Tcp => "tcp",
+            CaptureProtocol::Udp => "udp",
+            CaptureProtocol::Icmp => "icmp",
+            CaptureProtocol::Icmp6 => "icmp6",
+            CaptureProtocol::Arp => "arp",
+            CaptureProtocol::Dhcp => "dhcp",
+            CaptureProtocol::Dhcp6 => "dhcp6",
+        }
+    }
+
+    fn to_bpf(self) -> Vec<String> {
+        let (server, client) = match self {
+            CaptureProtocol::Dhcp => (67, 68),
+            CaptureProtocol::Dhcp6 => (547, 546),
+            _ => return vec![self.keyword().to_string()],
+        };
+        format!("udp and ( port {server} or port {client} )")
+            .split(' ')
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// The bounds of a capture, once checked against the caps.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CaptureSettings {
+    /// How long the capture runs at most, in seconds.
+    pub duration: u32,
+    /// How many packets are captured at most.
+    pub packet_count: u32,
+    /// How many bytes are kept of each packet.
+    pub snaplen: u32,
+    pub filter: CaptureFilter,
+}
+
+impl CaptureSettings {
+    /// The settings asked for in `input`, with the defaults of what is left out.
+    ///
+    /// Settings over their cap are rejected, rather than silently capped.
+    pub fn from_input(input: &InputCapture) -> Result<Self, String> {
+        let bounded = |name: &str, value: Option<u32>, default: u32, min: u32, max: u32| {
+            let value = value.unwrap_or(default);
+            if (min..=max).contains(&value) {
+                Ok(value)
+            } else {
+                Err(format!("The {name} must be between {min} and {max}."))
+            }
+        };
+        let filter = input.filter.clone().unwrap_or_default();
+        filter.validate()?;
+        Ok(Self {
+            duration: bounded(
+                "duration",
+                input.duration,
+                DEFAULT_CAPTURE_DURATION,
+                1,
+                MAX_CAPTURE_DURATION,
+            )?,
+            packet_count: bounded(
+                "packet count",
+                input.packet_count,
+                DEFAULT_CAPTURE_PACKETS,
+                1,
+                MAX_CAPTURE_PACKETS,
+            )?,
+            snaplen: bounded(
+                "snaplen",
+                input.snaplen,
+                MAX_CAPTURE_SNAPLEN,
+                MIN_CAPTURE_SNAPLEN,
+                MAX_CAPTURE_SNAPLEN,
+            )?,
+            filter,
+        })
+    }
+
+    /// The arguments of tcpdump capturing on `interface` into the file at `path`.
+    ///
+    /// Packets are written as they come, so the file is complete whenever tcpdump is stopped, and
+    /// tcpdump keeps the privileges to write into the spool.
+    pub fn tcpdump_args(&self, interface: &str, path: &Path) -> Vec<String> {
+        [
+            "-i",
+            interface,
+            "-n",
+            "-U",
+            "-Z",
+            "root",
+            "-c",
+            &self.packet_count.to_string(),
+            "-s",
+            &self.snaplen.to_string(),
+            "-w",
+            &path.to_string_lossy(),
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .chain(self.filter.to_bpf())
+        .collect()
+    }
+}
+
+/// Where a capture is at.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureStatus {
+    Running,
+    /// The capture ended, and its file can be downloaded until the retention window is over.
+    Finished,
+    /// tcpdump failed, e.g. as the interface went away.
+    Failed,
+    /// The capture was cancelled, and its file deleted.
+    Cancelled,
+}
+
+/// A capture of the packets of an interface.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Capture {
+    pub id: String,
+    pub interface: String,
+    pub status: CaptureStatus,
+    pub settings: CaptureSettings,
+    /// When the capture started, in milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the capture ended, in milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// Why the capture failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What is journaled of a capture, to clean it up after a restart of the service.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+struct JournalEntry {
+    id: String,
+    interface: String,
+    /// Process of tcpdump, while it runs.
+    pid: Option<u32>,
+    started_at: u64,
+    status: CaptureStatus,
+}
+
+/// Why a capture can't be started.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("Packet capture is disabled on this server.")]
+    Disabled,
+    #[error("{interface} is already being captured by {id}.")]
+    Busy { interface: String, id: String },
+    #[error(transparent)]
+    Netplan(#[from] NetplanError),
+}
+
+/// A capture, along with its tcpdump while it runs.
+struct CaptureEntry {
+    capture: Capture,
+    child: Option<Child>,
+    /// When tcpdump is stopped, unless it captured all its packets before.
+    until: Instant,
+    /// When tcpdump was asked to stop, after which it is killed if it didn't.
+    stopping_since: Option<Instant>,
+    /// When the file of the finished capture is deleted.
+    expires: Option<Instant>,
+}
+
+/// The packet captures of the interfaces, run by tcpdump into a private spool directory.
+///
+/// The captures are disabled unless enabled by the administrator. Each interface is captured
+/// once at a time, and every capture is bounded in time, packets and size, while their files are
+/// deleted once the retention window is over. The captures are journaled in the spool, so those
+/// left behind by a previous run of the service are cleaned up by `recover`.
+pub struct CaptureStore {
+    runner: Box<dyn CommandRunner + Send + Sync>,
+    spool: PathBuf,
+    retention: Duration,
+    enabled: bool,
+    captures: Mutex<HashMap<String, CaptureEntry>>,
+}
+
+impl Default for CaptureStore {
+    fn default() -> Self {
+        Self::new(PathBuf::from(CAPTURE_SPOOL_PATH))
+    }
+}
+
+impl CaptureStore {
+    /// Disabled captures, written into `spool`.
+    pub fn new(spool: PathBuf) -> Self {
+        Self {
+            runner: Box::new(SystemRunner),
+            spool,
+            retention: DEFAULT_CAPTURE_RETENTION,
+            enabled: false,
+            captures: Mutex::default(),
+        }
+    }
+
+    /// Runs tcpdump through `runner` instead of starting it directly.
+    pub fn with_runner(mut self, runner: Box<dyn CommandRunner + Send + Sync>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Keeps the finished captures for `retention` instead of `DEFAULT_CAPTURE_RETENTION`.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Allows captures to be started.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.spool.join(JOURNAL_FILE)
+    }
+
+    /// Path of the file of the capture `id`.
+    fn capture_path(&self, id: &str) -> PathBuf {
+        self.spool.join(format!("{id}.pcap"))
+    }
+
+    /// Cleans up the captures of a previous run of the service: their tcpdump still running is
+    /// stopped, and every file of the spool is deleted, as they can't be downloaded anymore.
+    ///
+    /// A journaled process is only stopped if it is still a tcpdump, as its ID may have been
+    /// reused since. Returns the IDs of the captures cleaned up.
+    pub fn recover(&self) -> io::Result<Vec<String>> {
+        let journal: Vec<JournalEntry> = match fs::read_to_string(self.journal_path()) {
+            Ok(content) => serde_yml::from_str(&content).unwrap_or_else(|err| {
+                log::warn!("Ignoring the unreadable journal of the captures: {err}");
+                vec![]
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err),
+        };
+        for entry in journal.iter() {
+            if let Some(pid) = entry.pid.filter(|pid| is_tcpdump(*pid)) {
+                log::warn!(
+                    "Stopping tcpdump {pid}, left behind by capture {}.",
+                    entry.id
+                );
+                // SAFETY: `kill` only sends a signal, to a process checked to be a tcpdump
+                unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+            }
+        }
+        match fs::read_dir(&self.spool) {
+            Ok(files) => {
+                for file in files {
+                    fs::remove_file(file?.path())?;
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        Ok(journal.into_iter().map(|entry| entry.id).collect())
+    }
+
+    /// Starts capturing the packets of `interface` with `settings`, returning the capture.
+    pub fn start(
+        &self,
+        interface: &str,
+        settings: CaptureSettings,
+    ) -> Result<Capture, CaptureError> {
+        if !self.enabled {
+            return Err(CaptureError::Disabled);
+        }
+        self.refresh();
+        let mut captures = self.captures.lock().unwrap();
+        if let Some(running) = captures.values().find(|entry| {
+            entry.capture.interface == interface && entry.capture.status == CaptureStatus::Running
+        }) {
+            return Err(CaptureError::Busy {
+                interface: interface.to_string(),
+                id: running.capture.id.clone(),
+            });
+        }
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&self.spool)
+            .map_err(NetplanError::from)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let args = settings.tcpdump_args(interface, &self.capture_path(&id));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let child = self.runner.spawn("tcpdump", &args)?;
+        log::info!(
+            "Capture {id} started on {interface}: {}",
+            command_line("tcpdump", &args)
+        );
+        let entry = CaptureEntry {
+            until: Instant::now() + Duration::from_secs(settings.duration.into()),
+            capture: Capture {
+                id: id.clone(),
+                interface: interface.to_string(),
+                status: CaptureStatus::Running,
+                settings,
+                started_at: unix_millis(),
+                finished_at: None,
+                error: None,
+            },
+            child: Some(child),
+            stopping_since: None,
+            expires: None,
+        };
+        let capture = entry.capture.clone();
+        captures.insert(id, entry);
+        self.save_journal(&captures);
+        Ok(capture)
+    }
+
+    /// The capture `id`, as it is now.
+    pub fn get(&self, id: &str) -> Option<Capture> {
+        self.refresh();
+        let captures = self.captures.lock().unwrap();
+        captures.get(id).map(|entry| entry.capture.clone())
+    }
+
+    /// The file of the finished capture `id`.
+    ///
+    /// The error is of the `NotFound` kind if the capture has no file to download.
+    pub fn read_file(&self, id: &str) -> io::Result<Vec<u8>> {
+        let finished = self
+            .get(id)
+            .is_some_and(|capture| capture.status == CaptureStatus::Finished);
+        if !finished {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Capture {id} has no file to download."),
+            ));
+        }
+        fs::read(self.capture_path(id))
+    }
+
+    /// Cancels the capture `id` if it runs, and deletes it with its file otherwise.
+    ///
+    /// Returns `false` if there is no such capture.
+    pub fn cancel(&self, id: &str) -> bool {
+        self.refresh();
+        let mut captures = self.captures.lock().unwrap();
+        let Some(entry) = captures.get_mut(id) else {
+            return false;
+        };
+        if entry.child.is_some() {
+            entry.capture.status = CaptureStatus::Cancelled;
+            if entry.stopping_since.is_none() {
+                stop(entry);
+            }
+            log::info!("Capture {id} was cancelled.");
+        } else {
+            self.delete_file(id);
+            captures.remove(id);
+            log::info!("Capture {id} was deleted.");
+        }
+        self.save_journal(&captures);
+        true
+    }
+
+    /// Brings the captures up to date: tcpdump is stopped at the end of the duration of its
+    /// capture, the ones which exited are collected, and the captures whose retention window is
+    /// over are deleted.
+    pub fn refresh(&self) {
+        let mut captures = self.captures.lock().unwrap();
+        let now = Instant::now();
+        let mut changed = false;
+        for entry in captures.values_mut() {
+            let Some(child) = entry.child.as_mut() else {
+                continue;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    entry.child = None;
+                    entry.capture.finished_at = Some(unix_millis());
+                    // tcpdump exits successfully once it captured all its packets, and the
+                    // stand-ins of the tests end with the signal stopping them
+                    if entry.capture.status == CaptureStatus::Running {
+                        if status.success() || entry.stopping_since.is_some() {
+                            entry.capture.status = CaptureStatus::Finished;
+                        } else {
+                            entry.capture.status = CaptureStatus::Failed;
+                            entry.capture.error = Some(format!("tcpdump exited with {status}."));
+                        }
+                    }
+                    entry.expires = Some(now + self.retention);
+                    log::info!(
+                        "Capture {} ended: {:?}",
+                        entry.capture.id,
+                        entry.capture.status
+                    );
+                    changed = true;
+                }
+                Ok(None) => match entry.stopping_since {
+                    None if now >= entry.until => stop(entry),
+                    Some(since) if now.duration_since(since) >= STOP_GRACE => {
+                        let _ = child.kill();
+                    }
+                    _ => (),
+                },
+                Err(err) => log::error!(
+                    "Could not check the tcpdump of capture {}: {err}",
+                    entry.capture.id
+                ),
+            }
+        }
+        let over: Vec<String> = captures
+            .iter()
+            .filter(|(_, entry)| {
+                entry.capture.status == CaptureStatus::Cancelled && entry.child.is_none()
+                    || entry.expires.is_some_and(|expires| now >= expires)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in over {
+            self.delete_file(&id);
+            captures.remove(&id);
+            changed = true;
+        }
+        if changed {
+            self.save_journal(&captures);
+        }
+    }
+
+    /// Refreshes the captures every `period`, so that they are bounded even if nobody asks
+    /// about them.
+    pub async fn supervise_every(&self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            self.refresh();
+        }
+    }
+
+    fn delete_file(&self, id: &str) {
+        match fs::remove_file(self.capture_path(id)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                log::error!("Could not delete the file of capture {id}: {err}")
+            }
+            _ => (),
+        }
+    }
+
+    /// Journals `captures`. The captures go on if it fails, so it is only logged.
+    fn save_journal(&self, captures: &HashMap<String, CaptureEntry>) {
+        let mut journal: Vec<JournalEntry> = captures
+            .values()
+            .map(|entry| JournalEntry {
+                id: entry.capture.id.clone(),
+                interface: entry.capture.interface.clone(),
+                pid: entry.child.as_ref().map(Child::id),
+                started_at: entry.capture.started_at,
+                status: entry.capture.status.clone(),
+            })
+            .collect();
+        journal.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
+        let result = serde_yml::to_string(&journal)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))
+            .and_then(|content| write_atomically(&self.journal_path(), content.as_bytes()));
+        if let Err(err) = result {
+            log::error!("Could not journal the captures: {err}");
+        }
+    }
+}
+
+/// Asks the tcpdump of `entry` to stop, which makes it flush its file.
+fn stop(entry: &mut CaptureEntry) {
+    if let Some(child) = entry.child.as_ref() {
+        // SAFETY: `kill` only sends a signal, to a child which was not waited for yet, so whose
+        // process ID was not reused
+        unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+    }
+    entry.stopping_since = Some(Instant::now());
+}
+
+/// Whether the process `pid` is a tcpdump.
+fn is_tcpdump(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{pid}/comm")).is_ok_and(|comm| comm.trim() == "tcpdump")
+}
+
+/// Milliseconds since the Unix epoch.
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ScriptedRunner;
+
+    fn settings(duration: u32) -> CaptureSettings {
+        CaptureSettings::from_input(&InputCapture {
+            duration: Some(duration),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// A store capturing into a temporary spool, tcpdump standing in as scripted by `runner`.
+    fn capture_store(spool: &tempfile::TempDir, runner: &ScriptedRunner) -> CaptureStore {
+        CaptureStore::new(spool.path().to_path_buf())
+            .with_runner(Box::new(runner.clone()))
+            .with_enabled(true)
+    }
+
+    /// Refreshes `store` until capture `id` ended, for at most 10 seconds. A cancelled capture
+    /// ends by being deleted.
+    fn wait_for_end(store: &CaptureStore, id: &str) -> Option<Capture> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let capture = store.get(id);
+            if capture.as_ref().is_none_or(|capture| {
+                !matches!(
+                    capture.status,
+                    CaptureStatus::Running | CaptureStatus::Cancelled
+                )
+            }) || Instant::now() >= deadline
+            {
+                return capture;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn filters_are_translated_to_bpf() {
+        let filter = CaptureFilter {
+            protocol: Some(CaptureProtocol::Tcp),
+            port: Some(443),
+            host: Some("192.168.1.10".parse().unwrap()),
+        };
+        assert_eq!(
+            filter.to_bpf(),
+            ["tcp", "and", "port", "443", "and", "host", "192.168.1.10"]
+        );
+        let dhcp = CaptureFilter {
+            protocol: Some(CaptureProtocol::Dhcp),
+            ..Default::default()
+        };
+        assert_eq!(dhcp.to_bpf().join(" "), "udp and ( port 67 or port 68 )");
+        assert!(CaptureFilter::default().to_bpf().is_empty());
+    }
+
+    #[test]
+    fn filters_which_cannot_be_combined_are_rejected() {
+        let port_without_ports = CaptureFilter {
+            protocol: Some(CaptureProtocol::Icmp),
+            port: Some(53),
+            host: None,
+        };
+        assert!(port_without_ports.validate().is_err());
+        let wrong_family = CaptureFilter {
+            protocol: Some(CaptureProtocol::Dhcp6),
+            port: None,
+            host: Some("10.0.0.1".parse().unwrap()),
+        };
+        assert!(wrong_family.validate().is_err());
+        let port_alone = CaptureFilter {
+            port: Some(53),
+            ..Default::default()
+        };
+        assert!(port_alone.validate().is_ok());
+    }
+
+    #[test]
+    fn settings_are_bounded() {
+        let defaults = CaptureSettings::from_input(&InputCapture::default()).unwrap();
+        assert_eq!(defaults.duration, DEFAULT_CAPTURE_DURATION);
+        assert_eq!(defaults.packet_count, DEFAULT_CAPTURE_PACKETS);
+        assert_eq!(defaults.snaplen, MAX_CAPTURE_SNAPLEN);
+        for input in [
+            InputCapture {
+                duration: Some(MAX_CAPTURE_DURATION + 1),
+                ..Default::default()
+            },
+            InputCapture {
+                duration: Some(0),
+                ..Default::default()
+            },
+            InputCapture {
+                packet_count: Some(MAX_CAPTURE_PACKETS + 1),
+                ..Default::default()
+            },
+            InputCapture {
+                snaplen: Some(MIN_CAPTURE_SNAPLEN - 1),
+                ..Default::default()
+            },
+        ] {
+            assert!(CaptureSettings::from_input(&input).is_err());
+        }
+    }
+
+    #[test]
+    fn tcpdump_is_bounded_and_filtered() {
+        let settings = CaptureSettings::from_input(&InputCapture {
+            duration: Some(5),
+            packet_count: Some(100),
+            snaplen: Some(128),
+            filter: Some(CaptureFilter {
+                protocol: Some(CaptureProtocol::Arp),
+                ..Default::default()
+            }),
+        })
+        .unwrap();
+        assert_eq!(
+            settings
+                .tcpdump_args("eth0", Path::new("/spool/id.pcap"))
+                .join(" "),
+            "-i eth0 -n -U -Z root -c 100 -s 128 -w /spool/id.pcap arp"
+        );
+    }
+
+    #[test]
+    fn captures_are_refused_while_disabled() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default();
+        let store = capture_store(&spool, &runner).with_enabled(false);
+        assert!(matches!(
+            store.start("eth0", settings(1)),
+            Err(CaptureError::Disabled)
+        ));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn a_capture_is_stopped_at_the_end_of_its_duration() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("tcpdump", Duration::from_secs(30), "");
+        let store = capture_store(&spool, &runner);
+        let capture = store.start("eth0", settings(1)).unwrap();
+        assert_eq!(capture.status, CaptureStatus::Running);
+        assert!(runner.calls()[0].starts_with("tcpdump -i eth0 -n -U -Z root -c 1000"));
+        assert!(matches!(
+            store.start("eth0", settings(1)),
+            Err(CaptureError::Busy { .. })
+        ));
+        let capture = wait_for_end(&store, &capture.id).unwrap();
+        assert_eq!(capture.status, CaptureStatus::Finished);
+        assert!(capture.finished_at.is_some());
+        fs::write(store.capture_path(&capture.id), b"pcap").unwrap();
+        assert_eq!(store.read_file(&capture.id).unwrap(), b"pcap");
+        // Another capture of the interface can start once the first one is over
+        let next = store.start("eth0", settings(1)).unwrap();
+        assert!(store.cancel(&next.id));
+    }
+
+    #[test]
+    fn a_failing_tcpdump_fails_its_capture() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().failing("tcpdump", 1, "no such device");
+        let store = capture_store(&spool, &runner);
+        let capture = store.start("eth0", settings(1)).unwrap();
+        let capture = wait_for_end(&store, &capture.id).unwrap();
+        assert_eq!(capture.status, CaptureStatus::Failed);
+        assert!(capture.error.is_some());
+        assert_eq!(
+            store.read_file(&capture.id).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn a_missing_tcpdump_is_reported() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().missing("tcpdump");
+        let store = capture_store(&spool, &runner);
+        assert!(matches!(
+            store.start("eth0", settings(1)),
+            Err(CaptureError::Netplan(NetplanError::NotInstalled(_)))
+        ));
+    }
+
+    #[test]
+    fn a_cancelled_capture_is_deleted() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("tcpdump", Duration::from_secs(30), "");
+        let store = capture_store(&spool, &runner);
+        let capture = store.start("eth0", settings(60)).unwrap();
+        fs::write(store.capture_path(&capture.id), b"partial").unwrap();
+        assert!(store.cancel(&capture.id));
+        assert!(wait_for_end(&store, &capture.id).is_none());
+        assert!(!store.capture_path(&capture.id).exists());
+        assert!(!store.cancel(&capture.id));
+    }
+
+    #[test]
+    fn finished_captures_are_deleted_after_their_retention() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default();
+        let store = capture_store(&spool, &runner).with_retention(Duration::from_millis(100));
+        let capture = store.start("eth0", settings(1)).unwrap();
+        let capture = wait_for_end(&store, &capture.id).unwrap();
+        assert_eq!(capture.status, CaptureStatus::Finished);
+        fs::write(store.capture_path(&capture.id), b"pcap").unwrap();
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(store.get(&capture.id).is_none());
+        assert!(!store.capture_path(&capture.id).exists());
+    }
+
+    #[test]
+    fn the_captures_of_a_previous_run_are_cleaned_up() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("tcpdump", Duration::from_secs(30), "");
+        let store = capture_store(&spool, &runner);
+        let capture = store.start("eth0", settings(60)).unwrap();
+        fs::write(store.capture_path(&capture.id), b"partial").unwrap();
+        // The stand-in is a sleep, which a new run must leave alone
+        let recovered = capture_store(&spool, &runner).recover().unwrap();
+        assert_eq!(recovered, vec![capture.id.clone()]);
+        assert_eq!(fs::read_dir(spool.path()).unwrap().count(), 0);
+        assert_eq!(
+            store.get(&capture.id).unwrap().status,
+            CaptureStatus::Running
+        );
+        assert!(store.cancel(&capture.id));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{device::Device, ethernet::Ethernet};
+
+/// Keys of the serialized Ethernet that are reported as collections rather than fields.
+const COLLECTION_KEYS: [&str; 4] = ["addresses", "routes", "routing-policy", "nameservers"];
+
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct CollectionChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl CollectionChanges {
+    fn between(current: HashSet<String>, desired: HashSet<String>) -> Self {
+        let mut added: Vec<String> = desired.difference(&current).cloned().collect();
+        let mut removed: Vec<String> = current.difference(&desired).cloned().collect();
+        added.sort();
+        removed.sort();
+        Self { added, removed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Changes of the configuration file which are not in effect in the system yet.
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct PendingChanges {
+    pub pending: bool,
+    /// Interfaces whose system state differs from the configuration.
+    pub interfaces: Vec<String>,
+}
+
+/// Minimal set of changes needed to turn an Ethernet into another one.
+#[derive(Debug, Serialize, Default, ToSchema)]
+pub struct ChangeSet {
+    /// Names of the (non-collection) settings whose value changes.
+    pub fields: Vec<String>,
+    pub addresses: CollectionChanges,
+    /// Ids of the routes that are added or removed.
+    pub routes: CollectionChanges,
+    /// Ids of the routing policy rules that are added or removed.
+    pub routing_policy: CollectionChanges,
+    pub nameservers_addresses: CollectionChanges,
+    pub nameservers_search: CollectionChanges,
+}
+
+/// Settings of an Ethernet as they end up in the netplan YAML, without its collections.
+fn settings(ethernet: &Ethernet) -> serde_yml::Mapping {
+    let mut settings = match serde_yml::to_value(ethernet) {
+        Ok(serde_yml::Value::Mapping(settings)) => settings,
+        _ => serde_yml::Mapping::new(),
+    };
+    for key in COLLECTION_KEYS {
+        settings.remove(key);
+    }
+    settings
+}
+
+impl ChangeSet {
+    pub fn between(current: &Ethernet, desired: &Ethernet) -> Self {
+        let current_settings = settings(current);
+        let desired_settings = settings(desired);
+        let mut fields: Vec<String> = current_settings
+            .keys()
+            .chain(desired_settings.keys())
+            .filter(|key| current_settings.get(*key) != desired_settings.get(*key))
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        fields.sort();
+        fields.dedup();
+        let addresses = |ethernet: &Ethernet| {
+            ethernet
+                .get_addresses()
+                .iter()
+                .map(|address| address.to_string())
+                .collect()
+        };
+        let routes = |ethernet: &Ethernet| ethernet.get_routes().into_keys().collect();
+        let routing_policy =
+            |ethernet: &Ethernet| ethernet.get_routing_policy().into_keys().collect();
+        let nameservers_addresses = |ethernet: &Ethernet| {
+            ethernet
+                .get_nameservers()
+                .addresses
+                .iter()
+                .map(|address| address.to_string())
+                .collect()
+        };
+        let nameservers_search = |ethernet: &Ethernet| ethernet.get_nameservers().search;
+        Self {
+            fields,
+            addresses: CollectionChanges::between(addresses(current), addresses(desired)),
+            routes: CollectionChanges::between(routes(current), routes(desired)),
+            routing_policy: CollectionChanges::between(
+                routing_policy(current),
+                routing_policy(desired),
+            ),
+            nameservers_addresses: CollectionChanges::between(
+                nameservers_addresses(current),
+                nameservers_addresses(desired),
+            ),
+            nameservers_search: CollectionChanges::between(
+                nameservers_search(current),
+                nameservers_search(desired),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+            && self.addresses.is_empty()
+            && self.routes.is_empty()
+            && self.routing_policy.is_empty()
+            && self.nameservers_addresses.is_empty()
+            && self.nameservers_search.is_empty()
+    }
+}
+
+/// An Ethernet together with the changes that were (or would be) applied to get it.
+#[derive(Debug, Serialize)]
+pub struct EthernetChanges {
+    pub ethernet: Ethernet,
+    pub changes: ChangeSet,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_types::IpNetwork;
+    use crate::models::{device::MTU, route::Route};
+
+    fn address(address: &str) -> IpNetwork {
+        address.parse().unwrap()
+    }
+
+    fn ethernet(addresses: &[&str]) -> Ethernet {
+        let mut ethernet = Ethernet::new("eth0".to_string());
+        for address in addresses {
+            ethernet.add_address(&self::address(address));
+        }
+        ethernet
+    }
+
+    #[test]
+    fn identical_ethernets_have_no_changes() {
+        let mut current = ethernet(&["10.0.0.2/24"]);
+        current.add_route(&Route::new(
+            "0.0.0.0".parse().unwrap(),
+            Some("10.0.0.1".parse().unwrap()),
+            None,
+        ));
+        let changes = ChangeSet::between(&current, &current.clone());
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn additions_only_are_reported_as_added() {
+        let current = ethernet(&["10.0.0.2/24"]);
+        let mut desired = ethernet(&["10.0.0.2/24", "10.0.0.3/24"]);
+        desired.add_nameservers_search("example.com");
+        let changes = ChangeSet::between(&current, &desired);
+        assert_eq!(changes.addresses.added, ["10.0.0.3/24"]);
+        assert!(changes.addresses.removed.is_empty());
+        assert_eq!(changes.nameservers_search.added, ["example.com"]);
+        assert!(changes.fields.is_empty());
+    }
+
+    #[test]
+    fn removals_only_are_reported_as_removed() {
+        let mut current = ethernet(&["10.0.0.2/24", "fd00::2/64"]);
+        let route = Route::new(
+            "10.1.0.0".parse().unwrap(),
+            Some("10.0.0.1".parse().unwrap()),
+            None,
+        );
+        current.add_route(&route);
+        let desired = ethernet(&["10.0.0.2/24"]);
+        let changes = ChangeSet::between(&current, &desired);
+        assert!(changes.addresses.added.is_empty());
+        assert_eq!(changes.addresses.removed, ["fd00::2/64"]);
+        assert_eq!(changes.routes.removed, [route.id()]);
+        assert!(changes.fields.is_empty());
+    }
+
+    #[test]
+    fn mixed_changes_report_fields_and_collections() {
+        let current = ethernet(&["10.0.0.2/24"]);
+        let mut desired = ethernet(&["10.0.0.3/24"]);
+        desired.set_dhcp6(true);
+        desired.set_mtu(Some(MTU::new(1400).unwrap()));
+        let changes = ChangeSet::between(&current, &desired);
+        assert_eq!(changes.fields, ["dhcp6", "mtu"]);
+        assert_eq!(changes.addresses.added, ["10.0.0.3/24"]);
+        assert_eq!(changes.addresses.removed, ["10.0.0.2/24"]);
+        assert!(!changes.is_empty());
+    }
+}
@@ -1,37 +1,148 @@
 use std::{
     collections::{HashMap, HashSet},
-    net::{IpAddr, SocketAddr},
+    net::IpAddr,
 };
 
-use super::{input_models::InputDevice, nameservers::Nameservers, route::Route};
-use crate::custom_types::BoundedU32;
+use super::{
+    device_match::Match, dhcp_overrides::DhcpOverrides, nameservers::Nameservers,
+    network::NetworkRenderer, offloads::Offloads, route::Route, routing_policy::RoutingPolicy,
+};
+use crate::custom_types::{BoundedU32, IpNetwork, MacAddress};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 pub type MTU = BoundedU32<68, 64000>;
 pub type MTUV6 = BoundedU32<1280, 64000>;
 
+/// Address families for which link-local addresses are configured.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkLocalKind {
+    Ipv4,
+    Ipv6,
+}
+
+/// Identifier sent to the DHCP server to obtain a lease.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DhcpIdentifier {
+    /// The DHCP unique identifier, netplan's default.
+    Duid,
+    /// The MAC address of the interface.
+    Mac,
+}
+
+/// Method used to generate the IPv6 addresses autoconfigured from router advertisements.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Ipv6AddressGeneration {
+    /// The interface identifier is derived from the MAC address.
+    Eui64,
+    /// Stable, opaque interface identifiers (RFC 7217).
+    StablePrivacy,
+}
+
+/// How the interface is brought up, when not automatically (netplan 0.103+).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActivationMode {
+    /// The interface is configured, but left to be brought up by the administrator.
+    Manual,
+    /// The interface is configured, but kept down.
+    Off,
+}
+
+/// Highest number of SR-IOV virtual functions that can be configured on an interface.
+pub const MAX_VIRTUAL_FUNCTION_COUNT: u16 = 255;
+
+/// Validates the number of SR-IOV virtual functions to create on a physical function.
+pub fn validate_virtual_function_count(count: u16) -> Result<(), String> {
+    if count > MAX_VIRTUAL_FUNCTION_COUNT {
+        return Err(format!(
+            "The virtual function count must be between 0 and {MAX_VIRTUAL_FUNCTION_COUNT}, got {count}."
+        ));
+    }
+    Ok(())
+}
+
+/// Validates an interface name against the rules of the kernel.
+pub fn validate_interface_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 15 {
+        return Err(format!(
+            "Interface name '{name}' must be between 1 and 15 characters long."
+        ));
+    }
+    if name == "." || name == ".." || name.contains('/') || name.contains(char::is_whitespace) {
+        return Err(format!(
+            "Interface name '{name}' must not contain '/' or whitespace, nor be '.' or '..'."
+        ));
+    }
+    Ok(())
+}
+
 pub trait Device {
-    fn from_input_device(name: &str, input_device: &InputDevice) -> Self;
-    fn update_from_device(&mut self, device: &impl Device);
     // DHCP stuff
     fn set_dhcp4(&mut self, set: bool);
     fn get_dhcp4(&self) -> bool;
     fn get_dhcp6(&self) -> bool;
     fn set_dhcp6(&mut self, set: bool);
+    fn get_dhcp_identifier(&self) -> Option<DhcpIdentifier>;
+    fn set_dhcp_identifier(&mut self, identifier: Option<DhcpIdentifier>);
+    fn get_dhcp4_overrides(&self) -> Option<DhcpOverrides>;
+    fn set_dhcp4_overrides(&mut self, overrides: Option<DhcpOverrides>);
+    fn get_dhcp6_overrides(&self) -> Option<DhcpOverrides>;
+    fn set_dhcp6_overrides(&mut self, overrides: Option<DhcpOverrides>);
     // ACCEPT_RA
     fn set_accept_ra(&mut self, set: Option<bool>);
     fn get_accept_ra(&self) -> Option<bool>;
+    fn get_ipv6_address_generation(&self) -> Option<Ipv6AddressGeneration>;
+    fn set_ipv6_address_generation(&mut self, generation: Option<Ipv6AddressGeneration>);
     // MTU
     fn get_mtu(&self) -> Option<MTU>;
     fn set_mtu(&mut self, mtu: Option<MTU>);
     fn set_ipv6_mtu(&mut self, mtu: Option<MTUV6>);
     fn get_ipv6_mtu(&self) -> Option<MTUV6>;
+    // IPV6 PRIVACY
+    fn get_ipv6_privacy(&self) -> Option<bool>;
+    fn set_ipv6_privacy(&mut self, ipv6_privacy: Option<bool>);
+    // RENDERER
+    fn get_renderer(&self) -> Option<NetworkRenderer>;
+    fn set_renderer(&mut self, renderer: Option<NetworkRenderer>);
+    // ACTIVATION-MODE
+    fn get_activation_mode(&self) -> Option<ActivationMode>;
+    fn set_activation_mode(&mut self, activation_mode: Option<ActivationMode>);
+    // VIRTUAL-FUNCTION-COUNT
+    fn get_virtual_function_count(&self) -> Option<u16>;
+    fn set_virtual_function_count(&mut self, count: Option<u16>);
+    // OFFLOADS
+    fn get_offloads(&self) -> Option<Offloads>;
+    fn set_offloads(&mut self, offloads: Option<Offloads>);
+    // OPTIONAL
+    fn get_optional(&self) -> Option<bool>;
+    fn set_optional(&mut self, optional: Option<bool>);
+    // WAKE-ON-LAN
+    fn get_wakeonlan(&self) -> Option<bool>;
+    fn set_wakeonlan(&mut self, wakeonlan: Option<bool>);
+    // SET-NAME
+    fn get_set_name(&self) -> Option<String>;
+    fn set_set_name(&mut self, set_name: Option<String>);
+    // MATCH
+    fn get_match(&self) -> Option<Match>;
+    fn set_match(&mut self, device_match: Option<Match>);
+    // MAC ADDRESS
+    fn get_macaddress(&self) -> Option<MacAddress>;
+    fn set_macaddress(&mut self, macaddress: Option<MacAddress>);
+    // LINK-LOCAL
+    fn get_link_local(&self) -> Option<Vec<LinkLocalKind>>;
+    fn set_link_local(&mut self, link_local: Option<Vec<LinkLocalKind>>);
     // ADDRESSES
-    fn get_addresses(&self) -> HashSet<SocketAddr>;
+    fn get_addresses(&self) -> HashSet<IpNetwork>;
     // fn add_address(&mut self, address: IpAddr);
-    fn add_address(&mut self, address: &SocketAddr);
+    fn add_address(&mut self, address: &IpNetwork);
+    fn set_addresses(&mut self, addresses: HashSet<IpNetwork>);
     fn get_dynamic_addresses(&self) -> Vec<String>;
     fn set_dynamic_addresses(&mut self, addresses: &[String]);
-    fn delete_address(&mut self, address: &SocketAddr) -> bool;
+    fn delete_address(&mut self, address: &IpNetwork) -> bool;
     // NAMESERVERS
     fn get_nameservers(&self) -> Nameservers;
     fn add_nameservers(&mut self, nameservers: Nameservers);
@@ -40,6 +151,7 @@ pub trait Device {
     fn add_nameservers_address(&mut self, address: &IpAddr);
     fn delete_nameservers_search(&mut self, search: &str) -> bool;
     fn delete_nameservers_address(&mut self, address: &IpAddr) -> bool;
+    fn clear_nameservers(&mut self);
     // ROUTES
     fn get_routes(&self) -> HashMap<String, Route>;
     fn add_route(&mut self, route: &Route);
@@ -47,6 +159,10 @@ pub trait Device {
     // fn add_gateway_route(&mut self, via: Option<IpAddr>, from: Option<IpAddr>);
     fn delete_route(&mut self, route_id: &str) -> bool;
     fn delete_all_routes(&mut self);
+    // ROUTING POLICY
+    fn get_routing_policy(&self) -> HashMap<String, RoutingPolicy>;
+    fn add_routing_policy(&mut self, routing_policy: &RoutingPolicy);
+    fn delete_routing_policy(&mut self, routing_policy_id: &str) -> bool;
     fn get_system_state(&self) -> HashMap<String, serde_yml::Value>;
     fn set_system_state(&mut self, state: HashMap<String, serde_yml::Value>);
 }
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::custom_types::IpNetwork;
+
+use super::{device::Device, network::Network};
+
+/// Kind of a listed device, i.e. the section of the configuration it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceKind {
+    Ethernet,
+    Vlan,
+    Bridge,
+    Bond,
+    Wifi,
+    Tunnel,
+    Dummy,
+    Vrf,
+    /// A physical interface of the system, not part of the configuration.
+    Unmanaged,
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceKind::Ethernet => "Ethernet",
+            DeviceKind::Vlan => "VLAN",
+            DeviceKind::Bridge => "bridge",
+            DeviceKind::Bond => "bond",
+            DeviceKind::Wifi => "WiFi",
+            DeviceKind::Tunnel => "tunnel",
+            DeviceKind::Dummy => "dummy device",
+            DeviceKind::Vrf => "VRF",
+            DeviceKind::Unmanaged => "unmanaged interface",
+        })
+    }
+}
+
+/// The bridge or bond an interface is a member of, which owns its L3 configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct Enslavement {
+    pub kind: DeviceKind,
+    pub name: String,
+}
+
+/// Addressing and enslavement of a listed device.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeviceSummary {
+    /// Static addresses, sorted.
+    #[schema(value_type = Vec<String>)]
+    pub addresses: Vec<IpNetwork>,
+    pub dhcp4: bool,
+    pub dhcp6: bool,
+    /// The bridge or bond the device is a member of, if any.
+    pub member_of: Option<String>,
+    /// The VRF the device is bound to, if any.
+    pub vrf: Option<String>,
+}
+
+/// An entry of the unified device listing.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeviceListing {
+    pub name: String,
+    pub kind: DeviceKind,
+    pub summary: DeviceSummary,
+    /// Whether changes to the device only take full effect after a reboot.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pending_reboot: bool,
+}
+
+impl DeviceListing {
+    fn new(network: &Network, name: &str, kind: DeviceKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            summary: DeviceSummary {
+                addresses: vec![],
+                dhcp4: false,
+                dhcp6: false,
+                member_of: network.master_of(name),
+                vrf: network.vrf_of(name),
+            },
+            pending_reboot: false,
+        }
+    }
+
+    fn of_device(network: &Network, name: &str, kind: DeviceKind, device: &impl Device) -> Self {
+        let mut result = Self::new(network, name, kind);
+        let mut addresses: Vec<IpNetwork> = device.get_addresses().into_iter().collect();
+        addresses.sort();
+        result.summary.addresses = addresses;
+        result.summary.dhcp4 = device.get_dhcp4();
+        result.summary.dhcp6 = device.get_dhcp6();
+        result
+    }
+}
+
+/// Lists the devices of every kind in the configuration, followed by the `unmanaged` interfaces
+/// of the system, each group sorted by name.
+pub fn list_devices(network: &Network, unmanaged: &[String]) -> Vec<DeviceListing> {
+    let mut result = vec![];
+    let mut push = |mut listings: Vec<DeviceListing>| {
+        listings.sort_by(|a, b| a.name.cmp(&b.name));
+        result.extend(listings);
+    };
+    push(
+        network
+            .get_ethernets()
+            .iter()
+            .map(|(name, ethernet)| {
+                let mut listing =
+                    DeviceListing::of_device(network, name, DeviceKind::Ethernet, ethernet);
+                listing.pending_reboot = !ethernet.pending_reboot_reasons().is_empty();
+                listing
+            })
+            .collect(),
+    );
+    push(
+        network
+            .get_vlans()
+            .iter()
+            .map(|(name, vlan)| DeviceListing::of_device(network, name, DeviceKind::Vlan, vlan))
+            .collect(),
+    );
+    push(
+        network
+            .get_bridges()
+            .iter()
+            .map(|(name, bridge)| {
+                DeviceListing::of_device(network, name, DeviceKind::Bridge, bridge)
+            })
+            .collect(),
+    );
+    push(
+        network
+            .get_bonds()
+            .iter()
+            .map(|(name, bond)| DeviceListing::of_device(network, name, DeviceKind::Bond, bond))
+            .collect(),
+    );
+    push(
+        network
+            .get_wifis()
+            .iter()
+            .map(|(name, wifi)| DeviceListing::of_device(network, name, DeviceKind::Wifi, wifi))
+            .collect(),
+    );
+    push(
+        network
+            .get_tunnels()
+            .iter()
+            .map(|(name, tunnel)| {
+                DeviceListing::of_device(network, name, DeviceKind::Tunnel, tunnel)
+            })
+            .collect(),
+    );
+    push(
+        network
+            .get_dummy_devices()
+            .iter()
+            .map(|(name, dummy)| DeviceListing::of_device(network, name, DeviceKind::Dummy, dummy))
+            .collect(),
+    );
+    push(
+        network
+            .get_vrfs()
+            .keys()
+            .map(|name| DeviceListing::new(network, name, DeviceKind::Vrf))
+            .collect(),
+    );
+    push(
+        unmanaged
+            .iter()
+            .map(|name| DeviceListing::new(network, name, DeviceKind::Unmanaged))
+            .collect(),
+    );
+    result
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::custom_types::MacAddress;
+
+/// Selects the physical interfaces a configuration applies to, instead of relying on their names.
+///
+/// All the given matchers must match for an interface to be selected.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct Match {
+    /// Interface name, supporting shell-style globs (`enp*`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Permanent MAC address of the interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macaddress: Option<MacAddress>,
+    /// Kernel driver of the interface, supporting shell-style globs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+}
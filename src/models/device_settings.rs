@@ -0,0 +1,428 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    custom_types::{IpNetwork, MacAddress},
+    misc::{
+        serialize_hash_map_values_as_yaml_sequence, serialize_hash_set_as_sorted_yaml_sequence,
+    },
+};
+
+use super::{
+    device::{
+        ActivationMode, Device, DhcpIdentifier, Ipv6AddressGeneration, LinkLocalKind, MTU, MTUV6,
+    },
+    device_match::Match,
+    dhcp_overrides::DhcpOverrides,
+    input_models::InputDevice,
+    nameservers::Nameservers,
+    network::{ConfigOrigin, NetworkRenderer},
+    offloads::Offloads,
+    route::Route,
+    routing_policy::RoutingPolicy,
+};
+
+/// Settings shared by every kind of virtual device (VLANs, bridges, bonds...).
+///
+/// Kept flattened into the stanza of the device. The settings only physical interfaces have
+/// (`match`, `set-name`, `wakeonlan`, SR-IOV and offloads) are always unset, and setting them
+/// has no effect.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct DeviceSettings {
+    #[serde(default)]
+    dhcp4: bool,
+    #[serde(default)]
+    dhcp6: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp4_overrides: Option<DhcpOverrides>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp6_overrides: Option<DhcpOverrides>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp_identifier: Option<DhcpIdentifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mtu: Option<MTU>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6_mtu: Option<MTUV6>,
+    /// `Some(vec![])` is kept and written as `link-local: []`, disabling link-local addressing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_local: Option<Vec<LinkLocalKind>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    optional: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6_privacy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    macaddress: Option<MacAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accept_ra: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6_address_generation: Option<Ipv6AddressGeneration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    renderer: Option<NetworkRenderer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activation_mode: Option<ActivationMode>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
+    routes: HashMap<String, Route>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
+    routing_policy: HashMap<String, RoutingPolicy>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_hash_set_as_sorted_yaml_sequence"
+    )]
+    addresses: HashSet<IpNetwork>,
+    #[serde(default)]
+    nameservers: Nameservers,
+    #[serde(skip)]
+    dynamic_addresses: Vec<String>,
+    #[serde(skip_serializing, default)]
+    system_state: HashMap<String, serde_yml::Value>,
+    /// Read only: the other netplan configuration file defining the device, e.g.
+    /// `50-cloud-init.yaml`. Such devices are left to that file, and can't be changed through
+    /// the API.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    managed_by: Option<String>,
+}
+
+impl DeviceSettings {
+    pub fn from_input_device(input_device: &InputDevice) -> Self {
+        let mut result = Self::default();
+        input_device.update(&mut result);
+        result
+    }
+}
+
+impl PartialEq for DeviceSettings {
+    /// Only compares the configuration, ignoring the information gathered from the running system.
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            dhcp4,
+            dhcp6,
+            dhcp4_overrides,
+            dhcp6_overrides,
+            dhcp_identifier,
+            mtu,
+            ipv6_mtu,
+            link_local,
+            optional,
+            ipv6_privacy,
+            macaddress,
+            accept_ra,
+            ipv6_address_generation,
+            renderer,
+            activation_mode,
+            routes,
+            routing_policy,
+            addresses,
+            nameservers,
+            dynamic_addresses: _,
+            system_state: _,
+            managed_by: _,
+        } = self;
+        *dhcp4 == other.dhcp4
+            && *dhcp6 == other.dhcp6
+            && *dhcp4_overrides == other.dhcp4_overrides
+            && *dhcp6_overrides == other.dhcp6_overrides
+            && *dhcp_identifier == other.dhcp_identifier
+            && *mtu == other.mtu
+            && *ipv6_mtu == other.ipv6_mtu
+            && *link_local == other.link_local
+            && *optional == other.optional
+            && *ipv6_privacy == other.ipv6_privacy
+            && *macaddress == other.macaddress
+            && *accept_ra == other.accept_ra
+            && *ipv6_address_generation == other.ipv6_address_generation
+            && *renderer == other.renderer
+            && *activation_mode == other.activation_mode
+            && *routes == other.routes
+            && *routing_policy == other.routing_policy
+            && *addresses == other.addresses
+            && *nameservers == other.nameservers
+    }
+}
+
+impl Eq for DeviceSettings {}
+
+/// Devices keeping their common settings in a `DeviceSettings`, from which they get their
+/// `Device` implementation.
+pub trait WithDeviceSettings {
+    fn settings(&self) -> &DeviceSettings;
+    fn settings_mut(&mut self) -> &mut DeviceSettings;
+}
+
+impl WithDeviceSettings for DeviceSettings {
+    fn settings(&self) -> &DeviceSettings {
+        self
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        self
+    }
+}
+
+impl<T: WithDeviceSettings> ConfigOrigin for T {
+    fn managed_by(&self) -> Option<String> {
+        self.settings().managed_by.clone()
+    }
+
+    fn set_managed_by(&mut self, file: Option<String>) {
+        self.settings_mut().managed_by = file;
+    }
+}
+
+impl<T: WithDeviceSettings> Device for T {
+    fn set_dhcp4(&mut self, set: bool) {
+        self.settings_mut().dhcp4 = set;
+    }
+
+    fn get_dhcp4(&self) -> bool {
+        self.settings().dhcp4
+    }
+
+    fn get_dhcp6(&self) -> bool {
+        self.settings().dhcp6
+    }
+
+    fn set_dhcp6(&mut self, set: bool) {
+        self.settings_mut().dhcp6 = set;
+    }
+
+    fn get_dhcp_identifier(&self) -> Option<DhcpIdentifier> {
+        self.settings().dhcp_identifier
+    }
+
+    fn set_dhcp_identifier(&mut self, identifier: Option<DhcpIdentifier>) {
+        self.settings_mut().dhcp_identifier = identifier;
+    }
+
+    fn get_dhcp4_overrides(&self) -> Option<DhcpOverrides> {
+        self.settings().dhcp4_overrides.clone()
+    }
+
+    fn set_dhcp4_overrides(&mut self, overrides: Option<DhcpOverrides>) {
+        self.settings_mut().dhcp4_overrides = overrides.filter(|overrides| !overrides.is_empty());
+    }
+
+    fn get_dhcp6_overrides(&self) -> Option<DhcpOverrides> {
+        self.settings().dhcp6_overrides.clone()
+    }
+
+    fn set_dhcp6_overrides(&mut self, overrides: Option<DhcpOverrides>) {
+        self.settings_mut().dhcp6_overrides = overrides.filter(|overrides| !overrides.is_empty());
+    }
+
+    fn set_accept_ra(&mut self, set: Option<bool>) {
+        self.settings_mut().accept_ra = set;
+    }
+
+    fn get_accept_ra(&self) -> Option<bool> {
+        self.settings().accept_ra
+    }
+
+    fn get_ipv6_address_generation(&self) -> Option<Ipv6AddressGeneration> {
+        self.settings().ipv6_address_generation
+    }
+
+    fn set_ipv6_address_generation(&mut self, generation: Option<Ipv6AddressGeneration>) {
+        self.settings_mut().ipv6_address_generation = generation;
+    }
+
+    fn get_mtu(&self) -> Option<MTU> {
+        self.settings().mtu
+    }
+
+    fn set_mtu(&mut self, mtu: Option<MTU>) {
+        self.settings_mut().mtu = mtu;
+    }
+
+    fn set_ipv6_mtu(&mut self, mtu: Option<MTUV6>) {
+        self.settings_mut().ipv6_mtu = mtu;
+    }
+
+    fn get_ipv6_mtu(&self) -> Option<MTUV6> {
+        self.settings().ipv6_mtu
+    }
+
+    fn get_ipv6_privacy(&self) -> Option<bool> {
+        self.settings().ipv6_privacy
+    }
+
+    fn set_ipv6_privacy(&mut self, ipv6_privacy: Option<bool>) {
+        self.settings_mut().ipv6_privacy = ipv6_privacy;
+    }
+
+    fn get_renderer(&self) -> Option<NetworkRenderer> {
+        self.settings().renderer
+    }
+
+    fn set_renderer(&mut self, renderer: Option<NetworkRenderer>) {
+        self.settings_mut().renderer = renderer;
+    }
+
+    fn get_activation_mode(&self) -> Option<ActivationMode> {
+        self.settings().activation_mode
+    }
+
+    fn set_activation_mode(&mut self, activation_mode: Option<ActivationMode>) {
+        self.settings_mut().activation_mode = activation_mode;
+    }
+
+    fn get_virtual_function_count(&self) -> Option<u16> {
+        None
+    }
+
+    fn set_virtual_function_count(&mut self, _count: Option<u16>) {}
+
+    fn get_offloads(&self) -> Option<Offloads> {
+        None
+    }
+
+    fn set_offloads(&mut self, _offloads: Option<Offloads>) {}
+
+    fn get_optional(&self) -> Option<bool> {
+        self.settings().optional
+    }
+
+    fn set_optional(&mut self, optional: Option<bool>) {
+        self.settings_mut().optional = optional;
+    }
+
+    fn get_wakeonlan(&self) -> Option<bool> {
+        None
+    }
+
+    fn set_wakeonlan(&mut self, _wakeonlan: Option<bool>) {}
+
+    fn get_set_name(&self) -> Option<String> {
+        None
+    }
+
+    fn set_set_name(&mut self, _set_name: Option<String>) {}
+
+    fn get_match(&self) -> Option<Match> {
+        None
+    }
+
+    fn set_match(&mut self, _device_match: Option<Match>) {}
+
+    fn get_macaddress(&self) -> Option<MacAddress> {
+        self.settings().macaddress
+    }
+
+    fn set_macaddress(&mut self, macaddress: Option<MacAddress>) {
+        self.settings_mut().macaddress = macaddress;
+    }
+
+    fn get_link_local(&self) -> Option<Vec<LinkLocalKind>> {
+        self.settings().link_local.clone()
+    }
+
+    fn set_link_local(&mut self, link_local: Option<Vec<LinkLocalKind>>) {
+        self.settings_mut().link_local = link_local;
+    }
+
+    fn get_addresses(&self) -> HashSet<IpNetwork> {
+        self.settings().addresses.clone()
+    }
+
+    fn add_address(&mut self, address: &IpNetwork) {
+        self.settings_mut().addresses.insert(*address);
+    }
+
+    fn set_addresses(&mut self, addresses: HashSet<IpNetwork>) {
+        self.settings_mut().addresses = addresses;
+    }
+
+    fn get_dynamic_addresses(&self) -> Vec<String> {
+        self.settings().dynamic_addresses.clone()
+    }
+
+    fn set_dynamic_addresses(&mut self, addresses: &[String]) {
+        self.settings_mut().dynamic_addresses = addresses.into();
+    }
+
+    fn delete_address(&mut self, address: &IpNetwork) -> bool {
+        self.settings_mut().addresses.remove(address)
+    }
+
+    fn get_nameservers(&self) -> Nameservers {
+        self.settings().nameservers.clone()
+    }
+
+    fn add_nameservers(&mut self, nameservers: Nameservers) {
+        self.settings_mut().nameservers = nameservers;
+    }
+
+    fn add_nameservers_search(&mut self, search: &str) {
+        self.settings_mut().nameservers.add_search(search);
+    }
+
+    fn add_nameservers_address(&mut self, address: &IpAddr) {
+        self.settings_mut().nameservers.add_address(address);
+    }
+
+    fn delete_nameservers_search(&mut self, search: &str) -> bool {
+        self.settings_mut().nameservers.remove_search(search)
+    }
+
+    fn delete_nameservers_address(&mut self, address: &IpAddr) -> bool {
+        self.settings_mut().nameservers.remove_address(address)
+    }
+
+    fn clear_nameservers(&mut self) {
+        self.settings_mut().nameservers = Nameservers::new();
+    }
+
+    fn get_routes(&self) -> HashMap<String, Route> {
+        self.settings().routes.clone()
+    }
+
+    fn add_route(&mut self, route: &Route) {
+        self.settings_mut().routes.insert(route.id(), *route);
+    }
+
+    fn delete_route(&mut self, route_id: &str) -> bool {
+        self.settings_mut().routes.remove(route_id).is_some()
+    }
+
+    fn delete_all_routes(&mut self) {
+        self.settings_mut().routes = HashMap::new();
+    }
+
+    fn get_routing_policy(&self) -> HashMap<String, RoutingPolicy> {
+        self.settings().routing_policy.clone()
+    }
+
+    fn add_routing_policy(&mut self, routing_policy: &RoutingPolicy) {
+        self.settings_mut()
+            .routing_policy
+            .insert(routing_policy.id(), *routing_policy);
+    }
+
+    fn delete_routing_policy(&mut self, routing_policy_id: &str) -> bool {
+        self.settings_mut()
+            .routing_policy
+            .remove(routing_policy_id)
+            .is_some()
+    }
+
+    fn get_system_state(&self) -> HashMap<String, serde_yml::Value> {
+        self.settings().system_state.clone()
+    }
+
+    fn set_system_state(&mut self, state: HashMap<String, serde_yml::Value>) {
+        self.settings_mut().system_state = state;
+    }
+}
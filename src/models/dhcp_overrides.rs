@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Overrides of the settings received from a DHCP server.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct DhcpOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_dns: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_ntp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_routes: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_hostname: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_hostname: Option<bool>,
+    /// Hostname sent to the DHCP server instead of the machine's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Metric of the routes received from the DHCP server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route_metric: Option<u32>,
+}
+
+impl DhcpOverrides {
+    /// Whether no setting is overridden at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
@@ -0,0 +1,634 @@
+use std::{
+    fs, io,
+    mem::MaybeUninit,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use utoipa::ToSchema;
+
+use crate::{custom_types::MacAddress, system::SYS_CLASS_NET_PATH};
+
+/// How long the segment is listened to for answers by default.
+pub const DHCP_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const DHCP_SERVER_PORT: u16 = 67;
+const DHCP_CLIENT_PORT: u16 = 68;
+const DHCPV6_SERVER_PORT: u16 = 547;
+const DHCPV6_CLIENT_PORT: u16 = 546;
+/// `ff02::2`, every router of the link.
+const ALL_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+/// `ff02::1:2`, every DHCPv6 server and relay agent of the link.
+const ALL_DHCP_SERVERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 1, 2);
+
+/// Magic cookie starting the options of a DHCP message, after the fixed BOOTP fields.
+const DHCP_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Length of the fixed BOOTP fields.
+const BOOTP_LENGTH: usize = 236;
+/// Some servers ignore BOOTP messages shorter than this.
+const BOOTP_MINIMUM_LENGTH: usize = 300;
+const DHCP_OPTION_PAD: u8 = 0;
+const DHCP_OPTION_MESSAGE_TYPE: u8 = 53;
+const DHCP_OPTION_SERVER_IDENTIFIER: u8 = 54;
+const DHCP_OPTION_PARAMETER_REQUEST_LIST: u8 = 55;
+const DHCP_OPTION_END: u8 = 255;
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+
+const ICMPV6_ROUTER_SOLICITATION: u8 = 133;
+const ICMPV6_ROUTER_ADVERTISEMENT: u8 = 134;
+const NDP_OPTION_SOURCE_LINK_LAYER_ADDRESS: u8 = 1;
+
+const DHCPV6_REPLY: u8 = 7;
+const DHCPV6_INFORMATION_REQUEST: u8 = 11;
+const DHCPV6_OPTION_CLIENT_ID: u16 = 1;
+const DHCPV6_OPTION_SERVER_ID: u16 = 2;
+const DHCPV6_OPTION_ORO: u16 = 6;
+const DHCPV6_OPTION_ELAPSED_TIME: u16 = 8;
+const DHCPV6_OPTION_DNS_SERVERS: u16 = 23;
+
+/// Bits of the capabilities needed to probe from the interface itself.
+const CAP_NET_BIND_SERVICE: u32 = 10;
+const CAP_NET_RAW: u32 = 13;
+
+/// How a segment was probed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeMethod {
+    /// A DHCPDISCOVER, a router solicitation and a DHCPv6 information request were sent from the
+    /// interface, and the answers listened to.
+    Socket,
+    /// The service lacks the privileges to send them, so only the lease state reported by
+    /// `networkctl status` was looked at.
+    Networkctl,
+}
+
+/// Why an interface is still waiting for its DHCP lease.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaseDiagnosis {
+    /// No DHCP server responded on the segment.
+    NoServer,
+    /// A DHCP server is present, but the lease is not completing.
+    LeaseNotCompleting,
+}
+
+/// Outcome of looking for a DHCP server on the segment of an interface.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct DhcpProbeResult {
+    pub interface: String,
+    /// Whether a DHCP (or DHCPv6) server was seen answering on the interface.
+    pub server_detected: bool,
+    /// Identifier of the server that answered, or handed out the lease, if known.
+    pub server: Option<String>,
+    /// Address of the IPv6 router that answered the router solicitation, if any.
+    #[serde(default)]
+    pub router: Option<String>,
+    pub method: ProbeMethod,
+    /// Set when the interface is waiting for its lease, telling whether a server is there at all.
+    #[serde(default)]
+    pub diagnosis: Option<LeaseDiagnosis>,
+}
+
+impl DhcpProbeResult {
+    /// Parses the output of `networkctl status <interface>`.
+    ///
+    /// Addresses obtained through DHCP are listed by networkd with the server
+    /// they were leased from, e.g. `192.168.1.23 (DHCP4 via 192.168.1.1)`.
+    pub fn from_networkctl_status(interface: &str, output: &str) -> Self {
+        let server = output.lines().find_map(|line| {
+            let (_, lease) = line.split_once("(DHCP")?;
+            let (_, server) = lease.split_once("via ")?;
+            Some(server.trim_end_matches(')').trim().to_string())
+        });
+        Self {
+            interface: interface.to_string(),
+            server_detected: server.is_some(),
+            server,
+            router: None,
+            method: ProbeMethod::Networkctl,
+            diagnosis: None,
+        }
+    }
+
+    /// The result for an interface known to be waiting for its lease, with its diagnosis.
+    pub fn waiting_for_lease(mut self) -> Self {
+        self.diagnosis = Some(if self.server_detected {
+            LeaseDiagnosis::LeaseNotCompleting
+        } else {
+            LeaseDiagnosis::NoServer
+        });
+        self
+    }
+
+    /// Probes the segment of `interface` by sending a DHCPDISCOVER, a router solicitation and a
+    /// DHCPv6 information request from it, and listening for answers for `timeout`.
+    ///
+    /// The sockets are bound to the interface with `SO_BINDTODEVICE`, which needs `CAP_NET_RAW`
+    /// like the ICMPv6 socket, and the DHCP client port needs `CAP_NET_BIND_SERVICE`. A missing
+    /// interface is `NotFound`, and the first error is returned if no message could be sent at all.
+    pub fn probe_with_sockets(interface: &str, timeout: Duration) -> io::Result<Self> {
+        let directory = Path::new(SYS_CLASS_NET_PATH).join(interface);
+        let attribute = |name: &str| -> io::Result<String> {
+            match fs::read_to_string(directory.join(name)) {
+                Ok(value) => Ok(value.trim().to_string()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Interface {interface} does not exist in the system."),
+                )),
+                Err(err) => Err(err),
+            }
+        };
+        let mac = attribute("address")?
+            .parse::<MacAddress>()
+            .map_err(|_| {
+                io::Error::other(format!("{interface} has no Ethernet hardware address."))
+            })?
+            .octets();
+        let index: u32 = attribute("ifindex")?
+            .parse()
+            .map_err(|_| io::Error::other(format!("Invalid index of {interface}.")))?;
+        let ids = uuid::Uuid::new_v4().into_bytes();
+        let xid = u32::from_be_bytes([ids[0], ids[1], ids[2], ids[3]]);
+        let xid6 = [ids[4], ids[5], ids[6]];
+        let until = Instant::now() + timeout;
+
+        let (offer, advertisement, reply) = thread::scope(|scope| {
+            let offer = scope.spawn(|| discover(interface, mac, xid, until));
+            let advertisement = scope.spawn(|| solicit_router(interface, index, mac, until));
+            let reply = scope.spawn(|| request_information(interface, index, mac, xid6, until));
+            (
+                joined(offer.join()),
+                joined(advertisement.join()),
+                joined(reply.join()),
+            )
+        });
+        if let (Err(err), Err(_), Err(_)) = (&offer, &advertisement, &reply) {
+            return Err(io::Error::new(err.kind(), err.to_string()));
+        }
+        let reply = reply.ok().flatten();
+        let server = match offer.ok().flatten() {
+            Some(server) => Some(server.to_string()),
+            None => reply.map(|(source, _)| source.to_string()),
+        };
+        Ok(Self {
+            interface: interface.to_string(),
+            server_detected: server.is_some(),
+            server,
+            router: advertisement
+                .ok()
+                .flatten()
+                .map(|(source, _)| source.to_string()),
+            method: ProbeMethod::Socket,
+            diagnosis: None,
+        })
+    }
+}
+
+/// The outcome of a probe run in its own thread.
+fn joined<T>(result: thread::Result<io::Result<T>>) -> io::Result<T> {
+    result.unwrap_or_else(|_| Err(io::Error::other("The probe failed unexpectedly.")))
+}
+
+/// Capabilities of the service that matter for probing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Privileges {
+    pub net_raw: bool,
+    pub net_bind_service: bool,
+}
+
+impl Privileges {
+    /// Reads the effective capabilities from the `CapEff` line of `/proc/<pid>/status`.
+    pub fn from_proc_status(status: &str) -> Self {
+        let effective = status
+            .lines()
+            .find_map(|line| line.strip_prefix("CapEff:"))
+            .and_then(|bits| u64::from_str_radix(bits.trim(), 16).ok())
+            .unwrap_or(0);
+        Self {
+            net_raw: effective & (1 << CAP_NET_RAW) != 0,
+            net_bind_service: effective & (1 << CAP_NET_BIND_SERVICE) != 0,
+        }
+    }
+
+    /// The capabilities of this process, none if they can't be read.
+    pub fn detect() -> Self {
+        fs::read_to_string("/proc/self/status")
+            .map(|status| Self::from_proc_status(&status))
+            .unwrap_or_default()
+    }
+
+    /// Whether messages can be sent from the interfaces themselves.
+    pub fn can_probe(&self) -> bool {
+        self.net_raw && self.net_bind_service
+    }
+}
+
+/// Builds a broadcast DHCPDISCOVER from the client with hardware address `mac`.
+pub fn dhcp_discover(xid: u32, mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0; BOOTP_LENGTH];
+    // BOOTREQUEST over Ethernet, with a 6 bytes long hardware address
+    packet[..3].copy_from_slice(&[1, 1, 6]);
+    packet[4..8].copy_from_slice(&xid.to_be_bytes());
+    // The offer is broadcast, as the client has no address yet
+    packet[10] = 0x80;
+    packet[28..34].copy_from_slice(&mac);
+    packet.extend_from_slice(&DHCP_MAGIC_COOKIE);
+    packet.extend_from_slice(&[DHCP_OPTION_MESSAGE_TYPE, 1, DHCPDISCOVER]);
+    // Subnet mask, router and DNS servers
+    packet.extend_from_slice(&[DHCP_OPTION_PARAMETER_REQUEST_LIST, 3, 1, 3, 6]);
+    packet.push(DHCP_OPTION_END);
+    packet.resize(BOOTP_MINIMUM_LENGTH, DHCP_OPTION_PAD);
+    packet
+}
+
+/// Parses a DHCPOFFER answering the discover `xid`, returning the identifier of the server.
+pub fn parse_dhcp_offer(packet: &[u8], xid: u32) -> Option<Ipv4Addr> {
+    if packet.len() < BOOTP_LENGTH + DHCP_MAGIC_COOKIE.len()
+        || packet[0] != 2
+        || packet[4..8] != xid.to_be_bytes()
+        || packet[BOOTP_LENGTH..BOOTP_LENGTH + 4] != DHCP_MAGIC_COOKIE
+    {
+        return None;
+    }
+    let mut message_type = None;
+    let mut server = None;
+    let mut options = &packet[BOOTP_LENGTH + 4..];
+    while let Some((&code, rest)) = options.split_first() {
+        match code {
+            DHCP_OPTION_PAD => {
+                options = rest;
+                continue;
+            }
+            DHCP_OPTION_END => break,
+            _ => {}
+        }
+        let (&length, rest) = rest.split_first()?;
+        let value = rest.get(..length as usize)?;
+        match (code, value) {
+            (DHCP_OPTION_MESSAGE_TYPE, [kind]) => message_type = Some(*kind),
+            (DHCP_OPTION_SERVER_IDENTIFIER, &[a, b, c, d]) => {
+                server = Some(Ipv4Addr::new(a, b, c, d))
+            }
+            _ => {}
+        }
+        options = &rest[length as usize..];
+    }
+    if message_type != Some(DHCPOFFER) {
+        return None;
+    }
+    // Without the option, the server is the next one of the bootstrap
+    server.or_else(|| {
+        let siaddr: [u8; 4] = packet[20..24].try_into().ok()?;
+        Some(Ipv4Addr::from(siaddr)).filter(|siaddr| !siaddr.is_unspecified())
+    })
+}
+
+/// Builds a router solicitation from the interface with hardware address `mac`. Its checksum is
+/// left to the kernel, which computes it for ICMPv6 sockets.
+pub fn router_solicitation(mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![ICMPV6_ROUTER_SOLICITATION, 0, 0, 0, 0, 0, 0, 0];
+    packet.extend_from_slice(&[NDP_OPTION_SOURCE_LINK_LAYER_ADDRESS, 1]);
+    packet.extend_from_slice(&mac);
+    packet
+}
+
+/// Flags of a router advertisement telling how the hosts of the link get their addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouterAdvertisement {
+    /// Addresses are handed out by DHCPv6.
+    pub managed: bool,
+    /// Other settings, like DNS servers, are handed out by DHCPv6.
+    pub other: bool,
+}
+
+/// Parses a router advertisement, as received on an ICMPv6 socket, without the IPv6 header.
+pub fn parse_router_advertisement(packet: &[u8]) -> Option<RouterAdvertisement> {
+    if packet.len() < 16 || packet[0] != ICMPV6_ROUTER_ADVERTISEMENT || packet[1] != 0 {
+        return None;
+    }
+    Some(RouterAdvertisement {
+        managed: packet[5] & 0x80 != 0,
+        other: packet[5] & 0x40 != 0,
+    })
+}
+
+/// Builds a DHCPv6 information request from the client with hardware address `mac`, asking for
+/// the DNS servers, which any DHCPv6 server answers.
+pub fn dhcpv6_information_request(xid: [u8; 3], mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![DHCPV6_INFORMATION_REQUEST, xid[0], xid[1], xid[2]];
+    let mut option = |code: u16, value: &[u8]| {
+        packet.extend_from_slice(&code.to_be_bytes());
+        packet.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        packet.extend_from_slice(value);
+    };
+    // DUID-LL of an Ethernet hardware address
+    let mut duid = vec![0, 3, 0, 1];
+    duid.extend_from_slice(&mac);
+    option(DHCPV6_OPTION_CLIENT_ID, &duid);
+    option(DHCPV6_OPTION_ELAPSED_TIME, &[0, 0]);
+    option(DHCPV6_OPTION_ORO, &DHCPV6_OPTION_DNS_SERVERS.to_be_bytes());
+    packet
+}
+
+/// Parses a DHCPv6 reply to the request `xid`, returning the DUID of the server in hexadecimal.
+pub fn parse_dhcpv6_reply(packet: &[u8], xid: [u8; 3]) -> Option<String> {
+    if packet.len() < 4 || packet[0] != DHCPV6_REPLY || packet[1..4] != xid {
+        return None;
+    }
+    let mut options = &packet[4..];
+    while options.len() >= 4 {
+        let code = u16::from_be_bytes([options[0], options[1]]);
+        let length = u16::from_be_bytes([options[2], options[3]]) as usize;
+        let value = options.get(4..4 + length)?;
+        if code == DHCPV6_OPTION_SERVER_ID {
+            return Some(value.iter().map(|byte| format!("{byte:02x}")).collect());
+        }
+        options = &options[4 + length..];
+    }
+    None
+}
+
+/// A socket of `domain` bound to `interface`, and to `port` unless it is a raw socket.
+fn bound_socket(
+    interface: &str,
+    domain: Domain,
+    kind: Type,
+    protocol: Protocol,
+    address: SocketAddr,
+) -> io::Result<Socket> {
+    let socket = Socket::new(domain, kind, Some(protocol))?;
+    socket.set_reuse_address(true)?;
+    socket
+        .bind_device(Some(interface.as_bytes()))
+        .map_err(|err| {
+            if err.raw_os_error() == Some(libc::ENODEV) {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Interface {interface} does not exist in the system."),
+                )
+            } else {
+                err
+            }
+        })?;
+    if kind == Type::DGRAM {
+        socket.bind(&address.into())?;
+    } else {
+        socket.set_multicast_hops_v6(255)?;
+    }
+    Ok(socket)
+}
+
+/// Receives datagrams until one is accepted by `parse`, or `until`.
+fn receive<T>(
+    socket: &Socket,
+    until: Instant,
+    parse: impl Fn(&[u8], SocketAddr) -> Option<T>,
+) -> io::Result<Option<T>> {
+    let mut buffer = [MaybeUninit::<u8>::uninit(); 1500];
+    loop {
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((length, source)) => {
+                // SAFETY: `recv_from` initialized the first `length` bytes of the buffer
+                let packet =
+                    unsafe { std::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), length) };
+                let Some(source) = source.as_socket() else {
+                    continue;
+                };
+                if let Some(found) = parse(packet, source) {
+                    return Ok(Some(found));
+                }
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Broadcasts a DHCPDISCOVER from `interface`, returning the server of the first offer.
+fn discover(
+    interface: &str,
+    mac: [u8; 6],
+    xid: u32,
+    until: Instant,
+) -> io::Result<Option<Ipv4Addr>> {
+    let client = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DHCP_CLIENT_PORT);
+    let socket = bound_socket(
+        interface,
+        Domain::IPV4,
+        Type::DGRAM,
+        Protocol::UDP,
+        client.into(),
+    )?;
+    socket.set_broadcast(true)?;
+    socket.send_to(
+        &dhcp_discover(xid, mac),
+        &SocketAddrV4::new(Ipv4Addr::BROADCAST, DHCP_SERVER_PORT).into(),
+    )?;
+    receive(&socket, until, |packet, _| parse_dhcp_offer(packet, xid))
+}
+
+/// Solicits the routers of the link of `interface`, returning the first one advertising itself.
+fn solicit_router(
+    interface: &str,
+    index: u32,
+    mac: [u8; 6],
+    until: Instant,
+) -> io::Result<Option<(SocketAddr, RouterAdvertisement)>> {
+    let socket = bound_socket(
+        interface,
+        Domain::IPV6,
+        Type::RAW,
+        Protocol::ICMPV6,
+        SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, index).into(),
+    )?;
+    socket.send_to(
+        &router_solicitation(mac),
+        &SocketAddrV6::new(ALL_ROUTERS, 0, 0, index).into(),
+    )?;
+    receive(&socket, until, |packet, source| {
+        parse_router_advertisement(packet).map(|advertisement| (source, advertisement))
+    })
+}
+
+/// Asks the DHCPv6 servers of the link of `interface` for information, returning the first one
+/// replying, with its DUID.
+fn request_information(
+    interface: &str,
+    index: u32,
+    mac: [u8; 6],
+    xid: [u8; 3],
+    until: Instant,
+) -> io::Result<Option<(SocketAddr, String)>> {
+    let client = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, DHCPV6_CLIENT_PORT, 0, index);
+    let socket = bound_socket(
+        interface,
+        Domain::IPV6,
+        Type::DGRAM,
+        Protocol::UDP,
+        client.into(),
+    )?;
+    socket.send_to(
+        &dhcpv6_information_request(xid, mac),
+        &SocketAddrV6::new(ALL_DHCP_SERVERS, DHCPV6_SERVER_PORT, 0, index).into(),
+    )?;
+    receive(&socket, until, |packet, source| {
+        parse_dhcpv6_reply(packet, xid).map(|duid| (source, duid))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+
+    /// A DHCPOFFER of 192.168.1.23 answering `xid`, with the given options.
+    fn offer(xid: u32, options: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; BOOTP_LENGTH];
+        packet[..3].copy_from_slice(&[2, 1, 6]);
+        packet[4..8].copy_from_slice(&xid.to_be_bytes());
+        packet[16..20].copy_from_slice(&[192, 168, 1, 23]);
+        packet[20..24].copy_from_slice(&[192, 168, 1, 2]);
+        packet[28..34].copy_from_slice(&MAC);
+        packet.extend_from_slice(&DHCP_MAGIC_COOKIE);
+        packet.extend_from_slice(options);
+        packet
+    }
+
+    #[test]
+    fn discovers_are_broadcast_bootp_requests() {
+        let packet = dhcp_discover(0x1234_5678, MAC);
+        assert_eq!(packet.len(), BOOTP_MINIMUM_LENGTH);
+        assert_eq!(packet[..8], [1, 1, 6, 0, 0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(packet[10..12], [0x80, 0]);
+        assert_eq!(packet[28..34], MAC);
+        assert_eq!(packet[236..240], DHCP_MAGIC_COOKIE);
+        assert_eq!(packet[240..249], [53, 1, 1, 55, 3, 1, 3, 6, 255]);
+        assert!(packet[249..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn offers_name_their_server() {
+        let xid = 0x1234_5678;
+        let packet = offer(xid, &[0, 53, 1, 2, 54, 4, 192, 168, 1, 1, 255]);
+        assert_eq!(
+            parse_dhcp_offer(&packet, xid),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        // Without the server identifier, the server is the next one of the bootstrap
+        let packet = offer(xid, &[53, 1, 2, 255]);
+        assert_eq!(
+            parse_dhcp_offer(&packet, xid),
+            Some(Ipv4Addr::new(192, 168, 1, 2))
+        );
+        // Offers for other clients, and other messages, are ignored
+        assert_eq!(parse_dhcp_offer(&packet, 0x8765_4321), None);
+        assert_eq!(parse_dhcp_offer(&offer(xid, &[53, 1, 5, 255]), xid), None);
+        assert_eq!(parse_dhcp_offer(&dhcp_discover(xid, MAC), xid), None);
+        assert_eq!(parse_dhcp_offer(&packet[..100], xid), None);
+        // A truncated option is not read past the end of the packet
+        assert_eq!(
+            parse_dhcp_offer(&offer(xid, &[53, 1, 2, 54, 4, 192]), xid),
+            None
+        );
+    }
+
+    #[test]
+    fn router_advertisements_tell_how_addresses_are_handed_out() {
+        assert_eq!(
+            router_solicitation(MAC),
+            [133, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0x52, 0x54, 0x00, 0x12, 0x34, 0x56]
+        );
+        let mut advertisement = vec![134, 0, 0x12, 0x34, 64, 0xc0, 0x07, 0x08];
+        advertisement.extend_from_slice(&[0; 8]);
+        assert_eq!(
+            parse_router_advertisement(&advertisement),
+            Some(RouterAdvertisement {
+                managed: true,
+                other: true
+            })
+        );
+        advertisement[5] = 0x40;
+        assert_eq!(
+            parse_router_advertisement(&advertisement),
+            Some(RouterAdvertisement {
+                managed: false,
+                other: true
+            })
+        );
+        assert_eq!(parse_router_advertisement(&router_solicitation(MAC)), None);
+        assert_eq!(parse_router_advertisement(&advertisement[..8]), None);
+    }
+
+    #[test]
+    fn dhcpv6_replies_name_their_server() {
+        let xid = [0xab, 0xcd, 0xef];
+        assert_eq!(
+            dhcpv6_information_request(xid, MAC),
+            [
+                11, 0xab, 0xcd, 0xef, // Information request
+                0, 1, 0, 10, 0, 3, 0, 1, 0x52, 0x54, 0x00, 0x12, 0x34, 0x56, // Client DUID
+                0, 8, 0, 2, 0, 0, // Elapsed time
+                0, 6, 0, 2, 0, 23, // DNS servers requested
+            ]
+        );
+        let reply = [
+            7, 0xab, 0xcd, 0xef, // Reply
+            0, 1, 0, 10, 0, 3, 0, 1, 0x52, 0x54, 0x00, 0x12, 0x34, 0x56, // Client DUID
+            0, 2, 0, 10, 0, 3, 0, 1, 0x02, 0, 0, 0, 0, 1, // Server DUID
+        ];
+        assert_eq!(
+            parse_dhcpv6_reply(&reply, xid),
+            Some("00030001020000000001".to_string())
+        );
+        assert_eq!(parse_dhcpv6_reply(&reply, [0, 0, 1]), None);
+        assert_eq!(parse_dhcpv6_reply(&reply[..20], xid), None);
+    }
+
+    #[test]
+    fn privileges_are_read_from_the_effective_capabilities() {
+        let status = "Name:\topensight\nCapInh:\t0000000000000000\nCapEff:\t0000000000002400\n";
+        let privileges = Privileges::from_proc_status(status);
+        assert!(privileges.net_raw && privileges.net_bind_service);
+        assert!(privileges.can_probe());
+        let privileges = Privileges::from_proc_status("CapEff:\t0000000000002000\n");
+        assert!(privileges.net_raw && !privileges.net_bind_service);
+        assert!(!privileges.can_probe());
+        assert_eq!(Privileges::from_proc_status(""), Privileges::default());
+    }
+
+    #[test]
+    fn leases_are_read_from_networkctl() {
+        let output = "● 2: enp1s0\n    Address: 192.168.1.23 (DHCP4 via 192.168.1.1)\n";
+        let probe = DhcpProbeResult::from_networkctl_status("enp1s0", output);
+        assert_eq!(probe.server.as_deref(), Some("192.168.1.1"));
+        assert_eq!(probe.method, ProbeMethod::Networkctl);
+        assert_eq!(
+            probe.waiting_for_lease().diagnosis,
+            Some(LeaseDiagnosis::LeaseNotCompleting)
+        );
+        let probe = DhcpProbeResult::from_networkctl_status("enp1s0", "● 2: enp1s0\n");
+        assert!(!probe.server_detected);
+        assert_eq!(
+            probe.waiting_for_lease().diagnosis,
+            Some(LeaseDiagnosis::NoServer)
+        );
+    }
+}
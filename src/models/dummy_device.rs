@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputDevice,
+};
+
+/// A virtual interface without any link, holding addresses and routes, e.g. anycast service
+/// addresses.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DummyDevice {
+    #[serde(skip_serializing)]
+    name: String,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl DummyDevice {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    pub fn from_input_device(name: &str, input_device: &InputDevice) -> Self {
+        Self {
+            name: name.to_string(),
+            settings: DeviceSettings::from_input_device(input_device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl WithDeviceSettings for DummyDevice {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
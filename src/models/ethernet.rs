@@ -1,15 +1,36 @@
 use std::{
     collections::{HashMap, HashSet},
-    net::{IpAddr, SocketAddr},
+    net::IpAddr,
 };
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    custom_types::{IpNetwork, MacAddress},
+    misc::{
+        serialize_hash_map_values_as_yaml_sequence, serialize_hash_set_as_sorted_yaml_sequence,
+    },
+    system::SYS_CLASS_NET_PATH,
+};
 
 use super::{
-    device::{Device, MTU, MTUV6},
-    input_models::InputDevice,
+    device::{
+        validate_interface_name, validate_virtual_function_count, ActivationMode, Device,
+        DhcpIdentifier, Ipv6AddressGeneration, LinkLocalKind, MTU, MTUV6,
+    },
+    device_listing::Enslavement,
+    device_match::Match,
+    dhcp_overrides::DhcpOverrides,
+    dhcp_probe::DhcpProbeResult,
+    input_models::{EthernetSpec, InputDevice},
     nameservers::Nameservers,
+    network::ConfigOrigin,
+    network::NetworkRenderer,
+    offloads::Offloads,
     route::Route,
+    routing_policy::RoutingPolicy,
+    wait_online::RequiredForOnline,
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,69 +38,455 @@ use super::{
 pub struct Ethernet {
     #[serde(skip_serializing)]
     name: String,
+    #[serde(default)]
     dhcp4: bool,
+    #[serde(default)]
     dhcp6: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp4_overrides: Option<DhcpOverrides>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp6_overrides: Option<DhcpOverrides>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhcp_identifier: Option<DhcpIdentifier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     mtu: Option<MTU>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     ipv6_mtu: Option<MTUV6>,
+    /// `Some(vec![])` is kept and written as `link-local: []`, disabling link-local addressing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_local: Option<Vec<LinkLocalKind>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    optional: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6_privacy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wakeonlan: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    macaddress: Option<MacAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    r#match: Option<Match>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    set_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     accept_ra: Option<bool>,
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ipv6_address_generation: Option<Ipv6AddressGeneration>,
+    /// Overrides the renderer of the whole network for this interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    renderer: Option<NetworkRenderer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activation_mode: Option<ActivationMode>,
+    /// SR-IOV virtual functions created on this physical function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    virtual_function_count: Option<u16>,
+    /// Written as keys of the stanza itself, as netplan expects them.
+    #[serde(default, flatten)]
+    offloads: Offloads,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
     routes: HashMap<String, Route>,
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
-    addresses: HashSet<SocketAddr>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
+    routing_policy: HashMap<String, RoutingPolicy>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "serialize_hash_set_as_sorted_yaml_sequence"
+    )]
+    addresses: HashSet<IpNetwork>,
+    #[serde(default)]
     nameservers: Nameservers,
-    #[serde(skip_serializing)]
+    #[serde(skip)]
     dynamic_addresses: Vec<String>,
-    #[serde(skip_serializing)]
+    #[serde(skip_serializing, default)]
     system_state: HashMap<String, serde_yml::Value>,
+    /// Read only: the bridge or bond the interface is a member of. Computed from the network when
+    /// responding, and never written to the configuration.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    enslaved_to: Option<Enslavement>,
+    /// Operator defined tags grouping the interface with others, e.g. `wan`. Netplan doesn't know
+    /// about them, so they are kept in a metadata file next to the configuration.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    labels: HashSet<String>,
+    /// Whether boot waits for the interface, and until which operational state. Netplan only
+    /// knows `optional`, so it is kept in the metadata file and set through a networkd drop-in,
+    /// which overrides `optional`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    required_for_online: Option<RequiredForOnline>,
+    /// Read only: the other netplan configuration file defining the interface, e.g.
+    /// `50-cloud-init.yaml`. Such interfaces are left to that file, and can't be changed through
+    /// the API.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    managed_by: Option<String>,
+    /// Read only: why the interface is still waiting for its DHCP lease, when the configuration
+    /// was applied without the system reaching it.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    dhcp_probe: Option<DhcpProbeResult>,
+    /// Read only: whether changes to the interface only take full effect after a reboot, as
+    /// listed by `GET /network/pending-actions`.
+    #[serde(
+        default,
+        skip_deserializing,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pending_reboot: bool,
+    /// Read only: why the interface waits for a reboot, one reason by pending change.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pending_reboot_reasons: Vec<String>,
+}
+
+/// Checks that every label is made of letters, digits, `-`, `_` or `.`, so it can be used as a
+/// query parameter as is.
+pub fn validate_labels(labels: &HashSet<String>) -> Result<(), String> {
+    for label in labels.iter() {
+        if label.is_empty()
+            || !label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "Invalid label '{label}': only letters, digits, '-', '_' and '.' are allowed."
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Lightweight view of an Ethernet entry, as shown in interface listings.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct EthernetSummary {
+    pub name: String,
+    /// Whether the interface is part of the netplan configuration.
+    pub managed: bool,
+    /// Operational state reported by the kernel (`up`, `down`, ...), if the interface exists.
+    pub oper_state: Option<String>,
+    /// One of `dhcp`, `static`, `dhcp+static` or `none`.
+    pub addressing_mode: String,
+    pub address_count: usize,
+    pub route_count: usize,
+    pub has_nameservers: bool,
+    pub dhcp4: bool,
+    pub dhcp6: bool,
+    /// Whether the state of the interface on the system differs from its configuration, e.g.
+    /// while it waits for a DHCP lease.
+    pub drift: bool,
 }
 
 impl Ethernet {
+    /// Builds the summary of this entry. `managed` tells whether it comes from the netplan configuration.
+    pub fn summary(&self, managed: bool) -> EthernetSummary {
+        let dhcp = self.dhcp4 || self.dhcp6;
+        let addressing_mode = match (dhcp, !self.addresses.is_empty()) {
+            (true, true) => "dhcp+static",
+            (true, false) => "dhcp",
+            (false, true) => "static",
+            (false, false) => "none",
+        };
+        EthernetSummary {
+            name: self.name.clone(),
+            managed,
+            oper_state: std::fs::read_to_string(format!(
+                "{SYS_CLASS_NET_PATH}/{}/operstate",
+                self.name
+            ))
+            .ok()
+            .map(|state| state.trim().to_string()),
+            addressing_mode: addressing_mode.to_string(),
+            address_count: self.addresses.len(),
+            route_count: self.routes.len(),
+            has_nameservers: !self.nameservers.addresses.is_empty(),
+            dhcp4: self.dhcp4,
+            dhcp6: self.dhcp6,
+            drift: !self.system_state.is_empty(),
+        }
+    }
+
     pub fn new(name: String) -> Self {
         Self {
             name,
             dhcp4: false,
             dhcp6: false,
+            dhcp4_overrides: None,
+            dhcp6_overrides: None,
+            dhcp_identifier: None,
+            link_local: None,
+            optional: None,
+            ipv6_privacy: None,
+            wakeonlan: None,
+            macaddress: None,
+            r#match: None,
+            set_name: None,
             mtu: None,
             ipv6_mtu: None,
             accept_ra: None,
+            ipv6_address_generation: None,
+            renderer: None,
+            activation_mode: None,
+            virtual_function_count: None,
+            offloads: Offloads::default(),
             routes: HashMap::new(),
+            routing_policy: HashMap::new(),
             addresses: HashSet::new(),
             nameservers: Nameservers::new(),
             dynamic_addresses: Vec::new(),
             system_state: HashMap::new(),
+            enslaved_to: None,
+            labels: HashSet::new(),
+            required_for_online: None,
+            managed_by: None,
+            dhcp_probe: None,
+            pending_reboot: false,
+            pending_reboot_reasons: vec![],
         }
     }
 
+    pub fn from_input_device(name: &str, input_device: &InputDevice) -> Self {
+        let mut result = Self::new(name.to_string());
+        input_device.update(&mut result);
+        if let Some(labels) = &input_device.labels {
+            result.set_labels(labels.clone());
+        }
+        if let Some(required_for_online) = input_device.required_for_online {
+            result.set_required_for_online(required_for_online);
+        }
+
+        result
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
-}
 
-impl Device for Ethernet {
-    fn from_input_device(name: &str, input_device: &InputDevice) -> Self {
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn enslaved_to(&self) -> Option<Enslavement> {
+        self.enslaved_to.clone()
+    }
+
+    pub fn set_enslaved_to(&mut self, enslaved_to: Option<Enslavement>) {
+        self.enslaved_to = enslaved_to;
+    }
+
+    pub fn dhcp_probe(&self) -> Option<DhcpProbeResult> {
+        self.dhcp_probe.clone()
+    }
+
+    pub fn set_dhcp_probe(&mut self, dhcp_probe: Option<DhcpProbeResult>) {
+        self.dhcp_probe = dhcp_probe;
+    }
+
+    pub fn pending_reboot_reasons(&self) -> Vec<String> {
+        self.pending_reboot_reasons.clone()
+    }
+
+    /// Marks the interface as waiting for a reboot for the given reasons, or as not waiting if
+    /// there are none.
+    pub fn set_pending_reboot(&mut self, reasons: Vec<String>) {
+        self.pending_reboot = !reasons.is_empty();
+        self.pending_reboot_reasons = reasons;
+    }
+
+    pub fn labels(&self) -> HashSet<String> {
+        self.labels.clone()
+    }
+
+    pub fn set_labels(&mut self, labels: HashSet<String>) {
+        self.labels = labels;
+    }
+
+    pub fn required_for_online(&self) -> Option<RequiredForOnline> {
+        self.required_for_online
+    }
+
+    pub fn set_required_for_online(&mut self, required_for_online: Option<RequiredForOnline>) {
+        self.required_for_online = required_for_online;
+    }
+
+    /// Whether boot waits for the interface, as networkd ends up being configured.
+    pub fn wait_online_participation(&self) -> RequiredForOnline {
+        self.required_for_online
+            .unwrap_or(if self.get_optional() == Some(true) {
+                RequiredForOnline::No
+            } else {
+                RequiredForOnline::Yes
+            })
+    }
+
+    /// Builds an Ethernet from its complete desired state.
+    ///
+    /// Every invalid setting, address, route or rule is reported, one per line.
+    pub fn from_spec(name: &str, spec: &EthernetSpec) -> Result<Self, String> {
         let mut result = Self::new(name.to_string());
-        if let Some(dhcp) = input_device.dhcp4 {
-            result.set_dhcp4(dhcp);
+        let mut errors = vec![];
+        result.set_dhcp4(spec.dhcp4);
+        result.set_dhcp6(spec.dhcp6);
+        result.set_dhcp4_overrides(spec.dhcp4_overrides.clone());
+        result.set_dhcp6_overrides(spec.dhcp6_overrides.clone());
+        result.set_dhcp_identifier(spec.dhcp_identifier);
+        result.set_accept_ra(spec.accept_ra);
+        result.set_ipv6_address_generation(spec.ipv6_address_generation);
+        if spec.ipv6_address_generation.is_some() && spec.accept_ra == Some(false) {
+            errors.push(format!(
+                "The IPv6 address generation of {name} only applies to addresses autoconfigured \
+                from router advertisements, which are disabled by 'accept-ra: false'."
+            ));
+        }
+        result.set_ipv6_privacy(spec.ipv6_privacy);
+        result.set_link_local(spec.link_local.clone());
+        result.set_mtu(spec.mtu);
+        result.set_ipv6_mtu(spec.ipv6_mtu);
+        result.set_optional(spec.optional);
+        if let Some(Err(err)) = spec
+            .required_for_online
+            .map(RequiredForOnline::validate_setting)
+        {
+            errors.push(err);
+        }
+        result.set_required_for_online(spec.required_for_online);
+        result.set_renderer(spec.renderer);
+        result.set_activation_mode(spec.activation_mode);
+        if let Some(count) = spec.virtual_function_count {
+            if let Err(err) = validate_virtual_function_count(count) {
+                errors.push(err);
+            }
+        }
+        result.set_virtual_function_count(spec.virtual_function_count);
+        result.set_offloads(spec.offloads.clone());
+        result.set_wakeonlan(spec.wakeonlan);
+        result.set_macaddress(spec.macaddress);
+        result.set_match(spec.r#match.clone());
+        if let Some(set_name) = &spec.set_name {
+            if let Err(err) = validate_interface_name(set_name) {
+                errors.push(err);
+            }
+        }
+        result.set_set_name(spec.set_name.clone());
+        for address in spec.addresses.iter() {
+            match address.parse::<IpNetwork>() {
+                Ok(address) => result.add_address(&address),
+                Err(err) => errors.push(err),
+            }
+        }
+        for route in spec.routes.iter() {
+            match Route::from_input_route(route) {
+                Ok(route) => result.add_route(&route),
+                Err(err) => errors.push(err),
+            }
         }
-        if let Some(dhcp6) = input_device.dhcp6 {
-            result.set_dhcp6(dhcp6);
+        for routing_policy in spec.routing_policy.iter() {
+            match RoutingPolicy::from_input_routing_policy(routing_policy) {
+                Ok(routing_policy) => result.add_routing_policy(&routing_policy),
+                Err(err) => errors.push(err),
+            }
         }
+        for address in spec.nameservers.addresses.iter() {
+            match address.parse::<IpAddr>() {
+                Ok(address) => result.add_nameservers_address(&address),
+                Err(err) => errors.push(format!("Invalid nameserver '{address}': {err}")),
+            }
+        }
+        for search in spec.nameservers.search.iter() {
+            result.add_nameservers_search(search);
+        }
+        if errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+impl PartialEq for Ethernet {
+    /// Only compares the configuration of the interfaces, ignoring the
+    /// information gathered from the running system.
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            name,
+            dhcp4,
+            dhcp6,
+            dhcp4_overrides,
+            dhcp6_overrides,
+            dhcp_identifier,
+            mtu,
+            ipv6_mtu,
+            link_local,
+            optional,
+            ipv6_privacy,
+            wakeonlan,
+            macaddress,
+            r#match,
+            set_name,
+            accept_ra,
+            ipv6_address_generation,
+            renderer,
+            activation_mode,
+            virtual_function_count,
+            offloads,
+            routes,
+            routing_policy,
+            addresses,
+            nameservers,
+            dynamic_addresses: _,
+            system_state: _,
+            enslaved_to: _,
+            labels,
+            required_for_online,
+            managed_by: _,
+            dhcp_probe: _,
+            pending_reboot: _,
+            pending_reboot_reasons: _,
+        } = self;
+        *name == other.name
+            && *dhcp4 == other.dhcp4
+            && *dhcp6 == other.dhcp6
+            && *dhcp4_overrides == other.dhcp4_overrides
+            && *dhcp6_overrides == other.dhcp6_overrides
+            && *dhcp_identifier == other.dhcp_identifier
+            && *mtu == other.mtu
+            && *ipv6_mtu == other.ipv6_mtu
+            && *link_local == other.link_local
+            && *optional == other.optional
+            && *ipv6_privacy == other.ipv6_privacy
+            && *wakeonlan == other.wakeonlan
+            && *macaddress == other.macaddress
+            && *r#match == other.r#match
+            && *set_name == other.set_name
+            && *accept_ra == other.accept_ra
+            && *ipv6_address_generation == other.ipv6_address_generation
+            && *renderer == other.renderer
+            && *activation_mode == other.activation_mode
+            && *virtual_function_count == other.virtual_function_count
+            && *offloads == other.offloads
+            && *routes == other.routes
+            && *routing_policy == other.routing_policy
+            && *addresses == other.addresses
+            && *nameservers == other.nameservers
+            && *labels == other.labels
+            && *required_for_online == other.required_for_online
+    }
+}
 
-        result.set_accept_ra(input_device.accept_ra);
-        result.set_mtu(input_device.mtu);
-        result.set_ipv6_mtu(input_device.ipv6_mtu);
+impl Eq for Ethernet {}
 
-        result
+impl ConfigOrigin for Ethernet {
+    fn managed_by(&self) -> Option<String> {
+        self.managed_by.clone()
     }
 
-    fn update_from_device(&mut self, device: &impl Device) {
-        self.set_dhcp4(device.get_dhcp4());
-        self.set_dhcp6(device.get_dhcp6());
-        self.set_accept_ra(device.get_accept_ra());
-        self.set_mtu(device.get_mtu());
-        self.set_ipv6_mtu(device.get_ipv6_mtu());
+    fn set_managed_by(&mut self, file: Option<String>) {
+        self.managed_by = file;
     }
+}
 
+impl Device for Ethernet {
     fn set_dhcp4(&mut self, set: bool) {
         self.dhcp4 = set;
     }
@@ -96,6 +503,87 @@ impl Device for Ethernet {
         self.dhcp6 = set;
     }
 
+    fn get_dhcp4_overrides(&self) -> Option<DhcpOverrides> {
+        self.dhcp4_overrides.clone()
+    }
+
+    /// Overrides without any setting are dropped, so no empty stanza is written.
+    fn set_dhcp4_overrides(&mut self, overrides: Option<DhcpOverrides>) {
+        self.dhcp4_overrides = overrides.filter(|overrides| !overrides.is_empty());
+    }
+
+    fn get_dhcp6_overrides(&self) -> Option<DhcpOverrides> {
+        self.dhcp6_overrides.clone()
+    }
+
+    fn set_dhcp6_overrides(&mut self, overrides: Option<DhcpOverrides>) {
+        self.dhcp6_overrides = overrides.filter(|overrides| !overrides.is_empty());
+    }
+
+    fn get_optional(&self) -> Option<bool> {
+        self.optional
+    }
+
+    fn set_optional(&mut self, optional: Option<bool>) {
+        self.optional = optional;
+    }
+
+    fn get_ipv6_privacy(&self) -> Option<bool> {
+        self.ipv6_privacy
+    }
+
+    fn set_ipv6_privacy(&mut self, ipv6_privacy: Option<bool>) {
+        self.ipv6_privacy = ipv6_privacy;
+    }
+
+    fn get_wakeonlan(&self) -> Option<bool> {
+        self.wakeonlan
+    }
+
+    fn set_wakeonlan(&mut self, wakeonlan: Option<bool>) {
+        self.wakeonlan = wakeonlan;
+    }
+
+    fn get_macaddress(&self) -> Option<MacAddress> {
+        self.macaddress
+    }
+
+    fn set_macaddress(&mut self, macaddress: Option<MacAddress>) {
+        self.macaddress = macaddress;
+    }
+
+    fn get_set_name(&self) -> Option<String> {
+        self.set_name.clone()
+    }
+
+    fn set_set_name(&mut self, set_name: Option<String>) {
+        self.set_name = set_name;
+    }
+
+    fn get_match(&self) -> Option<Match> {
+        self.r#match.clone()
+    }
+
+    fn set_match(&mut self, device_match: Option<Match>) {
+        self.r#match = device_match;
+    }
+
+    fn get_link_local(&self) -> Option<Vec<LinkLocalKind>> {
+        self.link_local.clone()
+    }
+
+    fn set_link_local(&mut self, link_local: Option<Vec<LinkLocalKind>>) {
+        self.link_local = link_local;
+    }
+
+    fn get_dhcp_identifier(&self) -> Option<DhcpIdentifier> {
+        self.dhcp_identifier
+    }
+
+    fn set_dhcp_identifier(&mut self, identifier: Option<DhcpIdentifier>) {
+        self.dhcp_identifier = identifier;
+    }
+
     fn set_accept_ra(&mut self, set: Option<bool>) {
         self.accept_ra = set;
     }
@@ -104,6 +592,46 @@ impl Device for Ethernet {
         self.accept_ra
     }
 
+    fn get_ipv6_address_generation(&self) -> Option<Ipv6AddressGeneration> {
+        self.ipv6_address_generation
+    }
+
+    fn set_ipv6_address_generation(&mut self, generation: Option<Ipv6AddressGeneration>) {
+        self.ipv6_address_generation = generation;
+    }
+
+    fn get_renderer(&self) -> Option<NetworkRenderer> {
+        self.renderer
+    }
+
+    fn set_renderer(&mut self, renderer: Option<NetworkRenderer>) {
+        self.renderer = renderer;
+    }
+
+    fn get_activation_mode(&self) -> Option<ActivationMode> {
+        self.activation_mode
+    }
+
+    fn set_activation_mode(&mut self, activation_mode: Option<ActivationMode>) {
+        self.activation_mode = activation_mode;
+    }
+
+    fn get_virtual_function_count(&self) -> Option<u16> {
+        self.virtual_function_count
+    }
+
+    fn set_virtual_function_count(&mut self, count: Option<u16>) {
+        self.virtual_function_count = count;
+    }
+
+    fn get_offloads(&self) -> Option<Offloads> {
+        Some(self.offloads.clone()).filter(|offloads| !offloads.is_empty())
+    }
+
+    fn set_offloads(&mut self, offloads: Option<Offloads>) {
+        self.offloads = offloads.unwrap_or_default();
+    }
+
     fn get_mtu(&self) -> Option<MTU> {
         self.mtu
     }
@@ -112,7 +640,7 @@ impl Device for Ethernet {
         self.mtu = mtu;
     }
 
-    fn get_addresses(&self) -> HashSet<SocketAddr> {
+    fn get_addresses(&self) -> HashSet<IpNetwork> {
         self.addresses.clone()
     }
 
@@ -144,22 +672,43 @@ impl Device for Ethernet {
         self.nameservers.remove_address(address)
     }
 
+    fn clear_nameservers(&mut self) {
+        self.nameservers = Nameservers::new();
+    }
+
     fn delete_route(&mut self, route_id: &str) -> bool {
         self.routes.remove(route_id).is_some()
     }
 
-    fn delete_address(&mut self, address: &SocketAddr) -> bool {
+    fn delete_address(&mut self, address: &IpNetwork) -> bool {
         self.addresses.remove(address)
     }
 
+    fn get_routing_policy(&self) -> HashMap<String, RoutingPolicy> {
+        self.routing_policy.clone()
+    }
+
+    fn add_routing_policy(&mut self, routing_policy: &RoutingPolicy) {
+        self.routing_policy
+            .insert(routing_policy.id(), *routing_policy);
+    }
+
+    fn delete_routing_policy(&mut self, routing_policy_id: &str) -> bool {
+        self.routing_policy.remove(routing_policy_id).is_some()
+    }
+
     fn delete_all_routes(&mut self) {
         self.routes = HashMap::new();
     }
 
-    fn add_address(&mut self, address: &SocketAddr) {
+    fn add_address(&mut self, address: &IpNetwork) {
         self.addresses.insert(*address);
     }
 
+    fn set_addresses(&mut self, addresses: HashSet<IpNetwork>) {
+        self.addresses = addresses;
+    }
+
     fn get_dynamic_addresses(&self) -> Vec<String> {
         self.dynamic_addresses.clone()
     }
@@ -0,0 +1,25 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::pending_action::PendingAction;
+
+/// Whether the service is up, with what it wants operators to know.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Health {
+    /// Always `ok` as long as the service answers, whatever the rest says.
+    pub status: &'static str,
+    /// Informational: whether changes to the configuration only take full effect after a reboot.
+    pub pending_reboot: bool,
+    /// Informational: the number of such changes, as listed by `GET /network/pending-actions`.
+    pub pending_actions: usize,
+}
+
+impl Health {
+    pub fn new(pending_actions: &[PendingAction]) -> Self {
+        Self {
+            status: "ok",
+            pending_reboot: !pending_actions.is_empty(),
+            pending_actions: pending_actions.len(),
+        }
+    }
+}
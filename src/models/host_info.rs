@@ -1,6 +1,9 @@
-use std::process::Command;
+use std::io;
 use std::sync::Mutex;
 
+use super::{device::Device, network::Network};
+use crate::command::{command_line, CommandRunner, Deadline, SystemRunner};
+
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -8,29 +11,214 @@ use utoipa::ToSchema;
 #[serde(rename_all = "kebab-case")]
 pub struct HostInfo;
 
-#[derive(Default)]
+/// Whether the domain of the host's FQDN is part of the configured search domains.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct DnsConsistency {
+    pub fqdn: String,
+    /// Domain part of the FQDN, if the hostname has one.
+    pub domain: Option<String>,
+    /// Union of the search domains of all the interfaces.
+    pub search_domains: Vec<String>,
+    /// Whether the domain is covered by the search domains. Always true without a domain.
+    pub covered: bool,
+}
+
 pub struct HostInfoStore {
     pub host_info: Mutex<HostInfo>,
+    /// Runs `hostnamectl`.
+    runner: Box<dyn CommandRunner + Send + Sync>,
+}
+
+impl Default for HostInfoStore {
+    fn default() -> Self {
+        Self::with_runner(Box::new(SystemRunner))
+    }
+}
+
+impl HostInfoStore {
+    /// A store running `hostnamectl` through `runner` instead of as a child process.
+    pub fn with_runner(runner: Box<dyn CommandRunner + Send + Sync>) -> Self {
+        Self {
+            host_info: Mutex::default(),
+            runner,
+        }
+    }
+
+    pub fn get_hostname(&self) -> io::Result<String> {
+        self.host_info.lock().unwrap().get_hostname(&*self.runner)
+    }
+
+    pub fn set_hostname(&self, hostname: &str) -> io::Result<String> {
+        self.host_info
+            .lock()
+            .unwrap()
+            .set_hostname(&*self.runner, hostname)
+    }
 }
 
 impl HostInfo {
-    fn _run_hostnamectl(args: &[&str]) -> Result<String, std::io::Error> {
-        // Implementation of _run_hostnamectl
-        let result = Command::new("hostnamectl")
-            .args(args)
-            .output()?
-            .stdout
-            .into_iter()
-            .map(|byte| byte as char)
-            .collect::<String>();
-        Ok(result)
+    fn _run_hostnamectl(runner: &dyn CommandRunner, args: &[&str]) -> io::Result<String> {
+        let output = runner.output("hostnamectl", args, Deadline::current())?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "`{}` failed ({}): {}",
+                command_line("hostnamectl", args),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub fn get_hostname(&self, runner: &dyn CommandRunner) -> io::Result<String> {
+        Self::_run_hostnamectl(runner, &["hostname"])
+    }
+
+    pub fn set_hostname(&self, runner: &dyn CommandRunner, hostname: &str) -> io::Result<String> {
+        Self::_run_hostnamectl(runner, &["hostname", hostname])
+    }
+}
+
+/// Validates a hostname, returning its domain part if it is a fully qualified name.
+///
+/// Every label must be 1-63 characters long, made of ASCII letters, digits and
+/// hyphens, and must not start or end with a hyphen. A single trailing dot is allowed.
+pub fn hostname_domain(hostname: &str) -> Result<Option<&str>, String> {
+    let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+    if hostname.is_empty() || hostname.len() > 253 {
+        return Err(format!(
+            "Hostname '{hostname}' must be 1-253 characters long."
+        ));
+    }
+    for label in hostname.split('.') {
+        if label.is_empty()
+            || label.len() > 63
+            || label.starts_with('-')
+            || label.ends_with('-')
+            || !label
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-')
+        {
+            return Err(format!(
+                "Hostname '{hostname}' has an invalid label '{label}'."
+            ));
+        }
+    }
+    Ok(hostname.split_once('.').map(|(_, domain)| domain))
+}
+
+/// Picks the interface carrying the default route, preferring the first one by name.
+pub fn default_route_interface(network: &Network) -> Option<String> {
+    let mut names: Vec<&String> = network
+        .get_ethernets()
+        .iter()
+        .filter(|(_, ethernet)| {
+            ethernet
+                .get_routes()
+                .values()
+                .any(|route| route.to.is_unspecified())
+        })
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names.first().map(|name| name.to_string())
+}
+
+impl DnsConsistency {
+    /// Checks the domain of `fqdn` against the search domains configured in `network`.
+    pub fn new(fqdn: &str, network: &Network) -> Self {
+        let fqdn = fqdn.trim().to_string();
+        let domain = hostname_domain(&fqdn)
+            .ok()
+            .flatten()
+            .map(|domain| domain.to_lowercase());
+        let mut search_domains: Vec<String> = network
+            .get_ethernets()
+            .values()
+            .flat_map(|ethernet| ethernet.get_nameservers().search)
+            .collect();
+        search_domains.sort();
+        search_domains.dedup();
+        let covered = domain.as_ref().is_none_or(|domain| {
+            search_domains
+                .iter()
+                .any(|search| search.trim_end_matches('.').eq_ignore_ascii_case(domain))
+        });
+        Self {
+            fqdn,
+            domain,
+            search_domains,
+            covered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ethernet::Ethernet, route::Route};
+
+    fn ethernet(name: &str, route_to: Option<&str>, search: &[&str]) -> Ethernet {
+        let mut ethernet = Ethernet::new(name.to_string());
+        if let Some(to) = route_to {
+            let via = "10.0.0.1".parse().ok();
+            ethernet.add_route(&Route::new(to.parse().unwrap(), via, None));
+        }
+        for search in search {
+            ethernet.add_nameservers_search(search);
+        }
+        ethernet
+    }
+
+    #[test]
+    fn fully_qualified_hostnames_have_a_domain() {
+        assert_eq!(hostname_domain("scanner1"), Ok(None));
+        assert_eq!(
+            hostname_domain("scanner1.lab.example"),
+            Ok(Some("lab.example"))
+        );
+        assert_eq!(
+            hostname_domain("scanner1.lab.example."),
+            Ok(Some("lab.example"))
+        );
+        let too_long = vec!["a".repeat(63); 5].join(".");
+        for hostname in [
+            "",
+            ".",
+            "-scanner1",
+            "scanner1-.lab",
+            "scan_ner1",
+            "a..b",
+            &too_long,
+        ] {
+            assert!(hostname_domain(hostname).is_err(), "{hostname}");
+        }
     }
 
-    pub fn get_hostname(&self) -> Result<String, std::io::Error> {
-        Self::_run_hostnamectl(&["hostname"])
+    #[test]
+    fn the_default_route_is_picked_by_name() {
+        let mut network = Network::new();
+        network.add_ethernet(&ethernet("enp3s0", Some("10.1.0.0"), &[]));
+        assert_eq!(default_route_interface(&network), None);
+        network.add_ethernet(&ethernet("enp2s0", Some("0.0.0.0"), &[]));
+        network.add_ethernet(&ethernet("enp1s0", Some("0.0.0.0"), &[]));
+        assert_eq!(
+            default_route_interface(&network),
+            Some("enp1s0".to_string())
+        );
     }
 
-    pub fn set_hostname(&self, hostname: &str) -> std::io::Result<String> {
-        Self::_run_hostnamectl(&[hostname])
+    #[test]
+    fn domains_are_covered_by_any_search_domain() {
+        let mut network = Network::new();
+        network.add_ethernet(&ethernet("enp1s0", None, &["corp.example"]));
+        network.add_ethernet(&ethernet("enp2s0", None, &["Lab.Example.", "corp.example"]));
+        let consistency = DnsConsistency::new("scanner1.lab.example\n", &network);
+        assert_eq!(consistency.fqdn, "scanner1.lab.example");
+        assert_eq!(consistency.domain.as_deref(), Some("lab.example"));
+        assert_eq!(consistency.search_domains, ["Lab.Example.", "corp.example"]);
+        assert!(consistency.covered);
+        assert!(!DnsConsistency::new("scanner1.other.example", &network).covered);
+        assert!(DnsConsistency::new("scanner1", &network).covered);
     }
 }
@@ -1,20 +1,486 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    num::NonZeroU32,
+};
+
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-use super::device::{MTU, MTUV6};
+use crate::{custom_types::MacAddress, misc::deserialize_double_option};
 
-#[derive(Deserialize)]
+use super::{
+    access_point::AccessPoint,
+    capture::CaptureFilter,
+    device::{
+        ActivationMode, Device, DhcpIdentifier, Ipv6AddressGeneration, LinkLocalKind, MTU, MTUV6,
+    },
+    device_match::Match,
+    dhcp_overrides::DhcpOverrides,
+    network::NetworkRenderer,
+    offloads::Offloads,
+    route::{AddressFamily, RouteScope, RouteType},
+    tunnel::{TunnelKey, TunnelMode, Vni},
+    wait_online::RequiredForOnline,
+};
+
+/// Ethernet entries included in the listing.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EthernetScope {
+    /// Only the Ethernet entries of the configuration.
+    #[default]
+    Configured,
+    /// The Ethernet entries of the configuration, and the interfaces of the system not configured yet.
+    All,
+}
+
+/// Representation of the Ethernet entries returned by the listing.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EthernetView {
+    /// The complete Ethernet entries.
+    #[default]
+    Full,
+    /// Lightweight per interface summaries, see `EthernetSummary`.
+    Summary,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ScopeQuery {
-    pub scope: String,
+    /// Which Ethernet entries to list, `configured` when absent.
+    #[serde(default)]
+    pub scope: EthernetScope,
+    /// Whether loopback interfaces are listed.
+    #[serde(default)]
+    pub include_loopback: bool,
+    /// Representation of the listed Ethernet entries, `full` when absent.
+    #[serde(default)]
+    pub view: EthernetView,
+    /// Only list the Ethernet entries with DHCPv4 enabled, or disabled.
+    pub dhcp4: Option<bool>,
+    /// Only list the Ethernet entries with DHCPv6 enabled, or disabled.
+    pub dhcp6: Option<bool>,
+    /// Only list the Ethernet entries with static addresses, or without.
+    pub has_addresses: Option<bool>,
+    /// Only list the Ethernet entries with this label.
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LabelQuery {
+    /// Apply the settings in the body to every Ethernet entry with this label, instead of
+    /// giving settings by name.
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceScopeQuery {
+    /// Which devices to list, `configured` when absent. With `all`, the physical interfaces of the
+    /// system which are not configured are listed as well, as `unmanaged`.
+    #[serde(default)]
+    pub scope: EthernetScope,
 }
 
+/// Settings of a device, as given to create or update it.
+///
+/// When updating, absent settings are kept, and those given as `null` are cleared.
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct InputDevice {
-    pub accept_ra: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<bool>)]
+    pub accept_ra: Option<Option<bool>>,
     pub dhcp4: Option<bool>,
     pub dhcp6: Option<bool>,
-    pub mtu: Option<MTU>,
-    pub ipv6_mtu: Option<MTUV6>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<MTU>)]
+    pub mtu: Option<Option<MTU>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<MTUV6>)]
+    pub ipv6_mtu: Option<Option<MTUV6>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<DhcpOverrides>)]
+    pub dhcp4_overrides: Option<Option<DhcpOverrides>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<DhcpOverrides>)]
+    pub dhcp6_overrides: Option<Option<DhcpOverrides>>,
+    /// Identifier sent to the DHCP server, `duid` or `mac`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<DhcpIdentifier>)]
+    pub dhcp_identifier: Option<Option<DhcpIdentifier>>,
+    /// Families to configure link-local addresses for. An empty list disables link-local addressing.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<Vec<LinkLocalKind>>)]
+    pub link_local: Option<Option<Vec<LinkLocalKind>>>,
+    /// Whether boot should not wait for the interface to come online. `null` clears the flag.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<bool>)]
+    pub optional: Option<Option<bool>>,
+    /// Whether IPv6 privacy extensions (RFC 4941) are used for the autoconfigured addresses.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<bool>)]
+    pub ipv6_privacy: Option<Option<bool>>,
+    /// How the addresses autoconfigured from router advertisements are generated.
+    /// Requires `accept-ra` not to be disabled.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<Ipv6AddressGeneration>)]
+    pub ipv6_address_generation: Option<Option<Ipv6AddressGeneration>>,
+    /// Renderer of this interface, `networkd` or `NetworkManager`, instead of the network's one.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<NetworkRenderer>)]
+    pub renderer: Option<Option<NetworkRenderer>>,
+    /// Whether the interface is brought up automatically (`null`), manually (`manual`) or kept down (`off`).
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<ActivationMode>)]
+    pub activation_mode: Option<Option<ActivationMode>>,
+    /// Number of SR-IOV virtual functions to create on this physical function, up to 255.
+    /// Changing it may require a reload of the driver.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<u16>)]
+    pub virtual_function_count: Option<Option<u16>>,
+    /// Hardware offloads to enable or disable. Those left out keep the driver's defaults.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<Offloads>)]
+    pub offloads: Option<Option<Offloads>>,
+    /// Whether Wake-on-LAN is enabled on the interface.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<bool>)]
+    pub wakeonlan: Option<Option<bool>>,
+    /// MAC address to set on the interface instead of its own.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<MacAddress>)]
+    pub macaddress: Option<Option<MacAddress>>,
+    /// Selects the interfaces this configuration applies to.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<Match>)]
+    pub r#match: Option<Option<Match>>,
+    /// Name given to the matched interface. Must be a valid kernel interface name.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<String>)]
+    pub set_name: Option<Option<String>>,
+    /// Labels grouping the interface with others, replacing the current ones. Only kept for Ethernets.
+    pub labels: Option<HashSet<String>>,
+    /// Whether boot waits for the interface, and until which operational state, overriding
+    /// `optional`. `null` leaves it to `optional`. Only kept for Ethernets.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schema(value_type = Option<RequiredForOnline>)]
+    pub required_for_online: Option<Option<RequiredForOnline>>,
+}
+
+impl InputDevice {
+    /// Sets the given settings on `device`, clearing those given as `null` and keeping the absent
+    /// ones. The labels and `required-for-online` are left to the caller, as only Ethernets keep them.
+    pub fn update(&self, device: &mut impl Device) {
+        if let Some(dhcp4) = self.dhcp4 {
+            device.set_dhcp4(dhcp4);
+        }
+        if let Some(dhcp6) = self.dhcp6 {
+            device.set_dhcp6(dhcp6);
+        }
+        if let Some(overrides) = &self.dhcp4_overrides {
+            device.set_dhcp4_overrides(overrides.clone());
+        }
+        if let Some(overrides) = &self.dhcp6_overrides {
+            device.set_dhcp6_overrides(overrides.clone());
+        }
+        if let Some(identifier) = self.dhcp_identifier {
+            device.set_dhcp_identifier(identifier);
+        }
+        if let Some(accept_ra) = self.accept_ra {
+            device.set_accept_ra(accept_ra);
+        }
+        if let Some(generation) = self.ipv6_address_generation {
+            device.set_ipv6_address_generation(generation);
+        }
+        if let Some(renderer) = self.renderer {
+            device.set_renderer(renderer);
+        }
+        if let Some(activation_mode) = self.activation_mode {
+            device.set_activation_mode(activation_mode);
+        }
+        if let Some(count) = self.virtual_function_count {
+            device.set_virtual_function_count(count);
+        }
+        if let Some(offloads) = &self.offloads {
+            device.set_offloads(offloads.clone());
+        }
+        if let Some(mtu) = self.mtu {
+            device.set_mtu(mtu);
+        }
+        if let Some(mtu) = self.ipv6_mtu {
+            device.set_ipv6_mtu(mtu);
+        }
+        if let Some(link_local) = &self.link_local {
+            device.set_link_local(link_local.clone());
+        }
+        if let Some(optional) = self.optional {
+            device.set_optional(optional);
+        }
+        if let Some(ipv6_privacy) = self.ipv6_privacy {
+            device.set_ipv6_privacy(ipv6_privacy);
+        }
+        if let Some(wakeonlan) = self.wakeonlan {
+            device.set_wakeonlan(wakeonlan);
+        }
+        if let Some(macaddress) = self.macaddress {
+            device.set_macaddress(macaddress);
+        }
+        if let Some(device_match) = &self.r#match {
+            device.set_match(device_match.clone());
+        }
+        if let Some(set_name) = &self.set_name {
+            device.set_set_name(set_name.clone());
+        }
+    }
+}
+
+/// A new VLAN, with its tag and the Ethernet it is created on.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputVlan {
+    /// VLAN id (tag), between 1 and 4094.
+    pub id: u16,
+    /// Name of the configured Ethernet the VLAN is created on.
+    pub link: String,
+    /// Settings of the VLAN interface. Those only physical interfaces have are ignored.
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// Partial update of a VLAN. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputVlanPatch {
+    pub id: Option<u16>,
+    pub link: Option<String>,
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// A new bridge, with its member interfaces.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputBridge {
+    /// Names of the configured Ethernets, VLANs or bonds to enslave to the bridge.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Settings of the bridge interface. Those only physical interfaces have are ignored.
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// Partial update of a bridge. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputBridgePatch {
+    /// The new complete list of member interfaces.
+    pub interfaces: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// A new bond, with the Ethernets it aggregates.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputBond {
+    /// Names of the configured Ethernets to enslave to the bond. They may not have addresses or
+    /// DHCP enabled themselves.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Settings of the bond interface. Those only physical interfaces have are ignored.
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// Partial update of a bond. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputBondPatch {
+    /// The new complete list of member Ethernets.
+    pub interfaces: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// A new wifi, with the wireless networks it connects to.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputWifi {
+    /// Access points of the wireless networks, keyed by SSID.
+    #[serde(default)]
+    pub access_points: HashMap<String, AccessPoint>,
+    /// Settings of the wifi interface. Those only Ethernets have are ignored.
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// Partial update of a wifi. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputWifiPatch {
+    /// The new complete map of access points.
+    pub access_points: Option<HashMap<String, AccessPoint>>,
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// A new tunnel, with its endpoints.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputTunnel {
+    pub mode: TunnelMode,
+    /// Address of this end of the tunnel, of the family the mode runs over. Required but by VXLAN.
+    #[schema(value_type = Option<String>)]
+    pub local: Option<IpAddr>,
+    /// Unicast address of the other end of the tunnel, which may not be one of the host's own.
+    /// Required but by VXLAN.
+    #[schema(value_type = Option<String>)]
+    pub remote: Option<IpAddr>,
+    /// Multicast group of a VXLAN, instead of a unicast `remote`.
+    #[schema(value_type = Option<String>)]
+    pub group: Option<IpAddr>,
+    /// Key of the tunnel, only accepted by the GRE modes.
+    pub key: Option<TunnelKey>,
+    /// Network identifier of a VXLAN, between 1 and 16777215. Required by VXLAN.
+    #[schema(value_type = Option<u32>, minimum = 1, maximum = 16777215)]
+    pub id: Option<Vni>,
+    /// Configured interface a VXLAN sends its packets through, required to join a group.
+    pub link: Option<String>,
+    /// Destination UDP port of a VXLAN.
+    pub port: Option<u16>,
+    /// Settings of the tunnel interface. Those only physical interfaces have are ignored.
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// Partial update of a tunnel. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputTunnelPatch {
+    pub mode: Option<TunnelMode>,
+    #[schema(value_type = Option<String>)]
+    pub local: Option<IpAddr>,
+    #[schema(value_type = Option<String>)]
+    pub remote: Option<IpAddr>,
+    /// Replaces the remote address as well.
+    #[schema(value_type = Option<String>)]
+    pub group: Option<IpAddr>,
+    pub key: Option<TunnelKey>,
+    #[schema(value_type = Option<u32>, minimum = 1, maximum = 16777215)]
+    pub id: Option<Vni>,
+    pub link: Option<String>,
+    pub port: Option<u16>,
+    #[serde(flatten)]
+    pub device: InputDevice,
+}
+
+/// A new VRF, binding interfaces to its own routing table.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputVrf {
+    /// Routing table of the VRF, which no other VRF may use.
+    #[schema(value_type = u32, minimum = 1)]
+    pub table: NonZeroU32,
+    /// Names of the configured interfaces to bind to the VRF.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Routes of the VRF, installed into its table.
+    #[serde(default)]
+    pub routes: Vec<InputRoute>,
+    #[serde(default)]
+    pub routing_policy: Vec<InputRoutingPolicy>,
+}
+
+/// Partial update of a VRF. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct InputVrfPatch {
+    #[schema(value_type = Option<u32>, minimum = 1)]
+    pub table: Option<NonZeroU32>,
+    /// The new complete list of member interfaces.
+    pub interfaces: Option<Vec<String>>,
+    /// The new complete list of routes.
+    pub routes: Option<Vec<InputRoute>>,
+    /// The new complete list of routing policy rules.
+    pub routing_policy: Option<Vec<InputRoutingPolicy>>,
+}
+
+/// An interface to enslave to a device.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputMember {
+    /// Name of the configured interface.
+    pub interface: String,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -22,4 +488,210 @@ pub struct InputRoute {
     pub to: String,
     pub from: Option<String>,
     pub via: Option<String>,
+    pub metric: Option<u32>,
+    /// Routing table the route is installed into. Must not be zero.
+    #[schema(value_type = Option<u32>, minimum = 1)]
+    pub table: Option<NonZeroU32>,
+    /// Scope of the route. Link scoped routes cannot have a gateway (`via`).
+    pub scope: Option<RouteScope>,
+    /// Type of the route. Only `unicast` routes can have a gateway (`via`).
+    pub r#type: Option<RouteType>,
+}
+
+/// Partial update of a route. Absent fields keep their current value.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputRoutePatch {
+    pub to: Option<String>,
+    pub from: Option<String>,
+    pub via: Option<String>,
+    pub metric: Option<u32>,
+    #[schema(value_type = Option<u32>, minimum = 1)]
+    pub table: Option<NonZeroU32>,
+    pub scope: Option<RouteScope>,
+    pub r#type: Option<RouteType>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputRename {
+    /// The name the Ethernet entry is moved to.
+    pub new_name: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct InputReset {
+    /// Whether DHCPv4 is enabled on the reset Ethernet.
+    #[serde(default)]
+    pub dhcp4: bool,
+}
+
+/// Administrative state of a link.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkState {
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputLink {
+    pub state: LinkState,
+}
+
+/// A packet capture to start, whose settings left out take their defaults.
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct InputCapture {
+    /// How long the capture runs at most, in seconds, from 1 to 60. Defaults to 30.
+    pub duration: Option<u32>,
+    /// How many packets are captured at most, from 1 to 10000. Defaults to 1000.
+    pub packet_count: Option<u32>,
+    /// How many bytes are kept of each packet, from 64 to 1518. Defaults to 1518.
+    pub snaplen: Option<u32>,
+    /// Which packets are captured, every one if left out.
+    pub filter: Option<CaptureFilter>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputGateway {
+    /// Address of the gateway. Its IP version selects the default route being set.
+    pub via: String,
+    pub metric: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct GatewayQuery {
+    /// Only remove the gateway of this IP version. Both are removed when absent.
+    pub family: Option<AddressFamily>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputRoutingPolicy {
+    /// Source network of the traffic, in CIDR notation.
+    pub from: Option<String>,
+    /// Destination network of the traffic, in CIDR notation.
+    pub to: Option<String>,
+    #[schema(value_type = Option<u32>, minimum = 1)]
+    pub table: Option<NonZeroU32>,
+    pub priority: Option<u32>,
+    /// Firewall mark of the traffic.
+    pub mark: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Default)]
+pub struct InputNameservers {
+    #[serde(default)]
+    pub search: Vec<String>,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+}
+
+/// Replacement of the nameservers of an Ethernet. Absent halves are left untouched.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct InputNameserversReplacement {
+    pub search: Option<Vec<String>>,
+    pub addresses: Option<Vec<String>>,
+}
+
+/// Complete desired state of an Ethernet.
+///
+/// Unlike `InputDevice`, nothing is left untouched: an absent setting is unset, and an absent
+/// collection is the same as an empty one, removing everything that was configured in it.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EthernetSpec {
+    #[serde(default)]
+    pub dhcp4: bool,
+    #[serde(default)]
+    pub dhcp6: bool,
+    pub dhcp4_overrides: Option<DhcpOverrides>,
+    pub dhcp6_overrides: Option<DhcpOverrides>,
+    pub dhcp_identifier: Option<DhcpIdentifier>,
+    pub accept_ra: Option<bool>,
+    pub ipv6_address_generation: Option<Ipv6AddressGeneration>,
+    pub ipv6_privacy: Option<bool>,
+    pub link_local: Option<Vec<LinkLocalKind>>,
+    pub mtu: Option<MTU>,
+    pub ipv6_mtu: Option<MTUV6>,
+    pub optional: Option<bool>,
+    pub required_for_online: Option<RequiredForOnline>,
+    pub renderer: Option<NetworkRenderer>,
+    pub activation_mode: Option<ActivationMode>,
+    pub virtual_function_count: Option<u16>,
+    pub offloads: Option<Offloads>,
+    pub wakeonlan: Option<bool>,
+    pub macaddress: Option<MacAddress>,
+    pub r#match: Option<Match>,
+    pub set_name: Option<String>,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub routes: Vec<InputRoute>,
+    #[serde(default)]
+    pub routing_policy: Vec<InputRoutingPolicy>,
+    #[serde(default)]
+    pub nameservers: InputNameservers,
+}
+
+#[derive(Deserialize)]
+pub struct ApplyQuery {
+    /// Apply the changes right away. Otherwise, they are only saved until the next apply.
+    #[serde(default = "default_apply")]
+    pub apply: bool,
+}
+
+fn default_apply() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct TryQuery {
+    /// Seconds after which the tried configuration is reverted, unless confirmed.
+    #[serde(default = "default_try_timeout")]
+    pub timeout: u32,
+}
+
+fn default_try_timeout() -> u32 {
+    120
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DetailedQuery {
+    /// List the dynamic addresses as well, each annotated with where it comes from.
+    #[serde(default)]
+    pub detailed: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CascadeQuery {
+    /// Remove the devices built on the removed one as well, and release it from the devices it
+    /// is a member of, instead of refusing the removal.
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RebuildStateQuery {
+    /// The files to rebuild, separated by commas, e.g. `metadata,pending-actions`. All of them if
+    /// absent.
+    pub targets: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ForceQuery {
+    /// Apply the change even though it conflicts with the configuration of other interfaces, or
+    /// restore a backup even though how it relates to the current configuration is unknown.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ReplaceQuery {
+    /// Overwrite an existing entry with the same identifier instead of rejecting the request.
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Deserialize)]
+pub struct DryRunQuery {
+    #[serde(default)]
+    pub dry_run: bool,
 }
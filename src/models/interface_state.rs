@@ -0,0 +1,59 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::dhcp_probe::DhcpProbeResult;
+use crate::system::SYS_CLASS_NET_PATH;
+
+/// Operational state of an interface, as seen by the kernel.
+///
+/// Every attribute is optional, since not all of them are exposed for every kind of
+/// interface (e.g. virtual interfaces have no speed) or while the interface is down.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct InterfaceState {
+    pub interface: String,
+    /// RFC 2863 operational state, e.g. `up`, `down` or `unknown`.
+    pub operstate: Option<String>,
+    /// Whether the physical link is detected.
+    pub carrier: Option<bool>,
+    /// Negotiated speed of the link, in Mbit/s.
+    pub speed_mbps: Option<u32>,
+    /// Current MAC address of the interface.
+    pub mac: Option<String>,
+    pub mtu: Option<u32>,
+    /// Why the interface is still waiting for its DHCP lease, if it was when the configuration
+    /// was last applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_probe: Option<DhcpProbeResult>,
+}
+
+impl InterfaceState {
+    /// Reads the state of the given interface from `/sys/class/net/<interface>`.
+    pub fn read(interface: &str) -> io::Result<Self> {
+        let directory = Path::new(SYS_CLASS_NET_PATH).join(interface);
+        if !directory.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Interface {interface} does not exist in the system."),
+            ));
+        }
+        // Reading some attributes fails with EINVAL while the interface is down
+        let attribute = |name: &str| {
+            fs::read_to_string(directory.join(name))
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(Self {
+            interface: interface.to_string(),
+            operstate: attribute("operstate"),
+            carrier: attribute("carrier").map(|carrier| carrier == "1"),
+            // An unknown speed is reported as -1
+            speed_mbps: attribute("speed").and_then(|speed| speed.parse().ok()),
+            mac: attribute("address"),
+            mtu: attribute("mtu").and_then(|mtu| mtu.parse().ok()),
+            dhcp_probe: None,
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+
+use crate::{custom_types::IpNetwork, system::SYS_CLASS_NET_PATH};
+/// `IFF_LOOPBACK` from `<net/if.h>`, as exposed in `/sys/class/net/<iface>/flags`.
+const IFF_LOOPBACK: u32 = 0x8;
+
+/// Checks whether the given interface is a loopback interface.
+///
+/// `lo` is always considered a loopback interface. Any other interface is
+/// classified by the loopback flag the kernel reports for it in sysfs.
+pub fn is_loopback(interface: &str) -> bool {
+    if interface == "lo" {
+        return true;
+    }
+    fs::read_to_string(format!("{SYS_CLASS_NET_PATH}/{interface}/flags"))
+        .is_ok_and(|flags| has_loopback_flag(&flags))
+}
+
+/// Whether the flags of an interface, as read from sysfs (e.g. `0x1003`), have the loopback bit.
+fn has_loopback_flag(flags: &str) -> bool {
+    u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16)
+        .is_ok_and(|flags| flags & IFF_LOOPBACK != 0)
+}
+
+/// Lists all loopback interfaces present in the system.
+pub fn list_loopback_interfaces() -> Vec<String> {
+    let mut result = vec![];
+    if let Ok(entries) = fs::read_dir(SYS_CLASS_NET_PATH) {
+        for entry in entries.flatten() {
+            if let Ok(name) = entry.file_name().into_string() {
+                if is_loopback(&name) {
+                    result.push(name);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Validates an extra (VIP) address that should be configured on a loopback interface.
+///
+/// Only host routes (`/32` for IPv4 and `/128` for IPv6) are allowed, and the
+/// implicit `127.0.0.0/8` and `::1/128` addresses must never be touched.
+pub fn validate_loopback_address(address: &IpNetwork) -> Result<(), String> {
+    let ip = address.ip();
+    if ip.is_loopback() {
+        return Err(format!(
+            "Address {ip} belongs to the implicit loopback range and cannot be modified."
+        ));
+    }
+    if !address.is_host() {
+        return Err(
+            "Only host addresses (/32 or /128) can be configured on a loopback interface."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopbacks_are_told_by_their_name_or_flag() {
+        assert!(is_loopback("lo"));
+        assert!(!is_loopback("does-not-exist0"));
+        assert!(has_loopback_flag("0x9\n"));
+        assert!(has_loopback_flag("0x49"));
+        assert!(!has_loopback_flag("0x1003\n"));
+        assert!(!has_loopback_flag("garbage"));
+    }
+
+    #[test]
+    fn only_extra_host_addresses_are_allowed() {
+        for address in ["10.255.0.1/32", "192.0.2.10/32", "2001:db8::10/128"] {
+            let address: IpNetwork = address.parse().unwrap();
+            assert_eq!(validate_loopback_address(&address), Ok(()), "{address}");
+        }
+        for address in [
+            "10.255.0.0/24",
+            "2001:db8::/64",
+            "127.0.0.1/8",
+            "127.0.0.2/32",
+            "::1/128",
+        ] {
+            let address: IpNetwork = address.parse().unwrap();
+            assert!(validate_loopback_address(&address).is_err(), "{address}");
+        }
+    }
+}
@@ -1,7 +1,39 @@
+pub mod access_point;
+pub mod address_conflict;
+pub mod annotated_address;
+pub mod bond;
+pub mod bond_parameters;
+pub mod bridge;
+pub mod bridge_parameters;
+pub mod capture;
+pub mod change_set;
 pub mod device;
+pub mod device_listing;
+pub mod device_match;
+pub mod device_settings;
+pub mod dhcp_overrides;
+pub mod dhcp_probe;
+pub mod dummy_device;
 pub mod ethernet;
+pub mod health;
 pub mod host_info;
 pub mod input_models;
+pub mod interface_state;
+pub mod loopback;
 pub mod nameservers;
 pub mod network;
+pub mod offloads;
+pub mod openvswitch;
+pub mod pending_action;
+pub mod reachability;
+pub mod resource_usage;
 pub mod route;
+pub mod routing_policy;
+pub mod sidecar;
+pub mod storage;
+pub mod tunnel;
+pub mod validation;
+pub mod vlan;
+pub mod vrf;
+pub mod wait_online;
+pub mod wifi;
@@ -6,15 +6,17 @@ use std::{collections::HashSet, net::IpAddr};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Nameservers {
     #[serde(
+        default,
         serialize_with = "serialize_hash_set_from_string_as_yaml_sequence",
         skip_serializing_if = "HashSet::is_empty"
     )]
     pub search: HashSet<String>,
     #[serde(
+        default,
         serialize_with = "serialize_hash_set_from_ip_addr_as_yaml_sequence",
         skip_serializing_if = "HashSet::is_empty"
     )]
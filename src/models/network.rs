@@ -1,23 +1,170 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    num::NonZeroU32,
+};
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use super::ethernet::Ethernet;
+use crate::{custom_types::IpNetwork, misc::serialize_hash_map_sorted};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use super::{
+    bond::Bond,
+    bridge::Bridge,
+    device::Device,
+    device_listing::{DeviceKind, Enslavement},
+    dummy_device::DummyDevice,
+    ethernet::{Ethernet, EthernetSummary},
+    route::Route,
+    tunnel::Tunnel,
+    vlan::Vlan,
+    vrf::Vrf,
+    wifi::Wifi,
+};
+
+/// Backend netplan renders the configuration for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum NetworkRenderer {
+    #[default]
     #[serde(rename = "networkd")]
     NetworkD,
+    #[serde(rename = "NetworkManager")]
     NetworkManager,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl NetworkRenderer {
+    /// Name of the systemd unit that renders the configuration for this backend.
+    pub fn service_unit(&self) -> &'static str {
+        match self {
+            NetworkRenderer::NetworkD => "systemd-networkd.service",
+            NetworkRenderer::NetworkManager => "NetworkManager.service",
+        }
+    }
+}
+
+/// Access to the devices of one kind in the network, e.g. its ethernets.
+///
+/// The handlers of the sub-resources every kind of device has (addresses, nameservers, routes)
+/// are written once over this trait.
+pub trait DeviceMap<D> {
+    /// Name of the kind of device, as used in messages.
+    const KIND: &'static str;
+    fn get_devices(&self) -> &HashMap<String, D>;
+    fn add_device(&mut self, device: &D);
+}
+
+/// Devices which may be defined in another netplan configuration file than the one the API
+/// manages. Netplan merges every file, but the API only writes its own, so such devices are read
+/// only.
+pub trait ConfigOrigin {
+    /// Name of the other file defining the device, if any.
+    fn managed_by(&self) -> Option<String>;
+    fn set_managed_by(&mut self, file: Option<String>);
+}
+
+/// Checks that `updated` holds the devices of `current` defined in other files unchanged, and
+/// marks them as such again.
+fn keep_foreign<D>(
+    current: &HashMap<String, D>,
+    updated: &mut HashMap<String, D>,
+) -> Result<(), String>
+where
+    D: ConfigOrigin + PartialEq,
+{
+    let mut names: Vec<&String> = current.keys().collect();
+    names.sort();
+    for name in names {
+        let device = &current[name];
+        let Some(file) = device.managed_by() else {
+            continue;
+        };
+        if let Some(kept) = updated.get_mut(name) {
+            kept.set_managed_by(Some(file.clone()));
+            if kept == device {
+                continue;
+            }
+        }
+        return Err(format!(
+            "{name} is defined in {file}, which is not managed by this API. Change it there instead."
+        ));
+    }
+    Ok(())
+}
+
+/// The devices which are not defined in other files.
+fn without_foreign<D>(devices: &HashMap<String, D>) -> HashMap<String, D>
+where
+    D: ConfigOrigin + Clone,
+{
+    devices
+        .iter()
+        .filter(|(_, device)| device.managed_by().is_none())
+        .map(|(name, device)| (name.clone(), device.clone()))
+        .collect()
+}
+
+/// Marks the devices found in `owners` with the file defining them.
+fn mark_foreign<D: ConfigOrigin>(
+    devices: &mut HashMap<String, D>,
+    owners: &HashMap<String, String>,
+) {
+    for (name, device) in devices.iter_mut() {
+        device.set_managed_by(owners.get(name).cloned());
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Network {
     pub version: usize,
+    /// Netplan renders for networkd unless told otherwise.
+    #[serde(default)]
     pub renderer: NetworkRenderer,
+    #[serde(default, serialize_with = "serialize_hash_map_sorted")]
     ethernets: HashMap<String, Ethernet>,
-    // pub vlans: Vec<Vlan>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    vlans: HashMap<String, Vlan>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    bridges: HashMap<String, Bridge>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    bonds: HashMap<String, Bond>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    wifis: HashMap<String, Wifi>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    tunnels: HashMap<String, Tunnel>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    dummy_devices: HashMap<String, DummyDevice>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    vrfs: HashMap<String, Vrf>,
 }
 
 impl Default for Network {
@@ -32,6 +179,13 @@ impl Network {
             version: 2,
             renderer: NetworkRenderer::NetworkD,
             ethernets: HashMap::new(),
+            vlans: HashMap::new(),
+            bridges: HashMap::new(),
+            bonds: HashMap::new(),
+            wifis: HashMap::new(),
+            tunnels: HashMap::new(),
+            dummy_devices: HashMap::new(),
+            vrfs: HashMap::new(),
         }
     }
 
@@ -43,7 +197,748 @@ impl Network {
         self.ethernets.insert(ethernet.name(), ethernet.clone());
     }
 
+    pub fn remove_ethernet(&mut self, name: &str) -> Option<Ethernet> {
+        self.ethernets.remove(name)
+    }
+
+    /// The summaries of `ethernets`, sorted by name. Those which are not part of this network are
+    /// reported as unmanaged.
+    pub fn ethernet_summaries<'a>(
+        &self,
+        ethernets: impl IntoIterator<Item = &'a Ethernet>,
+    ) -> Vec<EthernetSummary> {
+        let mut summaries: Vec<EthernetSummary> = ethernets
+            .into_iter()
+            .map(|ethernet| ethernet.summary(self.ethernets.contains_key(&ethernet.name())))
+            .collect();
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        summaries
+    }
+
     pub fn set_ethernets(&mut self, ethernets: HashMap<String, Ethernet>) {
         self.ethernets = ethernets;
     }
+
+    pub fn get_vlans(&self) -> &HashMap<String, Vlan> {
+        &self.vlans
+    }
+
+    pub fn add_vlan(&mut self, vlan: &Vlan) {
+        self.vlans.insert(vlan.name(), vlan.clone());
+    }
+
+    pub fn remove_vlan(&mut self, name: &str) -> Option<Vlan> {
+        self.vlans.remove(name)
+    }
+
+    /// Names of the VLANs created on the interface `link`, sorted.
+    pub fn vlans_on(&self, link: &str) -> Vec<String> {
+        let mut result: Vec<String> = self
+            .vlans
+            .values()
+            .filter(|vlan| vlan.link() == link)
+            .map(|vlan| vlan.name())
+            .collect();
+        result.sort();
+        result
+    }
+
+    pub fn get_bridges(&self) -> &HashMap<String, Bridge> {
+        &self.bridges
+    }
+
+    pub fn add_bridge(&mut self, bridge: &Bridge) {
+        self.bridges.insert(bridge.name(), bridge.clone());
+    }
+
+    pub fn remove_bridge(&mut self, name: &str) -> Option<Bridge> {
+        self.bridges.remove(name)
+    }
+
+    pub fn get_bonds(&self) -> &HashMap<String, Bond> {
+        &self.bonds
+    }
+
+    pub fn add_bond(&mut self, bond: &Bond) {
+        self.bonds.insert(bond.name(), bond.clone());
+    }
+
+    pub fn remove_bond(&mut self, name: &str) -> Option<Bond> {
+        self.bonds.remove(name)
+    }
+
+    pub fn get_wifis(&self) -> &HashMap<String, Wifi> {
+        &self.wifis
+    }
+
+    pub fn add_wifi(&mut self, wifi: &Wifi) {
+        self.wifis.insert(wifi.name(), wifi.clone());
+    }
+
+    pub fn remove_wifi(&mut self, name: &str) -> Option<Wifi> {
+        self.wifis.remove(name)
+    }
+
+    pub fn get_tunnels(&self) -> &HashMap<String, Tunnel> {
+        &self.tunnels
+    }
+
+    pub fn add_tunnel(&mut self, tunnel: &Tunnel) {
+        self.tunnels.insert(tunnel.name(), tunnel.clone());
+    }
+
+    pub fn remove_tunnel(&mut self, name: &str) -> Option<Tunnel> {
+        self.tunnels.remove(name)
+    }
+
+    pub fn get_dummy_devices(&self) -> &HashMap<String, DummyDevice> {
+        &self.dummy_devices
+    }
+
+    pub fn add_dummy_device(&mut self, dummy_device: &DummyDevice) {
+        self.dummy_devices
+            .insert(dummy_device.name(), dummy_device.clone());
+    }
+
+    pub fn remove_dummy_device(&mut self, name: &str) -> Option<DummyDevice> {
+        self.dummy_devices.remove(name)
+    }
+
+    pub fn get_vrfs(&self) -> &HashMap<String, Vrf> {
+        &self.vrfs
+    }
+
+    pub fn add_vrf(&mut self, vrf: &Vrf) {
+        self.vrfs.insert(vrf.name(), vrf.clone());
+    }
+
+    pub fn remove_vrf(&mut self, name: &str) -> Option<Vrf> {
+        self.vrfs.remove(name)
+    }
+
+    /// Whether an interface of any kind is configured under `name`.
+    pub fn is_configured(&self, name: &str) -> bool {
+        self.ethernets.contains_key(name)
+            || self.vlans.contains_key(name)
+            || self.bridges.contains_key(name)
+            || self.bonds.contains_key(name)
+            || self.wifis.contains_key(name)
+            || self.tunnels.contains_key(name)
+            || self.dummy_devices.contains_key(name)
+            || self.vrfs.contains_key(name)
+    }
+
+    /// Name of the device the interface `member` is enslaved to, if any.
+    pub fn master_of(&self, member: &str) -> Option<String> {
+        let bridges = self
+            .bridges
+            .values()
+            .filter(|bridge| bridge.interfaces().iter().any(|name| name == member))
+            .map(|bridge| bridge.name());
+        let bonds = self
+            .bonds
+            .values()
+            .filter(|bond| bond.interfaces().iter().any(|name| name == member))
+            .map(|bond| bond.name());
+        bridges.chain(bonds).next()
+    }
+
+    /// The bridge or bond the interface `member` is enslaved to, if any, along with its kind.
+    pub fn enslavement_of(&self, member: &str) -> Option<Enslavement> {
+        let master = self.master_of(member)?;
+        let kind = if self.bridges.contains_key(&master) {
+            DeviceKind::Bridge
+        } else {
+            DeviceKind::Bond
+        };
+        Some(Enslavement { kind, name: master })
+    }
+
+    /// Reports `member` if it is enslaved to another device than `master`.
+    fn check_master(&self, master: &str, member: &str) -> Option<String> {
+        self.master_of(member)
+            .filter(|other| other != master)
+            .map(|other| format!("{member} is already a member of {other}."))
+    }
+
+    /// Checks that `members` can be enslaved to the bridge `master`.
+    ///
+    /// Every member has to be a configured Ethernet, VLAN or bond, not enslaved to another device
+    /// yet. Every offending member is reported, one per line.
+    pub fn validate_members(&self, master: &str, members: &[String]) -> Result<(), String> {
+        let mut errors = vec![];
+        for member in members {
+            if !self.ethernets.contains_key(member)
+                && !self.vlans.contains_key(member)
+                && !self.bonds.contains_key(member)
+            {
+                errors.push(format!(
+                    "{member} is not a configured Ethernet, VLAN or bond."
+                ));
+            } else if let Some(err) = self.check_master(master, member) {
+                errors.push(err);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Checks that `members` can be aggregated by the bond `master`.
+    ///
+    /// Every member has to be a configured Ethernet, not enslaved to another device yet and
+    /// without any addressing of its own, as the bond carries it. Every offending member is
+    /// reported, one per line, along with its conflicting settings.
+    pub fn validate_bond_members(&self, master: &str, members: &[String]) -> Result<(), String> {
+        let mut errors = vec![];
+        for member in members {
+            let Some(ethernet) = self.ethernets.get(member) else {
+                errors.push(format!("{member} is not a configured Ethernet."));
+                continue;
+            };
+            if let Some(err) = self.check_master(master, member) {
+                errors.push(err);
+                continue;
+            }
+            let mut conflicts = vec![];
+            if !ethernet.get_addresses().is_empty() {
+                conflicts.push("addresses");
+            }
+            if ethernet.get_dhcp4() {
+                conflicts.push("dhcp4");
+            }
+            if ethernet.get_dhcp6() {
+                conflicts.push("dhcp6");
+            }
+            if !conflicts.is_empty() {
+                errors.push(format!(
+                    "{member} has its own configuration: {}.",
+                    conflicts.join(", ")
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Devices referencing the interface `name`, which its removal would leave dangling: the VLANs
+    /// and tunnels using it as link, and the bridges, bonds and VRFs it is a member of.
+    ///
+    /// Sorted by kind, then by name.
+    pub fn find_dependents(&self, name: &str) -> Vec<(DeviceKind, String)> {
+        let is_member = |interfaces: Vec<String>| interfaces.iter().any(|member| member == name);
+        let vlans = self
+            .vlans_on(name)
+            .into_iter()
+            .map(|vlan| (DeviceKind::Vlan, vlan));
+        let tunnels = self
+            .tunnels
+            .values()
+            .filter(|tunnel| tunnel.link().is_some_and(|link| link == name))
+            .map(|tunnel| (DeviceKind::Tunnel, tunnel.name()));
+        let bridges = self
+            .bridges
+            .values()
+            .filter(|bridge| is_member(bridge.interfaces()))
+            .map(|bridge| (DeviceKind::Bridge, bridge.name()));
+        let bonds = self
+            .bonds
+            .values()
+            .filter(|bond| is_member(bond.interfaces()))
+            .map(|bond| (DeviceKind::Bond, bond.name()));
+        let vrfs = self
+            .vrfs
+            .values()
+            .filter(|vrf| is_member(vrf.interfaces()))
+            .map(|vrf| (DeviceKind::Vrf, vrf.name()));
+        let mut result: Vec<(DeviceKind, String)> = vlans
+            .chain(tunnels)
+            .chain(bridges)
+            .chain(bonds)
+            .chain(vrfs)
+            .collect();
+        result.sort();
+        result
+    }
+
+    /// Removes every reference to the interface `name` found by `find_dependents`.
+    ///
+    /// The VLANs and tunnels built on the interface are removed, along with their own dependents,
+    /// while the bridges, bonds and VRFs it is a member of merely release it.
+    pub fn remove_dependents(&mut self, name: &str) {
+        let release = |interfaces: Vec<String>| -> Vec<String> {
+            interfaces
+                .into_iter()
+                .filter(|member| member != name)
+                .collect()
+        };
+        for (kind, dependent) in self.find_dependents(name) {
+            match kind {
+                DeviceKind::Vlan => {
+                    self.remove_dependents(&dependent);
+                    self.vlans.remove(&dependent);
+                }
+                DeviceKind::Tunnel => {
+                    self.remove_dependents(&dependent);
+                    self.tunnels.remove(&dependent);
+                }
+                DeviceKind::Bridge => {
+                    if let Some(bridge) = self.bridges.get_mut(&dependent) {
+                        bridge.set_interfaces(release(bridge.interfaces()));
+                    }
+                }
+                DeviceKind::Bond => {
+                    if let Some(bond) = self.bonds.get_mut(&dependent) {
+                        bond.set_interfaces(release(bond.interfaces()));
+                    }
+                }
+                DeviceKind::Vrf => {
+                    if let Some(vrf) = self.vrfs.get_mut(&dependent) {
+                        vrf.set_interfaces(release(vrf.interfaces()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Renames the Ethernet `old` to `new`, rewriting every reference to it found by
+    /// `find_dependents`, including the primary member of a bond.
+    ///
+    /// Returns whether the Ethernet was found.
+    pub fn rename_ethernet(&mut self, old: &str, new: &str) -> bool {
+        let dependents = self.find_dependents(old);
+        let Some(mut ethernet) = self.ethernets.remove(old) else {
+            return false;
+        };
+        ethernet.set_name(new.to_string());
+        self.ethernets.insert(new.to_string(), ethernet);
+        let rename = |interfaces: Vec<String>| -> Vec<String> {
+            interfaces
+                .into_iter()
+                .map(|member| {
+                    if member == old {
+                        new.to_string()
+                    } else {
+                        member
+                    }
+                })
+                .collect()
+        };
+        for (kind, dependent) in dependents {
+            match kind {
+                DeviceKind::Vlan => {
+                    if let Some(vlan) = self.vlans.get_mut(&dependent) {
+                        vlan.set_link(new.to_string());
+                    }
+                }
+                DeviceKind::Tunnel => {
+                    if let Some(tunnel) = self.tunnels.get_mut(&dependent) {
+                        tunnel.set_link(Some(new.to_string()));
+                    }
+                }
+                DeviceKind::Bridge => {
+                    if let Some(bridge) = self.bridges.get_mut(&dependent) {
+                        bridge.set_interfaces(rename(bridge.interfaces()));
+                    }
+                }
+                DeviceKind::Bond => {
+                    if let Some(bond) = self.bonds.get_mut(&dependent) {
+                        // The primary is renamed first, so it is kept as a member
+                        let mut parameters = bond.parameters();
+                        if parameters.primary.as_deref() == Some(old) {
+                            parameters.primary = Some(new.to_string());
+                        }
+                        bond.set_parameters(parameters);
+                        bond.set_interfaces(rename(bond.interfaces()));
+                    }
+                }
+                DeviceKind::Vrf => {
+                    if let Some(vrf) = self.vrfs.get_mut(&dependent) {
+                        vrf.set_interfaces(rename(vrf.interfaces()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Name of the VRF the interface `member` is bound to, if any.
+    pub fn vrf_of(&self, member: &str) -> Option<String> {
+        self.vrfs
+            .values()
+            .find(|vrf| vrf.interfaces().iter().any(|name| name == member))
+            .map(|vrf| vrf.name())
+    }
+
+    /// Name of another VRF than `vrf` already using `table`, if any.
+    pub fn vrf_using_table(&self, vrf: &str, table: NonZeroU32) -> Option<String> {
+        let mut users: Vec<String> = self
+            .vrfs
+            .values()
+            .filter(|other| other.name() != vrf && other.table() == table)
+            .map(|other| other.name())
+            .collect();
+        users.sort();
+        users.into_iter().next()
+    }
+
+    /// Checks that `members` can be bound to the VRF `vrf`.
+    ///
+    /// Every member has to be a configured interface other than a VRF, neither enslaved to a
+    /// bridge or bond, which is bound in its place, nor bound to another VRF. Every offending
+    /// member is reported, one per line.
+    pub fn validate_vrf_members(&self, vrf: &str, members: &[String]) -> Result<(), String> {
+        let mut errors = vec![];
+        for member in members {
+            if !self.is_configured(member) || self.vrfs.contains_key(member) {
+                errors.push(format!("{member} is not a configured interface."));
+            } else if let Some(master) = self.master_of(member) {
+                errors.push(format!("{member} is already a member of {master}."));
+            } else if let Some(other) = self.vrf_of(member).filter(|other| other != vrf) {
+                errors.push(format!("{member} is already a member of VRF {other}."));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Describes how `route` of the interface `name` escapes the table of the VRF the interface
+    /// is bound to, if it does.
+    pub fn check_vrf_table(&self, name: &str, route: &Route) -> Option<String> {
+        let vrf = self.vrfs.get(&self.vrf_of(name)?)?;
+        let table = route.table.filter(|table| *table != vrf.table())?;
+        Some(format!(
+            "Route {} of {name} targets table {table}, while the VRF {} it belongs to uses table {}.",
+            route.id(),
+            vrf.name(),
+            vrf.table()
+        ))
+    }
+
+    /// Static addresses and routes of every configured interface, whatever its kind, keyed by name.
+    pub fn interfaces(&self) -> HashMap<String, (HashSet<IpNetwork>, HashMap<String, Route>)> {
+        let ethernets = self.ethernets.iter().map(|(name, ethernet)| {
+            (
+                name.clone(),
+                (ethernet.get_addresses(), ethernet.get_routes()),
+            )
+        });
+        let vlans = self
+            .vlans
+            .iter()
+            .map(|(name, vlan)| (name.clone(), (vlan.get_addresses(), vlan.get_routes())));
+        let bridges = self
+            .bridges
+            .iter()
+            .map(|(name, bridge)| (name.clone(), (bridge.get_addresses(), bridge.get_routes())));
+        let bonds = self
+            .bonds
+            .iter()
+            .map(|(name, bond)| (name.clone(), (bond.get_addresses(), bond.get_routes())));
+        let wifis = self
+            .wifis
+            .iter()
+            .map(|(name, wifi)| (name.clone(), (wifi.get_addresses(), wifi.get_routes())));
+        let tunnels = self
+            .tunnels
+            .iter()
+            .map(|(name, tunnel)| (name.clone(), (tunnel.get_addresses(), tunnel.get_routes())));
+        let dummy_devices = self.dummy_devices.iter().map(|(name, dummy_device)| {
+            (
+                name.clone(),
+                (dummy_device.get_addresses(), dummy_device.get_routes()),
+            )
+        });
+        ethernets
+            .chain(vlans)
+            .chain(bridges)
+            .chain(bonds)
+            .chain(wifis)
+            .chain(tunnels)
+            .chain(dummy_devices)
+            .collect()
+    }
+
+    /// Name of the interface `address` is statically configured on, if any.
+    pub fn owner_of(&self, address: &IpAddr) -> Option<String> {
+        let mut owners: Vec<String> = self
+            .interfaces()
+            .into_iter()
+            .filter(|(_, (addresses, _))| addresses.iter().any(|network| network.ip() == *address))
+            .map(|(name, _)| name)
+            .collect();
+        owners.sort();
+        owners.into_iter().next()
+    }
+
+    /// Marks the devices defined in other configuration files, keyed by name in `owners`, with the
+    /// file defining them.
+    pub fn mark_foreign_devices(&mut self, owners: &HashMap<String, String>) {
+        mark_foreign(&mut self.ethernets, owners);
+        mark_foreign(&mut self.vlans, owners);
+        mark_foreign(&mut self.bridges, owners);
+        mark_foreign(&mut self.bonds, owners);
+        mark_foreign(&mut self.wifis, owners);
+        mark_foreign(&mut self.tunnels, owners);
+        mark_foreign(&mut self.dummy_devices, owners);
+        mark_foreign(&mut self.vrfs, owners);
+    }
+
+    /// Checks that `updated`, derived from this network, leaves the devices defined in other
+    /// configuration files as they are. They are marked in `updated` again, as it may have been
+    /// built from copies without the mark.
+    ///
+    /// The error names the first device changed or removed, and the file defining it.
+    pub fn keep_foreign_devices(&self, updated: &mut Network) -> Result<(), String> {
+        keep_foreign(&self.ethernets, &mut updated.ethernets)?;
+        keep_foreign(&self.vlans, &mut updated.vlans)?;
+        keep_foreign(&self.bridges, &mut updated.bridges)?;
+        keep_foreign(&self.bonds, &mut updated.bonds)?;
+        keep_foreign(&self.wifis, &mut updated.wifis)?;
+        keep_foreign(&self.tunnels, &mut updated.tunnels)?;
+        keep_foreign(&self.dummy_devices, &mut updated.dummy_devices)?;
+        keep_foreign(&self.vrfs, &mut updated.vrfs)
+    }
+
+    /// Returns a copy of the network without the devices defined in other configuration files,
+    /// i.e. what the API writes to its own file.
+    pub fn without_foreign_devices(&self) -> Self {
+        Self {
+            ethernets: without_foreign(&self.ethernets),
+            vlans: without_foreign(&self.vlans),
+            bridges: without_foreign(&self.bridges),
+            bonds: without_foreign(&self.bonds),
+            wifis: without_foreign(&self.wifis),
+            tunnels: without_foreign(&self.tunnels),
+            dummy_devices: without_foreign(&self.dummy_devices),
+            vrfs: without_foreign(&self.vrfs),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the network fit for exporting, with any secret masked.
+    ///
+    /// The only secrets are the passwords of wifi access points.
+    pub fn redacted(&self) -> Self {
+        Self {
+            wifis: self
+                .wifis
+                .iter()
+                .map(|(name, wifi)| (name.clone(), wifi.redacted()))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl DeviceMap<Ethernet> for Network {
+    const KIND: &'static str = "Ethernet";
+
+    fn get_devices(&self) -> &HashMap<String, Ethernet> {
+        self.get_ethernets()
+    }
+
+    fn add_device(&mut self, device: &Ethernet) {
+        self.add_ethernet(device);
+    }
+}
+
+impl DeviceMap<Vlan> for Network {
+    const KIND: &'static str = "VLAN";
+
+    fn get_devices(&self) -> &HashMap<String, Vlan> {
+        self.get_vlans()
+    }
+
+    fn add_device(&mut self, device: &Vlan) {
+        self.add_vlan(device);
+    }
+}
+
+impl DeviceMap<Bridge> for Network {
+    const KIND: &'static str = "Bridge";
+
+    fn get_devices(&self) -> &HashMap<String, Bridge> {
+        self.get_bridges()
+    }
+
+    fn add_device(&mut self, device: &Bridge) {
+        self.add_bridge(device);
+    }
+}
+
+impl DeviceMap<Bond> for Network {
+    const KIND: &'static str = "Bond";
+
+    fn get_devices(&self) -> &HashMap<String, Bond> {
+        self.get_bonds()
+    }
+
+    fn add_device(&mut self, device: &Bond) {
+        self.add_bond(device);
+    }
+}
+
+impl DeviceMap<Wifi> for Network {
+    const KIND: &'static str = "WiFi";
+
+    fn get_devices(&self) -> &HashMap<String, Wifi> {
+        self.get_wifis()
+    }
+
+    fn add_device(&mut self, device: &Wifi) {
+        self.add_wifi(device);
+    }
+}
+
+impl DeviceMap<Tunnel> for Network {
+    const KIND: &'static str = "Tunnel";
+
+    fn get_devices(&self) -> &HashMap<String, Tunnel> {
+        self.get_tunnels()
+    }
+
+    fn add_device(&mut self, device: &Tunnel) {
+        self.add_tunnel(device);
+    }
+}
+
+impl DeviceMap<DummyDevice> for Network {
+    const KIND: &'static str = "Dummy device";
+
+    fn get_devices(&self) -> &HashMap<String, DummyDevice> {
+        self.get_dummy_devices()
+    }
+
+    fn add_device(&mut self, device: &DummyDevice) {
+        self.add_dummy_device(device);
+    }
+}
+
+impl DeviceMap<Vrf> for Network {
+    const KIND: &'static str = "VRF";
+
+    fn get_devices(&self) -> &HashMap<String, Vrf> {
+        self.get_vrfs()
+    }
+
+    fn add_device(&mut self, device: &Vrf) {
+        self.add_vrf(device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netplan::Netplan;
+
+    #[test]
+    fn summarizes_the_ethernets() {
+        let mut network = Netplan::parse_config(
+            b"{version: 2, ethernets: {\
+                enp1s0: {dhcp4: true, routes: [{to: 10.1.0.0, via: 10.0.0.1}, {to: 10.2.0.0, via: 10.0.0.1}],\
+                    nameservers: {addresses: [10.0.0.53]}},\
+                enp2s0: {addresses: [192.168.1.2/24, 192.168.1.3/24]},\
+                enp3s0: {dhcp6: true, addresses: [fd00::2/64]},\
+                enp4s0: {}}}",
+        )
+        .unwrap();
+        let mut drifting = network.get_ethernets()["enp1s0"].clone();
+        drifting.set_system_state(serde_yml::from_str("missing_dhcp4_address: true").unwrap());
+        network.add_ethernet(&drifting);
+        let unmanaged = Ethernet::new("enp5s0".to_string());
+        let listed = network
+            .get_ethernets()
+            .values()
+            .chain([&unmanaged])
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let summaries = network.ethernet_summaries(&listed);
+
+        let names: Vec<&str> = summaries.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["enp1s0", "enp2s0", "enp3s0", "enp4s0", "enp5s0"]);
+        let modes: Vec<&str> = summaries
+            .iter()
+            .map(|s| s.addressing_mode.as_str())
+            .collect();
+        assert_eq!(modes, ["dhcp", "static", "dhcp+static", "none", "none"]);
+        let counts: Vec<(usize, usize)> = summaries
+            .iter()
+            .map(|s| (s.address_count, s.route_count))
+            .collect();
+        assert_eq!(counts, [(0, 2), (2, 0), (1, 0), (0, 0), (0, 0)]);
+        let nameservers: Vec<bool> = summaries.iter().map(|s| s.has_nameservers).collect();
+        assert_eq!(nameservers, [true, false, false, false, false]);
+        let drift: Vec<bool> = summaries.iter().map(|s| s.drift).collect();
+        assert_eq!(drift, [true, false, false, false, false]);
+        let managed: Vec<bool> = summaries.iter().map(|s| s.managed).collect();
+        assert_eq!(managed, [true, true, true, true, false]);
+    }
+
+    #[test]
+    fn removes_an_ethernet_once() {
+        let mut network =
+            Netplan::parse_config(b"{version: 2, ethernets: {enp1s0: {dhcp4: true}, enp2s0: {}}}")
+                .unwrap();
+        let removed = network.remove_ethernet("enp1s0").unwrap();
+        assert_eq!(removed.name(), "enp1s0");
+        assert!(network.remove_ethernet("enp1s0").is_none());
+        assert_eq!(
+            network.get_ethernets().keys().collect::<Vec<_>>(),
+            ["enp2s0"]
+        );
+    }
+
+    const DEPENDENCIES_CONFIG: &[u8] = b"{version: 2, \
+        ethernets: {eth0: {}, eth1: {}, eth2: {}}, \
+        vlans: {vlan10: {id: 10, link: eth0}}, \
+        tunnels: {vxlan1: {mode: vxlan, id: 1, link: eth0}}, \
+        bridges: {br0: {interfaces: [eth0, eth1]}}, \
+        bonds: {bond0: {interfaces: [eth0]}}, \
+        vrfs: {vrf1: {table: 100, interfaces: [eth0, eth2]}}}";
+
+    #[test]
+    fn finds_the_dependents_of_an_interface() {
+        let network = Netplan::parse_config(DEPENDENCIES_CONFIG).unwrap();
+        assert_eq!(
+            network.find_dependents("eth0"),
+            [
+                (DeviceKind::Vlan, "vlan10".to_string()),
+                (DeviceKind::Bridge, "br0".to_string()),
+                (DeviceKind::Bond, "bond0".to_string()),
+                (DeviceKind::Tunnel, "vxlan1".to_string()),
+                (DeviceKind::Vrf, "vrf1".to_string()),
+            ]
+        );
+        assert_eq!(
+            network.find_dependents("eth1"),
+            [(DeviceKind::Bridge, "br0".to_string())]
+        );
+        assert!(network.find_dependents("eth9").is_empty());
+        assert!(network.find_dependents("vlan10").is_empty());
+    }
+
+    #[test]
+    fn removing_the_dependents_leaves_no_dangling_reference() {
+        let mut network = Netplan::parse_config(DEPENDENCIES_CONFIG).unwrap();
+        network.remove_dependents("eth0");
+        network.remove_ethernet("eth0");
+        assert!(network.find_dependents("eth0").is_empty());
+        assert!(network.get_vlans().is_empty());
+        assert!(network.get_tunnels().is_empty());
+        assert_eq!(network.get_bridges()["br0"].interfaces(), ["eth1"]);
+        assert!(network.get_bonds()["bond0"].interfaces().is_empty());
+        assert_eq!(network.get_vrfs()["vrf1"].interfaces(), ["eth2"]);
+    }
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Hardware offloads of an interface, left to the driver's defaults unless set.
+///
+/// Netplan reads these keys directly from the interface's stanza.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct Offloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receive_checksum_offload: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transmit_checksum_offload: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_segmentation_offload: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generic_segmentation_offload: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generic_receive_offload: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_receive_offload: Option<bool>,
+}
+
+impl Offloads {
+    /// Whether no offload is set at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
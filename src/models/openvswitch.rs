@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Connection methods netplan accepts for an OpenFlow controller address.
+const CONTROLLER_METHODS: [&str; 6] = ["tcp:", "ssl:", "unix:", "ptcp:", "pssl:", "punix:"];
+
+/// How the bridge forwards traffic while it has no connection to its controller.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FailMode {
+    /// Only the flows set up by the controller are used.
+    Secure,
+    /// The bridge falls back to acting as a regular MAC learning switch.
+    Standalone,
+}
+
+/// OpenFlow protocol versions the bridge may speak with its controller.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum OpenFlowProtocol {
+    OpenFlow10,
+    OpenFlow11,
+    OpenFlow12,
+    OpenFlow13,
+    OpenFlow14,
+    OpenFlow15,
+}
+
+/// OpenFlow controllers the bridge connects to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct OpenVSwitchController {
+    /// Addresses of the controllers, e.g. `tcp:192.0.2.1:6653` or `unix:/run/ovs/controller`.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+}
+
+/// Open vSwitch settings of a bridge, written under its `openvswitch` key.
+///
+/// Setting them makes netplan create the bridge through Open vSwitch instead of the kernel, which
+/// requires the `openvswitch` package on the host. Unset fields are left out of the configuration.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub struct OpenVSwitch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_mode: Option<FailMode>,
+    /// OpenFlow versions the bridge supports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocols: Option<Vec<OpenFlowProtocol>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<OpenVSwitchController>,
+}
+
+impl OpenVSwitch {
+    /// Whether no setting is present at all.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Checks that every controller address uses a connection method known to Open vSwitch.
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(controller) = &self.controller else {
+            return Ok(());
+        };
+        for address in controller.addresses.iter() {
+            if !CONTROLLER_METHODS
+                .iter()
+                .any(|method| address.starts_with(method) && address.len() > method.len())
+            {
+                return Err(format!(
+                    "The controller address {address} must start with one of {}, followed by the \
+                    address to connect to or listen on.",
+                    CONTROLLER_METHODS.join(" ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Overlays the settings present in `other` on these ones. Protocols and controller addresses
+    /// are replaced as a whole, and an empty list removes them.
+    pub fn merge(&mut self, other: &OpenVSwitch) {
+        self.fail_mode = other.fail_mode.or(self.fail_mode);
+        if let Some(protocols) = &other.protocols {
+            self.protocols = Some(protocols.clone()).filter(|protocols| !protocols.is_empty());
+        }
+        if let Some(controller) = &other.controller {
+            self.controller = Some(controller.clone()).filter(|c| !c.addresses.is_empty());
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{netplan::write_atomically, system::SYS_CLASS_NET_PATH};
+
+use super::{
+    device::Device,
+    ethernet::Ethernet,
+    network::Network,
+    sidecar::{salvage_entries, set_aside, RebuildReport, Sidecar, WHOLE_FILE},
+};
+
+/// Changes on every boot, telling whether the system rebooted since a change was made.
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// A setting whose changes only take full effect after a reboot.
+struct RebootRule {
+    /// Key of the setting in netplan.
+    field: &'static str,
+    /// Drivers of the interfaces the rule is limited to, any driver if empty.
+    drivers: &'static [&'static str],
+    reason: &'static str,
+}
+
+/// The settings known to need a reboot.
+const REBOOT_RULES: [RebootRule; 4] = [
+    RebootRule {
+        field: "set-name",
+        drivers: &[],
+        reason: "Interfaces are only renamed when their device appears, so set-name takes effect at the next boot.",
+    },
+    RebootRule {
+        field: "match",
+        drivers: &[],
+        reason: "Devices are only matched when they appear, so match takes effect at the next boot.",
+    },
+    RebootRule {
+        field: "renderer",
+        drivers: &[],
+        reason: "The previous renderer keeps managing the interfaces until the next boot.",
+    },
+    // Virtual functions negotiate their MTU with the physical function when they are probed
+    RebootRule {
+        field: "mtu",
+        drivers: &["iavf", "i40evf", "ixgbevf"],
+        reason: "The driver of the interface only takes a new MTU when it is loaded, so at the next boot.",
+    },
+];
+
+/// The rule of `field` for an interface driven by `driver`, if its changes need a reboot.
+fn reboot_rule(field: &str, driver: Option<&str>) -> Option<&'static RebootRule> {
+    REBOOT_RULES.iter().find(|rule| {
+        rule.field == field
+            && (rule.drivers.is_empty()
+                || driver.is_some_and(|driver| rule.drivers.contains(&driver)))
+    })
+}
+
+/// What the system shows once a change took effect.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Expectation {
+    /// Any reboot puts the change into effect.
+    Reboot,
+    /// The system has an interface named `name`.
+    Interface { name: String },
+    /// `interface` has an MTU of `mtu`.
+    Mtu { interface: String, mtu: u32 },
+}
+
+/// A change made to the configuration which only takes full effect after a reboot.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct PendingAction {
+    /// The interface the change is for, none if it is for the whole network.
+    pub interface: Option<String>,
+    /// The setting that changed, as named in netplan, e.g. `set-name`.
+    pub field: String,
+    /// The value in effect, none if the setting was unset.
+    pub from: Option<String>,
+    /// The value waiting for a reboot, none if the setting is unset.
+    pub to: Option<String>,
+    /// Why the change waits for a reboot.
+    pub reason: String,
+    /// Entity tag of the configuration which introduced the change.
+    pub revision: String,
+    /// Boot during which the change was made.
+    pub boot_id: String,
+    /// What the system shows once the change took effect, checked after the next boot.
+    pub expected: Expectation,
+}
+
+/// `value` as written in the configuration.
+fn describe(value: &impl Serialize) -> String {
+    serde_yml::to_string(value)
+        .map(|yaml| yaml.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+/// The settings of `ethernet` the rules are about, with their values.
+fn reboot_settings(ethernet: Option<&Ethernet>) -> [(&'static str, Option<String>); 4] {
+    [
+        ("set-name", ethernet.and_then(Device::get_set_name)),
+        (
+            "match",
+            ethernet
+                .and_then(Device::get_match)
+                .map(|r#match| describe(&r#match)),
+        ),
+        (
+            "renderer",
+            ethernet
+                .and_then(Device::get_renderer)
+                .map(|renderer| describe(&renderer)),
+        ),
+        (
+            "mtu",
+            ethernet
+                .and_then(Device::get_mtu)
+                .map(|mtu| mtu.value().to_string()),
+        ),
+    ]
+}
+
+/// The changes from `current` to `updated` which need a reboot, made in `revision` during boot
+/// `boot_id`, sorted by interface.
+///
+/// The rules concern the physical interfaces, i.e. the Ethernets, and the network as a whole.
+/// The driver of an interface, which some rules are limited to, is found with `driver_of`.
+pub fn classify(
+    current: &Network,
+    updated: &Network,
+    revision: &str,
+    boot_id: &str,
+    driver_of: impl Fn(&str) -> Option<String>,
+) -> Vec<PendingAction> {
+    let mut result = vec![];
+    let mut push = |interface: Option<&String>, field: &str, from, to, reason, expected| {
+        result.push(PendingAction {
+            interface: interface.cloned(),
+            field: field.to_string(),
+            from,
+            to,
+            reason,
+            revision: revision.to_string(),
+            boot_id: boot_id.to_string(),
+            expected,
+        })
+    };
+    if current.renderer != updated.renderer {
+        let rule = reboot_rule("renderer", None).expect("renderer switches need a reboot");
+        push(
+            None,
+            rule.field,
+            Some(describe(&current.renderer)),
+            Some(describe(&updated.renderer)),
+            rule.reason.to_string(),
+            Expectation::Reboot,
+        );
+    }
+    let mut names: Vec<&String> = current
+        .get_ethernets()
+        .keys()
+        .chain(updated.get_ethernets().keys())
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let before = current.get_ethernets().get(name);
+        let after = updated.get_ethernets().get(name);
+        // The name of the interface in the system, as long as the rename is pending, and after
+        let kernel_name = before
+            .and_then(Device::get_set_name)
+            .unwrap_or_else(|| name.clone());
+        let booted_name = after
+            .and_then(Device::get_set_name)
+            .unwrap_or_else(|| name.clone());
+        let changes = reboot_settings(before)
+            .into_iter()
+            .zip(reboot_settings(after))
+            .filter(|((_, from), (_, to))| from != to);
+        for ((field, from), (_, to)) in changes {
+            let driver = driver_of(&kernel_name);
+            let Some(rule) = reboot_rule(field, driver.as_deref()) else {
+                continue;
+            };
+            let expected = match (field, &to) {
+                ("set-name", Some(new_name)) => Expectation::Interface {
+                    name: new_name.clone(),
+                },
+                ("mtu", Some(mtu)) => match mtu.parse() {
+                    Ok(mtu) => Expectation::Mtu {
+                        interface: booted_name.clone(),
+                        mtu,
+                    },
+                    Err(_) => Expectation::Reboot,
+                },
+                _ => Expectation::Reboot,
+            };
+            push(
+                Some(name),
+                field,
+                from,
+                to,
+                rule.reason.to_string(),
+                expected,
+            );
+        }
+    }
+    result
+}
+
+/// Marks the Ethernets of `network` affected by `actions` as waiting for a reboot, with the
+/// reasons. The changes for the whole network affect every Ethernet.
+pub fn mark_pending_reboot(network: &mut Network, actions: &[PendingAction]) {
+    let ethernets: Vec<(String, Ethernet)> = network
+        .get_ethernets()
+        .iter()
+        .map(|(name, ethernet)| (name.clone(), ethernet.clone()))
+        .collect();
+    for (name, mut ethernet) in ethernets {
+        let reasons: Vec<String> = actions
+            .iter()
+            .filter(|action| {
+                action
+                    .interface
+                    .as_ref()
+                    .is_none_or(|interface| *interface == name)
+            })
+            .map(|action| action.reason.clone())
+            .collect();
+        if !reasons.is_empty() {
+            ethernet.set_pending_reboot(reasons);
+            network.add_ethernet(&ethernet);
+        }
+    }
+}
+
+/// The changes waiting for a reboot, kept in a file so they survive restarts of the service.
+///
+/// After a reboot, the changes which took effect are cleared the next time they are listed,
+/// while the others are kept until the system shows them.
+pub struct PendingActions {
+    /// File the changes are kept in.
+    path: PathBuf,
+    /// File holding the id of the current boot.
+    boot_id_path: PathBuf,
+    /// Directory laid out like `/sys/class/net`, where the interfaces are checked.
+    sys_class_net: PathBuf,
+}
+
+impl PendingActions {
+    /// Keeps the changes in the file at `path`.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            boot_id_path: PathBuf::from(BOOT_ID_PATH),
+            sys_class_net: PathBuf::from(SYS_CLASS_NET_PATH),
+        }
+    }
+
+    /// Reads the id of the boot from `boot_id_path`, and checks the interfaces in
+    /// `sys_class_net`, instead of the system ones.
+    pub fn with_system_paths(mut self, boot_id_path: PathBuf, sys_class_net: PathBuf) -> Self {
+        self.boot_id_path = boot_id_path;
+        self.sys_class_net = sys_class_net;
+        self
+    }
+
+    fn boot_id(&self) -> io::Result<String> {
+        Ok(fs::read_to_string(&self.boot_id_path)?.trim().to_string())
+    }
+
+    /// Name of the driver of `interface`, if it is backed by a device.
+    fn driver(&self, interface: &str) -> Option<String> {
+        let driver =
+            fs::read_link(self.sys_class_net.join(interface).join("device/driver")).ok()?;
+        Some(driver.file_name()?.to_string_lossy().to_string())
+    }
+
+    fn load(&self) -> io::Result<Vec<PendingAction>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => serde_yml::from_str(&content)
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether the file can't be read, a missing file being empty.
+    pub fn is_corrupt(&self) -> bool {
+        self.load()
+            .is_err_and(|err| err.kind() == ErrorKind::InvalidData)
+    }
+
+    /// Rebuilds a corrupt file from the changes which can still be read, setting the file aside.
+    /// A readable file is left as is, so rebuilding again changes nothing.
+    pub fn rebuild(&self) -> io::Result<RebuildReport> {
+        let content = match fs::read_to_string(&self.path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(RebuildReport::intact(Sidecar::PendingActions))
+            }
+            content => content?,
+        };
+        if serde_yml::from_str::<Vec<PendingAction>>(&content).is_ok() {
+            return Ok(RebuildReport::intact(Sidecar::PendingActions));
+        }
+        let (actions, lost) = match serde_yml::from_str(&content) {
+            Ok(value) => salvage_entries::<PendingAction>(&value, |position| {
+                format!("pending action {position}")
+            }),
+            Err(_) => (vec![], vec![WHOLE_FILE.to_string()]),
+        };
+        let actions: Vec<PendingAction> = actions.into_iter().map(|(_, action)| action).collect();
+        let mut report = RebuildReport::salvaged(Sidecar::PendingActions, actions.len(), lost);
+        report.set_aside = Some(set_aside(&self.path)?);
+        self.save(&actions)?;
+        Ok(report)
+    }
+
+    /// Writes `actions`, removing the file when there are none left.
+    fn save(&self, actions: &[PendingAction]) -> io::Result<()> {
+        if actions.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        let yaml_string = serde_yml::to_string(actions)
+            .expect("Error: couldn't serialize the pending actions into YAML string.");
+        write_atomically(&self.path, yaml_string.as_bytes())
+    }
+
+    /// Records the changes from `current` to `updated` which need a reboot, `updated` being saved
+    /// as `revision`.
+    ///
+    /// A change replaces the pending change of the same setting, and a change undone during the
+    /// same boot is dropped, as what is in effect never changed.
+    pub fn record(&self, current: &Network, updated: &Network, revision: &str) -> io::Result<()> {
+        let boot_id = self.boot_id()?;
+        let mut actions = self.load()?;
+        let changes = classify(current, updated, revision, &boot_id, |interface| {
+            self.driver(interface)
+        });
+        if changes.is_empty() {
+            return Ok(());
+        }
+        for mut change in changes {
+            let earlier = actions.iter().position(|action| {
+                action.interface == change.interface && action.field == change.field
+            });
+            if let Some(earlier) = earlier.map(|index| actions.remove(index)) {
+                if earlier.boot_id == boot_id {
+                    if earlier.from == change.to {
+                        continue;
+                    }
+                    change.from = earlier.from;
+                }
+            }
+            actions.push(change);
+        }
+        self.save(&actions)
+    }
+
+    /// The changes still waiting for a reboot.
+    ///
+    /// The changes made before the current boot are checked against the system, and cleared if
+    /// they took effect.
+    pub fn list(&self) -> io::Result<Vec<PendingAction>> {
+        let actions = self.load()?;
+        if actions.is_empty() {
+            return Ok(actions);
+        }
+        let boot_id = self.boot_id()?;
+        let count = actions.len();
+        let remaining: Vec<PendingAction> = actions
+            .into_iter()
+            .filter(|action| action.boot_id == boot_id || !self.in_effect(&action.expected))
+            .collect();
+        if remaining.len() != count {
+            log::info!(
+                "{} changes took effect since the last boot.",
+                count - remaining.len()
+            );
+            self.save(&remaining)?;
+        }
+        Ok(remaining)
+    }
+
+    /// Whether the system shows `expected`, a reboot having happened.
+    fn in_effect(&self, expected: &Expectation) -> bool {
+        match expected {
+            Expectation::Reboot => true,
+            Expectation::Interface { name } => self.sys_class_net.join(name).exists(),
+            Expectation::Mtu { interface, mtu } => {
+                read_number(&self.sys_class_net.join(interface).join("mtu")) == Some(*mtu)
+            }
+        }
+    }
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::sidecar::RebuildOutcome;
+    use crate::netplan::Netplan;
+    use std::os::unix::fs::symlink;
+
+    fn network(yaml: &str) -> Network {
+        Netplan::parse_config(yaml.as_bytes()).unwrap()
+    }
+
+    /// A system with `interfaces` of the given driver and MTU, booted as `boot_id`.
+    fn system(interfaces: &[(&str, &str, u32)], boot_id: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("boot_id"), format!("{boot_id}\n")).unwrap();
+        for (name, driver, mtu) in interfaces {
+            add_interface(&dir, name, driver, *mtu);
+        }
+        dir
+    }
+
+    fn add_interface(dir: &tempfile::TempDir, name: &str, driver: &str, mtu: u32) {
+        let interface = dir.path().join("net").join(name);
+        let driver_path = dir.path().join("drivers").join(driver);
+        fs::create_dir_all(interface.join("device")).unwrap();
+        fs::create_dir_all(&driver_path).unwrap();
+        symlink(driver_path, interface.join("device/driver")).unwrap();
+        fs::write(interface.join("mtu"), format!("{mtu}\n")).unwrap();
+    }
+
+    fn pending_actions(dir: &tempfile::TempDir) -> PendingActions {
+        PendingActions::new(dir.path().join("01-network-conf.yaml.pending"))
+            .with_system_paths(dir.path().join("boot_id"), dir.path().join("net"))
+    }
+
+    fn no_driver(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn renames_matches_and_renderer_switches_need_a_reboot() {
+        let current = network("{version: 2, ethernets: {eth0: {dhcp4: true}, eth1: {}}}");
+        let updated = network(
+            "{version: 2, renderer: NetworkManager, ethernets: {\
+                eth0: {dhcp4: false, set-name: lan0}, \
+                eth1: {match: {driver: e1000e}, renderer: networkd}}}",
+        );
+        let actions = classify(&current, &updated, "\"1\"", "boot", no_driver);
+        let summary: Vec<(Option<&str>, &str, Option<&str>, &Expectation)> = actions
+            .iter()
+            .map(|action| {
+                (
+                    action.interface.as_deref(),
+                    action.field.as_str(),
+                    action.to.as_deref(),
+                    &action.expected,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    None,
+                    "renderer",
+                    Some("NetworkManager"),
+                    &Expectation::Reboot
+                ),
+                (
+                    Some("eth0"),
+                    "set-name",
+                    Some("lan0"),
+                    &Expectation::Interface {
+                        name: "lan0".to_string()
+                    }
+                ),
+                (
+                    Some("eth1"),
+                    "match",
+                    Some("driver: e1000e"),
+                    &Expectation::Reboot
+                ),
+                (
+                    Some("eth1"),
+                    "renderer",
+                    Some("networkd"),
+                    &Expectation::Reboot
+                ),
+            ]
+        );
+        assert!(actions
+            .iter()
+            .all(|action| action.revision == "\"1\"" && action.boot_id == "boot"));
+        // Changing anything else takes effect on apply
+        let dhcp_only = network("{version: 2, ethernets: {eth0: {dhcp4: false}, eth1: {}}}");
+        assert!(classify(&current, &dhcp_only, "\"1\"", "boot", no_driver).is_empty());
+    }
+
+    #[test]
+    fn mtu_changes_need_a_reboot_only_on_some_drivers() {
+        let current = network("{version: 2, ethernets: {eth0: {}, eth1: {}}}");
+        let updated = network("{version: 2, ethernets: {eth0: {mtu: 9000}, eth1: {mtu: 9000}}}");
+        let driver_of = |interface: &str| {
+            Some(
+                if interface == "eth0" {
+                    "iavf"
+                } else {
+                    "e1000e"
+                }
+                .to_string(),
+            )
+        };
+        let actions = classify(&current, &updated, "\"1\"", "boot", driver_of);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].interface.as_deref(), Some("eth0"));
+        assert_eq!(
+            actions[0].expected,
+            Expectation::Mtu {
+                interface: "eth0".to_string(),
+                mtu: 9000
+            }
+        );
+        // Without a device, the driver is unknown
+        assert!(classify(&current, &updated, "\"1\"", "boot", no_driver).is_empty());
+    }
+
+    #[test]
+    fn pending_actions_survive_restarts() {
+        let dir = system(&[("eth0", "iavf", 1500)], "first");
+        let current = network("{version: 2, ethernets: {eth0: {}}}");
+        let updated = network("{version: 2, ethernets: {eth0: {mtu: 9000, set-name: lan0}}}");
+        pending_actions(&dir)
+            .record(&current, &updated, "\"1\"")
+            .unwrap();
+
+        let actions = pending_actions(&dir).list().unwrap();
+        assert_eq!(
+            actions,
+            classify(&current, &updated, "\"1\"", "first", |_| {
+                Some("iavf".to_string())
+            })
+        );
+        assert_eq!(actions.len(), 2);
+
+        // Undone before a reboot, the rename never was pending
+        pending_actions(&dir)
+            .record(
+                &updated,
+                &network("{version: 2, ethernets: {eth0: {mtu: 9000}}}"),
+                "\"2\"",
+            )
+            .unwrap();
+        let actions = pending_actions(&dir).list().unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].field, "mtu");
+        assert_eq!(actions[0].revision, "\"1\"");
+    }
+
+    #[test]
+    fn a_reboot_clears_the_changes_that_took_effect() {
+        let dir = system(&[("eth0", "iavf", 1500), ("eth1", "iavf", 1500)], "first");
+        let current = network("{version: 2, ethernets: {eth0: {}, eth1: {}}}");
+        let updated = network(
+            "{version: 2, renderer: NetworkManager, ethernets: {\
+                eth0: {mtu: 9000}, eth1: {mtu: 9000, set-name: lan1}}}",
+        );
+        let pending = pending_actions(&dir);
+        pending.record(&current, &updated, "\"1\"").unwrap();
+        assert_eq!(pending.list().unwrap().len(), 4);
+
+        // Reboot: eth1 was renamed, eth0 took its MTU, but the driver of lan1 did not
+        fs::write(dir.path().join("boot_id"), "second\n").unwrap();
+        fs::remove_dir_all(dir.path().join("net/eth1")).unwrap();
+        fs::write(dir.path().join("net/eth0/mtu"), "9000\n").unwrap();
+        add_interface(&dir, "lan1", "iavf", 1500);
+
+        let actions = pending.list().unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].interface.as_deref(), Some("eth1"));
+        assert_eq!(actions[0].field, "mtu");
+        // Still pending across restarts of the service, until the system shows it
+        assert_eq!(pending_actions(&dir).list().unwrap(), actions);
+        fs::write(dir.path().join("net/lan1/mtu"), "9000\n").unwrap();
+        assert!(pending.list().unwrap().is_empty());
+        assert!(!dir.path().join("01-network-conf.yaml.pending").exists());
+    }
+
+    #[test]
+    fn affected_ethernets_are_marked() {
+        let mut network = network("{version: 2, ethernets: {eth0: {}, eth1: {}}}");
+        let updated = {
+            let mut updated = network.clone();
+            let mut eth0 = updated.get_ethernets()["eth0"].clone();
+            eth0.set_set_name(Some("lan0".to_string()));
+            updated.add_ethernet(&eth0);
+            updated
+        };
+        let actions = classify(&network, &updated, "\"1\"", "boot", no_driver);
+        mark_pending_reboot(&mut network, &actions);
+        assert_eq!(
+            network.get_ethernets()["eth0"].pending_reboot_reasons(),
+            [REBOOT_RULES[0].reason]
+        );
+        assert!(network.get_ethernets()["eth1"]
+            .pending_reboot_reasons()
+            .is_empty());
+        let json = serde_json::to_value(&network.get_ethernets()["eth0"]).unwrap();
+        assert_eq!(json["pending-reboot"], true);
+        assert!(
+            serde_json::to_value(&network.get_ethernets()["eth1"]).unwrap()["pending-reboot"]
+                .is_null()
+        );
+    }
+
+    #[test]
+    fn a_corrupt_file_is_rebuilt_from_the_readable_changes() {
+        let dir = system(&[("eth0", "iavf", 1500)], "first");
+        let current = network("{version: 2, ethernets: {eth0: {}}}");
+        pending_actions(&dir)
+            .record(
+                &current,
+                &network("{version: 2, ethernets: {eth0: {set-name: lan0}}}"),
+                "\"1\"",
+            )
+            .unwrap();
+        pending_actions(&dir)
+            .record(
+                &current,
+                &network("{version: 2, ethernets: {eth0: {set-name: lan0, mtu: 9000}}}"),
+                "\"2\"",
+            )
+            .unwrap();
+        let path = dir.path().join("01-network-conf.yaml.pending");
+        let mut actions: serde_yml::Value =
+            serde_yml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let kept = actions[0].clone();
+        actions[1]["expected"] = "nonsense".into();
+        actions.as_sequence_mut().unwrap().push(42.into());
+        fs::write(&path, serde_yml::to_string(&actions).unwrap()).unwrap();
+        assert!(pending_actions(&dir).is_corrupt());
+
+        let report = pending_actions(&dir).rebuild().unwrap();
+        assert_eq!(report.outcome, RebuildOutcome::PartiallyRecovered);
+        assert_eq!(report.recovered, 1);
+        assert_eq!(report.lost, ["pending action 2", "pending action 3"]);
+        let set_aside = report.set_aside.unwrap();
+        assert_eq!(set_aside, format!("{}.corrupt", path.display()));
+        assert!(Path::new(&set_aside).exists());
+        assert!(!pending_actions(&dir).is_corrupt());
+        let actions = pending_actions(&dir).list().unwrap();
+        assert_eq!(
+            serde_yml::to_value(&actions).unwrap(),
+            serde_yml::Value::Sequence(vec![kept])
+        );
+
+        // Rebuilding again finds nothing to do
+        assert_eq!(
+            pending_actions(&dir).rebuild().unwrap(),
+            RebuildReport::intact(Sidecar::PendingActions)
+        );
+
+        fs::write(&path, "{[").unwrap();
+        let report = pending_actions(&dir).rebuild().unwrap();
+        assert_eq!(report.outcome, RebuildOutcome::Unrecoverable);
+        assert_eq!(report.lost, [WHOLE_FILE]);
+        assert!(!path.exists());
+        assert!(pending_actions(&dir).list().unwrap().is_empty());
+    }
+}
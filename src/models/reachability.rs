@@ -0,0 +1,209 @@
+use std::{fmt, net::IpAddr};
+
+use serde::{Serialize, Serializer};
+use utoipa::ToSchema;
+
+use crate::custom_types::IpNetwork;
+
+use super::{nameservers::Nameservers, network::Network, route::RouteType};
+
+/// How an address can be reached from the interface it is configured on.
+///
+/// The analysis is purely static: it only looks at the configured subnets and routes,
+/// so routes learned through DHCP or router advertisements are not taken into account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reachability {
+    /// The address is on one of the interface's subnets or reachable through one of its routes.
+    ThisInterface,
+    /// The best matching subnet or route belongs to another interface.
+    OtherInterface(String),
+    /// No configured subnet or route covers the address.
+    NoKnownRoute,
+}
+
+impl fmt::Display for Reachability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ThisInterface => write!(f, "this-interface"),
+            Self::OtherInterface(name) => write!(f, "other-interface:{name}"),
+            Self::NoKnownRoute => write!(f, "no-known-route"),
+        }
+    }
+}
+
+impl Serialize for Reachability {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A nameserver address, annotated with how it can be reached.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct NameserverAddress {
+    #[schema(value_type = String)]
+    pub address: IpAddr,
+    /// One of `this-interface`, `other-interface:<name>` or `no-known-route`.
+    #[schema(value_type = String)]
+    pub reachable_via: Reachability,
+}
+
+/// Nameservers of an interface, with the reachability of each of their addresses.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct AnnotatedNameservers {
+    pub search: Vec<String>,
+    pub addresses: Vec<NameserverAddress>,
+}
+
+/// Finds out how `address` would be reached from the interface `interface`.
+///
+/// The subnets and routes of every interface in the network are matched against the
+/// address, and the longest prefix wins. On a tie, the given interface is preferred.
+/// Routes only carry a destination address, so they match either as a default route
+/// (unspecified destination) or as a host route; a route to a whole subnet can't be
+/// configured through the model, so it is never matched. Blackhole, unreachable and
+/// prohibit routes lead nowhere, so they are left out.
+pub fn reachable_via(network: &Network, interface: &str, address: &IpAddr) -> Reachability {
+    let host_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let mut best: Option<(u8, &str)> = None;
+    let interfaces = network.interfaces();
+    for (name, (addresses, routes)) in interfaces.iter() {
+        let subnets = addresses.iter().copied();
+        let routes = routes.values().filter_map(|route| {
+            if route
+                .r#type
+                .is_some_and(|route_type| route_type != RouteType::Unicast)
+            {
+                return None;
+            }
+            let prefix = if route.to.is_unspecified() {
+                0
+            } else {
+                host_prefix
+            };
+            IpNetwork::new(route.to, prefix).ok()
+        });
+        for destination in subnets.chain(routes) {
+            if !destination.contains(address) {
+                continue;
+            }
+            let prefix = destination.prefix();
+            let better = match best {
+                None => true,
+                Some((best_prefix, _)) => {
+                    prefix > best_prefix || (prefix == best_prefix && name == interface)
+                }
+            };
+            if better {
+                best = Some((prefix, name.as_str()));
+            }
+        }
+    }
+    match best {
+        None => Reachability::NoKnownRoute,
+        Some((_, name)) if name == interface => Reachability::ThisInterface,
+        Some((_, name)) => Reachability::OtherInterface(name.to_string()),
+    }
+}
+
+impl AnnotatedNameservers {
+    /// Annotates the nameservers configured on `interface` with their reachability.
+    pub fn new(network: &Network, interface: &str, nameservers: &Nameservers) -> Self {
+        let mut search: Vec<String> = nameservers.search.iter().cloned().collect();
+        search.sort();
+        let mut addresses: Vec<IpAddr> = nameservers.addresses.iter().copied().collect();
+        addresses.sort();
+        Self {
+            search,
+            addresses: addresses
+                .into_iter()
+                .map(|address| NameserverAddress {
+                    address,
+                    reachable_via: reachable_via(network, interface, &address),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netplan::Netplan;
+
+    const CONFIG: &str = "network:
+  version: 2
+  ethernets:
+    eth0:
+      addresses: [10.0.0.5/24, 2001:db8:1::5/64]
+      routes:
+        - to: 192.168.5.53
+          via: 10.0.0.1
+        - to: 172.16.0.53
+          type: blackhole
+        - to: 172.16.0.54
+          type: unreachable
+        - to: 172.16.0.55
+          type: prohibit
+    eth1:
+      addresses: [10.1.0.5/24]
+      routes:
+        - to: default
+          via: 10.1.0.1
+";
+
+    #[test]
+    fn addresses_are_reached_through_the_longest_prefix() {
+        let network = Netplan::parse_config(CONFIG.as_bytes()).unwrap();
+        let other = Reachability::OtherInterface("eth1".to_string());
+        for (interface, address, expected) in [
+            // On the subnet of the interface
+            ("eth0", "10.0.0.53", Reachability::ThisInterface),
+            ("eth0", "2001:db8:1::53", Reachability::ThisInterface),
+            // Through a route of the interface
+            ("eth0", "192.168.5.53", Reachability::ThisInterface),
+            // On the subnet of another interface
+            ("eth0", "10.1.0.53", other.clone()),
+            // Through the default route of another interface
+            ("eth0", "8.8.8.8", other.clone()),
+            ("eth1", "8.8.8.8", Reachability::ThisInterface),
+            // A host route wins over the default route
+            (
+                "eth1",
+                "192.168.5.53",
+                Reachability::OtherInterface("eth0".to_string()),
+            ),
+            // Routes leading nowhere don't count, so the default route of eth1 is used
+            ("eth0", "172.16.0.53", other.clone()),
+            ("eth0", "172.16.0.54", other.clone()),
+            ("eth0", "172.16.0.55", other),
+            // The default route is an IPv4 one, and there is no IPv6 route
+            ("eth0", "2001:db8:2::53", Reachability::NoKnownRoute),
+            ("eth1", "2001:db8:2::53", Reachability::NoKnownRoute),
+        ] {
+            let address: IpAddr = address.parse().unwrap();
+            assert_eq!(
+                reachable_via(&network, interface, &address),
+                expected,
+                "{address} from {interface}"
+            );
+        }
+    }
+
+    #[test]
+    fn nothing_is_reached_without_a_route() {
+        let network = Netplan::parse_config(
+            b"network: {version: 2, ethernets: {eth0: {addresses: [10.0.0.5/24]}}}",
+        )
+        .unwrap();
+        let address: IpAddr = "172.16.0.1".parse().unwrap();
+        assert_eq!(
+            reachable_via(&network, "eth0", &address),
+            Reachability::NoKnownRoute
+        );
+        assert_eq!(Reachability::NoKnownRoute.to_string(), "no-known-route");
+        assert_eq!(
+            Reachability::OtherInterface("eth1".to_string()).to_string(),
+            "other-interface:eth1"
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// How many entries an in-memory structure of the service holds, to tell leaks apart in the field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct StructureUsage {
+    /// Name of the structure, e.g. `storage_evictions`.
+    pub name: String,
+    pub count: usize,
+    /// The most entries the structure held since the service started.
+    pub high_water_mark: usize,
+    /// The most entries the structure may hold, `None` if it is bounded by something else, like
+    /// the configured interfaces.
+    pub cap: Option<usize>,
+}
+
+/// The most entries a structure held, updated as it changes.
+#[derive(Debug, Default)]
+pub struct HighWaterMark(AtomicUsize);
+
+impl HighWaterMark {
+    /// Records that the structure holds `count` entries.
+    pub fn record(&self, count: usize) {
+        self.0.fetch_max(count, Ordering::Relaxed);
+    }
+
+    /// The usage of the structure `name`, which holds `count` entries.
+    pub fn usage(&self, name: &str, count: usize, cap: Option<usize>) -> StructureUsage {
+        self.record(count);
+        StructureUsage {
+            name: name.to_string(),
+            count,
+            high_water_mark: self.0.load(Ordering::Relaxed),
+            cap,
+        }
+    }
+}
@@ -1,14 +1,68 @@
 use crate::misc::{deserialize_ip, deserialize_ip_option, serialize_ip, serialize_ip_option};
-use std::net::{AddrParseError, IpAddr};
+use std::{
+    net::{AddrParseError, IpAddr, Ipv4Addr},
+    num::NonZeroU32,
+};
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use super::input_models::InputRoute;
+use super::input_models::{InputRoute, InputRoutePatch};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Kind of a route. Routes other than `unicast` drop the matching traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RouteType {
+    Unicast,
+    Blackhole,
+    Unreachable,
+    Prohibit,
+}
+
+impl RouteType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unicast => "unicast",
+            Self::Blackhole => "blackhole",
+            Self::Unreachable => "unreachable",
+            Self::Prohibit => "prohibit",
+        }
+    }
+}
+
+/// Distance to the destination of a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RouteScope {
+    Global,
+    /// The destination is directly reachable on the link, without a gateway.
+    Link,
+    Host,
+}
+
+/// IP version of an address or a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn of(address: &IpAddr) -> Self {
+        if address.is_ipv4() {
+            Self::Ipv4
+        } else {
+            Self::Ipv6
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Route {
     #[serde(
+        default,
         skip_serializing_if = "Option::is_none",
         serialize_with = "serialize_ip_option",
         deserialize_with = "deserialize_ip_option"
@@ -17,26 +71,44 @@ pub struct Route {
     #[serde(serialize_with = "serialize_ip", deserialize_with = "deserialize_ip")]
     pub to: IpAddr,
     #[serde(
+        default,
         skip_serializing_if = "Option::is_none",
         serialize_with = "serialize_ip_option",
         deserialize_with = "deserialize_ip_option"
     )]
     pub via: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<NonZeroU32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<RouteScope>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<RouteType>,
 }
 
 impl Route {
     pub fn new(to: IpAddr, via: Option<IpAddr>, from: Option<IpAddr>) -> Self {
-        Route { from, to, via }
+        Route {
+            from,
+            to,
+            via,
+            metric: None,
+            table: None,
+            scope: None,
+            r#type: None,
+        }
     }
 
-    pub fn from_input_route(input_route: &InputRoute) -> Result<Self, AddrParseError> {
+    pub fn from_input_route(input_route: &InputRoute) -> Result<Self, String> {
+        let invalid = |err: AddrParseError| format!("Invalid route to '{}': {err}", input_route.to);
         let result = Route {
             from: {
                 if let Some(from) = input_route.from.clone() {
                     let new_value = if from == "default" {
-                        "::/0".parse::<IpAddr>()?
+                        "::/0".parse::<IpAddr>().map_err(invalid)?
                     } else {
-                        from.parse::<IpAddr>()?
+                        from.parse::<IpAddr>().map_err(invalid)?
                     };
                     Some(new_value)
                 } else {
@@ -45,27 +117,97 @@ impl Route {
             },
             to: {
                 if input_route.to == "default" {
-                    "::/0".parse()?
+                    "::/0".parse().map_err(invalid)?
                 } else {
-                    input_route.to.parse()?
+                    input_route.to.parse().map_err(invalid)?
                 }
             },
             via: {
                 if let Some(via) = input_route.via.clone() {
                     let new_value = if via == "default" {
-                        "::/0".parse::<IpAddr>()?
+                        "::/0".parse::<IpAddr>().map_err(invalid)?
                     } else {
-                        via.parse::<IpAddr>()?
+                        via.parse::<IpAddr>().map_err(invalid)?
                     };
                     Some(new_value)
                 } else {
                     None
                 }
             },
+            metric: input_route.metric,
+            table: input_route.table,
+            scope: input_route.scope,
+            r#type: input_route.r#type,
+        };
+        result.validate()?;
+        Ok(result)
+    }
+
+    /// Returns a copy of the route with the fields present in `patch` overlaid on it.
+    pub fn patched(&self, patch: &InputRoutePatch) -> Result<Self, String> {
+        let parse = |address: &str| {
+            if address == "default" {
+                Ok(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+            } else {
+                address
+                    .parse::<IpAddr>()
+                    .map_err(|err| format!("Invalid address '{address}': {err}"))
+            }
         };
+        let mut result = *self;
+        if let Some(to) = &patch.to {
+            result.to = parse(to)?;
+        }
+        if let Some(from) = &patch.from {
+            result.from = Some(parse(from)?);
+        }
+        if let Some(via) = &patch.via {
+            result.via = Some(parse(via)?);
+        }
+        result.metric = patch.metric.or(self.metric);
+        result.table = patch.table.or(self.table);
+        result.scope = patch.scope.or(self.scope);
+        result.r#type = patch.r#type.or(self.r#type);
+        result.validate()?;
         Ok(result)
     }
 
+    /// Whether the route is a default route, i.e. its destination is unspecified.
+    pub fn is_default(&self) -> bool {
+        self.to.is_unspecified()
+    }
+
+    /// IP version of the route.
+    ///
+    /// Default routes are read back from the configuration as `0.0.0.0`, whatever their
+    /// version, so the gateway takes precedence over the destination.
+    pub fn family(&self) -> AddressFamily {
+        AddressFamily::of(&self.via.unwrap_or(self.to))
+    }
+
+    /// Checks the consistency of the route's settings.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scope == Some(RouteScope::Link) && self.via.is_some() {
+            return Err(format!(
+                "Route to {} has link scope, so it cannot have a gateway (via).",
+                self.to
+            ));
+        }
+        if let Some(route_type) = self
+            .r#type
+            .filter(|&route_type| route_type != RouteType::Unicast)
+        {
+            if self.via.is_some() {
+                return Err(format!(
+                    "Route to {} is of type {}, so it cannot have a gateway (via).",
+                    self.to,
+                    route_type.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn display(&self) {
         println!("Route:");
         if let Some(origin) = &self.from {
@@ -79,10 +221,26 @@ impl Route {
         } else {
             println!("  Via: None");
         }
+        if let Some(metric) = &self.metric {
+            println!("  Metric: {}", metric);
+        }
+        if let Some(table) = &self.table {
+            println!("  Table: {}", table);
+        }
+        if let Some(scope) = &self.scope {
+            println!("  Scope: {:?}", scope);
+        }
+        if let Some(route_type) = &self.r#type {
+            println!("  Type: {}", route_type.as_str());
+        }
     }
 
+    /// Identifier of the route within its interface.
+    ///
+    /// The metric, the table and the type are only part of the identifier when set, so routes
+    /// which only differ in them (e.g. two default routes) can coexist.
     pub fn id(&self) -> String {
-        format!(
+        let id = format!(
             "{}-{}-{}",
             match self.from {
                 Some(from) => from.to_string(),
@@ -97,6 +255,104 @@ impl Route {
                 Some(via) => via.to_string(),
                 None => "via".to_string(),
             }
-        )
+        );
+        let id = match self.metric {
+            Some(metric) => format!("{id}-{metric}"),
+            None => id,
+        };
+        let id = match self.table {
+            Some(table) => format!("{id}-table{table}"),
+            None => id,
+        };
+        match self.r#type {
+            Some(route_type) => format!("{id}-{}", route_type.as_str()),
+            None => id,
+        }
+    }
+}
+
+/// Default gateways of an interface, at most one per IP version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Gateways {
+    pub ipv4: Option<Route>,
+    pub ipv6: Option<Route>,
+}
+
+impl Gateways {
+    /// Collects the default routes among the given ones.
+    pub fn from_routes<'a>(routes: impl IntoIterator<Item = &'a Route>) -> Self {
+        let mut result = Self::default();
+        for route in routes.into_iter().filter(|route| route.is_default()) {
+            match route.family() {
+                AddressFamily::Ipv4 => result.ipv4 = Some(*route),
+                AddressFamily::Ipv6 => result.ipv6 = Some(*route),
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_route(to: &str, via: Option<&str>, r#type: Option<RouteType>) -> InputRoute {
+        InputRoute {
+            to: to.to_string(),
+            from: None,
+            via: via.map(str::to_string),
+            metric: None,
+            table: None,
+            scope: None,
+            r#type,
+        }
+    }
+
+    #[test]
+    fn route_types_round_trip_through_yaml() {
+        for (route_type, name) in [
+            (RouteType::Unicast, "unicast"),
+            (RouteType::Blackhole, "blackhole"),
+            (RouteType::Unreachable, "unreachable"),
+            (RouteType::Prohibit, "prohibit"),
+        ] {
+            let route =
+                Route::from_input_route(&input_route("10.66.0.0", None, Some(route_type))).unwrap();
+            let yaml = serde_yml::to_string(&route).unwrap();
+            assert!(yaml.contains(&format!("type: {name}")), "{yaml}");
+            assert_eq!(serde_yml::from_str::<Route>(&yaml).unwrap(), route);
+        }
+        let yaml = serde_yml::to_string(&Route::new("10.66.0.0".parse().unwrap(), None, None));
+        assert!(!yaml.unwrap().contains("type"));
+    }
+
+    #[test]
+    fn only_unicast_routes_have_a_gateway() {
+        for route_type in [
+            RouteType::Blackhole,
+            RouteType::Unreachable,
+            RouteType::Prohibit,
+        ] {
+            let input = input_route("10.66.0.0", Some("10.0.0.1"), Some(route_type));
+            assert!(Route::from_input_route(&input).is_err());
+        }
+        for route_type in [None, Some(RouteType::Unicast)] {
+            let input = input_route("10.66.0.0", Some("10.0.0.1"), route_type);
+            assert!(Route::from_input_route(&input).is_ok());
+        }
+    }
+
+    #[test]
+    fn the_type_tells_routes_to_the_same_prefix_apart() {
+        let unicast = Route::from_input_route(&input_route("10.66.0.0", None, None)).unwrap();
+        let blackhole =
+            Route::from_input_route(&input_route("10.66.0.0", None, Some(RouteType::Blackhole)))
+                .unwrap();
+        let prohibit =
+            Route::from_input_route(&input_route("10.66.0.0", None, Some(RouteType::Prohibit)))
+                .unwrap();
+        assert_ne!(unicast.id(), blackhole.id());
+        assert_ne!(blackhole.id(), prohibit.id());
+        assert!(blackhole.id().ends_with("-blackhole"));
     }
 }
@@ -0,0 +1,58 @@
+use std::num::NonZeroU32;
+
+use serde::{Deserialize, Serialize};
+
+use crate::custom_types::IpNetwork;
+
+use super::input_models::InputRoutingPolicy;
+
+/// A routing policy rule, selecting the routing table used for the matching traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RoutingPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<IpNetwork>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<IpNetwork>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<NonZeroU32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark: Option<u32>,
+}
+
+impl RoutingPolicy {
+    pub fn from_input_routing_policy(input: &InputRoutingPolicy) -> Result<Self, String> {
+        let parse =
+            |network: &Option<String>| network.as_deref().map(str::parse::<IpNetwork>).transpose();
+        let result = RoutingPolicy {
+            from: parse(&input.from)?,
+            to: parse(&input.to)?,
+            table: input.table,
+            priority: input.priority,
+            mark: input.mark,
+        };
+        if result.from.is_none() && result.to.is_none() {
+            return Err("A routing policy rule needs at least a 'from' or a 'to'.".to_string());
+        }
+        Ok(result)
+    }
+
+    pub fn id(&self) -> String {
+        let or = |value: Option<String>, placeholder: &str| {
+            value.unwrap_or_else(|| placeholder.to_string())
+        };
+        format!(
+            "{}-{}-{}-{}-{}",
+            or(self.from.map(|from| from.to_string()), "from"),
+            or(self.to.map(|to| to.to_string()), "to"),
+            or(self.table.map(|table| table.to_string()), "table"),
+            or(
+                self.priority.map(|priority| priority.to_string()),
+                "priority"
+            ),
+            or(self.mark.map(|mark| mark.to_string()), "mark"),
+        )
+    }
+}
@@ -0,0 +1,204 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What is lost when nothing of a file can be read.
+pub const WHOLE_FILE: &str = "the whole file";
+
+/// A file the service keeps next to the configuration, besides its backups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sidecar {
+    /// The labels and `required-for-online` of the Ethernets, in the `.meta` file.
+    Metadata,
+    /// The changes waiting for a reboot, in the `.pending` file.
+    PendingActions,
+}
+
+impl Sidecar {
+    pub const ALL: [Sidecar; 2] = [Sidecar::Metadata, Sidecar::PendingActions];
+}
+
+impl FromStr for Sidecar {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "metadata" => Ok(Self::Metadata),
+            "pending-actions" => Ok(Self::PendingActions),
+            other => Err(format!(
+                "Unknown target '{other}', expected 'metadata' or 'pending-actions'."
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Sidecar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Metadata => "metadata",
+            Self::PendingActions => "pending-actions",
+        })
+    }
+}
+
+/// What rebuilding a sidecar came to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RebuildOutcome {
+    /// The file could be read, or there was none, so it was left as is.
+    Intact,
+    /// Every entry of the file was recovered, and the file rewritten.
+    Rebuilt,
+    /// Some entries of the file could not be read, and were dropped.
+    PartiallyRecovered,
+    /// Nothing could be read from the file, which was reset.
+    Unrecoverable,
+}
+
+/// Outcome of the rebuild of a sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RebuildReport {
+    pub target: Sidecar,
+    pub outcome: RebuildOutcome,
+    /// Number of entries kept.
+    pub recovered: usize,
+    /// The entries which could not be read, e.g. `labels of eth0`.
+    pub lost: Vec<String>,
+    /// Where the corrupt file was set aside, for it to be looked at by hand.
+    pub set_aside: Option<String>,
+}
+
+impl RebuildReport {
+    pub fn intact(target: Sidecar) -> Self {
+        Self {
+            target,
+            outcome: RebuildOutcome::Intact,
+            recovered: 0,
+            lost: vec![],
+            set_aside: None,
+        }
+    }
+
+    /// The report of a file from which `recovered` entries were kept and the `lost` ones dropped.
+    pub fn salvaged(target: Sidecar, recovered: usize, lost: Vec<String>) -> Self {
+        let outcome = match (recovered, lost.is_empty()) {
+            (_, true) => RebuildOutcome::Rebuilt,
+            (0, false) => RebuildOutcome::Unrecoverable,
+            _ => RebuildOutcome::PartiallyRecovered,
+        };
+        Self {
+            target,
+            outcome,
+            recovered,
+            lost,
+            set_aside: None,
+        }
+    }
+}
+
+/// The entries of `value` which can be read as `T`, along with the descriptions of those which
+/// can't.
+///
+/// The entries are those of a mapping, described by `describe` from their key, or those of a
+/// sequence, described by their position. Anything else is lost as a whole.
+pub fn salvage_entries<T: DeserializeOwned>(
+    value: &serde_yml::Value,
+    describe: impl Fn(&str) -> String,
+) -> (Vec<(String, T)>, Vec<String>) {
+    let entries: Vec<(String, &serde_yml::Value)> = match value {
+        serde_yml::Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    serde_yml::Value::String(key) => key.clone(),
+                    key => serde_yml::to_string(key)
+                        .unwrap_or_default()
+                        .trim_end()
+                        .to_string(),
+                };
+                (key, value)
+            })
+            .collect(),
+        serde_yml::Value::Sequence(sequence) => sequence
+            .iter()
+            .enumerate()
+            .map(|(index, value)| ((index + 1).to_string(), value))
+            .collect(),
+        _ => return (vec![], vec![WHOLE_FILE.to_string()]),
+    };
+    let mut recovered = vec![];
+    let mut lost = vec![];
+    for (key, value) in entries {
+        match serde_yml::from_value(value.clone()) {
+            Ok(entry) => recovered.push((key, entry)),
+            Err(_) => lost.push(describe(&key)),
+        }
+    }
+    (recovered, lost)
+}
+
+/// Moves the corrupt file at `path` aside, next to it, returning where it went.
+pub fn set_aside(path: &Path) -> io::Result<String> {
+    let mut corrupt_path = path.as_os_str().to_owned();
+    corrupt_path.push(".corrupt");
+    fs::rename(path, &corrupt_path)?;
+    Ok(corrupt_path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readable_entries_are_salvaged() {
+        let value: serde_yml::Value =
+            serde_yml::from_str("eth0: [wan]\neth1: {not: labels}\neth2: [lan, mgmt]\n").unwrap();
+        let (recovered, lost) =
+            salvage_entries::<Vec<String>>(&value, |key| format!("labels of {key}"));
+        assert_eq!(
+            recovered,
+            [
+                ("eth0".to_string(), vec!["wan".to_string()]),
+                (
+                    "eth2".to_string(),
+                    vec!["lan".to_string(), "mgmt".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(lost, ["labels of eth1"]);
+
+        let value: serde_yml::Value = serde_yml::from_str("[1, two, 3]\n").unwrap();
+        let (recovered, lost) = salvage_entries::<u32>(&value, |key| format!("entry {key}"));
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(lost, ["entry 2"]);
+    }
+
+    #[test]
+    fn outcomes_tell_how_much_was_recovered() {
+        let outcome = |recovered, lost: &[&str]| {
+            RebuildReport::salvaged(
+                Sidecar::Metadata,
+                recovered,
+                lost.iter().map(|lost| lost.to_string()).collect(),
+            )
+            .outcome
+        };
+        assert_eq!(outcome(2, &[]), RebuildOutcome::Rebuilt);
+        assert_eq!(outcome(0, &[]), RebuildOutcome::Rebuilt);
+        assert_eq!(
+            outcome(1, &["labels of eth1"]),
+            RebuildOutcome::PartiallyRecovered
+        );
+        assert_eq!(outcome(0, &[WHOLE_FILE]), RebuildOutcome::Unrecoverable);
+    }
+
+    #[test]
+    fn targets_are_parsed_by_name() {
+        for target in Sidecar::ALL {
+            assert_eq!(target.to_string().parse(), Ok(target));
+        }
+        assert!("provenance".parse::<Sidecar>().is_err());
+    }
+}
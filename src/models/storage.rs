@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::resource_usage::{HighWaterMark, StructureUsage};
+
+/// Number of evictions kept for the report, the oldest ones being forgotten.
+const EVICTION_HISTORY: usize = 20;
+
+/// A kind of file the service writes besides the configuration itself, budgeted on its own.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    /// The timestamped backups of the configuration.
+    Backups,
+    /// The companion file of the configuration, holding what netplan doesn't know about.
+    Metadata,
+}
+
+/// What happens to a write that would take its category over budget.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StoragePolicy {
+    /// The oldest files of the category are removed until it fits again.
+    EvictOldest,
+    /// The write is rejected, as removing anything would lose what users set.
+    Reject,
+}
+
+impl StorageCategory {
+    pub const ALL: [StorageCategory; 2] = [StorageCategory::Backups, StorageCategory::Metadata];
+
+    pub fn policy(self) -> StoragePolicy {
+        match self {
+            // The latest backup is the one a rollback needs, the others are a convenience
+            StorageCategory::Backups => StoragePolicy::EvictOldest,
+            // There is a single file, and dropping labels would silently lose them
+            StorageCategory::Metadata => StoragePolicy::Reject,
+        }
+    }
+}
+
+/// How many bytes the service may keep on disk besides the configuration, in total and by
+/// category. Anything left out is unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageBudget {
+    pub total: Option<u64>,
+    pub categories: BTreeMap<StorageCategory, u64>,
+}
+
+impl StorageBudget {
+    /// Bytes by which `usage` goes over the budget of `category`, or over the total budget if
+    /// that is more.
+    pub fn excess(&self, usage: &BTreeMap<StorageCategory, u64>, category: StorageCategory) -> u64 {
+        let used = usage.get(&category).copied().unwrap_or_default();
+        let category_excess = self
+            .categories
+            .get(&category)
+            .map_or(0, |budget| used.saturating_sub(*budget));
+        let total_excess = self.total.map_or(0, |budget| {
+            usage.values().sum::<u64>().saturating_sub(budget)
+        });
+        category_excess.max(total_excess)
+    }
+}
+
+/// A file removed to keep its category within budget.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Eviction {
+    pub category: StorageCategory,
+    /// Name of the file, in the directory of the configuration.
+    pub file: String,
+    /// Size of the file, in bytes.
+    pub size: u64,
+    /// When the file was removed, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Outcome of measuring what is actually on disk.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub struct Reconciliation {
+    /// When the disk was measured, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Bytes the bookkeeping was off by, positive if it counted less than what was on disk.
+    pub drift: i64,
+}
+
+/// Disk usage of a category.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    /// Bytes used, as last counted.
+    pub used: u64,
+    /// Bytes the category may use, if limited.
+    pub budget: Option<u64>,
+    pub policy: StoragePolicy,
+}
+
+/// Disk usage of what the service writes besides the configuration, against its budget.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct StorageReport {
+    /// Bytes used by all the categories.
+    pub used: u64,
+    /// Bytes all the categories may use together, if limited.
+    pub budget: Option<u64>,
+    pub categories: Vec<CategoryUsage>,
+    /// The last files evicted, latest first.
+    pub evictions: Vec<Eviction>,
+    /// The last time the bookkeeping was checked against the disk, if it was yet.
+    pub last_reconciliation: Option<Reconciliation>,
+}
+
+/// Bookkeeping of the disk usage, updated on every write and eviction, and corrected from time
+/// to time with what is actually on disk.
+#[derive(Debug, Default)]
+pub struct StorageLedger {
+    /// Bytes used by category, unknown until the disk is first measured.
+    usage: Option<BTreeMap<StorageCategory, u64>>,
+    evictions: VecDeque<Eviction>,
+    evictions_high_water: HighWaterMark,
+    last_reconciliation: Option<Reconciliation>,
+}
+
+impl StorageLedger {
+    /// Bytes used by category, measured with `measure` if they are not known yet.
+    pub fn usage<E>(
+        &mut self,
+        measure: impl FnOnce() -> Result<BTreeMap<StorageCategory, u64>, E>,
+    ) -> Result<&BTreeMap<StorageCategory, u64>, E> {
+        if self.usage.is_none() {
+            self.usage = Some(measure()?);
+        }
+        Ok(self.usage.get_or_insert_with(BTreeMap::new))
+    }
+
+    /// Counts `size` more bytes in `category`.
+    pub fn grow(&mut self, category: StorageCategory, size: u64) {
+        let used = self.used_mut(category);
+        *used = used.saturating_add(size);
+    }
+
+    /// Counts `size` fewer bytes in `category`.
+    pub fn shrink(&mut self, category: StorageCategory, size: u64) {
+        let used = self.used_mut(category);
+        *used = used.saturating_sub(size);
+    }
+
+    /// Counts `size` bytes in `category`, whatever was counted before, for categories made of a
+    /// single file which is replaced as a whole.
+    pub fn replace(&mut self, category: StorageCategory, size: u64) {
+        *self.used_mut(category) = size;
+    }
+
+    fn used_mut(&mut self, category: StorageCategory) -> &mut u64 {
+        self.usage
+            .get_or_insert_with(BTreeMap::new)
+            .entry(category)
+            .or_default()
+    }
+
+    /// Keeps `eviction` for the report, forgetting the oldest ones beyond the history.
+    pub fn record_eviction(&mut self, eviction: Eviction) {
+        self.shrink(eviction.category, eviction.size);
+        self.evictions.push_front(eviction);
+        self.evictions.truncate(EVICTION_HISTORY);
+        self.evictions_high_water.record(self.evictions.len());
+    }
+
+    /// How many evictions are kept for the report, against the history.
+    pub fn evictions_usage(&self) -> StructureUsage {
+        self.evictions_high_water.usage(
+            "storage_evictions",
+            self.evictions.len(),
+            Some(EVICTION_HISTORY),
+        )
+    }
+
+    /// Replaces the bookkeeping with what was `measured` on disk at `timestamp`, returning by how
+    /// many bytes it was off.
+    pub fn reconcile(&mut self, measured: BTreeMap<StorageCategory, u64>, timestamp: u64) -> i64 {
+        let counted: u64 = self.usage.iter().flat_map(BTreeMap::values).sum();
+        let drift = measured.values().sum::<u64>() as i64 - counted as i64;
+        // Before the first measurement, there was nothing to be off
+        let drift = if self.usage.is_some() { drift } else { 0 };
+        self.usage = Some(measured);
+        self.last_reconciliation = Some(Reconciliation { timestamp, drift });
+        drift
+    }
+
+    pub fn report(&self, budget: &StorageBudget) -> StorageReport {
+        let categories: Vec<CategoryUsage> = StorageCategory::ALL
+            .into_iter()
+            .map(|category| CategoryUsage {
+                category,
+                used: self
+                    .usage
+                    .as_ref()
+                    .and_then(|usage| usage.get(&category))
+                    .copied()
+                    .unwrap_or_default(),
+                budget: budget.categories.get(&category).copied(),
+                policy: category.policy(),
+            })
+            .collect();
+        StorageReport {
+            used: categories.iter().map(|category| category.used).sum(),
+            budget: budget.total,
+            categories,
+            evictions: self.evictions.iter().cloned().collect(),
+            last_reconciliation: self.last_reconciliation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(backups: u64, metadata: u64) -> BTreeMap<StorageCategory, u64> {
+        BTreeMap::from([
+            (StorageCategory::Backups, backups),
+            (StorageCategory::Metadata, metadata),
+        ])
+    }
+
+    #[test]
+    fn the_excess_is_the_larger_of_the_category_and_the_total() {
+        let budget = StorageBudget {
+            total: Some(1000),
+            categories: BTreeMap::from([(StorageCategory::Backups, 500)]),
+        };
+        assert_eq!(budget.excess(&usage(400, 100), StorageCategory::Backups), 0);
+        assert_eq!(
+            budget.excess(&usage(700, 100), StorageCategory::Backups),
+            200
+        );
+        assert_eq!(
+            budget.excess(&usage(400, 900), StorageCategory::Backups),
+            300
+        );
+        // The metadata has no budget of its own
+        assert_eq!(budget.excess(&usage(0, 900), StorageCategory::Metadata), 0);
+        assert_eq!(
+            StorageBudget::default().excess(&usage(u64::MAX, 1), StorageCategory::Backups),
+            0
+        );
+    }
+
+    #[test]
+    fn reconciling_reports_the_drift_of_the_bookkeeping() {
+        let mut ledger = StorageLedger::default();
+        // The first measurement has nothing to correct
+        assert_eq!(ledger.reconcile(usage(300, 20), 1), 0);
+        ledger.grow(StorageCategory::Backups, 100);
+        ledger.record_eviction(Eviction {
+            category: StorageCategory::Backups,
+            file: "01-network-conf.yaml.1.bak".to_string(),
+            size: 150,
+            timestamp: 2,
+        });
+        ledger.replace(StorageCategory::Metadata, 40);
+        assert_eq!(ledger.report(&StorageBudget::default()).used, 290);
+
+        assert_eq!(ledger.reconcile(usage(260, 40), 3), 10);
+        let report = ledger.report(&StorageBudget::default());
+        assert_eq!(report.used, 300);
+        assert_eq!(
+            report.last_reconciliation,
+            Some(Reconciliation {
+                timestamp: 3,
+                drift: 10
+            })
+        );
+        assert_eq!(report.evictions.len(), 1);
+    }
+
+    #[test]
+    fn the_eviction_history_stays_at_its_cap() {
+        let mut ledger = StorageLedger::default();
+        ledger.replace(StorageCategory::Backups, 5000);
+        for timestamp in 0..5000 {
+            ledger.record_eviction(Eviction {
+                category: StorageCategory::Backups,
+                file: format!("01-network-conf.yaml.{timestamp}.bak"),
+                size: 1,
+                timestamp,
+            });
+        }
+        // The newest evictions are kept, the oldest being forgotten first
+        let timestamps: Vec<u64> = ledger
+            .evictions
+            .iter()
+            .map(|eviction| eviction.timestamp)
+            .collect();
+        assert_eq!(timestamps, (4980..5000).rev().collect::<Vec<_>>());
+        assert_eq!(
+            ledger.evictions_usage(),
+            StructureUsage {
+                name: "storage_evictions".to_string(),
+                count: EVICTION_HISTORY,
+                high_water_mark: EVICTION_HISTORY,
+                cap: Some(EVICTION_HISTORY),
+            }
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::custom_types::BoundedU32;
+
+use super::{
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputTunnel,
+    route::AddressFamily,
+};
+
+/// VXLAN network identifier, 24 bits long.
+pub type Vni = BoundedU32<1, 16777215>;
+
+/// Encapsulation of a tunnel.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelMode {
+    /// Generic routing encapsulation of IP packets, over IPv4.
+    Gre,
+    /// Generic routing encapsulation of Ethernet frames, over IPv4.
+    Gretap,
+    /// IPv4 in IPv4.
+    Ipip,
+    /// IPv6 in IPv4.
+    Sit,
+    /// Generic routing encapsulation of IP packets, over IPv6.
+    Ip6gre,
+    /// Ethernet frames in UDP, over IPv4 or IPv6.
+    Vxlan,
+}
+
+impl TunnelMode {
+    /// Address family of the `local` and `remote` endpoints the mode runs over, if it is bound
+    /// to one.
+    pub fn endpoint_family(&self) -> Option<AddressFamily> {
+        match self {
+            TunnelMode::Gre | TunnelMode::Gretap | TunnelMode::Ipip | TunnelMode::Sit => {
+                Some(AddressFamily::Ipv4)
+            }
+            TunnelMode::Ip6gre => Some(AddressFamily::Ipv6),
+            TunnelMode::Vxlan => None,
+        }
+    }
+
+    /// Whether packets of the mode can carry a key.
+    pub fn supports_key(&self) -> bool {
+        matches!(
+            self,
+            TunnelMode::Gre | TunnelMode::Gretap | TunnelMode::Ip6gre
+        )
+    }
+}
+
+/// Key identifying the traffic of a GRE tunnel, as a number or in dotted-quad notation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(untagged)]
+pub enum TunnelKey {
+    Number(u32),
+    #[schema(value_type = String, example = "1.2.3.4")]
+    Address(Ipv4Addr),
+}
+
+/// Combines the unicast `remote` and multicast `group` a VXLAN is given, which netplan both
+/// takes as `remote`.
+pub fn remote_or_group(
+    remote: Option<IpAddr>,
+    group: Option<IpAddr>,
+) -> Result<Option<IpAddr>, String> {
+    match (remote, group) {
+        (Some(_), Some(_)) => {
+            Err("A tunnel has either a unicast remote or a multicast group, not both.".to_string())
+        }
+        (Some(remote), None) if remote.is_multicast() => Err(format!(
+            "The remote address {remote} is a multicast address, which has to be set as group."
+        )),
+        (None, Some(group)) if !group.is_multicast() => {
+            Err(format!("The group {group} is not a multicast address."))
+        }
+        (remote, group) => Ok(remote.or(group)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tunnel {
+    #[serde(skip_serializing)]
+    name: String,
+    mode: TunnelMode,
+    /// Address of this end of the tunnel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    local: Option<IpAddr>,
+    /// Address of the other end of the tunnel, or multicast group of a VXLAN.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote: Option<IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<TunnelKey>,
+    /// Network identifier of a VXLAN.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<Vni>,
+    /// Interface a VXLAN sends its packets through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+    /// Destination UDP port of a VXLAN.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl Tunnel {
+    pub fn new(name: String, mode: TunnelMode) -> Self {
+        Self {
+            name,
+            mode,
+            local: None,
+            remote: None,
+            key: None,
+            id: None,
+            link: None,
+            port: None,
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    /// Builds the tunnel from its input, whose remote end has to be checked with `remote_or_group`
+    /// first.
+    pub fn from_input_tunnel(name: &str, input_tunnel: &InputTunnel) -> Self {
+        Self {
+            name: name.to_string(),
+            mode: input_tunnel.mode,
+            local: input_tunnel.local,
+            remote: input_tunnel.remote.or(input_tunnel.group),
+            key: input_tunnel.key,
+            id: input_tunnel.id,
+            link: input_tunnel.link.clone(),
+            port: input_tunnel.port,
+            settings: DeviceSettings::from_input_device(&input_tunnel.device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn mode(&self) -> TunnelMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TunnelMode) {
+        self.mode = mode;
+    }
+
+    pub fn local(&self) -> Option<IpAddr> {
+        self.local
+    }
+
+    pub fn set_local(&mut self, local: Option<IpAddr>) {
+        self.local = local;
+    }
+
+    pub fn remote(&self) -> Option<IpAddr> {
+        self.remote
+    }
+
+    pub fn set_remote(&mut self, remote: Option<IpAddr>) {
+        self.remote = remote;
+    }
+
+    pub fn key(&self) -> Option<TunnelKey> {
+        self.key
+    }
+
+    pub fn set_key(&mut self, key: Option<TunnelKey>) {
+        self.key = key;
+    }
+
+    pub fn id(&self) -> Option<Vni> {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: Option<Vni>) {
+        self.id = id;
+    }
+
+    pub fn link(&self) -> Option<String> {
+        self.link.clone()
+    }
+
+    pub fn set_link(&mut self, link: Option<String>) {
+        self.link = link;
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    pub fn set_port(&mut self, port: Option<u16>) {
+        self.port = port;
+    }
+
+    /// Validates that the endpoints and the other settings suit the mode of the tunnel.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode == TunnelMode::Vxlan {
+            return self.validate_vxlan();
+        }
+        let family = self.mode.endpoint_family();
+        for (end, address) in [("local", self.local), ("remote", self.remote)] {
+            let Some(address) = address else {
+                return Err(format!("The {end} address of {} is missing.", self.name));
+            };
+            if family.is_some_and(|family| AddressFamily::of(&address) != family) {
+                return Err(format!(
+                    "The {end} address {address} of {} is not an {} address, as its mode requires.",
+                    self.name,
+                    match family {
+                        Some(AddressFamily::Ipv6) => "IPv6",
+                        _ => "IPv4",
+                    }
+                ));
+            }
+        }
+        if self.key.is_some() && !self.mode.supports_key() {
+            return Err(format!(
+                "Only GRE tunnels can carry a key, unlike {}.",
+                self.name
+            ));
+        }
+        if self.id.is_some() || self.link.is_some() || self.port.is_some() {
+            return Err(format!(
+                "Only VXLAN tunnels have an id, link and port, unlike {}.",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_vxlan(&self) -> Result<(), String> {
+        if self.id.is_none() {
+            return Err(format!("The VXLAN {} needs an id.", self.name));
+        }
+        if self.key.is_some() {
+            return Err(format!(
+                "Only GRE tunnels can carry a key, unlike {}.",
+                self.name
+            ));
+        }
+        if let (Some(local), Some(remote)) = (self.local, self.remote) {
+            if AddressFamily::of(&local) != AddressFamily::of(&remote) {
+                return Err(format!(
+                    "The local address {local} and remote address {remote} of {} are of different families.",
+                    self.name
+                ));
+            }
+        }
+        if self.remote.is_some_and(|remote| remote.is_multicast()) && self.link.is_none() {
+            return Err(format!(
+                "The VXLAN {} needs a link to join its multicast group on.",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl WithDeviceSettings for Tunnel {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vxlan(remote: &str) -> Tunnel {
+        let mut tunnel = Tunnel::new("vxlan42".to_string(), TunnelMode::Vxlan);
+        tunnel.set_id(Vni::new(42));
+        tunnel.set_local(Some("10.0.0.1".parse().unwrap()));
+        tunnel.set_remote(Some(remote.parse().unwrap()));
+        tunnel
+    }
+
+    #[test]
+    fn vnis_are_24_bits_long() {
+        for (value, valid) in [(0, false), (1, true), (16777215, true), (16777216, false)] {
+            assert_eq!(Vni::new(value).is_some(), valid, "{value}");
+            let read: Result<Vni, _> = serde_yml::from_str(&value.to_string());
+            assert_eq!(read.is_ok(), valid, "{value}");
+        }
+    }
+
+    #[test]
+    fn a_vxlan_has_either_a_remote_or_a_group() {
+        let unicast: IpAddr = "10.0.0.2".parse().unwrap();
+        let multicast: IpAddr = "239.1.1.1".parse().unwrap();
+        assert_eq!(remote_or_group(Some(unicast), None), Ok(Some(unicast)));
+        assert_eq!(remote_or_group(None, Some(multicast)), Ok(Some(multicast)));
+        assert_eq!(remote_or_group(None, None), Ok(None));
+        assert!(remote_or_group(Some(unicast), Some(multicast)).is_err());
+        assert!(remote_or_group(Some(multicast), None).is_err());
+        assert!(remote_or_group(None, Some(unicast)).is_err());
+    }
+
+    #[test]
+    fn vxlans_are_validated_on_their_own_terms() {
+        assert_eq!(vxlan("10.0.0.2").validate(), Ok(()));
+
+        let mut without_id = vxlan("10.0.0.2");
+        without_id.set_id(None);
+        assert!(without_id.validate().is_err());
+
+        let mut mixed_families = vxlan("fd00::2");
+        assert!(mixed_families.validate().is_err());
+        mixed_families.set_local(None);
+        assert_eq!(mixed_families.validate(), Ok(()));
+
+        let mut group = vxlan("239.1.1.1");
+        assert!(group.validate().is_err());
+        group.set_link(Some("eth0".to_string()));
+        assert_eq!(group.validate(), Ok(()));
+
+        let mut gre = Tunnel::new("gre1".to_string(), TunnelMode::Gre);
+        gre.set_local(Some("10.0.0.1".parse().unwrap()));
+        gre.set_remote(Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(gre.validate(), Ok(()));
+        gre.set_id(Vni::new(42));
+        assert!(gre.validate().is_err());
+    }
+
+    #[test]
+    fn vxlan_settings_are_keys_of_the_tunnel() {
+        let mut tunnel = vxlan("239.1.1.1");
+        tunnel.set_link(Some("eth0".to_string()));
+        tunnel.set_port(Some(4789));
+        let yaml: serde_yml::Value =
+            serde_yml::from_str(&serde_yml::to_string(&tunnel).unwrap()).unwrap();
+        assert_eq!(yaml["mode"].as_str(), Some("vxlan"));
+        assert_eq!(yaml["id"].as_u64(), Some(42));
+        assert_eq!(yaml["link"].as_str(), Some("eth0"));
+        assert_eq!(yaml["port"].as_u64(), Some(4789));
+        assert_eq!(yaml["remote"].as_str(), Some("239.1.1.1"));
+        assert!(yaml.get("name").is_none());
+    }
+}
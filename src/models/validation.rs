@@ -0,0 +1,322 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{
+    address_conflict::{find_address_conflicts, ConflictKind},
+    device::{validate_interface_name, Device},
+    ethernet::Ethernet,
+    loopback::{is_loopback, validate_loopback_address},
+    network::Network,
+    reachability::{reachable_via, Reachability},
+    route::Route,
+    vlan::validate_vlan_id,
+    wait_online::nothing_required_warning,
+};
+
+/// A problem found in a network configuration, naming the offending interface and field.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ValidationIssue {
+    /// The interface the problem was found on, if it is specific to one.
+    pub interface: Option<String>,
+    /// The offending field, as named in the netplan YAML (e.g. `routes` or `addresses`).
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(interface: &str, field: &str, message: String) -> Self {
+        Self {
+            interface: Some(interface.to_string()),
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+/// Outcome of the validation of a network configuration.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ValidationReport {
+    /// Problems that would make netplan reject the configuration, or leave it unusable.
+    pub errors: Vec<ValidationIssue>,
+    /// Suspicious settings that netplan would accept nonetheless.
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Structurally validates a complete network configuration, without touching the system.
+///
+/// Value formats and bounds (addresses, MTUs...) are already enforced while deserializing,
+/// so this looks at the consistency of the settings within and across interfaces.
+pub fn validate_network(network: &Network) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut names: Vec<&String> = network.get_ethernets().keys().collect();
+    names.sort();
+    for name in names {
+        let ethernet = &network.get_ethernets()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Some(set_name) = ethernet.get_set_name() {
+            if let Err(err) = validate_interface_name(&set_name) {
+                report
+                    .errors
+                    .push(ValidationIssue::new(name, "set-name", err));
+            }
+        }
+        let loopback = is_loopback(name);
+        let mut addresses: Vec<_> = ethernet.get_addresses().into_iter().collect();
+        addresses.sort();
+        for address in addresses {
+            if loopback {
+                if let Err(err) = validate_loopback_address(&address) {
+                    report
+                        .errors
+                        .push(ValidationIssue::new(name, "addresses", err));
+                }
+            }
+            for conflict in find_address_conflicts(network, name, &address) {
+                let issue = ValidationIssue::new(
+                    name,
+                    "addresses",
+                    format!(
+                        "Address {address} conflicts with {} on {}.",
+                        conflict.address, conflict.interface
+                    ),
+                );
+                match conflict.kind {
+                    ConflictKind::Identical => report.errors.push(issue),
+                    ConflictKind::SameSubnet => report.warnings.push(issue),
+                }
+            }
+        }
+        if ethernet.get_ipv6_address_generation().is_some()
+            && ethernet.get_accept_ra() == Some(false)
+        {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "ipv6-address-generation",
+                "The IPv6 address generation only applies to addresses autoconfigured from \
+                router advertisements, which are disabled by 'accept-ra: false'."
+                    .to_string(),
+            ));
+        }
+        if let Some(Err(err)) = ethernet
+            .required_for_online()
+            .map(|required_for_online| required_for_online.validate_setting())
+        {
+            report
+                .errors
+                .push(ValidationIssue::new(name, "required-for-online", err));
+        }
+        let dhcp = ethernet.get_dhcp4() || ethernet.get_dhcp6();
+        let mut routes: Vec<_> = ethernet.get_routes().into_iter().collect();
+        routes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, route) in routes {
+            if let Err(err) = route.validate() {
+                report
+                    .errors
+                    .push(ValidationIssue::new(name, "routes", err));
+            }
+            let Some(via) = route.via else {
+                continue;
+            };
+            if reachable_via(network, name, &via) == Reachability::ThisInterface {
+                continue;
+            }
+            let issue = ValidationIssue::new(
+                name,
+                "routes",
+                format!(
+                    "Gateway {via} of the route to {} is not on any subnet of {name}.",
+                    route.to
+                ),
+            );
+            // The subnets obtained through DHCP are only known at runtime
+            if dhcp {
+                report.warnings.push(issue);
+            } else {
+                report.errors.push(issue);
+            }
+        }
+    }
+    if let Some(message) = nothing_required_warning(
+        network
+            .get_ethernets()
+            .values()
+            .map(Ethernet::wait_online_participation),
+    ) {
+        report.warnings.push(ValidationIssue {
+            interface: None,
+            field: "required-for-online".to_string(),
+            message,
+        });
+    }
+    let mut vlan_names: Vec<&String> = network.get_vlans().keys().collect();
+    vlan_names.sort();
+    for name in vlan_names {
+        let vlan = &network.get_vlans()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Err(err) = validate_vlan_id(vlan.id()) {
+            report.errors.push(ValidationIssue::new(name, "id", err));
+        }
+        if !network.get_ethernets().contains_key(&vlan.link()) {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "link",
+                format!("Ethernet {} is not configured.", vlan.link()),
+            ));
+        }
+    }
+    let mut bridge_names: Vec<&String> = network.get_bridges().keys().collect();
+    bridge_names.sort();
+    for name in bridge_names {
+        let bridge = &network.get_bridges()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Err(err) = bridge.parameters().validate() {
+            report
+                .errors
+                .push(ValidationIssue::new(name, "parameters", err));
+        }
+        for member in bridge.interfaces() {
+            if let Err(err) = network.validate_members(name, &[member]) {
+                report
+                    .errors
+                    .push(ValidationIssue::new(name, "interfaces", err));
+            }
+        }
+    }
+    let mut bond_names: Vec<&String> = network.get_bonds().keys().collect();
+    bond_names.sort();
+    for name in bond_names {
+        let bond = &network.get_bonds()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        let parameters = bond.parameters();
+        if let Err(err) = parameters.validate() {
+            report
+                .errors
+                .push(ValidationIssue::new(name, "parameters", err));
+        }
+        if let Some(primary) = parameters
+            .primary
+            .filter(|primary| !bond.interfaces().contains(primary))
+        {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "parameters",
+                format!("The primary interface {primary} is not a member of {name}."),
+            ));
+        }
+        for member in bond.interfaces() {
+            if let Err(err) = network.validate_bond_members(name, &[member]) {
+                report
+                    .errors
+                    .push(ValidationIssue::new(name, "interfaces", err));
+            }
+        }
+    }
+    let mut wifi_names: Vec<&String> = network.get_wifis().keys().collect();
+    wifi_names.sort();
+    for name in wifi_names {
+        let wifi = &network.get_wifis()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Err(err) = wifi.validate_access_points() {
+            report
+                .errors
+                .push(ValidationIssue::new(name, "access-points", err));
+        }
+    }
+    let mut tunnel_names: Vec<&String> = network.get_tunnels().keys().collect();
+    tunnel_names.sort();
+    for name in tunnel_names {
+        let tunnel = &network.get_tunnels()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Err(err) = tunnel.validate() {
+            report.errors.push(ValidationIssue::new(name, "mode", err));
+        }
+        if let Some(remote) = tunnel.remote() {
+            if let Some(owner) = network.owner_of(&remote) {
+                report.errors.push(ValidationIssue::new(
+                    name,
+                    "remote",
+                    format!("The remote address {remote} of {name} is configured on {owner}."),
+                ));
+            }
+        }
+        if let Some(link) = tunnel.link().filter(|link| !network.is_configured(link)) {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "link",
+                format!("The link {link} of {name} is not configured."),
+            ));
+        }
+    }
+    let mut dummy_names: Vec<&String> = network.get_dummy_devices().keys().collect();
+    dummy_names.sort();
+    for name in dummy_names {
+        let dummy = &network.get_dummy_devices()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if dummy.get_dhcp4() || dummy.get_dhcp6() {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "dhcp",
+                "A dummy device has no link to obtain a DHCP lease on.".to_string(),
+            ));
+        }
+    }
+    let mut vrf_names: Vec<&String> = network.get_vrfs().keys().collect();
+    vrf_names.sort();
+    for name in vrf_names {
+        let vrf = &network.get_vrfs()[name];
+        if let Err(err) = validate_interface_name(name) {
+            report.errors.push(ValidationIssue::new(name, "name", err));
+        }
+        if let Some(other) = network.vrf_using_table(name, vrf.table()) {
+            report.errors.push(ValidationIssue::new(
+                name,
+                "table",
+                format!(
+                    "The table {} is already used by the VRF {other}.",
+                    vrf.table()
+                ),
+            ));
+        }
+        for member in vrf.interfaces() {
+            if let Err(err) = network.validate_vrf_members(name, std::slice::from_ref(&member)) {
+                report
+                    .errors
+                    .push(ValidationIssue::new(name, "interfaces", err));
+                continue;
+            }
+            let Some((_, routes)) = network.interfaces().remove(&member) else {
+                continue;
+            };
+            let mut routes: Vec<Route> = routes.into_values().collect();
+            routes.sort_by_key(|route| route.id());
+            for route in routes {
+                if let Some(warning) = network.check_vrf_table(&member, &route) {
+                    report
+                        .warnings
+                        .push(ValidationIssue::new(&member, "routes", warning));
+                }
+            }
+        }
+    }
+    report
+}
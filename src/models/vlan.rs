@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputVlan,
+};
+
+/// Lowest VLAN id that can be configured, 0 being reserved for priority tagging.
+pub const MIN_VLAN_ID: u16 = 1;
+/// Highest VLAN id that can be configured, 4095 being reserved.
+pub const MAX_VLAN_ID: u16 = 4094;
+
+/// Validates the id of a VLAN against the range allowed by IEEE 802.1Q.
+pub fn validate_vlan_id(id: u16) -> Result<(), String> {
+    if !(MIN_VLAN_ID..=MAX_VLAN_ID).contains(&id) {
+        return Err(format!(
+            "The VLAN id must be between {MIN_VLAN_ID} and {MAX_VLAN_ID}, got {id}."
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Vlan {
+    #[serde(skip_serializing)]
+    name: String,
+    id: u16,
+    /// Name of the Ethernet the VLAN is created on.
+    link: String,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl Vlan {
+    pub fn new(name: String, id: u16, link: String) -> Self {
+        Self {
+            name,
+            id,
+            link,
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    pub fn from_input_vlan(name: &str, input_vlan: &InputVlan) -> Self {
+        Self {
+            name: name.to_string(),
+            id: input_vlan.id,
+            link: input_vlan.link.clone(),
+            settings: DeviceSettings::from_input_device(&input_vlan.device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: u16) {
+        self.id = id;
+    }
+
+    pub fn link(&self) -> String {
+        self.link.clone()
+    }
+
+    pub fn set_link(&mut self, link: String) {
+        self.link = link;
+    }
+}
+
+impl WithDeviceSettings for Vlan {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
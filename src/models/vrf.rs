@@ -0,0 +1,133 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use serde::{Deserialize, Serialize};
+
+use crate::misc::serialize_hash_map_values_as_yaml_sequence;
+
+use super::{
+    input_models::{InputRoute, InputRoutingPolicy, InputVrf},
+    network::ConfigOrigin,
+    route::Route,
+    routing_policy::RoutingPolicy,
+};
+
+/// A virtual routing and forwarding device, binding its member interfaces to its own routing
+/// table.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Vrf {
+    #[serde(skip_serializing)]
+    name: String,
+    /// Routing table the traffic of the members is routed with.
+    table: NonZeroU32,
+    /// Names of the interfaces bound to the VRF.
+    #[serde(default)]
+    interfaces: Vec<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
+    routes: HashMap<String, Route>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_values_as_yaml_sequence"
+    )]
+    routing_policy: HashMap<String, RoutingPolicy>,
+    /// Read only: the other netplan configuration file defining the VRF, e.g.
+    /// `50-cloud-init.yaml`. Such VRFs are left to that file, and can't be changed through
+    /// the API.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    managed_by: Option<String>,
+}
+
+/// Parses the routes of a VRF, keyed by id.
+pub fn parse_vrf_routes(input_routes: &[InputRoute]) -> Result<HashMap<String, Route>, String> {
+    input_routes
+        .iter()
+        .map(|input_route| Route::from_input_route(input_route).map(|route| (route.id(), route)))
+        .collect()
+}
+
+/// Parses the routing policy rules of a VRF, keyed by id.
+pub fn parse_vrf_routing_policy(
+    input_rules: &[InputRoutingPolicy],
+) -> Result<HashMap<String, RoutingPolicy>, String> {
+    input_rules
+        .iter()
+        .map(|input_rule| {
+            RoutingPolicy::from_input_routing_policy(input_rule).map(|rule| (rule.id(), rule))
+        })
+        .collect()
+}
+
+impl Vrf {
+    pub fn new(name: String, table: NonZeroU32) -> Self {
+        Self {
+            name,
+            table,
+            interfaces: Vec::new(),
+            routes: HashMap::new(),
+            routing_policy: HashMap::new(),
+            managed_by: None,
+        }
+    }
+
+    pub fn from_input_vrf(name: &str, input_vrf: &InputVrf) -> Result<Self, String> {
+        Ok(Self {
+            name: name.to_string(),
+            table: input_vrf.table,
+            interfaces: input_vrf.interfaces.clone(),
+            routes: parse_vrf_routes(&input_vrf.routes)?,
+            routing_policy: parse_vrf_routing_policy(&input_vrf.routing_policy)?,
+            managed_by: None,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn table(&self) -> NonZeroU32 {
+        self.table
+    }
+
+    pub fn set_table(&mut self, table: NonZeroU32) {
+        self.table = table;
+    }
+
+    pub fn interfaces(&self) -> Vec<String> {
+        self.interfaces.clone()
+    }
+
+    pub fn set_interfaces(&mut self, interfaces: Vec<String>) {
+        self.interfaces = interfaces;
+    }
+
+    pub fn routes(&self) -> HashMap<String, Route> {
+        self.routes.clone()
+    }
+
+    pub fn set_routes(&mut self, routes: HashMap<String, Route>) {
+        self.routes = routes;
+    }
+
+    pub fn routing_policy(&self) -> HashMap<String, RoutingPolicy> {
+        self.routing_policy.clone()
+    }
+
+    pub fn set_routing_policy(&mut self, routing_policy: HashMap<String, RoutingPolicy>) {
+        self.routing_policy = routing_policy;
+    }
+}
+
+impl ConfigOrigin for Vrf {
+    fn managed_by(&self) -> Option<String> {
+        self.managed_by.clone()
+    }
+
+    fn set_managed_by(&mut self, file: Option<String>) {
+        self.managed_by = file;
+    }
+}
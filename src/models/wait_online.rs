@@ -0,0 +1,335 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Directory where netplan renders the systemd-networkd configuration.
+pub const NETWORKD_RUN_PATH: &str = "/run/systemd/network";
+/// Directory of the systemd-networkd configuration of the administrator, where the drop-ins
+/// setting `RequiredForOnline=` are written.
+pub const NETWORKD_CONFIG_PATH: &str = "/etc/systemd/network";
+pub const WAIT_ONLINE_UNIT: &str = "systemd-networkd-wait-online.service";
+/// Name of the drop-in holding the `RequiredForOnline=` setting of an interface.
+pub const DROP_IN_NAME: &str = "required-for-online.conf";
+const NOTHING_REQUIRED_WARNING: &str =
+    "No interface is required for online: boot will never wait for the network.";
+
+/// Values accepted by systemd-networkd's `RequiredForOnline=` setting.
+///
+/// Besides `yes`/`no`, networkd accepts the minimum operational state the link
+/// must reach to be considered online.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequiredForOnline {
+    No,
+    Yes,
+    Missing,
+    Off,
+    NoCarrier,
+    Dormant,
+    DegradedCarrier,
+    Carrier,
+    Degraded,
+    Enslaved,
+    Routable,
+}
+
+impl FromStr for RequiredForOnline {
+    type Err = String;
+
+    /// Parses the value of `RequiredForOnline=` the way networkd does: as a boolean
+    /// first, and otherwise as an operational state or `minimum:maximum` range.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().to_lowercase();
+        match value.as_str() {
+            "yes" | "y" | "true" | "t" | "on" | "1" => return Ok(Self::Yes),
+            "no" | "n" | "false" | "f" | "off" | "0" => return Ok(Self::No),
+            _ => (),
+        }
+        let minimum = value.split(':').next().unwrap_or_default();
+        Ok(match minimum {
+            "missing" => Self::Missing,
+            "off" => Self::Off,
+            "no-carrier" => Self::NoCarrier,
+            "dormant" => Self::Dormant,
+            "degraded-carrier" => Self::DegradedCarrier,
+            "carrier" => Self::Carrier,
+            "degraded" => Self::Degraded,
+            "enslaved" => Self::Enslaved,
+            "routable" => Self::Routable,
+            other => return Err(format!("Unknown RequiredForOnline value '{other}'")),
+        })
+    }
+}
+
+/// Writes the value as networkd reads it.
+impl fmt::Display for RequiredForOnline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::No => "no",
+            Self::Yes => "yes",
+            Self::Missing => "missing",
+            Self::Off => "off",
+            Self::NoCarrier => "no-carrier",
+            Self::Dormant => "dormant",
+            Self::DegradedCarrier => "degraded-carrier",
+            Self::Carrier => "carrier",
+            Self::Degraded => "degraded",
+            Self::Enslaved => "enslaved",
+            Self::Routable => "routable",
+        })
+    }
+}
+
+impl RequiredForOnline {
+    /// Checks that the value can be configured: networkd reads a lone `off` as a boolean, so
+    /// `Off` is only ever found as the minimum of a range.
+    pub fn validate_setting(self) -> Result<(), String> {
+        match self {
+            Self::Off => Err(
+                "'off' is read as 'no' by networkd when it is not the minimum of a range; use 'no' instead."
+                    .to_string(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The content of the networkd drop-in setting the value.
+    pub fn drop_in(self) -> String {
+        format!("[Link]\nRequiredForOnline={self}\n")
+    }
+
+    /// Whether boot waits for the interface.
+    pub fn gates_boot(self) -> bool {
+        self != Self::No
+    }
+}
+
+/// Warns when no interface gates boot, which never waits for the network then, as long as there
+/// is at least one interface.
+pub fn nothing_required_warning(
+    participations: impl IntoIterator<Item = RequiredForOnline>,
+) -> Option<String> {
+    let mut participations = participations.into_iter().peekable();
+    if participations.peek().is_none() || participations.any(RequiredForOnline::gates_boot) {
+        return None;
+    }
+    Some(NOTHING_REQUIRED_WARNING.to_string())
+}
+
+/// Whether an interface is taken into account by systemd-networkd-wait-online.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct WaitOnlineParticipation {
+    pub interface: String,
+    pub required_for_online: RequiredForOnline,
+}
+
+/// Current boot-time participation of the interfaces in systemd-networkd-wait-online.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct WaitOnlineReport {
+    /// State of the wait-online service, as reported by `systemctl is-active`.
+    pub service_state: String,
+    pub interfaces: Vec<WaitOnlineParticipation>,
+    pub warnings: Vec<String>,
+}
+
+impl WaitOnlineParticipation {
+    /// Parses a rendered `.network` file, returning `None` if it doesn't match an interface by name.
+    ///
+    /// Interfaces without an explicit `RequiredForOnline=` are required (`yes`).
+    pub fn from_network_file(content: &str) -> Option<Self> {
+        let (interface, required_for_online) = parse_network_file(content);
+        interface.map(|interface| Self {
+            interface,
+            required_for_online: required_for_online.unwrap_or(RequiredForOnline::Yes),
+        })
+    }
+
+    /// Applies a drop-in of the `.network` file, whose `RequiredForOnline=` overrides the one of
+    /// the file.
+    pub fn apply_drop_in(&mut self, content: &str) {
+        if let (_, Some(required_for_online)) = parse_network_file(content) {
+            self.required_for_online = required_for_online;
+        }
+    }
+}
+
+/// The `Name=` of the `[Match]` section and the `RequiredForOnline=` of the `[Link]` section of a
+/// `.network` file, an invalid value being ignored like networkd does.
+fn parse_network_file(content: &str) -> (Option<String>, Option<RequiredForOnline>) {
+    let mut section = "";
+    let mut interface = None;
+    let mut required_for_online = None;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match (section, key.trim()) {
+            ("[Match]", "Name") => interface = Some(value.trim().to_string()),
+            ("[Link]", "RequiredForOnline") => {
+                if let Ok(value) = value.parse() {
+                    required_for_online = Some(value);
+                }
+            }
+            _ => (),
+        }
+    }
+    (interface, required_for_online)
+}
+
+impl WaitOnlineReport {
+    /// Builds the report from the participations found in the rendered configuration.
+    pub fn new(service_state: String, mut interfaces: Vec<WaitOnlineParticipation>) -> Self {
+        interfaces.sort_by(|a, b| a.interface.cmp(&b.interface));
+        let warnings = nothing_required_warning(
+            interfaces
+                .iter()
+                .map(|participation| participation.required_for_online),
+        )
+        .into_iter()
+        .collect();
+        Self {
+            service_state,
+            interfaces,
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!(
+            "{}/tests/fixtures/networkd/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn values_are_parsed_the_way_networkd_does() {
+        for (value, expected) in [
+            ("yes", RequiredForOnline::Yes),
+            ("true", RequiredForOnline::Yes),
+            ("1", RequiredForOnline::Yes),
+            ("no", RequiredForOnline::No),
+            ("false", RequiredForOnline::No),
+            // A lone `off` is a boolean
+            ("off", RequiredForOnline::No),
+            ("off:routable", RequiredForOnline::Off),
+            ("missing", RequiredForOnline::Missing),
+            ("no-carrier", RequiredForOnline::NoCarrier),
+            ("dormant", RequiredForOnline::Dormant),
+            (
+                "degraded-carrier:routable",
+                RequiredForOnline::DegradedCarrier,
+            ),
+            ("carrier", RequiredForOnline::Carrier),
+            (" Degraded ", RequiredForOnline::Degraded),
+            ("enslaved", RequiredForOnline::Enslaved),
+            ("routable", RequiredForOnline::Routable),
+        ] {
+            assert_eq!(value.parse(), Ok(expected), "{value}");
+        }
+        assert!("online".parse::<RequiredForOnline>().is_err());
+    }
+
+    #[test]
+    fn values_are_written_as_networkd_reads_them() {
+        for value in [
+            RequiredForOnline::No,
+            RequiredForOnline::Yes,
+            RequiredForOnline::NoCarrier,
+            RequiredForOnline::DegradedCarrier,
+            RequiredForOnline::Routable,
+        ] {
+            assert_eq!(value.to_string().parse(), Ok(value));
+        }
+        assert_eq!(
+            RequiredForOnline::DegradedCarrier.drop_in(),
+            "[Link]\nRequiredForOnline=degraded-carrier\n"
+        );
+        // Written alone, `off` would be read back as `no`
+        assert!(RequiredForOnline::Off.validate_setting().is_err());
+        assert!(RequiredForOnline::Routable.validate_setting().is_ok());
+    }
+
+    #[test]
+    fn participation_is_read_from_the_rendered_files() {
+        for (file, interface, expected) in [
+            ("10-netplan-eth0.network", "eth0", RequiredForOnline::Yes),
+            ("10-netplan-eth1.network", "eth1", RequiredForOnline::No),
+            (
+                "10-netplan-eth2.network",
+                "eth2",
+                RequiredForOnline::DegradedCarrier,
+            ),
+        ] {
+            let participation = WaitOnlineParticipation::from_network_file(&fixture(file)).unwrap();
+            assert_eq!(participation.interface, interface);
+            assert_eq!(participation.required_for_online, expected, "{file}");
+        }
+        // Matched by MAC address, so not an interface by name
+        assert!(
+            WaitOnlineParticipation::from_network_file(&fixture("10-netplan-lan.network"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn drop_ins_override_the_rendered_file() {
+        let mut participation =
+            WaitOnlineParticipation::from_network_file(&fixture("10-netplan-eth1.network"))
+                .unwrap();
+        participation.apply_drop_in("[Link]\nMTUBytes=1400\n");
+        assert_eq!(participation.required_for_online, RequiredForOnline::No);
+        participation.apply_drop_in(&RequiredForOnline::Routable.drop_in());
+        assert_eq!(
+            participation.required_for_online,
+            RequiredForOnline::Routable
+        );
+        // Invalid values are ignored
+        participation.apply_drop_in("[Link]\nRequiredForOnline=sometimes\n");
+        assert_eq!(
+            participation.required_for_online,
+            RequiredForOnline::Routable
+        );
+    }
+
+    #[test]
+    fn warns_when_no_interface_is_required() {
+        let participation = |interface: &str, required_for_online| WaitOnlineParticipation {
+            interface: interface.to_string(),
+            required_for_online,
+        };
+        let report = WaitOnlineReport::new(
+            "active".to_string(),
+            vec![
+                participation("eth1", RequiredForOnline::No),
+                participation("eth0", RequiredForOnline::No),
+            ],
+        );
+        assert_eq!(report.interfaces[0].interface, "eth0");
+        assert_eq!(report.warnings, [NOTHING_REQUIRED_WARNING]);
+
+        let report = WaitOnlineReport::new(
+            "active".to_string(),
+            vec![
+                participation("eth0", RequiredForOnline::No),
+                participation("eth1", RequiredForOnline::Degraded),
+            ],
+        );
+        assert!(report.warnings.is_empty());
+
+        // Without any interface, there is nothing to wait for in the first place
+        assert!(WaitOnlineReport::new("inactive".to_string(), vec![])
+            .warnings
+            .is_empty());
+    }
+}
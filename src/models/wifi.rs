@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::misc::serialize_hash_map_sorted;
+
+use super::{
+    access_point::AccessPoint,
+    device_settings::{DeviceSettings, WithDeviceSettings},
+    input_models::InputWifi,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Wifi {
+    #[serde(skip_serializing)]
+    name: String,
+    /// Wireless networks the device connects to, keyed by SSID.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_hash_map_sorted"
+    )]
+    access_points: HashMap<String, AccessPoint>,
+    #[serde(flatten)]
+    settings: DeviceSettings,
+}
+
+impl Wifi {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            access_points: HashMap::new(),
+            settings: DeviceSettings::default(),
+        }
+    }
+
+    pub fn from_input_wifi(name: &str, input_wifi: &InputWifi) -> Self {
+        Self {
+            name: name.to_string(),
+            access_points: input_wifi.access_points.clone(),
+            settings: DeviceSettings::from_input_device(&input_wifi.device),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn access_points(&self) -> HashMap<String, AccessPoint> {
+        self.access_points.clone()
+    }
+
+    /// Replaces the access points. Passwords sent back masked keep their current value.
+    pub fn set_access_points(&mut self, mut access_points: HashMap<String, AccessPoint>) {
+        for (ssid, access_point) in access_points.iter_mut() {
+            access_point.unmask(self.access_points.get(ssid));
+        }
+        self.access_points = access_points;
+    }
+
+    /// Adds or replaces the access point of the network `ssid`, keeping the current value of the
+    /// passwords sent back masked.
+    pub fn set_access_point(&mut self, ssid: &str, mut access_point: AccessPoint) {
+        access_point.unmask(self.access_points.get(ssid));
+        self.access_points.insert(ssid.to_string(), access_point);
+    }
+
+    /// Validates every access point, reporting the offending ones one per line.
+    pub fn validate_access_points(&self) -> Result<(), String> {
+        let mut ssids: Vec<&String> = self.access_points.keys().collect();
+        ssids.sort();
+        let errors: Vec<String> = ssids
+            .into_iter()
+            .filter_map(|ssid| {
+                self.access_points[ssid]
+                    .validate()
+                    .err()
+                    .map(|err| format!("{ssid}: {err}"))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Returns a copy of the wifi with the passwords of its access points masked, fit for
+    /// responses.
+    pub fn redacted(&self) -> Self {
+        Self {
+            access_points: self
+                .access_points
+                .iter()
+                .map(|(ssid, access_point)| (ssid.clone(), access_point.redacted()))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl WithDeviceSettings for Wifi {
+    fn settings(&self) -> &DeviceSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut DeviceSettings {
+        &mut self.settings
+    }
+}
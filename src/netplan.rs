@@ -1,217 +1,1110 @@
+use crate::bootstrap::EnvironmentSpec;
+use crate::command::{command_line, CommandRunner, Deadline, SystemRunner};
+use crate::misc::find_yaml_references;
+use crate::models::bond::Bond;
+use crate::models::bridge::Bridge;
 use crate::models::device::Device;
+use crate::models::dhcp_probe::{DhcpProbeResult, Privileges, DHCP_PROBE_TIMEOUT};
+use crate::models::dummy_device::DummyDevice;
 use crate::models::ethernet::Ethernet;
-use crate::models::network::Network;
+use crate::models::network::{DeviceMap, Network, NetworkRenderer};
+use crate::models::pending_action::{mark_pending_reboot, PendingAction, PendingActions};
+use crate::models::resource_usage::{HighWaterMark, StructureUsage};
 use crate::models::route::Route;
-use actix_web::{HttpResponse, Result};
+use crate::models::routing_policy::RoutingPolicy;
+use crate::models::sidecar::{salvage_entries, set_aside, RebuildReport, Sidecar, WHOLE_FILE};
+use crate::models::storage::{
+    Eviction, StorageBudget, StorageCategory, StorageLedger, StorageReport,
+};
+use crate::models::tunnel::Tunnel;
+use crate::models::vlan::Vlan;
+use crate::models::wait_online::{
+    RequiredForOnline, WaitOnlineParticipation, WaitOnlineReport, DROP_IN_NAME,
+    NETWORKD_CONFIG_PATH, NETWORKD_RUN_PATH, WAIT_ONLINE_UNIT,
+};
+use crate::models::wifi::Wifi;
+use crate::system::System;
+use actix_web::{http::StatusCode, web, HttpResponse, ResponseError, Result};
+use serde::{Deserialize, Serialize};
 use serde_yml;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::{self, ErrorKind};
-use std::process::Command;
-use std::sync::Mutex;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::OwnedMutexGuard;
 
-const NETPLAN_CONFIG_PATH: &str = "/etc/netplan/01-network-conf.yaml";
+/// Default path of the configuration file the API manages.
+pub const NETPLAN_CONFIG_PATH: &str = "/etc/netplan/01-network-conf.yaml";
+/// What can go wrong while managing the configuration through netplan.
+#[derive(Debug, thiserror::Error)]
+pub enum NetplanError {
+    /// The `netplan` command exited with an error.
+    #[error("`{command}` failed ({status}): {stderr}")]
+    CommandFailed {
+        command: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// The command could not be run, as its program is not installed.
+    #[error("{0} binary not found. Make sure {0} is installed and in the PATH.")]
+    NotInstalled(String),
+    /// The operation did not finish in time, and the command it was running, if any, was killed.
+    #[error("The operation did not finish within {} seconds, so `{command}` was stopped.", timeout.as_secs())]
+    Timeout { command: String, timeout: Duration },
+    /// The output of netplan, or a configuration file, is not what was expected.
+    #[error("Could not parse {context}")]
+    ParseError { context: String },
+    /// The service of the renderer is not running, so applying would have no effect.
+    #[error("{unit} is {active} ({enabled}), so the config can't take effect. {remediation}")]
+    RendererInactive {
+        unit: String,
+        active: String,
+        enabled: String,
+        remediation: String,
+    },
+    /// The state of the service of the renderer could not be checked, so nothing is applied.
+    #[error("Could not check the state of {unit} ({reason}), so the config was not applied.")]
+    RendererUnknown { unit: String, reason: String },
+    /// Netplan failed to apply the configuration, with the outcome of the rollback.
+    #[error("{0}")]
+    ApplyFailed(String),
+    /// The system did not reach the applied configuration, or its state could not be checked.
+    #[error("{0}")]
+    SystemState(String),
+    /// The change touches devices defined in other configuration files.
+    #[error("{0}")]
+    ForeignDevice(String),
+    #[error("Backup {0} was not found.")]
+    BackupNotFound(u64),
+    /// The backup was imported from before the upgrade, so restoring it has to be forced.
+    #[error("Backup {0} was taken before the upgrade, so how it relates to the current configuration is unknown. Restore it with force=true if this is wanted.")]
+    PreUpgradeBackup(u64),
+    /// Saving would expand the anchors, aliases or merge keys of the configuration file, which
+    /// strict mode refuses.
+    #[error("The configuration file uses YAML anchors, aliases or merge keys ({}), which saving the change would expand. Remove them from the file first, or disable strict mode.", .0.join(", "))]
+    YamlReferences(Vec<String>),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
 
-#[derive(Default)]
-pub struct Netplan;
+impl NetplanError {
+    fn parse(context: impl Into<String>) -> Self {
+        Self::ParseError {
+            context: context.into(),
+        }
+    }
 
-#[derive(Default)]
-pub struct NetplanStore {
-    pub netplan: Mutex<Netplan>,
+    /// The error of starting `program`, telling a missing binary apart.
+    pub(crate) fn spawn(program: &str, err: io::Error) -> Self {
+        if err.kind() == ErrorKind::NotFound {
+            Self::NotInstalled(program.to_string())
+        } else {
+            Self::Io(err)
+        }
+    }
 }
 
-impl Netplan {
-    fn run_command(args: &[&str]) -> io::Result<String> {
-        let output = Command::new("netplan").args(args).output()?;
+/// Body of the error responses, e.g. `{"code": "command_failed", "message": "`netplan apply` failed ..."}`.
+#[derive(Serialize)]
+struct ErrorBody {
+    /// What went wrong, for clients to tell errors apart.
+    code: &'static str,
+    /// What went wrong, for humans.
+    message: String,
+}
 
-        if !output.status.success() {
-            eprintln!("Command failed: {:?}", output);
-            return Err(io::Error::new(ErrorKind::Other, "Command execution failed"));
+impl NetplanError {
+    /// Machine-readable identifier of the kind of error, served as `code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            NetplanError::CommandFailed { .. } => "command_failed",
+            NetplanError::NotInstalled(_) => "not_installed",
+            NetplanError::Timeout { .. } => "timeout",
+            NetplanError::ParseError { .. } => "parse_error",
+            NetplanError::RendererInactive { .. } => "renderer_service_inactive",
+            NetplanError::RendererUnknown { .. } => "renderer_service_unknown",
+            NetplanError::ApplyFailed(_) => "apply_failed",
+            NetplanError::SystemState(_) => "system_state",
+            NetplanError::ForeignDevice(_) => "foreign_device",
+            NetplanError::BackupNotFound(_) => "backup_not_found",
+            NetplanError::PreUpgradeBackup(_) => "pre_upgrade_backup",
+            NetplanError::YamlReferences(_) => "yaml_references",
+            NetplanError::Io(err) if err.kind() == ErrorKind::StorageFull => "insufficient_storage",
+            NetplanError::Io(_) => "io",
         }
-        let result = String::from_utf8_lossy(&output.stdout).to_string();
-        println!("{}", result);
-        Ok(result)
+    }
+}
+
+impl ResponseError for NetplanError {
+    /// Failures of the `netplan` command are reported as a bad gateway, and a command that hangs
+    /// as a gateway timeout. Failing to apply is an internal error, as the configuration was
+    /// checked beforehand. Going over the storage budget, like running out of disk, is reported
+    /// as insufficient storage.
+    fn status_code(&self) -> StatusCode {
+        match self {
+            NetplanError::CommandFailed { .. } => StatusCode::BAD_GATEWAY,
+            NetplanError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            NetplanError::RendererInactive { .. } | NetplanError::RendererUnknown { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            NetplanError::ForeignDevice(_)
+            | NetplanError::PreUpgradeBackup(_)
+            | NetplanError::YamlReferences(_) => StatusCode::CONFLICT,
+            NetplanError::BackupNotFound(_) => StatusCode::NOT_FOUND,
+            NetplanError::Io(err) if err.kind() == ErrorKind::StorageFull => {
+                StatusCode::INSUFFICIENT_STORAGE
+            }
+            NetplanError::NotInstalled(_)
+            | NetplanError::ParseError { .. }
+            | NetplanError::ApplyFailed(_)
+            | NetplanError::SystemState(_)
+            | NetplanError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The error is served as JSON, with its `code` and message.
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+        })
+    }
+}
+
+impl From<NetplanError> for io::Error {
+    fn from(err: NetplanError) -> Self {
+        match err {
+            NetplanError::Io(err) => err,
+            err => io::Error::other(err.to_string()),
+        }
+    }
+}
+
+/// Sections of the configuration holding the devices, keyed by name.
+const DEVICE_SECTIONS: [&str; 8] = [
+    "ethernets",
+    "vlans",
+    "bridges",
+    "bonds",
+    "wifis",
+    "tunnels",
+    "dummy-devices",
+    "vrfs",
+];
+/// Marks the name of the backup imported from before the upgrade, e.g.
+/// `01-network-conf.yaml.1700000000000.pre-upgrade.bak`.
+const PRE_UPGRADE_MARKER: &str = "pre-upgrade";
+/// Number of backups of the configuration kept by default.
+pub const DEFAULT_BACKUP_RETENTION: usize = 10;
+/// How long an operation, with all the commands it runs, may take by default before it is
+/// stopped.
+pub const DEFAULT_OPERATION_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long an apply waits for the DHCP leases of the interfaces by default.
+pub const DEFAULT_LEASE_WAIT: Duration = Duration::from_secs(15);
+/// How often the disk usage is measured, to correct the bookkeeping of the storage budget.
+pub const STORAGE_RECONCILIATION_PERIOD: Duration = Duration::from_secs(300);
+
+pub struct Netplan {
+    /// Path of the configuration file the API manages. Its backups and metadata are kept next to it.
+    config_path: PathBuf,
+    /// Number of backups of the configuration kept, the oldest ones being removed on save.
+    backup_retention: usize,
+    /// Runs `netplan` and `systemctl`.
+    runner: Box<dyn CommandRunner + Send + Sync>,
+    /// Variables a missing configuration is seeded from, the process environment if unset.
+    seed_vars: Option<Vec<(String, String)>>,
+    /// Capabilities of the service, telling whether DHCP servers can be probed from the
+    /// interfaces themselves, or only through `networkctl`.
+    privileges: Privileges,
+    /// How much the backups and the metadata may take on disk.
+    storage_budget: StorageBudget,
+    /// What the backups and the metadata are counted to take on disk.
+    storage: Mutex<StorageLedger>,
+    /// The changes waiting for a reboot, kept next to the configuration.
+    pending_actions: PendingActions,
+    /// Where netplan renders the networkd configuration.
+    networkd_run_path: PathBuf,
+    /// Where the networkd drop-ins setting `RequiredForOnline=` are written.
+    networkd_config_path: PathBuf,
+    /// Whether changes are refused when saving them would expand the anchors, aliases or merge
+    /// keys of the configuration file, instead of normalizing it.
+    strict_yaml: bool,
+}
+
+impl Default for Netplan {
+    fn default() -> Self {
+        Self::with_config_path(PathBuf::from(NETPLAN_CONFIG_PATH))
+    }
+}
+
+/// What the API keeps about the configuration that netplan doesn't know about, stored in a
+/// companion file of the configuration.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ConfigMetadata {
+    /// Labels of the Ethernets, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, BTreeSet<String>>,
+    /// `RequiredForOnline=` of the Ethernets setting it, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    required_for_online: BTreeMap<String, RequiredForOnline>,
+}
+
+/// A change to the network, told apart by what saving it takes.
+pub enum Mutation {
+    /// Only what netplan doesn't know about changed, which is saved without running netplan.
+    Metadata(MetadataMutation),
+    /// The netplan configuration changed, and maybe the metadata along with it.
+    Config(ConfigMutation),
+}
+
+/// A change to the netplan configuration that is worth saving and applying.
+///
+/// It can only be built by `Mutation::new` from a `Network` whose netplan configuration actually
+/// differs from the current one, so that changes that never touch the netplan YAML can't trigger
+/// `save_and_apply`.
+pub struct ConfigMutation {
+    network: Network,
+    /// Why the change can't be saved, if it touches devices defined in other configuration files.
+    rejection: Option<String>,
+}
+
+/// A change to what the API keeps about the network besides the netplan configuration, like
+/// labels.
+pub struct MetadataMutation {
+    network: Network,
+}
+
+impl Mutation {
+    /// Returns `None` if `updated` is the same network as `current`, labels included.
+    pub fn new(current: &Network, mut updated: Network) -> Option<Self> {
+        let rejection = current.keep_foreign_devices(&mut updated).err();
+        if rejection.is_some() || without_metadata(current) != without_metadata(&updated) {
+            Some(Self::Config(ConfigMutation {
+                network: updated,
+                rejection,
+            }))
+        } else if *current != updated {
+            Some(Self::Metadata(MetadataMutation { network: updated }))
+        } else {
+            None
+        }
+    }
+}
+
+/// `network` without what netplan doesn't know about.
+///
+/// `required-for-online` is kept, as the networkd drop-in setting it only takes effect once the
+/// configuration is applied.
+pub(crate) fn without_metadata(network: &Network) -> Network {
+    let mut result = network.clone();
+    for ethernet in network.get_ethernets().values() {
+        let mut ethernet = ethernet.clone();
+        ethernet.set_labels(HashSet::new());
+        result.add_ethernet(&ethernet);
+    }
+    result
+}
+
+/// A running `netplan try`, waiting to be confirmed or rolled back.
+///
+/// If neither happens before the timeout, netplan reverts the system on its own, and the
+/// configuration file is restored by the next session opened on the store.
+pub struct PendingTry {
+    child: Child,
+    /// The network the tried change replaced, whose metadata is restored along with the
+    /// configuration, as the backups only hold the configuration file.
+    previous: Option<Network>,
+}
+
+impl PendingTry {
+    /// Keeps `network` as the one the tried change replaced.
+    pub fn with_previous(mut self, network: Network) -> Self {
+        self.previous = Some(network);
+        self
+    }
+
+    /// The network the tried change replaced, if it was kept.
+    pub fn previous(&self) -> Option<Network> {
+        self.previous.clone()
     }
 
-    pub fn apply(&self) -> io::Result<()> {
-        Self::run_command(&["apply"])?;
+    fn signal(&self, signal: libc::c_int) -> io::Result<()> {
+        // SAFETY: `kill` only sends a signal, to a child which was not waited for yet, so whose
+        // process ID was not reused
+        if unsafe { libc::kill(self.child.id() as libc::pid_t, signal) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
         Ok(())
     }
 
-    fn interfaces_with_misssing_dhcp_address(
-        data: &HashMap<String, serde_yml::Mapping>,
-    ) -> Vec<String> {
-        let mut interfaces: Vec<String> = vec![];
-        let search_strings = &["missing_dhcp4_address", "missing_dhcp6_address"];
-        for (eth, eth_dict) in data.iter() {
-            if let Some(missing_dhcp4) = eth_dict.get(search_strings[0]) {
-                if missing_dhcp4.as_bool().unwrap() {
-                    interfaces.push(eth.clone());
-                }
-            } else if let Some(missing_dhcp6) = eth_dict.get(search_strings[1]) {
-                if missing_dhcp6.as_bool().unwrap() {
-                    interfaces.push(eth.clone());
-                }
+    /// Whether the try is still waiting, i.e. it did not time out yet.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Keeps the tried configuration.
+    pub fn confirm(mut self) -> io::Result<()> {
+        // `netplan try` accepts the configuration on SIGUSR1
+        self.signal(libc::SIGUSR1)?;
+        self.child.wait()?;
+        Ok(())
+    }
+
+    /// Stops the try, making netplan revert the configuration if it still runs.
+    pub fn cancel(mut self) -> io::Result<()> {
+        if self.is_running() {
+            self.signal(libc::SIGINT)?;
+        }
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+/// Restores the configuration saved before a try, and the metadata of `previous`, the network the
+/// tried change replaced, if it is known.
+pub fn restore_tried(
+    netplan: &dyn NetplanBackend,
+    previous: Option<&Network>,
+) -> Result<(), NetplanError> {
+    netplan.restore_config()?;
+    if let Some(previous) = previous {
+        netplan.save_metadata(previous)?;
+    }
+    Ok(())
+}
+
+/// The configuration as last loaded by a session, with what listing the interfaces needs besides.
+pub struct Snapshot {
+    pub network: Network,
+    /// Entity tag of the configuration the network was loaded from.
+    pub etag: String,
+    /// Names of the physical interfaces of the system, sorted.
+    pub system_interfaces: Vec<String>,
+}
+
+#[derive(Default)]
+struct SnapshotCache {
+    /// The last snapshot taken, even if it is stale.
+    latest: Option<Arc<Snapshot>>,
+    /// Whether nothing may have changed through a session since the snapshot was taken.
+    fresh: bool,
+}
+
+pub struct NetplanStore {
+    backend: Arc<dyn NetplanBackend + Send + Sync>,
+    /// Taken on every load, so listings can be served without opening a session.
+    snapshots: Arc<RwLock<SnapshotCache>>,
+    /// Held by the session of a request, so requests use the backend one at a time. Locked
+    /// asynchronously, so requests waiting for an apply to finish don't block their worker.
+    lock: Arc<tokio::sync::Mutex<()>>,
+    /// How long a session may take, with all the commands it runs.
+    operation_timeout: Duration,
+    /// How long an apply waits for the DHCP leases of the interfaces, before probing why they are
+    /// missing.
+    lease_wait: Duration,
+    /// Outcome of the last probe of the interfaces still waiting for their lease, by name.
+    dhcp_probes: Arc<RwLock<HashMap<String, DhcpProbeResult>>>,
+    /// The most probes kept at once.
+    dhcp_probes_high_water: Arc<HighWaterMark>,
+    /// The `netplan try` in progress, if any. Locked after a session is opened when both are
+    /// needed, and never held across an `await`.
+    pub pending_try: Mutex<Option<PendingTry>>,
+}
+
+impl NetplanStore {
+    pub fn new(backend: Box<dyn NetplanBackend + Send + Sync>) -> Self {
+        Self {
+            backend: Arc::from(backend),
+            snapshots: Arc::default(),
+            lock: Arc::new(tokio::sync::Mutex::new(())),
+            operation_timeout: DEFAULT_OPERATION_TIMEOUT,
+            lease_wait: DEFAULT_LEASE_WAIT,
+            dhcp_probes: Arc::default(),
+            dhcp_probes_high_water: Arc::default(),
+            pending_try: Mutex::new(None),
+        }
+    }
+
+    /// Gives each session `operation_timeout` instead of one minute.
+    pub fn with_operation_timeout(mut self, operation_timeout: Duration) -> Self {
+        self.operation_timeout = operation_timeout;
+        self
+    }
+
+    /// Waits `lease_wait` for the DHCP leases after an apply instead of fifteen seconds.
+    pub fn with_lease_wait(mut self, lease_wait: Duration) -> Self {
+        self.lease_wait = lease_wait;
+        self
+    }
+
+    /// Outcome of the last probe of `interface`, if it was still waiting for its DHCP lease after
+    /// the last apply.
+    pub fn dhcp_probe(&self, interface: &str) -> Option<DhcpProbeResult> {
+        self.dhcp_probes.read().unwrap().get(interface).cloned()
+    }
+
+    /// The configuration as last loaded, unless it may have changed since.
+    pub fn snapshot(&self) -> Option<Arc<Snapshot>> {
+        let cache = self.snapshots.read().unwrap();
+        cache.latest.clone().filter(|_| cache.fresh)
+    }
+
+    /// The configuration as last loaded successfully, even if it may have changed since.
+    pub fn last_known_good(&self) -> Option<Arc<Snapshot>> {
+        self.snapshots.read().unwrap().latest.clone()
+    }
+
+    /// Waits for the backend to be free, and opens a session on it, whose deadline starts now.
+    ///
+    /// A try which timed out meanwhile is done with first, so the session never loads the
+    /// configuration it rejected.
+    pub async fn lock(&self) -> NetplanSession {
+        let guard = self.lock.clone().lock_owned().await;
+        let session = NetplanSession {
+            backend: self.backend.clone(),
+            snapshots: self.snapshots.clone(),
+            dhcp_probes: self.dhcp_probes.clone(),
+            dhcp_probes_high_water: self.dhcp_probes_high_water.clone(),
+            lease_wait: self.lease_wait,
+            _guard: guard,
+            deadline: Deadline::after(self.operation_timeout),
+        };
+        let expired = {
+            let mut pending_try = self.pending_try.lock().unwrap();
+            let timed_out = pending_try
+                .as_mut()
+                .is_some_and(|pending_try| !pending_try.is_running());
+            pending_try.take_if(|_| timed_out)
+        };
+        if let Some(expired) = expired {
+            // netplan only reverted the system, so the file follows
+            let result = session
+                .run(move |netplan| {
+                    let previous = expired.previous();
+                    expired.cancel()?;
+                    restore_tried(netplan, previous.as_ref())
+                })
+                .await;
+            match result {
+                Ok(()) => log::warn!(
+                    "The netplan try timed out, so the previous configuration was restored."
+                ),
+                Err(err) => log::error!(
+                    "The netplan try timed out, but the previous configuration could not be restored: {err}"
+                ),
             }
         }
-        interfaces
+        session
     }
 
-    fn interfaces_expecting_dhcp_address(network: &Network) -> Vec<String> {
-        let mut result = vec![];
-        for (eth_name, eth) in network.get_ethernets().iter() {
-            if eth.get_dhcp4()
-                || (eth.get_dhcp6()
-                    && eth.get_accept_ra().is_some()
-                    && eth
-                        .get_accept_ra()
-                        .expect("Accept RA is set and it should be a bool."))
-            {
-                result.push(eth_name.clone());
+    /// Reconciles the bookkeeping of the storage budget with the disk every `period`, starting
+    /// now, for as long as the service runs.
+    pub async fn reconcile_storage_every(&self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.lock().await.reconcile_storage().await {
+                log::error!("Could not measure the disk usage: {err}");
             }
         }
-        result
+    }
+}
+
+impl Default for NetplanStore {
+    fn default() -> Self {
+        Self::new(Box::new(Netplan::default()))
+    }
+}
+
+/// The backend locked by a request, for as long as the request needs it.
+///
+/// The backend is only used in the blocking thread pool, as its commands would otherwise stall
+/// every other request served by the same worker. Everything a session does must be done by its
+/// deadline, the commands still running then being killed with `Timeout`.
+pub struct NetplanSession {
+    backend: Arc<dyn NetplanBackend + Send + Sync>,
+    snapshots: Arc<RwLock<SnapshotCache>>,
+    dhcp_probes: Arc<RwLock<HashMap<String, DhcpProbeResult>>>,
+    dhcp_probes_high_water: Arc<HighWaterMark>,
+    lease_wait: Duration,
+    _guard: OwnedMutexGuard<()>,
+    deadline: Deadline,
+}
+
+impl NetplanSession {
+    /// Runs `operation` on the backend in the blocking thread pool, within the deadline of the
+    /// session.
+    ///
+    /// The snapshot of the configuration is considered stale from then on, as `operation` may
+    /// change the configuration.
+    pub async fn run<T, F>(&self, operation: F) -> Result<T, NetplanError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn NetplanBackend) -> Result<T, NetplanError> + Send + 'static,
+    {
+        self.snapshots.write().unwrap().fresh = false;
+        self.read(operation).await
+    }
+
+    /// Like `run`, for operations which don't change the configuration.
+    async fn read<T, F>(&self, operation: F) -> Result<T, NetplanError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn NetplanBackend) -> Result<T, NetplanError> + Send + 'static,
+    {
+        let backend = self.backend.clone();
+        let deadline = self.deadline;
+        web::block(move || deadline.enforce(|| operation(&*backend)))
+            .await
+            .map_err(|err| NetplanError::Io(io::Error::other(err.to_string())))?
+    }
+
+    /// See `NetplanBackend::load_stored_config`.
+    pub async fn load_stored_config(&self) -> Result<Network, NetplanError> {
+        self.read(|netplan| netplan.load_stored_config()).await
+    }
+
+    /// See `NetplanBackend::load_config`. A snapshot of the configuration is taken as well.
+    ///
+    /// The Ethernets affected by changes waiting for a reboot are marked as such.
+    pub async fn load_config(&self) -> Result<Network, NetplanError> {
+        let (network, snapshot) = self
+            .read(|netplan| {
+                let mut network = netplan.load_config()?;
+                match netplan.pending_actions() {
+                    Ok(actions) => mark_pending_reboot(&mut network, &actions),
+                    Err(err) => {
+                        log::warn!("Could not list the changes waiting for a reboot: {err}")
+                    }
+                }
+                // Both are cheap next to `netplan status`, and the listings need them
+                let snapshot = match (netplan.config_etag(), netplan.list_system_interfaces()) {
+                    (Ok(etag), Ok(system_interfaces)) => Some(Snapshot {
+                        network: network.clone(),
+                        etag,
+                        system_interfaces,
+                    }),
+                    _ => None,
+                };
+                Ok((network, snapshot))
+            })
+            .await?;
+        if let Some(snapshot) = snapshot {
+            *self.snapshots.write().unwrap() = SnapshotCache {
+                latest: Some(Arc::new(snapshot)),
+                fresh: true,
+            };
+        }
+        Ok(network)
+    }
+
+    /// See `NetplanBackend::config_etag`.
+    pub async fn config_etag(&self) -> Result<String, NetplanError> {
+        self.read(|netplan| Ok(netplan.config_etag()?)).await
+    }
+
+    /// See `NetplanBackend::metadata_revision`.
+    pub async fn metadata_revision(&self) -> Result<String, NetplanError> {
+        self.read(|netplan| Ok(netplan.metadata_revision()?)).await
+    }
+
+    /// See `NetplanBackend::get_diff`.
+    pub async fn get_diff(&self) -> Result<HashMap<String, serde_yml::Mapping>, NetplanError> {
+        self.read(|netplan| netplan.get_diff()).await
+    }
+
+    /// See `NetplanBackend::list_system_interfaces`.
+    pub async fn list_system_interfaces(&self) -> Result<Vec<String>, NetplanError> {
+        self.read(|netplan| Ok(netplan.list_system_interfaces()?))
+            .await
+    }
+
+    /// See `NetplanBackend::probe_dhcp`. The segment is listened to for three seconds, or until
+    /// the deadline of the session if it comes first.
+    pub async fn probe_dhcp(&self, interface: String) -> Result<DhcpProbeResult, NetplanError> {
+        let timeout = DHCP_PROBE_TIMEOUT.min(self.deadline.remaining());
+        self.read(move |netplan| netplan.probe_dhcp(&interface, timeout))
+            .await
+    }
+
+    /// See `NetplanBackend::pending_actions`.
+    pub async fn pending_actions(&self) -> Result<Vec<PendingAction>, NetplanError> {
+        self.read(|netplan| Ok(netplan.pending_actions()?)).await
     }
 
-    pub fn apply_with_diff(&self) -> Result<Network, HttpResponse> {
-        if self.apply().is_err() {
-            return Err(HttpResponse::InternalServerError()
-                .body("There was a problem applying the current config."));
+    /// See `NetplanBackend::wait_online`.
+    pub async fn wait_online(&self) -> Result<WaitOnlineReport, NetplanError> {
+        self.read(|netplan| Ok(netplan.wait_online()?)).await
+    }
+
+    /// See `NetplanBackend::yaml_references`.
+    pub async fn yaml_references(&self) -> Result<Vec<String>, NetplanError> {
+        self.read(|netplan| Ok(netplan.yaml_references()?)).await
+    }
+
+    /// See `NetplanBackend::storage_report`.
+    pub async fn storage_report(&self) -> Result<StorageReport, NetplanError> {
+        self.read(|netplan| Ok(netplan.storage_report()?)).await
+    }
+
+    /// Usage of the in-memory structures of the store and of its backend, by name.
+    ///
+    /// The probes are replaced on every apply by those of the interfaces still waiting for their
+    /// lease, so they are bounded by the configured interfaces rather than capped.
+    pub async fn resource_usage(&self) -> Result<Vec<StructureUsage>, NetplanError> {
+        let probes = self.dhcp_probes.read().unwrap().len();
+        let mut usage = self.read(|netplan| Ok(netplan.resource_usage()?)).await?;
+        usage.push(
+            self.dhcp_probes_high_water
+                .usage("dhcp_probes", probes, None),
+        );
+        usage.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(usage)
+    }
+
+    /// See `NetplanBackend::rebuild_state`. The configuration may look different afterwards, as
+    /// metadata of it may have been lost.
+    pub async fn rebuild_state(
+        &self,
+        targets: Vec<Sidecar>,
+    ) -> Result<Vec<RebuildReport>, NetplanError> {
+        self.run(move |netplan| Ok(netplan.rebuild_state(&targets)?))
+            .await
+    }
+
+    /// See `NetplanBackend::reconcile_storage`. Only backups may be evicted, so the configuration
+    /// is the same afterwards.
+    pub async fn reconcile_storage(&self) -> Result<StorageReport, NetplanError> {
+        self.read(|netplan| Ok(netplan.reconcile_storage()?)).await
+    }
+
+    /// See `NetplanBackend::pending_interfaces`.
+    pub async fn pending_interfaces(&self) -> Result<Vec<String>, NetplanError> {
+        self.read(|netplan| netplan.pending_interfaces()).await
+    }
+
+    /// Applies the saved configuration, and waits for the system to reach it.
+    pub async fn apply_with_diff(
+        &self,
+        renderer: NetworkRenderer,
+    ) -> Result<Network, NetplanError> {
+        self.run(move |netplan| {
+            netplan.check_renderer_service(&renderer)?;
+            netplan.apply()
+        })
+        .await?;
+        self.wait_for_system_state().await
+    }
+
+    /// Replaces the whole configuration with `network` like `NetplanBackend::replace_config`, and
+    /// waits for the system to reach it.
+    pub async fn replace_config(&self, network: Network) -> Result<Network, NetplanError> {
+        self.run(move |netplan| netplan.replace_config(&network))
+            .await?;
+        self.wait_for_system_state().await
+    }
+
+    /// Restores and applies a backup like `NetplanBackend::apply_backup`, and waits for the system
+    /// to reach it.
+    pub async fn apply_backup(&self, timestamp: u64, force: bool) -> Result<Network, NetplanError> {
+        self.run(move |netplan| netplan.apply_backup(timestamp, force))
+            .await?;
+        self.wait_for_system_state().await
+    }
+
+    /// Commits the mutated configuration like `NetplanBackend::commit`, and waits for the system
+    /// to reach it if it was applied.
+    ///
+    /// Changes to the metadata only are saved without running netplan, whatever `apply` is.
+    pub async fn commit(&self, mutation: Mutation, apply: bool) -> Result<Network, NetplanError> {
+        let mutation = match mutation {
+            Mutation::Metadata(MetadataMutation { network }) => {
+                return self
+                    .run(move |netplan| {
+                        netplan.save_metadata(&network)?;
+                        Ok(network)
+                    })
+                    .await
+            }
+            Mutation::Config(mutation) => mutation,
+        };
+        let network = self
+            .run(move |netplan| netplan.commit(mutation, apply))
+            .await?;
+        if apply {
+            self.wait_for_system_state().await
+        } else {
+            Ok(network)
         }
-        const SECONDS_TO_WAIT: i32 = 15;
-        let mut there_are_differences = false;
-        for _waited in 0..SECONDS_TO_WAIT {
-            match self.get_diff() {
+    }
+
+    /// Waits for the system to reach the applied configuration, which takes a while for DHCP leases.
+    ///
+    /// The system is checked every second for the lease wait of the store, and until the deadline
+    /// of the session at the latest, the worker serving other requests in between. The interfaces
+    /// still waiting for their lease then are probed for a DHCP server, and the outcome is
+    /// attached to them in the returned network, besides being kept by the store.
+    async fn wait_for_system_state(&self) -> Result<Network, NetplanError> {
+        let waiting_until = Instant::now() + self.lease_wait;
+        let waiting_ifaces = loop {
+            match self.get_diff().await {
                 Ok(diff) => {
                     if diff.is_empty() {
-                        there_are_differences = false;
-                        break;
+                        break vec![];
                     }
-                    there_are_differences = true;
                     let ifaces_without_dhcp_address =
-                        Self::interfaces_with_misssing_dhcp_address(&diff);
+                        Netplan::interfaces_with_misssing_dhcp_address(&diff)?;
                     if ifaces_without_dhcp_address.is_empty() {
                         // There is an error, but it's not the addresses
-                        return Err(HttpResponse::InternalServerError()
-                            .body("There are unchecked system_state differences"));
+                        return Err(NetplanError::SystemState(
+                            "There are unchecked system_state differences".to_string(),
+                        ));
                     }
                     let ifaces_expecting_dhcp_address =
-                        Self::interfaces_expecting_dhcp_address(&self.load_config().unwrap());
-                    let affected_ifaces = ifaces_without_dhcp_address
-                        .iter()
-                        .filter(|iface| ifaces_expecting_dhcp_address.contains(*iface))
-                        .collect::<Vec<&String>>();
-                    if !affected_ifaces.is_empty() {
-                        // Sleep for 1 second and try again
-                        std::thread::sleep(std::time::Duration::from_secs(1));
+                        Netplan::interfaces_expecting_dhcp_address(&self.load_config().await?);
+                    let waiting_ifaces: Vec<String> = ifaces_without_dhcp_address
+                        .into_iter()
+                        .filter(|iface| ifaces_expecting_dhcp_address.contains(iface))
+                        .collect();
+                    if !waiting_ifaces.is_empty() {
+                        let waiting = waiting_until.saturating_duration_since(Instant::now());
+                        if waiting.is_zero() {
+                            break waiting_ifaces;
+                        }
+                        // Check again in a second, or at the deadline, which the next check fails
+                        let interval = Duration::from_secs(1)
+                            .min(waiting)
+                            .min(self.deadline.remaining());
+                        tokio::time::sleep(interval).await;
                         continue;
                     }
                     // Only diff is from dhcp, but no affected interfaces present.
-                    there_are_differences = false;
-                    break;
+                    break vec![];
                 }
+                Err(err @ NetplanError::Timeout { .. }) => return Err(err),
                 Err(_) => {
-                    return Err(HttpResponse::InternalServerError()
-                        .body("The config was not applied correctly."));
+                    return Err(NetplanError::SystemState(
+                        "The config was not applied correctly.".to_string(),
+                    ));
                 }
             }
+        };
+        // Explain why the interfaces are still waiting for a lease, so user is aware.
+        let mut probes = HashMap::new();
+        for iface in waiting_ifaces {
+            match self.probe_dhcp(iface.clone()).await {
+                Ok(probe) => {
+                    let probe = probe.waiting_for_lease();
+                    match &probe.server {
+                        Some(server) => log::warn!(
+                            "{iface} is still missing its DHCP address, although server {server} is present"
+                        ),
+                        None => log::warn!(
+                            "{iface} is still missing its DHCP address: no DHCP server responded on this segment"
+                        ),
+                    }
+                    probes.insert(iface, probe);
+                }
+                Err(err @ NetplanError::Timeout { .. }) => return Err(err),
+                Err(err) => log::warn!("{iface} is still missing its DHCP address ({err})"),
+            }
         }
-        if there_are_differences {
-            // LOG a warning, so user is aware.
-        }
-        match self.load_config() {
-            Ok(network) => Ok(network),
-            Err(_) => Err(HttpResponse::InternalServerError()
-                .body("There was an error while loading the config.")),
+        // The interfaces which got their lease are not waiting anymore
+        *self.dhcp_probes.write().unwrap() = probes.clone();
+        self.dhcp_probes_high_water.record(probes.len());
+        let mut network = self.load_config().await.map_err(|err| match err {
+            NetplanError::Timeout { .. } => err,
+            _ => NetplanError::SystemState(
+                "There was an error while loading the config.".to_string(),
+            ),
+        })?;
+        for (iface, probe) in probes {
+            if let Some(mut ethernet) = network.get_ethernets().get(&iface).cloned() {
+                ethernet.set_dhcp_probe(Some(probe));
+                network.add_ethernet(&ethernet);
+            }
         }
+        Ok(network)
     }
-    pub fn tryout(&self) -> io::Result<()> {
-        let cmd = &[
-            "try",
-            "--timeout",
-            "5",
-            "--config-file",
-            NETPLAN_CONFIG_PATH,
-        ];
-        Self::run_command(cmd)?;
+}
+
+/// What the API needs from netplan.
+///
+/// `Netplan` runs the real `netplan` binary over the configuration file, while `MockNetplan` keeps
+/// the configuration in memory. The operations built on top of them, like `commit`, are shared.
+/// Requests use them through a `NetplanSession`.
+pub trait NetplanBackend {
+    /// Loads the configuration, creating it if there is none yet.
+    fn load_config(&self) -> Result<Network, NetplanError>;
+
+    /// Loads the configuration as it is stored, without the state of the system, so without
+    /// running netplan unless the configuration has to be created.
+    fn load_stored_config(&self) -> Result<Network, NetplanError>;
+
+    /// Saves `network` as the configuration, keeping a backup of the previous one.
+    fn save_config(&self, network: &Network) -> io::Result<()>;
+
+    /// Saves what the API keeps about `network` besides the configuration, like the labels of its
+    /// Ethernets, leaving the configuration as is.
+    fn save_metadata(&self, network: &Network) -> io::Result<()>;
+
+    /// Applies the saved configuration to the system.
+    fn apply(&self) -> Result<(), NetplanError>;
+
+    /// Differences between the configuration and the state of the system, by interface.
+    fn get_diff(&self) -> Result<HashMap<String, serde_yml::Mapping>, NetplanError>;
+
+    /// Names of the physical interfaces of the system, configured or not, sorted.
+    fn list_system_interfaces(&self) -> io::Result<Vec<String>>;
+
+    /// Entity tag of the current configuration, which changes whenever the configuration file
+    /// or what is applied along with it, like `required-for-online`, does. Changes to the labels
+    /// leave it as is, and bump `metadata_revision` instead.
+    fn config_etag(&self) -> io::Result<String>;
+
+    /// Entity tag of what the API keeps about the configuration besides it, which changes
+    /// whenever the metadata does.
+    fn metadata_revision(&self) -> io::Result<String>;
+
+    /// Restores the configuration saved before the last save.
+    fn restore_config(&self) -> io::Result<()>;
+
+    /// Timestamps of the backups of the configuration, in milliseconds since the Unix epoch,
+    /// oldest first.
+    fn list_backups(&self) -> io::Result<Vec<u64>>;
+
+    /// Restores the backup taken at `timestamp`, after backing up the current configuration.
+    ///
+    /// The error is of the `NotFound` kind if there is no such backup.
+    fn restore_backup(&self, timestamp: u64) -> io::Result<()>;
+
+    /// Whether the backup taken at `timestamp` was imported from before the upgrade, so that how
+    /// it relates to the current configuration is unknown.
+    fn is_pre_upgrade_backup(&self, timestamp: u64) -> bool;
+
+    /// Starts trying the saved configuration for `timeout` seconds.
+    ///
+    /// The backup of the previous configuration is kept, so the try can be rolled back.
+    fn tryout(&self, timeout: u32) -> Result<PendingTry, NetplanError>;
+
+    /// Checks that netplan accepts `network`, without saving nor applying it.
+    ///
+    /// The error holds what netplan reported.
+    fn generate(&self, network: &Network) -> io::Result<()>;
+
+    /// Makes sure the service of the given renderer is running, since otherwise
+    /// applying silently has no effect.
+    fn check_renderer_service(&self, renderer: &NetworkRenderer) -> Result<(), NetplanError>;
+
+    /// Looks for a DHCP server on the segment of `interface`, listening for answers for
+    /// `timeout`.
+    ///
+    /// The error is `Io` of the `NotFound` kind if there is no such interface.
+    fn probe_dhcp(
+        &self,
+        interface: &str,
+        timeout: Duration,
+    ) -> Result<DhcpProbeResult, NetplanError>;
+
+    /// The changes to the configuration which only take full effect after a reboot, those which
+    /// took effect since the last boot being cleared.
+    fn pending_actions(&self) -> io::Result<Vec<PendingAction>>;
+
+    /// Which interfaces boot waits for through systemd-networkd-wait-online, as networkd is
+    /// configured, along with the state of the service.
+    fn wait_online(&self) -> io::Result<WaitOnlineReport>;
+
+    /// The YAML anchors, aliases and merge keys the configuration file uses, which are expanded
+    /// when it is loaded, and so don't survive saving it, sorted.
+    fn yaml_references(&self) -> io::Result<Vec<String>>;
+
+    /// Fails with `YamlReferences` if saving a change would expand the anchors, aliases or merge
+    /// keys of the configuration file, and the backend is strict about them.
+    fn check_yaml_references(&self) -> Result<(), NetplanError>;
+
+    /// Disk usage of what is written besides the configuration, against its budget, as last
+    /// counted.
+    fn storage_report(&self) -> io::Result<StorageReport>;
+
+    /// Measures the actual disk usage, corrects the bookkeeping with it, and evicts what went
+    /// over budget since, e.g. as files were added behind the back of the API.
+    fn reconcile_storage(&self) -> io::Result<StorageReport>;
+
+    /// Usage of the in-memory structures of the backend.
+    fn resource_usage(&self) -> io::Result<Vec<StructureUsage>>;
+
+    /// Rebuilds the corrupt `targets` from the entries which can still be read, reporting what
+    /// was recovered and what was lost for each. The files which can be read are left as is.
+    fn rebuild_state(&self, targets: &[Sidecar]) -> io::Result<Vec<RebuildReport>>;
+
+    /// Replaces the whole configuration with `network` and applies it.
+    ///
+    /// If netplan fails to apply it, the previous configuration is restored and applied again,
+    /// and the `ApplyFailed` error holds what netplan reported.
+    fn replace_config(&self, network: &Network) -> Result<(), NetplanError> {
+        self.check_renderer_service(&network.renderer)?;
+        self.save_config(network)?;
+        if let Err(err) = self.apply() {
+            return Err(self.roll_back(err));
+        }
         Ok(())
     }
 
-    fn get_dynamic_addresses_from_netplan_status(
-        data: serde_yml::Mapping,
-    ) -> HashMap<String, Vec<String>> {
-        let mut result: HashMap<String, Vec<String>> = HashMap::new();
-        data.iter().for_each(|(eth, data)| {
-            if data
-                .get("type")
-                .expect("All interfaces in the netplan output should have a `type`.")
-                .as_str()
-                .unwrap()
-                == "ethernet"
-            {
-                if let Some(addresses_dict) = data.get("addresses") {
-                    let mut found_addresses: Vec<String> = vec![];
-                    let addresses_dict = addresses_dict.as_mapping().unwrap();
-                    addresses_dict
-                        .iter()
-                        .for_each(|(address_type, properties)| {
-                            let properties = properties.as_mapping().unwrap();
-                            if let Some(flags) = properties.get("flags") {
-                                let mut parsed_address = address_type.as_str().unwrap().to_string();
-                                let flags: Vec<String> = flags
-                                    .as_sequence()
-                                    .unwrap()
-                                    .iter()
-                                    .map(|entry| entry.as_str().unwrap().to_string())
-                                    .collect();
-                                let flags: String = flags.join(", ");
-                                let suffix = format!("({})", flags);
-                                if let Some(prefix) = properties.get("prefix") {
-                                    parsed_address.insert_str(0, prefix.as_str().unwrap());
-                                }
-                                parsed_address.push_str(&suffix);
-                                found_addresses.push(parsed_address);
-                            }
-                        });
-                    result.insert(eth.as_str().unwrap().to_string(), found_addresses);
-                }
+    /// Restores the configuration saved before the last save and applies it again, after netplan
+    /// failed to apply the new one with `err`.
+    ///
+    /// Returns `ApplyFailed` with the message reporting both the failure and the outcome of the
+    /// rollback, or the timeout as is if netplan hung, the outcome being logged then.
+    fn roll_back(&self, err: NetplanError) -> NetplanError {
+        let message = match self
+            .restore_config()
+            .and_then(|_| self.apply().map_err(io::Error::from))
+        {
+            Ok(()) => format!(
+                "netplan could not apply the configuration, so the previous one was restored:\n{err}"
+            ),
+            Err(rollback_err) => {
+                log::error!("Could not roll back to the previous configuration: {rollback_err}");
+                format!(
+                    "netplan could not apply the configuration:\n{err}\n\
+                    Rolling back to the previous configuration failed as well:\n{rollback_err}"
+                )
             }
-        });
-        result
+        };
+        match err {
+            NetplanError::Timeout { .. } => {
+                log::warn!("{message}");
+                err
+            }
+            _ => NetplanError::ApplyFailed(message),
+        }
     }
 
-    pub fn load_config(&self) -> io::Result<Network> {
-        let status_yaml: serde_yml::Mapping = serde_yml::from_str(&Self::run_command(&[
-            "status", "--format", "yaml", "--all",
-        ])?)
-        .unwrap();
+    /// Saves the mutated configuration and applies it, returning the saved configuration.
+    ///
+    /// Nothing is applied if the configuration can't be saved, e.g. on a read-only filesystem, as
+    /// netplan would apply the stale file; the error is returned as is instead. If netplan fails to
+    /// apply the configuration, the previous one is restored and applied again, so the system
+    /// doesn't keep a configuration that would also fail on the next boot.
+    fn save_and_apply(&self, mutation: ConfigMutation) -> Result<Network, NetplanError> {
+        if let Some(rejection) = mutation.rejection {
+            return Err(NetplanError::ForeignDevice(rejection));
+        }
+        self.check_yaml_references()?;
+        self.save_config(&mutation.network)?;
+        self.apply_or_roll_back(&mutation.network.renderer)?;
+        Ok(mutation.network)
+    }
+
+    /// Restores the backup taken at `timestamp` and applies it, rolling back to the current
+    /// configuration like `save_and_apply` if netplan fails to apply it.
+    ///
+    /// The backup imported from before the upgrade is only restored if `force` is set, and is
+    /// rejected with `PreUpgradeBackup` otherwise.
+    fn apply_backup(&self, timestamp: u64, force: bool) -> Result<(), NetplanError> {
+        if !force && self.is_pre_upgrade_backup(timestamp) {
+            return Err(NetplanError::PreUpgradeBackup(timestamp));
+        }
+        match self.restore_backup(timestamp) {
+            Ok(()) => (),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(NetplanError::BackupNotFound(timestamp))
+            }
+            Err(err) => return Err(err.into()),
+        }
+        let network = self.load_config()?;
+        self.apply_or_roll_back(&network.renderer)
+    }
+
+    /// Applies the saved configuration, rolling back to the previous configuration if netplan
+    /// fails to apply it.
+    fn apply_or_roll_back(&self, renderer: &NetworkRenderer) -> Result<(), NetplanError> {
+        self.check_renderer_service(renderer)?;
+        if let Err(err) = self.apply() {
+            return Err(self.roll_back(err));
+        }
+        Ok(())
+    }
+
+    /// Saves the mutated configuration, and applies it unless `apply` is false, returning the
+    /// saved configuration.
+    ///
+    /// Unapplied changes stay staged in the configuration file until the next apply. Changes to
+    /// devices defined in other configuration files are rejected with `ForeignDevice`, and
+    /// changes which would expand YAML references in strict mode with `YamlReferences`. Replacing
+    /// the whole configuration is explicit, so strict mode doesn't apply to it.
+    fn commit(&self, mutation: ConfigMutation, apply: bool) -> Result<Network, NetplanError> {
+        if apply {
+            return self.save_and_apply(mutation);
+        }
+        if let Some(rejection) = mutation.rejection {
+            return Err(NetplanError::ForeignDevice(rejection));
+        }
+        self.check_yaml_references()?;
+        self.save_config(&mutation.network)?;
+        Ok(mutation.network)
+    }
+
+    /// Names of the interfaces whose system state differs from the configuration file, sorted.
+    fn pending_interfaces(&self) -> Result<Vec<String>, NetplanError> {
+        let mut result: Vec<String> = self.get_diff()?.into_keys().collect();
+        result.sort();
+        Ok(result)
+    }
+}
+
+impl NetplanBackend for Netplan {
+    fn load_config(&self) -> Result<Network, NetplanError> {
+        let status_yaml: serde_yml::Mapping =
+            serde_yml::from_str(&self.run_command(&["status", "--format", "yaml", "--all"])?)
+                .map_err(|err| {
+                    NetplanError::parse(format!("the output of netplan status: {err}"))
+                })?;
         let interfaces_dynamic_addresses =
-            Self::get_dynamic_addresses_from_netplan_status(status_yaml);
+            Self::get_dynamic_addresses_from_netplan_status(status_yaml)?;
         let diff = self.get_diff()?;
 
-        let config_content = fs::read_to_string(NETPLAN_CONFIG_PATH);
+        let config_content = fs::read_to_string(&self.config_path);
         match config_content {
-            Err(_) => {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
                 // The config file does not exist, so we create it.
+                // Prefer the configuration seeded through the environment, if any
+                let seed = match &self.seed_vars {
+                    Some(vars) => EnvironmentSpec::from_vars(vars.clone()),
+                    None => EnvironmentSpec::from_env(),
+                };
+                match seed {
+                    Ok(Some(spec)) => {
+                        let result = spec.to_network();
+                        self.save_config(&result)?;
+                        return Ok(result);
+                    }
+                    Ok(None) => (),
+                    Err(err) => log::error!(
+                        "Ignoring the configuration seeded through the environment:\n{err}"
+                    ),
+                }
                 // Check for existing ethernets in /sys/class/net
                 let mut result = Network::new();
                 let mut base_interface: Option<Ethernet> = None;
-                if fs::read_dir("/sys/class/net")?.any(|entry| {
-                    entry
-                        .expect("All entries in /sys/class/net should be proper entries")
-                        .file_name()
-                        .into_string()
-                        .unwrap()
-                        == "eth0"
-                }) {
+                if System::physical_interfaces()?
+                    .iter()
+                    .any(|name| name == "eth0")
+                {
                     let mut iface = Ethernet::new("eth0".to_string());
                     iface.set_dhcp4(true);
+                    // The differences of an interface are its system state
                     if let Some(eth0_diff) = diff.get("eth0") {
-                        iface.set_system_state(serde_yml::from_value(
-                            eth0_diff.get("system_state")
-                                .expect("Ethernet should have system_state")
-                                .clone(),
-                        ).expect("Mapping from system state should be made (at least) an empty mapping."));
+                        iface.set_system_state(
+                            serde_yml::from_value(eth0_diff.clone().into()).map_err(|err| {
+                                NetplanError::parse(format!("the system state of eth0: {err}"))
+                            })?,
+                        );
                     }
                     if let Some(eth0_addresses) = interfaces_dynamic_addresses.get("eth0") {
                         iface.set_dynamic_addresses(eth0_addresses);
@@ -225,176 +1118,2618 @@ impl Netplan {
                 self.save_config(&result)?;
                 Ok(result)
             }
+            // Any other error, e.g. a permission denied, must not overwrite the file
+            Err(err) => Err(err.into()),
             Ok(config_content) => {
-                // This should never fail, since the users are not allowed to
-                // modify the config file directly.
-                let mut netplan_config: serde_yml::Value =
-                    serde_yml::from_str(&config_content).unwrap();
-
-                if let Some(network) = netplan_config.get_mut("network") {
-                    if let Some(ethernets) = network.get_mut("ethernets") {
-                        if let Some(ethernets_map) = ethernets.as_mapping_mut() {
-                            for (ethernet_name, actual_ethernet) in ethernets_map.iter_mut() {
-                                if let Some(ethernet_map) = actual_ethernet.as_mapping_mut() {
-                                    ethernet_map.insert("name".into(), ethernet_name.clone());
-                                    // Make sure to parse the routes, since they don't come as a mapping but rather as sequence
-                                    // Need to turn routes from a sequence to a mapping
-                                    if let Some(routes) = ethernet_map.get_mut("routes") {
-                                        if let Some(routes_seq) = routes.as_sequence_mut() {
-                                            let mut new_routes = serde_yml::Mapping::new();
-                                            for route in routes_seq.iter() {
-                                                let parsed_route: Route =
-                                                    serde_yml::from_value(route.clone())
-                                                        .expect("Error: there was a problem while parsing Route yaml string.");
-                                                new_routes.insert(
-                                                    serde_yml::Value::String(parsed_route.id()),
-                                                    route.clone(),
-                                                );
-                                            }
-                                            ethernet_map.insert("routes".into(), new_routes.into());
-                                        }
-                                    }
-                                    // Also add the system_state, if it exists
-                                    if let Some(interface_diff) =
-                                        diff.get(ethernet_name.as_str().unwrap())
-                                    {
-                                        if let Some(system_state) =
-                                            interface_diff.get("system_state")
-                                        {
-                                            if let Some(system_state_mapping) =
-                                                system_state.as_mapping()
-                                            {
-                                                if !system_state_mapping.is_empty() {
-                                                    ethernet_map.insert(
-                                                        "system_state".into(),
-                                                        system_state.clone(),
-                                                    );
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+                self.parse_stored_config(&config_content, &diff, &interfaces_dynamic_addresses)
+            }
+        }
+    }
 
-                let network: Network = serde_yml::from_value(netplan_config["network"].clone())
-                    .expect("Error: there was a problem while loading the parsed yaml string.");
-                Ok(network)
+    fn load_stored_config(&self) -> Result<Network, NetplanError> {
+        match fs::read_to_string(&self.config_path) {
+            Ok(config_content) => {
+                self.parse_stored_config(&config_content, &HashMap::new(), &HashMap::new())
             }
+            // Seeding it takes netplan anyway
+            Err(err) if err.kind() == ErrorKind::NotFound => self.load_config(),
+            Err(err) => Err(err.into()),
         }
     }
 
-    pub fn backup_config() -> io::Result<()> {
-        let backup_path = format!("{}.bak", NETPLAN_CONFIG_PATH);
-        fs::copy(NETPLAN_CONFIG_PATH, backup_path)?;
+    /// Metadata going over the storage budget is rejected before anything is written, so the
+    /// configuration and its metadata stay in step. The changes which only take effect after a
+    /// reboot are recorded once both are saved.
+    fn save_config(&self, network: &Network) -> io::Result<()> {
+        let metadata = Self::metadata_content(network);
+        self.check_metadata_budget(metadata.as_deref())?;
+        let previous = self.stored_network();
+        let references = self.yaml_references()?;
+        self.backup_config()?;
+        write_atomically(&self.config_path, Self::to_netplan_yaml(network).as_bytes())?;
+        self.write_metadata(metadata.as_deref())?;
+        self.write_wait_online_drop_ins(network)?;
+        if !references.is_empty() {
+            log::warn!(
+                "{} used YAML anchors, aliases or merge keys ({}), which were expanded as it was \
+                saved. The previous file is kept as a backup.",
+                self.config_path.display(),
+                references.join(", ")
+            );
+        }
+        self.record_pending_actions(&previous, network);
         Ok(())
     }
 
-    pub fn save_config(&self, network: &Network) -> io::Result<()> {
-        Self::backup_config()?;
-        // let data = serde_yml::to_value(network)
-        // .expect("Error: there was a problem while serializing the Network into YAML.");
-        // let mut network_data = serde_yml::Mapping::new();
-        // network_data.insert(serde_yml::Value::String("network".to_string()), data);
-
-        let yaml_string = serde_yml::to_string(&network)
-            .expect("Error: couldn't serialize network into YAML string.");
-        fs::write(NETPLAN_CONFIG_PATH, yaml_string)?;
-        Ok(())
+    /// The labels of the Ethernets are written to the metadata file, which is removed when there
+    /// is nothing to keep. It is never evicted, so metadata going over the storage budget is
+    /// rejected with `StorageFull` instead.
+    fn save_metadata(&self, network: &Network) -> io::Result<()> {
+        let metadata = Self::metadata_content(network);
+        self.check_metadata_budget(metadata.as_deref())?;
+        self.write_metadata(metadata.as_deref())
     }
 
-    pub fn restore_config(&self) {
-        let backup_path = format!("{}.bak", NETPLAN_CONFIG_PATH);
-        fs::copy(backup_path, NETPLAN_CONFIG_PATH).unwrap();
+    fn apply(&self) -> Result<(), NetplanError> {
+        self.run_command(&["apply"])?;
+        Ok(())
     }
 
-    pub fn get_diff(&self) -> io::Result<HashMap<String, serde_yml::Mapping>> {
+    fn get_diff(&self) -> Result<HashMap<String, serde_yml::Mapping>, NetplanError> {
         let cmd = &["status", "--diff-only", "--format", "yaml"];
         let mut result: HashMap<String, serde_yml::Mapping> = HashMap::new();
-        let output = Self::run_command(cmd)?;
-        let yaml_output: serde_yml::Mapping = serde_yml::from_str(&output).unwrap();
-        let managed_interfaces = yaml_output
+        let output = self.run_command(cmd)?;
+        let yaml_output: serde_yml::Mapping = serde_yml::from_str(&output)
+            .map_err(|err| NetplanError::parse(format!("the output of netplan status: {err}")))?;
+        let Some(managed_interfaces) = yaml_output
             .get("interfaces")
-            .expect("Output of diff should contain managed interfaces")
-            .as_mapping()
-            .unwrap();
-        managed_interfaces
-            .iter()
-            .for_each(|(interface, interface_data)| {
-                let iface_date = interface_data.as_mapping().unwrap();
-                if let Some(system_state) = iface_date.get("system_state") {
-                    result.insert(
-                        interface.as_str().unwrap().to_string(),
-                        system_state.as_mapping().unwrap().clone(),
-                    );
-                }
-            });
+            .and_then(serde_yml::Value::as_mapping)
+        else {
+            return Err(NetplanError::parse(
+                "the output of netplan status, which lacks the managed interfaces",
+            ));
+        };
+        for (interface, interface_data) in managed_interfaces.iter() {
+            let (Some(interface), Some(interface_data)) =
+                (interface.as_str(), interface_data.as_mapping())
+            else {
+                return Err(NetplanError::parse(format!(
+                    "the differences of {interface:?} reported by netplan status"
+                )));
+            };
+            if let Some(system_state) = interface_data.get("system_state") {
+                let Some(system_state) = system_state.as_mapping() else {
+                    return Err(NetplanError::parse(format!(
+                        "the system state of {interface} reported by netplan status"
+                    )));
+                };
+                result.insert(interface.to_string(), system_state.clone());
+            }
+        }
         Ok(result)
     }
 
-    pub fn save_and_apply(&self, network: &Network) -> Result<Network, HttpResponse> {
-        match self.save_config(network) {
-            Ok(_) => (),
-            Err(err) => return Err(HttpResponse::InternalServerError().body(err.to_string())),
+    fn list_system_interfaces(&self) -> io::Result<Vec<String>> {
+        System::physical_interfaces()
+    }
+
+    fn config_etag(&self) -> io::Result<String> {
+        let mut hasher = DefaultHasher::new();
+        // The other files are part of the network as well
+        let paths = std::iter::once(self.config_path.clone()).chain(self.other_config_files()?);
+        for path in paths {
+            read_or_empty(&path)?.hash(&mut hasher);
         }
-        self.apply_with_diff()
+        // Applied through the networkd drop-ins, unlike the rest of the metadata. Unreadable
+        // metadata sets nothing, so it counts as none
+        let required_for_online = self
+            .load_metadata()
+            .map(|metadata| metadata.required_for_online)
+            .unwrap_or_default();
+        serde_yml::to_string(&required_for_online)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?
+            .hash(&mut hasher);
+        Ok(format!("\"{:016x}\"", hasher.finish()))
     }
 
-    pub fn get_all_ethernets(&self) -> io::Result<Vec<String>> {
-        let output = Self::run_command(&["status", "--diff-only", "--format", "yaml"])?;
-        let mut result: Vec<String> = Vec::new();
-        let yaml_output: serde_yml::Mapping = serde_yml::from_str(&output)
-            .expect("Netplan status command should be YAML, but failed to parse");
-        // Normal interfaces
-        yaml_output
-            .get("interfaces")
-            .expect("Output of diff should contain managed interfaces")
-            .as_mapping()
-            .unwrap()
-            .iter()
-            .for_each(|(interface, _interface_data)| {
-                if interface.as_str().unwrap().starts_with("eth") {
-                    result.push(interface.as_str().unwrap().to_string());
-                }
-            });
-        // missing_interfaces_netplan
-        yaml_output
-            .get("missing_interfaces_netplan")
-            .expect("Output of diff should contain missing interfaces")
-            .as_mapping()
-            .unwrap()
-            .iter()
-            .for_each(|(interface, _interface_data)| {
-                if interface.as_str().unwrap().starts_with("eth") {
-                    result.push(interface.as_str().unwrap().to_string());
+    fn metadata_revision(&self) -> io::Result<String> {
+        let mut hasher = DefaultHasher::new();
+        read_or_empty(&self.metadata_path())?.hash(&mut hasher);
+        Ok(format!("\"{:016x}\"", hasher.finish()))
+    }
+
+    /// The latest backup is copied back, and kept.
+    fn restore_config(&self) -> io::Result<()> {
+        let Some(timestamp) = self.backup_timestamps()?.pop() else {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                "There is no backup of the configuration to restore.",
+            ));
+        };
+        let content = fs::read(self.backup_path(timestamp))?;
+        self.write_config(&content)
+    }
+
+    fn list_backups(&self) -> io::Result<Vec<u64>> {
+        self.backup_timestamps()
+    }
+
+    fn restore_backup(&self, timestamp: u64) -> io::Result<()> {
+        let backup_path = self.backup_path(timestamp);
+        if !backup_path.exists() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Backup {timestamp} was not found."),
+            ));
+        }
+        // Read first, as backing up the current configuration may prune this backup
+        let content = fs::read(backup_path)?;
+        self.backup_config()?;
+        self.write_config(&content)
+    }
+
+    fn is_pre_upgrade_backup(&self, timestamp: u64) -> bool {
+        self.pre_upgrade_backup_path(timestamp).exists()
+    }
+
+    fn pending_actions(&self) -> io::Result<Vec<PendingAction>> {
+        self.pending_actions.list()
+    }
+
+    /// The `.network` files rendered by netplan are read along with their drop-ins, those of the
+    /// administrator overriding the runtime ones of the same name. The state of the service is
+    /// `unknown` if `systemctl` can't tell it.
+    fn wait_online(&self) -> io::Result<WaitOnlineReport> {
+        let mut interfaces = vec![];
+        let entries = match fs::read_dir(&self.networkd_run_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => vec![],
+            entries => entries?.collect::<io::Result<_>>()?,
+        };
+        for entry in entries {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != "network")
+            {
+                continue;
+            }
+            let Some(mut participation) =
+                WaitOnlineParticipation::from_network_file(&fs::read_to_string(&path)?)
+            else {
+                continue;
+            };
+            let drop_in_directory = format!("{}.d", entry.file_name().to_string_lossy());
+            let mut drop_ins = BTreeMap::new();
+            for directory in [&self.networkd_run_path, &self.networkd_config_path] {
+                let entries = match fs::read_dir(directory.join(&drop_in_directory)) {
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    entries => entries?,
+                };
+                for drop_in in entries {
+                    let drop_in = drop_in?;
+                    if drop_in
+                        .path()
+                        .extension()
+                        .is_some_and(|extension| extension == "conf")
+                    {
+                        drop_ins.insert(drop_in.file_name(), drop_in.path());
+                    }
                 }
+            }
+            for drop_in in drop_ins.values() {
+                participation.apply_drop_in(&fs::read_to_string(drop_in)?);
+            }
+            interfaces.push(participation);
+        }
+        let service_state = match self.run_systemctl(&["is-active", WAIT_ONLINE_UNIT]) {
+            Ok(state) if !state.is_empty() => state,
+            _ => "unknown".to_string(),
+        };
+        Ok(WaitOnlineReport::new(service_state, interfaces))
+    }
+
+    fn yaml_references(&self) -> io::Result<Vec<String>> {
+        match fs::read_to_string(&self.config_path) {
+            Ok(content) => Ok(find_yaml_references(&content)
+                .into_iter()
+                .flatten()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn check_yaml_references(&self) -> Result<(), NetplanError> {
+        if !self.strict_yaml {
+            return Ok(());
+        }
+        let references = self.yaml_references()?;
+        if references.is_empty() {
+            return Ok(());
+        }
+        Err(NetplanError::YamlReferences(references))
+    }
+
+    fn tryout(&self, timeout: u32) -> Result<PendingTry, NetplanError> {
+        let timeout = timeout.to_string();
+        let config_path = self.config_path.to_string_lossy();
+        let child = self.runner.spawn(
+            "netplan",
+            &["try", "--timeout", &timeout, "--config-file", &config_path],
+        )?;
+        Ok(PendingTry {
+            child,
+            previous: None,
+        })
+    }
+
+    /// Runs `netplan generate` in a scratch root directory, so nothing is written to `/etc/netplan`.
+    fn generate(&self, network: &Network) -> io::Result<()> {
+        let root_dir = std::env::temp_dir().join(format!("netplan-{}", uuid::Uuid::new_v4()));
+        // Wherever the configuration is kept, netplan only reads it from /etc/netplan, along
+        // with the other files defining the rest of the network
+        let netplan_dir = root_dir.join("etc/netplan");
+        let result = (|| {
+            fs::create_dir_all(&netplan_dir)?;
+            let file_name = self.config_path.file_name().unwrap_or_default();
+            fs::write(netplan_dir.join(file_name), Self::to_netplan_yaml(network))?;
+            for other in self.other_config_files()? {
+                fs::copy(
+                    &other,
+                    netplan_dir.join(other.file_name().unwrap_or_default()),
+                )?;
+            }
+            let output = self.runner.output(
+                "netplan",
+                &["generate", "--root-dir", &root_dir.to_string_lossy()],
+                Deadline::current(),
+            )?;
+            if !output.status.success() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                ));
+            }
+            Ok(())
+        })();
+        let _ = fs::remove_dir_all(&root_dir);
+        result
+    }
+
+    /// Without the privileges to send messages from the interface, or if they can't be sent,
+    /// the lease state reported by `networkctl status` is used instead.
+    fn probe_dhcp(
+        &self,
+        interface: &str,
+        timeout: Duration,
+    ) -> Result<DhcpProbeResult, NetplanError> {
+        if self.privileges.can_probe() {
+            match DhcpProbeResult::probe_with_sockets(interface, timeout) {
+                Ok(result) => return Ok(result),
+                Err(err) if err.kind() == ErrorKind::NotFound => return Err(err.into()),
+                Err(err) => log::warn!(
+                    "Could not probe {interface} for a DHCP server, asking networkctl instead: {err}"
+                ),
+            }
+        }
+        let args = ["status", "--no-pager", interface];
+        let output = self
+            .runner
+            .output("networkctl", &args, Deadline::current())?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if stderr.contains("not found") {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Interface {interface} does not exist in the system."),
+                )
+                .into());
+            }
+            return Err(NetplanError::CommandFailed {
+                command: command_line("networkctl", &args),
+                status: output.status,
+                stderr,
             });
-        // missing_interfaces_system
-        yaml_output
-            .get("missing_interfaces_system")
-            .expect("Output of diff should contain missing system interfaces")
-            .as_mapping()
-            .unwrap()
+        }
+        Ok(DhcpProbeResult::from_networkctl_status(
+            interface,
+            &String::from_utf8_lossy(&output.stdout),
+        ))
+    }
+
+    /// A corrupt file is set aside next to itself, with the `.corrupt` suffix, before the entries
+    /// recovered from it are written. Nothing records what was in the files besides themselves,
+    /// so the entries which can't be read are lost.
+    fn rebuild_state(&self, targets: &[Sidecar]) -> io::Result<Vec<RebuildReport>> {
+        let mut reports = vec![];
+        for target in targets {
+            let report = match target {
+                Sidecar::Metadata => self.rebuild_metadata()?,
+                Sidecar::PendingActions => self.pending_actions.rebuild()?,
+            };
+            if !report.lost.is_empty() {
+                log::warn!(
+                    "Rebuilding the {target} file lost {}.",
+                    report.lost.join(", ")
+                );
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Only the history of the evictions is held in memory, bounded to its last entries.
+    fn resource_usage(&self) -> io::Result<Vec<StructureUsage>> {
+        Ok(vec![self.storage.lock().unwrap().evictions_usage()])
+    }
+
+    /// The usage is as counted, the disk being only measured the first time.
+    fn storage_report(&self) -> io::Result<StorageReport> {
+        Ok(self.storage_ledger()?.report(&self.storage_budget))
+    }
+
+    /// Metadata going over budget is only logged, as there is nothing to evict.
+    fn reconcile_storage(&self) -> io::Result<StorageReport> {
+        let mut ledger = self.storage.lock().unwrap();
+        let drift = ledger.reconcile(self.measure_storage()?, unix_millis()?);
+        if drift != 0 {
+            log::warn!("The disk usage was counted {drift} bytes off, so it was measured again.");
+        }
+        self.evict_backups(&mut ledger)?;
+        let usage = ledger.usage(|| self.measure_storage())?;
+        if self.storage_budget.excess(usage, StorageCategory::Metadata) > 0 {
+            log::warn!("The metadata of the configuration goes over the storage budget.");
+        }
+        Ok(ledger.report(&self.storage_budget))
+    }
+
+    /// If the state of the service can't be queried (e.g. `systemctl` is not available), the
+    /// check fails with `RendererUnknown` rather than applying blindly. The unit is only queried,
+    /// never started nor unmasked.
+    fn check_renderer_service(&self, renderer: &NetworkRenderer) -> Result<(), NetplanError> {
+        let unit = renderer.service_unit();
+        let active = match self.run_systemctl(&["is-active", unit]) {
+            Ok(active) if !active.is_empty() => active,
+            Ok(_) => {
+                return Err(NetplanError::RendererUnknown {
+                    unit: unit.to_string(),
+                    reason: "systemctl is-active reported nothing".to_string(),
+                })
+            }
+            Err(err) => {
+                return Err(NetplanError::RendererUnknown {
+                    unit: unit.to_string(),
+                    reason: err.to_string(),
+                })
+            }
+        };
+        if active == "active" || active == "activating" || active == "reloading" {
+            return Ok(());
+        }
+        let enabled = match self.run_systemctl(&["is-enabled", unit]) {
+            Ok(enabled) if !enabled.is_empty() => enabled,
+            _ => "unknown".to_string(),
+        };
+        let remediation = if enabled == "masked" {
+            format!("The unit is masked; unmask it with `systemctl unmask {unit}` and start it with `systemctl enable --now {unit}`.")
+        } else {
+            format!("Start it with `systemctl enable --now {unit}`.")
+        };
+        Err(NetplanError::RendererInactive {
+            unit: unit.to_string(),
+            active,
+            enabled,
+            remediation,
+        })
+    }
+}
+
+impl Netplan {
+    /// Manages the configuration file at `config_path` instead of the default one.
+    pub fn with_config_path(config_path: PathBuf) -> Self {
+        let mut pending_actions_path = config_path.clone().into_os_string();
+        pending_actions_path.push(".pending");
+        Self {
+            config_path,
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            runner: Box::new(SystemRunner),
+            seed_vars: None,
+            privileges: Privileges::detect(),
+            storage_budget: StorageBudget::default(),
+            storage: Mutex::default(),
+            pending_actions: PendingActions::new(PathBuf::from(pending_actions_path)),
+            networkd_run_path: PathBuf::from(NETWORKD_RUN_PATH),
+            networkd_config_path: PathBuf::from(NETWORKD_CONFIG_PATH),
+            strict_yaml: false,
+        }
+    }
+
+    /// Seeds a missing configuration from the `OPENSIGHT_NET_*` variables among `vars` instead of
+    /// the process environment.
+    pub fn with_seed_vars(mut self, vars: Vec<(String, String)>) -> Self {
+        self.seed_vars = Some(vars);
+        self
+    }
+
+    /// Keeps `backup_retention` backups of the configuration instead of the default ten.
+    pub fn with_backup_retention(mut self, backup_retention: usize) -> Self {
+        self.backup_retention = backup_retention;
+        self
+    }
+
+    /// Keeps the backups and the metadata within `storage_budget` instead of letting them grow
+    /// unbounded.
+    pub fn with_storage_budget(mut self, storage_budget: StorageBudget) -> Self {
+        self.storage_budget = storage_budget;
+        self
+    }
+
+    /// Reads the id of the boot from `boot_id_path`, and checks whether the changes waiting for a
+    /// reboot took effect in `sys_class_net`, instead of the system ones.
+    pub fn with_system_paths(mut self, boot_id_path: PathBuf, sys_class_net: PathBuf) -> Self {
+        self.pending_actions = self
+            .pending_actions
+            .with_system_paths(boot_id_path, sys_class_net);
+        self
+    }
+
+    /// Reads the networkd configuration rendered by netplan from `run_path`, and writes the
+    /// drop-ins setting `RequiredForOnline=` to `config_path`, instead of the system directories.
+    pub fn with_networkd_paths(mut self, run_path: PathBuf, config_path: PathBuf) -> Self {
+        self.networkd_run_path = run_path;
+        self.networkd_config_path = config_path;
+        self
+    }
+
+    /// Refuses the changes which would expand the YAML anchors, aliases or merge keys of the
+    /// configuration file if `strict_yaml` is set, instead of normalizing the file.
+    pub fn with_strict_yaml(mut self, strict_yaml: bool) -> Self {
+        self.strict_yaml = strict_yaml;
+        self
+    }
+
+    /// Runs the commands through `runner` instead of as child processes.
+    pub fn with_runner(mut self, runner: Box<dyn CommandRunner + Send + Sync>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Probes DHCP servers as if the service had `privileges`, instead of its own capabilities.
+    pub fn with_privileges(mut self, privileges: Privileges) -> Self {
+        self.privileges = privileges;
+        self
+    }
+
+    fn run_command(&self, args: &[&str]) -> Result<String, NetplanError> {
+        let command = command_line("netplan", args);
+        let output = self.runner.output("netplan", args, Deadline::current())?;
+
+        if !output.status.success() {
+            let err = NetplanError::CommandFailed {
+                command,
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            };
+            log::error!("{err}");
+            return Err(err);
+        }
+        // The output is not logged, as it can hold secrets like the passwords of wifis
+        log::debug!("`{command}` succeeded");
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Interfaces whose system state reports a missing DHCP address.
+    ///
+    /// Returns `ParseError` if a flag reported by `netplan status` is not a boolean.
+    fn interfaces_with_misssing_dhcp_address(
+        data: &HashMap<String, serde_yml::Mapping>,
+    ) -> Result<Vec<String>, NetplanError> {
+        let mut interfaces: Vec<String> = vec![];
+        let search_strings = ["missing_dhcp4_address", "missing_dhcp6_address"];
+        for (eth, eth_dict) in data.iter() {
+            let Some((key, missing)) = search_strings
+                .iter()
+                .find_map(|key| eth_dict.get(*key).map(|missing| (key, missing)))
+            else {
+                continue;
+            };
+            let Some(missing) = missing.as_bool() else {
+                return Err(NetplanError::parse(format!(
+                    "{key} of {eth} reported by netplan status, which is not a boolean: {missing:?}"
+                )));
+            };
+            if missing {
+                interfaces.push(eth.clone());
+            }
+        }
+        Ok(interfaces)
+    }
+
+    fn interfaces_expecting_dhcp_address(network: &Network) -> Vec<String> {
+        let mut result = vec![];
+        for (eth_name, eth) in network.get_ethernets().iter() {
+            // Interfaces not brought up automatically never get an address on their own
+            if eth.get_activation_mode().is_some() {
+                continue;
+            }
+            if eth.get_dhcp4() || (eth.get_dhcp6() && eth.get_accept_ra() == Some(true)) {
+                result.push(eth_name.clone());
+            }
+        }
+        result
+    }
+
+    /// Runs `systemctl` with the given arguments, returning its trimmed output.
+    ///
+    /// Unlike `run_command`, a non-zero exit status is not an error, since the
+    /// `is-active`/`is-enabled` queries use it to report the state of the unit.
+    fn run_systemctl(&self, args: &[&str]) -> Result<String, NetplanError> {
+        let output = self.runner.output("systemctl", args, Deadline::current())?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn get_dynamic_addresses_from_netplan_status(
+        data: serde_yml::Mapping,
+    ) -> Result<HashMap<String, Vec<String>>, NetplanError> {
+        let mut result: HashMap<String, Vec<String>> = HashMap::new();
+        for (eth, data) in data.iter() {
+            let Some(eth) = eth.as_str() else {
+                return Err(NetplanError::parse(format!(
+                    "the interface {eth:?} reported by netplan status"
+                )));
+            };
+            // Besides the interfaces, the status holds global entries like `netplan-global-state`
+            let Some(kind) = data.get("type") else {
+                continue;
+            };
+            let Some(kind) = kind.as_str() else {
+                return Err(NetplanError::parse(format!(
+                    "the type of {eth} reported by netplan status"
+                )));
+            };
+            if kind == "ethernet" {
+                if let Some(addresses_dict) = data.get("addresses") {
+                    let mut found_addresses: Vec<String> = vec![];
+                    // The addresses are listed as a sequence of single entry mappings,
+                    // e.g. `- 10.0.0.5: {prefix: 24, flags: [dhcp]}`
+                    let entries: Vec<(&serde_yml::Value, &serde_yml::Value)> = match addresses_dict
+                    {
+                        serde_yml::Value::Sequence(sequence) => sequence
+                            .iter()
+                            .filter_map(|entry| entry.as_mapping())
+                            .flat_map(|entry| entry.iter())
+                            .collect(),
+                        serde_yml::Value::Mapping(mapping) => mapping.iter().collect(),
+                        _ => vec![],
+                    };
+                    entries.into_iter().for_each(|(address, properties)| {
+                        let Some(properties) = properties.as_mapping() else {
+                            return;
+                        };
+                        // Only the dynamic addresses are flagged (`dhcp`, `ra`, ...)
+                        if let (Some(address), Some(flags)) =
+                            (address.as_str(), properties.get("flags"))
+                        {
+                            let mut parsed_address = address.to_string();
+                            let flags: Vec<String> = flags
+                                .as_sequence()
+                                .map(|flags| {
+                                    flags
+                                        .iter()
+                                        .filter_map(|entry| entry.as_str())
+                                        .map(str::to_string)
+                                        .collect()
+                                })
+                                .unwrap_or_default();
+                            if let Some(prefix) = properties.get("prefix").and_then(|p| p.as_u64())
+                            {
+                                parsed_address.push_str(&format!("/{prefix}"));
+                            }
+                            parsed_address.push_str(&format!(" ({})", flags.join(", ")));
+                            found_addresses.push(parsed_address);
+                        }
+                    });
+                    result.insert(eth.to_string(), found_addresses);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Attaches the addresses obtained through DHCP or router advertisements to the devices of one
+    /// kind. `netplan status` reports them under the actual interface name.
+    fn attach_dynamic_addresses<D>(
+        network: &mut Network,
+        dynamic_addresses: &HashMap<String, Vec<String>>,
+    ) where
+        D: Device + Clone,
+        Network: DeviceMap<D>,
+    {
+        for (name, mut device) in network.get_devices().clone() {
+            let interface = device.get_set_name().unwrap_or(name);
+            if let Some(addresses) = dynamic_addresses.get(&interface) {
+                device.set_dynamic_addresses(addresses);
+                network.add_device(&device);
+            }
+        }
+    }
+
+    /// Parses the content of the configuration file, merging the other configuration files into
+    /// it, and attaching the given differences and dynamic addresses as well as the labels.
+    fn parse_stored_config(
+        &self,
+        config_content: &str,
+        diff: &HashMap<String, serde_yml::Mapping>,
+        interfaces_dynamic_addresses: &HashMap<String, Vec<String>>,
+    ) -> Result<Network, NetplanError> {
+        let mut netplan_config: serde_yml::Value = serde_yml::from_str(config_content)
+            .map_err(|err| NetplanError::parse(format!("{}: {err}", self.config_path.display())))?;
+        // Hand-written files may use anchors, aliases and merge keys. The parser resolves the
+        // aliases, and the merge keys are expanded here like netplan does, so neither survives
+        // the next save
+        let has_merge_keys = find_yaml_references(config_content)
             .iter()
-            .for_each(|(interface, _interface_data)| {
-                if interface.as_str().unwrap().starts_with("eth") {
-                    result.push(interface.as_str().unwrap().to_string());
+            .any(|references| references.contains("<<"));
+        if has_merge_keys {
+            if let Err(err) = netplan_config.apply_merge() {
+                return Err(NetplanError::parse(format!(
+                    "{}: {err}",
+                    self.config_path.display()
+                )));
+            }
+        }
+        // Files written by earlier versions lack the `network` root key
+        Self::wrap_network_root(&mut netplan_config);
+        let owners = self.merge_other_configs(&mut netplan_config)?;
+
+        Self::normalize_config(&mut netplan_config, diff)?;
+
+        let mut network: Network = serde_yml::from_value(netplan_config["network"].clone())
+            .map_err(|err| NetplanError::parse(format!("the configuration: {err}")))?;
+        Self::attach_dynamic_addresses::<Ethernet>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<Vlan>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<Bridge>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<Bond>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<Wifi>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<Tunnel>(&mut network, interfaces_dynamic_addresses);
+        Self::attach_dynamic_addresses::<DummyDevice>(&mut network, interfaces_dynamic_addresses);
+        self.attach_metadata(&mut network)?;
+        network.mark_foreign_devices(&owners);
+        Ok(network)
+    }
+
+    /// Attaches the labels and `required-for-online` kept in the metadata file to the configured
+    /// Ethernets.
+    fn attach_metadata(&self, network: &mut Network) -> io::Result<()> {
+        let metadata = self.load_metadata()?;
+        for (name, labels) in metadata.labels {
+            if let Some(mut ethernet) = network.get_ethernets().get(&name).cloned() {
+                ethernet.set_labels(labels.into_iter().collect());
+                network.add_ethernet(&ethernet);
+            }
+        }
+        for (name, required_for_online) in metadata.required_for_online {
+            if let Some(mut ethernet) = network.get_ethernets().get(&name).cloned() {
+                ethernet.set_required_for_online(Some(required_for_online));
+                network.add_ethernet(&ethernet);
+            }
+        }
+        Ok(())
+    }
+
+    /// Directory of the drop-ins of the `.network` file netplan renders for the device `name`.
+    fn drop_in_directory(&self, name: &str) -> PathBuf {
+        self.networkd_config_path
+            .join(format!("10-netplan-{name}.network.d"))
+    }
+
+    /// Writes the networkd drop-in of every Ethernet setting `required-for-online`, and removes
+    /// those of the others. Only the drop-in written by the API is touched in each directory.
+    fn write_wait_online_drop_ins(&self, network: &Network) -> io::Result<()> {
+        let mut kept = HashSet::new();
+        for (name, ethernet) in network.without_foreign_devices().get_ethernets() {
+            let Some(required_for_online) = ethernet.required_for_online() else {
+                continue;
+            };
+            let directory = self.drop_in_directory(name);
+            fs::create_dir_all(&directory)?;
+            let path = directory.join(DROP_IN_NAME);
+            write_atomically(&path, required_for_online.drop_in().as_bytes())?;
+            // networkd reads its configuration as its own user
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+            kept.insert(directory);
+        }
+        let entries = match fs::read_dir(&self.networkd_config_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            entries => entries?,
+        };
+        for entry in entries {
+            let directory = entry?.path();
+            let is_netplan_drop_in_directory = directory.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                name.starts_with("10-netplan-") && name.ends_with(".network.d")
+            });
+            if !is_netplan_drop_in_directory || kept.contains(&directory) {
+                continue;
+            }
+            match fs::remove_file(directory.join(DROP_IN_NAME)) {
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                result => result?,
+            }
+            // Left in place if the administrator added drop-ins of their own
+            let _ = fs::remove_dir(&directory);
+        }
+        Ok(())
+    }
+
+    /// Parses a configuration pushed by a client, as netplan YAML or as its JSON equivalent.
+    ///
+    /// JSON documents are valid YAML, so both are read the same way. The `network` root key
+    /// may be left out.
+    pub fn parse_config(content: &[u8]) -> io::Result<Network> {
+        let mut netplan_config: serde_yml::Value = serde_yml::from_slice(content)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        netplan_config
+            .apply_merge()
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        Self::wrap_network_root(&mut netplan_config);
+        Self::normalize_config(&mut netplan_config, &HashMap::new())?;
+        serde_yml::from_value(netplan_config["network"].clone())
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Brings a parsed configuration into the shape of the `Network` model.
+    ///
+    /// Devices get their name from the key of their stanza, routes and routing policy rules
+    /// are keyed by their id, and the differences from `diff` are attached as their system state.
+    fn normalize_config(
+        netplan_config: &mut serde_yml::Value,
+        diff: &HashMap<String, serde_yml::Mapping>,
+    ) -> io::Result<()> {
+        if let Some(network) = netplan_config.get_mut("network") {
+            for kind in DEVICE_SECTIONS {
+                if let Some(devices) = network.get_mut(kind) {
+                    if let Some(devices_map) = devices.as_mapping_mut() {
+                        for (device_name, actual_device) in devices_map.iter_mut() {
+                            if let Some(device_map) = actual_device.as_mapping_mut() {
+                                // The name is the key of the stanza, which can differ from the actual
+                                // interface name if it uses `match`. The rest of the stanza is kept as is
+                                device_map.insert("name".into(), device_name.clone());
+                                // Make sure to parse the routes, since they don't come as a mapping but rather as sequence
+                                // Need to turn routes from a sequence to a mapping
+                                if let Some(routes) = device_map.get_mut("routes") {
+                                    if let Some(routes_seq) = routes.as_sequence_mut() {
+                                        let mut new_routes = serde_yml::Mapping::new();
+                                        for route in routes_seq.iter() {
+                                            let parsed_route: Route = serde_yml::from_value(
+                                                route.clone(),
+                                            )
+                                            .map_err(|err| {
+                                                io::Error::new(
+                                                    ErrorKind::InvalidData,
+                                                    err.to_string(),
+                                                )
+                                            })?;
+                                            // The route itself is kept untouched, so fields like
+                                            // `metric` and `table` survive the re-keying
+                                            new_routes.insert(
+                                                serde_yml::Value::String(parsed_route.id()),
+                                                route.clone(),
+                                            );
+                                        }
+                                        device_map.insert("routes".into(), new_routes.into());
+                                    }
+                                }
+                                // Routing policy rules are a sequence as well
+                                if let Some(rules) = device_map.get_mut("routing-policy") {
+                                    if let Some(rules_seq) = rules.as_sequence_mut() {
+                                        let mut new_rules = serde_yml::Mapping::new();
+                                        for rule in rules_seq.iter() {
+                                            let parsed_rule: RoutingPolicy = serde_yml::from_value(
+                                                rule.clone(),
+                                            )
+                                            .map_err(|err| {
+                                                io::Error::new(
+                                                    ErrorKind::InvalidData,
+                                                    err.to_string(),
+                                                )
+                                            })?;
+                                            new_rules.insert(
+                                                serde_yml::Value::String(parsed_rule.id()),
+                                                rule.clone(),
+                                            );
+                                        }
+                                        device_map
+                                            .insert("routing-policy".into(), new_rules.into());
+                                    }
+                                }
+                                // Also add the system state, if it differs from the configuration.
+                                // The differences of an interface are its system state already
+                                if let Some(system_state) =
+                                    diff.get(device_name.as_str().unwrap_or_default())
+                                {
+                                    if !system_state.is_empty() {
+                                        device_map.insert(
+                                            "system-state".into(),
+                                            system_state.clone().into(),
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
+            }
+        }
+        Ok(())
+    }
+
+    /// Path of the metadata file, next to the configuration. Netplan only reads `*.yaml` files,
+    /// so it is ignored by netplan.
+    fn metadata_path(&self) -> PathBuf {
+        self.path_next_to_config(".meta")
+    }
+
+    /// Path of the configuration file, followed by `suffix`.
+    fn path_next_to_config(&self, suffix: &str) -> PathBuf {
+        let mut path = self.config_path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// The configuration as stored, an empty one if it is missing or can't be parsed.
+    fn stored_network(&self) -> Network {
+        fs::read(&self.config_path)
+            .ok()
+            .and_then(|content| Self::parse_config(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the configuration file with `content`, recording the changes which only take
+    /// effect after a reboot.
+    fn write_config(&self, content: &[u8]) -> io::Result<()> {
+        let previous = self.stored_network();
+        write_atomically(&self.config_path, content)?;
+        if let Ok(network) = Self::parse_config(content) {
+            self.record_pending_actions(&previous, &network);
+        }
+        Ok(())
+    }
+
+    /// Records the changes from `previous` to the saved `network` which only take effect after a
+    /// reboot. The configuration is saved already, so failing to record them is only logged.
+    fn record_pending_actions(&self, previous: &Network, network: &Network) {
+        let result = self
+            .config_etag()
+            .and_then(|revision| self.pending_actions.record(previous, network, &revision));
+        if let Err(err) = result {
+            log::error!("Could not record the changes waiting for a reboot: {err}");
+        }
+    }
+
+    /// The metadata file of `network`, `None` if there is nothing to keep.
+    pub(crate) fn metadata_content(network: &Network) -> Option<String> {
+        let metadata = ConfigMetadata {
+            labels: network
+                .get_ethernets()
+                .iter()
+                .filter(|(_, ethernet)| !ethernet.labels().is_empty())
+                .map(|(name, ethernet)| (name.clone(), ethernet.labels().into_iter().collect()))
+                .collect(),
+            required_for_online: network
+                .get_ethernets()
+                .iter()
+                .filter_map(|(name, ethernet)| {
+                    Some((name.clone(), ethernet.required_for_online()?))
+                })
+                .collect(),
+        };
+        if metadata.labels.is_empty() && metadata.required_for_online.is_empty() {
+            return None;
+        }
+        Some(
+            serde_yml::to_string(&metadata)
+                .expect("Error: couldn't serialize the metadata into YAML string."),
+        )
+    }
+
+    /// Fails with `StorageFull` if replacing the metadata file with `content` would go over the
+    /// storage budget. Metadata which doesn't grow is always accepted, so labels can still be
+    /// removed when the backups took the budget.
+    fn check_metadata_budget(&self, content: Option<&str>) -> io::Result<()> {
+        let size = content.map_or(0, |content| content.len() as u64);
+        let mut ledger = self.storage_ledger()?;
+        let mut usage = ledger.usage(|| self.measure_storage())?.clone();
+        let previous = usage.insert(StorageCategory::Metadata, size);
+        let excess = self
+            .storage_budget
+            .excess(&usage, StorageCategory::Metadata);
+        if excess > 0 && size > previous.unwrap_or_default() {
+            return Err(io::Error::new(
+                ErrorKind::StorageFull,
+                format!(
+                    "The metadata of the configuration would take {size} bytes, {excess} bytes over the storage budget."
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the metadata file with `content`, removing it if there is none.
+    fn write_metadata(&self, content: Option<&str>) -> io::Result<()> {
+        let mut ledger = self.storage_ledger()?;
+        match content {
+            Some(content) => write_atomically(&self.metadata_path(), content.as_bytes())?,
+            None => match fs::remove_file(self.metadata_path()) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                _ => (),
+            },
+        }
+        ledger.replace(
+            StorageCategory::Metadata,
+            content.map_or(0, |content| content.len() as u64),
+        );
+        Ok(())
+    }
+
+    /// The bookkeeping of the disk usage, measured first if it was not yet.
+    fn storage_ledger(&self) -> io::Result<MutexGuard<'_, StorageLedger>> {
+        let mut ledger = self.storage.lock().unwrap();
+        ledger.usage(|| self.measure_storage())?;
+        Ok(ledger)
+    }
+
+    /// What the backups and the metadata take on disk, by category, found by walking the
+    /// directory of the configuration.
+    fn measure_storage(&self) -> io::Result<BTreeMap<StorageCategory, u64>> {
+        let timestamps = match self.backup_timestamps() {
+            Err(err) if err.kind() == ErrorKind::NotFound => vec![],
+            result => result?,
+        };
+        let mut backups = 0;
+        for timestamp in timestamps {
+            backups += file_size(&self.backup_path(timestamp))?;
+        }
+        Ok(BTreeMap::from([
+            (StorageCategory::Backups, backups),
+            (StorageCategory::Metadata, file_size(&self.metadata_path())?),
+        ]))
+    }
+
+    /// Rebuilds the metadata file like `NetplanBackend::rebuild_state`, section by section and
+    /// Ethernet by Ethernet.
+    fn rebuild_metadata(&self) -> io::Result<RebuildReport> {
+        let path = self.metadata_path();
+        let content = match fs::read_to_string(&path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(RebuildReport::intact(Sidecar::Metadata))
+            }
+            content => content?,
+        };
+        if serde_yml::from_str::<ConfigMetadata>(&content).is_ok() {
+            return Ok(RebuildReport::intact(Sidecar::Metadata));
+        }
+        let mut metadata = ConfigMetadata::default();
+        let mut lost = vec![];
+        match serde_yml::from_str(&content) {
+            Ok(serde_yml::Value::Mapping(sections)) => {
+                for (section, value) in sections {
+                    match section.as_str() {
+                        Some("labels") => {
+                            let (labels, section_lost) =
+                                salvage_entries(&value, |name| format!("labels of {name}"));
+                            metadata.labels.extend(labels);
+                            lost.extend(section_lost);
+                        }
+                        Some("required_for_online") => {
+                            let (required_for_online, section_lost) =
+                                salvage_entries(&value, |name| {
+                                    format!("required-for-online of {name}")
+                                });
+                            metadata.required_for_online.extend(required_for_online);
+                            lost.extend(section_lost);
+                        }
+                        _ => lost.push(format!(
+                            "section {}",
+                            serde_yml::to_string(&section)
+                                .unwrap_or_default()
+                                .trim_end()
+                        )),
+                    }
+                }
+            }
+            _ => lost.push(WHOLE_FILE.to_string()),
+        }
+        let recovered = metadata.labels.len() + metadata.required_for_online.len();
+        let mut report = RebuildReport::salvaged(Sidecar::Metadata, recovered, lost);
+        report.set_aside = Some(set_aside(&path)?);
+        let content = (recovered > 0).then(|| {
+            serde_yml::to_string(&metadata)
+                .expect("Error: couldn't serialize the metadata into YAML string.")
+        });
+        self.write_metadata(content.as_deref())?;
+        Ok(report)
+    }
+
+    /// The files kept next to the configuration which can't be read.
+    pub fn corrupt_sidecars(&self) -> Vec<Sidecar> {
+        let mut corrupt = vec![];
+        if self
+            .load_metadata()
+            .is_err_and(|err| err.kind() == ErrorKind::InvalidData)
+        {
+            corrupt.push(Sidecar::Metadata);
+        }
+        if self.pending_actions.is_corrupt() {
+            corrupt.push(Sidecar::PendingActions);
+        }
+        corrupt
+    }
+
+    fn load_metadata(&self) -> io::Result<ConfigMetadata> {
+        match fs::read_to_string(self.metadata_path()) {
+            Ok(content) => serde_yml::from_str(&content)
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(ConfigMetadata::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Puts a configuration whose `network` root key was left out under it.
+    fn wrap_network_root(netplan_config: &mut serde_yml::Value) {
+        if netplan_config.get("network").is_none() {
+            let mut root = serde_yml::Mapping::new();
+            root.insert("network".into(), std::mem::take(netplan_config));
+            *netplan_config = root.into();
+        }
+    }
+
+    /// Directory holding the configuration file, along with the other netplan files.
+    fn config_directory(&self) -> &Path {
+        match self.config_path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        }
+    }
+
+    /// The other netplan configuration files, i.e. the `*.yaml` files next to the configuration,
+    /// sorted by name.
+    fn other_config_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut result = vec![];
+        for entry in fs::read_dir(self.config_directory())? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name() else {
+                continue;
+            };
+            if Some(file_name) != self.config_path.file_name()
+                && !file_name.to_string_lossy().starts_with('.')
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "yaml")
+                && path.is_file()
+            {
+                result.push(path);
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    /// Merges the other configuration files into `netplan_config`, the way netplan does: in the
+    /// order of their names, mappings are merged key by key, sequences are concatenated, and any
+    /// other value is overridden by the later file.
+    ///
+    /// Returns the name of the file defining each device found in the other files, the last one
+    /// if several do.
+    fn merge_other_configs(
+        &self,
+        netplan_config: &mut serde_yml::Value,
+    ) -> Result<HashMap<String, String>, NetplanError> {
+        let mut files: Vec<(String, Option<PathBuf>)> = self
+            .other_config_files()?
+            .into_iter()
+            .map(|path| {
+                let file_name = path.file_name().unwrap_or_default();
+                (file_name.to_string_lossy().to_string(), Some(path))
+            })
+            .collect();
+        let mut owners = HashMap::new();
+        if files.is_empty() {
+            return Ok(owners);
+        }
+        let own_name = self.config_path.file_name().unwrap_or_default();
+        files.push((own_name.to_string_lossy().to_string(), None));
+        files.sort();
+        let mut merged = serde_yml::Value::Mapping(serde_yml::Mapping::new());
+        for (file_name, path) in files {
+            let Some(path) = path else {
+                merge_yaml(&mut merged, std::mem::take(netplan_config));
+                continue;
+            };
+            let mut other: serde_yml::Value = serde_yml::from_str(&fs::read_to_string(&path)?)
+                .map_err(|err| NetplanError::parse(format!("{}: {err}", path.display())))?;
+            other
+                .apply_merge()
+                .map_err(|err| NetplanError::parse(format!("{}: {err}", path.display())))?;
+            for kind in DEVICE_SECTIONS {
+                if let Some(devices) = other["network"][kind].as_mapping() {
+                    for name in devices.keys().filter_map(serde_yml::Value::as_str) {
+                        owners.insert(name.to_string(), file_name.clone());
+                    }
+                }
+            }
+            merge_yaml(&mut merged, other);
+        }
+        *netplan_config = merged;
+        Ok(owners)
+    }
+
+    /// Serializes `network` as netplan YAML, leaving out the devices defined in other files, and
+    /// what netplan doesn't know about, like labels or `required-for-online`.
+    pub(crate) fn to_netplan_yaml(network: &Network) -> String {
+        // Serialized to text first, as the value serializer is not human readable and would
+        // write IP addresses as sequences of bytes
+        let mut config: serde_yml::Value = serde_yml::to_string(&network.without_foreign_devices())
+            .and_then(|yaml| serde_yml::from_str(&yaml))
+            .expect("Error: couldn't serialize network into YAML value.");
+        if let Some(ethernets) = config
+            .get_mut("ethernets")
+            .and_then(serde_yml::Value::as_mapping_mut)
+        {
+            for (_, ethernet) in ethernets.iter_mut() {
+                if let Some(ethernet) = ethernet.as_mapping_mut() {
+                    ethernet.remove("labels");
+                    ethernet.remove("required-for-online");
+                    ethernet.remove("dhcp-probe");
+                    ethernet.remove("pending-reboot");
+                    ethernet.remove("pending-reboot-reasons");
+                }
+            }
+        }
+        Self::wrap_network_root(&mut config);
+        serde_yml::to_string(&config).expect("Error: couldn't serialize network into YAML string.")
+    }
+
+    /// Path of the backup taken at `timestamp`, in milliseconds since the Unix epoch, the name of
+    /// the backup imported from before the upgrade being marked as such.
+    fn backup_path(&self, timestamp: u64) -> PathBuf {
+        let pre_upgrade_path = self.pre_upgrade_backup_path(timestamp);
+        if pre_upgrade_path.exists() {
+            return pre_upgrade_path;
+        }
+        self.path_next_to_config(&format!(".{timestamp}.bak"))
+    }
+
+    fn pre_upgrade_backup_path(&self, timestamp: u64) -> PathBuf {
+        self.path_next_to_config(&format!(".{timestamp}.{PRE_UPGRADE_MARKER}.bak"))
+    }
+
+    /// Imports the single backup kept before the backups were rotated, i.e. `<config>.bak`, as the
+    /// oldest backup, marked as taken before the upgrade and timestamped with its modification
+    /// time. Run at startup, before serving any request.
+    ///
+    /// The legacy backup is renamed to `<config>.bak.migrated` afterwards, so that it is imported
+    /// once. If it is not a configuration, in either the wrapped or the unwrapped form, it is
+    /// quarantined as `<config>.bak.quarantined` instead, and nothing is imported.
+    ///
+    /// Returns the timestamp of the imported backup, if any.
+    pub fn migrate_legacy_backup(&self) -> io::Result<Option<u64>> {
+        let legacy_path = self.path_next_to_config(".bak");
+        let content = match fs::read(&legacy_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            result => result?,
+        };
+        let config = match Self::parse_config(&content).and_then(|_| Self::wrapped(&content)) {
+            Ok(config) => config,
+            Err(err) => {
+                let quarantine_path = self.path_next_to_config(".bak.quarantined");
+                fs::rename(&legacy_path, &quarantine_path)?;
+                log::error!(
+                    "The legacy backup {} is not a configuration ({err}), so it was quarantined as {} instead of imported.",
+                    legacy_path.display(),
+                    quarantine_path.display()
+                );
+                return Ok(None);
+            }
+        };
+        let modified = fs::metadata(&legacy_path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?
+            .as_millis() as u64;
+        // A backup imported earlier, when the migration was interrupted, is replaced
+        let oldest = self
+            .backup_timestamps()?
+            .into_iter()
+            .find(|timestamp| !self.is_pre_upgrade_backup(*timestamp));
+        let timestamp = match oldest {
+            // The rotation started after the upgrade, so the clock must have been off
+            Some(oldest) if oldest <= modified => oldest.saturating_sub(1),
+            _ => modified,
+        };
+        let mut ledger = self.storage_ledger()?;
+        write_atomically(&self.pre_upgrade_backup_path(timestamp), &config)?;
+        ledger.grow(StorageCategory::Backups, config.len() as u64);
+        fs::rename(&legacy_path, self.path_next_to_config(".bak.migrated"))?;
+        log::info!(
+            "Imported the legacy backup {} as backup {timestamp}, taken before the upgrade.",
+            legacy_path.display()
+        );
+        Ok(Some(timestamp))
+    }
+
+    /// `content` with its `network` root key, which the legacy unwrapped form left out. Wrapped
+    /// content is kept as is, comments included.
+    fn wrapped(content: &[u8]) -> io::Result<Vec<u8>> {
+        let mut config: serde_yml::Value = serde_yml::from_slice(content)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        if config.get("network").is_some() {
+            return Ok(content.to_vec());
+        }
+        config
+            .apply_merge()
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        Self::wrap_network_root(&mut config);
+        serde_yml::to_string(&config)
+            .map(String::into_bytes)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Timestamps of the backups of the configuration file, oldest first.
+    fn backup_timestamps(&self) -> io::Result<Vec<u64>> {
+        let Some(file_name) = self.config_path.file_name() else {
+            return Ok(vec![]);
+        };
+        let prefix = format!("{}.", file_name.to_string_lossy());
+        let mut result = vec![];
+        for entry in fs::read_dir(self.config_directory())? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(timestamp) = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .map(|rest| {
+                    rest.strip_suffix(&format!(".{PRE_UPGRADE_MARKER}"))
+                        .unwrap_or(rest)
+                })
+                .and_then(|timestamp| timestamp.parse().ok())
+            {
+                result.push(timestamp);
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    /// Copies the configuration file to a new timestamped backup, and removes the oldest backups
+    /// beyond the retention count, or going over the storage budget. Before the first save, there
+    /// is nothing to back up.
+    ///
+    /// Earlier backups are never overwritten, so a good copy survives a failed rollback.
+    fn backup_config(&self) -> io::Result<()> {
+        let mut ledger = self.storage_ledger()?;
+        let content = match fs::read(&self.config_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            result => result?,
+        };
+        self.write_backup(unix_millis()?, &content)?;
+        ledger.grow(StorageCategory::Backups, content.len() as u64);
+        let timestamps = self.backup_timestamps()?;
+        let excess = timestamps.len().saturating_sub(self.backup_retention);
+        for timestamp in timestamps.into_iter().take(excess) {
+            let size = file_size(&self.backup_path(timestamp))?;
+            fs::remove_file(self.backup_path(timestamp))?;
+            ledger.shrink(StorageCategory::Backups, size);
+        }
+        self.evict_backups(&mut ledger)
+    }
+
+    /// Writes `content` to a new backup taken at `timestamp`, returning the timestamp it was given.
+    /// A backup taken within the same millisecond as an earlier one is given the next free one.
+    fn write_backup(&self, timestamp: u64, content: &[u8]) -> io::Result<u64> {
+        let mut timestamp = timestamp;
+        loop {
+            if !self.pre_upgrade_backup_path(timestamp).exists() {
+                let backup_path = self.path_next_to_config(&format!(".{timestamp}.bak"));
+                match write_synced(&backup_path, content) {
+                    Err(err) if err.kind() == ErrorKind::AlreadyExists => (),
+                    result => return result.map(|_| timestamp),
+                }
+            }
+            timestamp += 1;
+        }
+    }
+
+    /// Removes the oldest backups for as long as they go over the storage budget. The latest one
+    /// is kept whatever its size, as rolling back needs it.
+    fn evict_backups(&self, ledger: &mut StorageLedger) -> io::Result<()> {
+        let timestamps = self.backup_timestamps()?;
+        let Some((_, older)) = timestamps.split_last() else {
+            return Ok(());
+        };
+        for &timestamp in older {
+            let usage = ledger.usage(|| self.measure_storage())?;
+            if self.storage_budget.excess(usage, StorageCategory::Backups) == 0 {
+                break;
+            }
+            let path = self.backup_path(timestamp);
+            let size = file_size(&path)?;
+            fs::remove_file(&path)?;
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            log::warn!(
+                "Removed {file} ({size} bytes), as the backups went over the storage budget."
+            );
+            ledger.record_eviction(Eviction {
+                category: StorageCategory::Backups,
+                file: file.to_string(),
+                size,
+                timestamp: unix_millis()?,
             });
-        // missing_interfaces_system
-        yaml_output
-            .get("missing_interfaces_system")
-            .expect("Output of diff should contain missing system interfaces")
-            .as_mapping()
+        }
+        Ok(())
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn unix_millis() -> io::Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(io::Error::other)?
+        .as_millis() as u64)
+}
+
+/// Size of the file at `path`, 0 if there is none.
+fn file_size(path: &Path) -> io::Result<u64> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(err),
+    }
+}
+
+/// Content of the file at `path`, empty if there is none.
+fn read_or_empty(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+        content => content,
+    }
+}
+
+/// Merges `overlay` into `base` like netplan merges its configuration files.
+fn merge_yaml(base: &mut serde_yml::Value, overlay: serde_yml::Value) {
+    match (base, overlay) {
+        (serde_yml::Value::Mapping(base), serde_yml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (serde_yml::Value::Sequence(base), serde_yml::Value::Sequence(overlay)) => {
+            base.extend(overlay);
+        }
+        // An empty file doesn't remove anything
+        (_, serde_yml::Value::Null) => (),
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Replaces the content of the file at `path`, so that it holds either the previous or the new
+/// content even if the process dies midway, and is only readable by root as netplan expects.
+///
+/// The content is written to a temporary file in the same directory, synced, and renamed over
+/// `path`.
+pub(crate) fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let Some(file_name) = path.file_name() else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is not a file path.", path.display()),
+        ));
+    };
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        uuid::Uuid::new_v4()
+    ));
+    let result = write_synced(&temp_path, content).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Writes `content` to the new file at `path`, only readable by root, and syncs it to disk.
+fn write_synced(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::offloads::Offloads;
+    use crate::models::sidecar::RebuildOutcome;
+    use crate::test_support::{scripted_netplan, ScriptedRunner};
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn failing_commands_report_their_command_line_status_and_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().failing(
+            "netplan apply",
+            78,
+            "/etc/netplan/01-network-conf.yaml:3:7: Invalid YAML\n",
+        );
+        let netplan = scripted_netplan(&dir.path().join("01-network-conf.yaml"), &runner);
+
+        let err = netplan.apply().unwrap_err();
+        let NetplanError::CommandFailed {
+            command,
+            status,
+            stderr,
+        } = &err
+        else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(command, "netplan apply");
+        assert_eq!(status.code(), Some(78));
+        assert_eq!(
+            stderr,
+            "/etc/netplan/01-network-conf.yaml:3:7: Invalid YAML"
+        );
+        assert_eq!(err.status_code(), StatusCode::BAD_GATEWAY);
+        assert_eq!(runner.calls(), ["netplan apply"]);
+    }
+
+    /// A configuration file managed by a scripted backend, holding `content`.
+    fn netplan_with_config(content: &str, runner: &ScriptedRunner) -> (tempfile::TempDir, Netplan) {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(&config_path, content).unwrap();
+        let netplan = scripted_netplan(&config_path, runner);
+        (dir, netplan)
+    }
+
+    const DHCP_CONFIG: &str =
+        "network:\n  version: 2\n  ethernets:\n    eth0:\n      dhcp4: true\n";
+
+    #[test]
+    fn route_metrics_survive_a_save() {
+        let runner = ScriptedRunner::default();
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        let mut network = netplan.load_config().unwrap();
+        let mut eth0 = network.get_ethernets()["eth0"].clone();
+        for (via, metric) in [("192.168.1.1", 100), ("192.168.1.2", 200)] {
+            let mut route = Route::new("0.0.0.0".parse().unwrap(), via.parse().ok(), None);
+            route.metric = Some(metric);
+            eth0.add_route(&route);
+        }
+        network.add_ethernet(&eth0);
+
+        netplan.save_config(&network).unwrap();
+        let loaded = netplan.load_config().unwrap();
+        let mut metrics: Vec<_> = loaded.get_ethernets()["eth0"]
+            .get_routes()
+            .values()
+            .map(|route| (route.via.unwrap().to_string(), route.metric))
+            .collect();
+        metrics.sort();
+        assert_eq!(
+            metrics,
+            [
+                ("192.168.1.1".to_string(), Some(100)),
+                ("192.168.1.2".to_string(), Some(200))
+            ]
+        );
+        assert_eq!(loaded, network);
+    }
+
+    #[test]
+    fn dhcp6_overrides_survive_a_save() {
+        const CONFIG: &str = "network:\n  version: 2\n  ethernets:\n    eth0:\n      \
+            dhcp6: true\n      dhcp6-overrides:\n        use-dns: false\n";
+        let runner = ScriptedRunner::default();
+        let (dir, netplan) = netplan_with_config(CONFIG, &runner);
+        let network = netplan.load_config().unwrap();
+        let eth0 = &network.get_ethernets()["eth0"];
+        assert_eq!(eth0.get_dhcp4_overrides(), None);
+        assert_eq!(
+            eth0.get_dhcp6_overrides()
+                .and_then(|overrides| overrides.use_dns),
+            Some(false)
+        );
+
+        netplan.save_config(&network).unwrap();
+        let saved: serde_yml::Value = serde_yml::from_str(
+            &fs::read_to_string(dir.path().join("01-network-conf.yaml")).unwrap(),
+        )
+        .unwrap();
+        let eth0 = &saved["network"]["ethernets"]["eth0"];
+        assert_eq!(
+            eth0["dhcp6-overrides"]["use-dns"],
+            serde_yml::Value::Bool(false)
+        );
+        assert!(eth0.get("dhcp4-overrides").is_none());
+        assert_eq!(netplan.load_config().unwrap(), network);
+    }
+
+    #[test]
+    fn wakeonlan_is_written_under_its_ethernet() {
+        let mut network = Network::new();
+        let mut eth0 = Ethernet::new("eth0".to_string());
+        eth0.set_wakeonlan(Some(true));
+        network.add_ethernet(&eth0);
+        assert_eq!(
+            Netplan::to_netplan_yaml(&network),
+            "network:\n  version: 2\n  renderer: networkd\n  ethernets:\n    eth0:\n      \
+            dhcp4: false\n      dhcp6: false\n      wakeonlan: true\n      nameservers: {}\n"
+        );
+
+        network.add_ethernet(&Ethernet::new("eth1".to_string()));
+        let config: serde_yml::Value =
+            serde_yml::from_str(&Netplan::to_netplan_yaml(&network)).unwrap();
+        let ethernets = &config["network"]["ethernets"];
+        assert_eq!(ethernets["eth0"]["wakeonlan"], serde_yml::Value::Bool(true));
+        assert!(ethernets["eth1"].get("wakeonlan").is_none());
+    }
+
+    #[test]
+    fn ipv6_privacy_survives_a_save() {
+        const CONFIG: &str = "network:\n  version: 2\n  ethernets:\n    eth0:\n      \
+            dhcp6: true\n      ipv6-privacy: false\n    eth1:\n      dhcp6: true\n";
+        let runner = ScriptedRunner::default();
+        let (dir, netplan) = netplan_with_config(CONFIG, &runner);
+        let network = netplan.load_config().unwrap();
+        assert_eq!(
+            network.get_ethernets()["eth0"].get_ipv6_privacy(),
+            Some(false)
+        );
+        assert_eq!(network.get_ethernets()["eth1"].get_ipv6_privacy(), None);
+
+        netplan.save_config(&network).unwrap();
+        let saved: serde_yml::Value = serde_yml::from_str(
+            &fs::read_to_string(dir.path().join("01-network-conf.yaml")).unwrap(),
+        )
+        .unwrap();
+        let ethernets = &saved["network"]["ethernets"];
+        assert_eq!(
+            ethernets["eth0"]["ipv6-privacy"],
+            serde_yml::Value::Bool(false)
+        );
+        assert!(ethernets["eth1"].get("ipv6-privacy").is_none());
+        assert_eq!(netplan.load_config().unwrap(), network);
+    }
+
+    #[test]
+    fn only_the_offloads_set_are_written() {
+        let mut network = Network::new();
+        let mut eth0 = Ethernet::new("eth0".to_string());
+        eth0.set_offloads(Some(Offloads {
+            generic_receive_offload: Some(false),
+            large_receive_offload: Some(false),
+            ..Default::default()
+        }));
+        network.add_ethernet(&eth0);
+        assert_eq!(
+            Netplan::to_netplan_yaml(&network),
+            "network:\n  version: 2\n  renderer: networkd\n  ethernets:\n    eth0:\n      \
+             dhcp4: false\n      dhcp6: false\n      generic-receive-offload: false\n      \
+             large-receive-offload: false\n      nameservers: {}\n"
+        );
+    }
+
+    fn config_mutation(updated: &str) -> ConfigMutation {
+        let current = Netplan::parse_config(b"{version: 2}").unwrap();
+        let updated = Netplan::parse_config(updated.as_bytes()).unwrap();
+        let Some(Mutation::Config(mutation)) = Mutation::new(&current, updated) else {
+            panic!("the configurations do not differ");
+        };
+        mutation
+    }
+
+    #[actix_web::test]
+    async fn nothing_is_applied_if_the_configuration_cannot_be_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("missing").join("01-network-conf.yaml");
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner);
+
+        let err = netplan
+            .save_and_apply(config_mutation(DHCP_CONFIG))
+            .unwrap_err();
+        let NetplanError::Io(io_err) = &err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(io_err.kind(), ErrorKind::NotFound);
+        assert!(runner.calls().is_empty(), "{:?}", runner.calls());
+        let resp = err.error_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(&io_err.to_string()), "{body}");
+    }
+
+    #[test]
+    fn a_new_configuration_is_saved_without_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner);
+
+        netplan
+            .save_and_apply(config_mutation(DHCP_CONFIG))
+            .unwrap();
+        assert!(netplan.load_config().unwrap().get_ethernets()["eth0"].get_dhcp4());
+        assert!(netplan.list_backups().unwrap().is_empty());
+        assert!(runner.calls().contains(&"netplan apply".to_string()));
+    }
+
+    #[test]
+    fn backups_are_kept_next_to_the_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("90-capture.yaml");
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+
+        netplan
+            .save_config(&Netplan::parse_config(DHCP_CONFIG.as_bytes()).unwrap())
+            .unwrap();
+        let first = fs::read_to_string(&config_path).unwrap();
+        assert!(netplan.list_backups().unwrap().is_empty());
+        netplan
+            .save_config(&Netplan::parse_config(b"{version: 2}").unwrap())
+            .unwrap();
+        let backups = netplan.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        let backup_path = dir
+            .path()
+            .join(format!("90-capture.yaml.{}.bak", backups[0]));
+        assert_eq!(fs::read_to_string(backup_path).unwrap(), first);
+
+        netplan.restore_config().unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), first);
+        assert!(netplan.load_config().unwrap().get_ethernets()["eth0"].get_dhcp4());
+    }
+
+    #[test]
+    fn backups_taken_within_the_same_millisecond_are_all_kept() {
+        let (dir, config_path) = seeded_config_directory(0, 0, 0);
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+
+        assert_eq!(netplan.write_backup(1000, b"first").unwrap(), 1000);
+        assert_eq!(netplan.write_backup(1000, b"second").unwrap(), 1001);
+        assert_eq!(netplan.list_backups().unwrap(), vec![1000, 1001]);
+        let first_path = dir.path().join("01-network-conf.yaml.1000.bak");
+        assert_eq!(fs::read(first_path).unwrap(), b"first");
+    }
+
+    /// Timestamp of the legacy backups, before any backup of the rotation.
+    const LEGACY_MODIFIED: u64 = 1_600_000_000_000;
+
+    fn legacy_fixture(fixture: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/legacy-backups")
+            .join(fixture)
+    }
+
+    /// A configuration directory holding the legacy backup `fixture`, last modified at
+    /// `LEGACY_MODIFIED`.
+    fn legacy_config_directory(fixture: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(&config_path, DHCP_CONFIG).unwrap();
+        let legacy_path = dir.path().join("01-network-conf.yaml.bak");
+        fs::copy(legacy_fixture(fixture), &legacy_path).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&legacy_path)
             .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_millis(LEGACY_MODIFIED))
+            .unwrap();
+        (dir, config_path)
+    }
+
+    #[test]
+    fn the_legacy_backup_is_imported_once_in_either_form() {
+        let expected =
+            Netplan::parse_config(&fs::read(legacy_fixture("wrapped.yaml")).unwrap()).unwrap();
+        for fixture in ["wrapped.yaml", "unwrapped.yaml"] {
+            let (dir, config_path) = legacy_config_directory(fixture);
+            let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+
+            assert_eq!(
+                netplan.migrate_legacy_backup().unwrap(),
+                Some(LEGACY_MODIFIED),
+                "{fixture}"
+            );
+            assert_eq!(netplan.list_backups().unwrap(), vec![LEGACY_MODIFIED]);
+            assert!(netplan.is_pre_upgrade_backup(LEGACY_MODIFIED));
+            let imported_path = dir.path().join(format!(
+                "01-network-conf.yaml.{LEGACY_MODIFIED}.pre-upgrade.bak"
+            ));
+            let imported = fs::read(&imported_path).unwrap();
+            // Netplan only reads the wrapped form
+            let content: serde_yml::Value = serde_yml::from_slice(&imported).unwrap();
+            assert!(content.get("network").is_some(), "{fixture}");
+            assert_eq!(Netplan::parse_config(&imported).unwrap(), expected);
+            let legacy_path = dir.path().join("01-network-conf.yaml.bak");
+            assert!(!legacy_path.exists());
+            let migrated_path = dir.path().join("01-network-conf.yaml.bak.migrated");
+            assert!(migrated_path.exists());
+
+            // Restarting imports nothing more
+            let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+            assert_eq!(netplan.migrate_legacy_backup().unwrap(), None);
+            // A migration interrupted before the rename replaces what it imported
+            fs::rename(&migrated_path, &legacy_path).unwrap();
+            assert_eq!(
+                netplan.migrate_legacy_backup().unwrap(),
+                Some(LEGACY_MODIFIED)
+            );
+            assert_eq!(netplan.list_backups().unwrap(), vec![LEGACY_MODIFIED]);
+        }
+    }
+
+    #[test]
+    fn the_legacy_backup_is_older_than_the_rotation() {
+        let (dir, config_path) = legacy_config_directory("wrapped.yaml");
+        let rotated = LEGACY_MODIFIED - 1000;
+        fs::write(
+            dir.path()
+                .join(format!("01-network-conf.yaml.{rotated}.bak")),
+            DHCP_CONFIG,
+        )
+        .unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+
+        assert_eq!(netplan.migrate_legacy_backup().unwrap(), Some(rotated - 1));
+        assert_eq!(netplan.list_backups().unwrap(), vec![rotated - 1, rotated]);
+        assert!(!netplan.is_pre_upgrade_backup(rotated));
+    }
+
+    #[test]
+    fn a_corrupt_legacy_backup_is_quarantined() {
+        let (dir, config_path) = legacy_config_directory("corrupt.yaml");
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+
+        assert_eq!(netplan.migrate_legacy_backup().unwrap(), None);
+        assert!(netplan.list_backups().unwrap().is_empty());
+        assert!(!dir.path().join("01-network-conf.yaml.bak").exists());
+        assert_eq!(
+            fs::read(dir.path().join("01-network-conf.yaml.bak.quarantined")).unwrap(),
+            fs::read(legacy_fixture("corrupt.yaml")).unwrap()
+        );
+        assert_eq!(netplan.migrate_legacy_backup().unwrap(), None);
+    }
+
+    #[test]
+    fn the_pre_upgrade_backup_is_only_restored_by_force() {
+        let (_dir, config_path) = legacy_config_directory("unwrapped.yaml");
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+        netplan.migrate_legacy_backup().unwrap();
+
+        assert!(matches!(
+            netplan.apply_backup(LEGACY_MODIFIED, false),
+            Err(NetplanError::PreUpgradeBackup(LEGACY_MODIFIED))
+        ));
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), DHCP_CONFIG);
+
+        netplan.apply_backup(LEGACY_MODIFIED, true).unwrap();
+        let expected =
+            Netplan::parse_config(&fs::read(legacy_fixture("wrapped.yaml")).unwrap()).unwrap();
+        assert_eq!(netplan.load_config().unwrap(), expected);
+    }
+
+    /// A configuration directory holding `backups` backups of `backup_size` bytes, the oldest
+    /// first, and metadata of `metadata_size` bytes.
+    fn seeded_config_directory(
+        backups: u64,
+        backup_size: usize,
+        metadata_size: usize,
+    ) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(&config_path, DHCP_CONFIG).unwrap();
+        for timestamp in 1..=backups {
+            let backup_path = dir
+                .path()
+                .join(format!("01-network-conf.yaml.{timestamp}.bak"));
+            fs::write(backup_path, vec![b'#'; backup_size]).unwrap();
+        }
+        if metadata_size > 0 {
+            let metadata_path = dir.path().join("01-network-conf.yaml.meta");
+            fs::write(metadata_path, vec![b'#'; metadata_size]).unwrap();
+        }
+        (dir, config_path)
+    }
+
+    fn budget(total: Option<u64>, categories: &[(StorageCategory, u64)]) -> StorageBudget {
+        StorageBudget {
+            total,
+            categories: categories.iter().copied().collect(),
+        }
+    }
+
+    fn labeled(labels: &str) -> Network {
+        let mut network = Netplan::parse_config(DHCP_CONFIG.as_bytes()).unwrap();
+        let mut eth0 = network.get_ethernets()["eth0"].clone();
+        eth0.set_labels(HashSet::from([labels.to_string()]));
+        network.add_ethernet(&eth0);
+        network
+    }
+
+    #[test]
+    fn backups_over_their_budget_evict_the_oldest() {
+        let (dir, config_path) = seeded_config_directory(4, 100, 0);
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_storage_budget(budget(None, &[(StorageCategory::Backups, 250)]));
+
+        netplan
+            .save_config(&Netplan::parse_config(b"{version: 2}").unwrap())
+            .unwrap();
+        // The new backup takes the size of the configuration, so three of the old ones go
+        let backups = netplan.list_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0], 4);
+        let report = netplan.storage_report().unwrap();
+        let evicted: Vec<&str> = report
+            .evictions
             .iter()
-            .for_each(|(interface, _interface_data)| {
-                if interface.as_str().unwrap().starts_with("eth") {
-                    result.push(interface.as_str().unwrap().to_string());
-                }
+            .map(|eviction| eviction.file.as_str())
+            .collect();
+        assert_eq!(
+            evicted,
+            [
+                "01-network-conf.yaml.3.bak",
+                "01-network-conf.yaml.2.bak",
+                "01-network-conf.yaml.1.bak",
+            ]
+        );
+        assert!(report
+            .evictions
+            .iter()
+            .all(|eviction| eviction.category == StorageCategory::Backups && eviction.size == 100));
+        assert_eq!(report.categories[0].used, 100 + DHCP_CONFIG.len() as u64);
+        assert!(!dir.path().join("01-network-conf.yaml.1.bak").exists());
+    }
+
+    #[test]
+    fn the_latest_backup_is_kept_whatever_the_budget() {
+        let (_dir, config_path) = seeded_config_directory(2, 100, 0);
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_storage_budget(budget(Some(10), &[]));
+
+        netplan
+            .save_config(&Netplan::parse_config(b"{version: 2}").unwrap())
+            .unwrap();
+        assert_eq!(netplan.list_backups().unwrap().len(), 1);
+        netplan.restore_config().unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), DHCP_CONFIG);
+    }
+
+    #[test]
+    fn metadata_over_its_budget_is_rejected() {
+        // The backups take most of the total budget, and are not evicted for the metadata
+        let (dir, config_path) = seeded_config_directory(2, 100, 0);
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_storage_budget(budget(Some(220), &[]));
+
+        let err = netplan.save_metadata(&labeled("wan")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::StorageFull);
+        let err = NetplanError::from(err);
+        assert_eq!(err.status_code(), StatusCode::INSUFFICIENT_STORAGE);
+        assert_eq!(err.code(), "insufficient_storage");
+        assert!(!dir.path().join("01-network-conf.yaml.meta").exists());
+
+        // Nothing is written along with a change to the configuration either
+        let err = netplan.save_config(&labeled("wan")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::StorageFull);
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), DHCP_CONFIG);
+        assert_eq!(netplan.list_backups().unwrap(), [1, 2]);
+        assert!(netplan.storage_report().unwrap().evictions.is_empty());
+
+        // Metadata of its own budget is checked against it as well
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_storage_budget(budget(None, &[(StorageCategory::Metadata, 5)]));
+        let err = netplan.save_metadata(&labeled("wan")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::StorageFull);
+        // Removing the labels frees the metadata, so it always fits
+        netplan
+            .save_metadata(&Netplan::parse_config(DHCP_CONFIG.as_bytes()).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn reconciling_corrects_the_bookkeeping_from_the_disk() {
+        let (dir, config_path) = seeded_config_directory(1, 100, 30);
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_storage_budget(budget(None, &[(StorageCategory::Backups, 300)]));
+        assert_eq!(netplan.storage_report().unwrap().used, 130);
+
+        // Files change behind the back of the API
+        for timestamp in 2..=4 {
+            let backup_path = dir
+                .path()
+                .join(format!("01-network-conf.yaml.{timestamp}.bak"));
+            fs::write(backup_path, vec![b'#'; 100]).unwrap();
+        }
+        fs::remove_file(dir.path().join("01-network-conf.yaml.meta")).unwrap();
+        assert_eq!(netplan.storage_report().unwrap().used, 130);
+
+        let report = netplan.reconcile_storage().unwrap();
+        let reconciliation = report.last_reconciliation.unwrap();
+        assert_eq!(reconciliation.drift, 270);
+        // Going over budget since, the oldest backup is evicted
+        assert_eq!(report.evictions.len(), 1);
+        assert_eq!(report.evictions[0].file, "01-network-conf.yaml.1.bak");
+        assert_eq!(report.categories[0].used, 300);
+        assert_eq!(report.categories[1].used, 0);
+        assert_eq!(netplan.list_backups().unwrap(), [2, 3, 4]);
+
+        assert_eq!(
+            netplan
+                .reconcile_storage()
+                .unwrap()
+                .last_reconciliation
+                .unwrap()
+                .drift,
+            0
+        );
+    }
+
+    #[test]
+    fn rolled_back_changes_never_wait_for_a_reboot() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(dir.path().join("boot_id"), "first\n").unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_system_paths(dir.path().join("boot_id"), dir.path().join("net"));
+        netplan
+            .save_config(&Netplan::parse_config(DHCP_CONFIG.as_bytes()).unwrap())
+            .unwrap();
+        assert!(netplan.pending_actions().unwrap().is_empty());
+
+        let renamed = "{version: 2, ethernets: {eth0: {dhcp4: true, set-name: lan0}}}";
+        netplan
+            .save_config(&Netplan::parse_config(renamed.as_bytes()).unwrap())
+            .unwrap();
+        let actions = netplan.pending_actions().unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].field, "set-name");
+        assert_eq!(actions[0].revision, netplan.config_etag().unwrap());
+
+        netplan.restore_config().unwrap();
+        assert!(netplan.pending_actions().unwrap().is_empty());
+        assert!(!dir.path().join("01-network-conf.yaml.pending").exists());
+    }
+
+    #[test]
+    fn configuration_files_are_only_readable_by_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("01-network-conf.yaml");
+        fs::write(&path, "network: {version: 2}\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_atomically(&path, DHCP_CONFIG.as_bytes()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DHCP_CONFIG);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // No temporary file is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn an_interrupted_write_leaves_the_configuration_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("01-network-conf.yaml");
+        fs::write(&path, DHCP_CONFIG).unwrap();
+
+        // The process dies after writing the temporary file, before renaming it
+        let temp_path = dir.path().join(".01-network-conf.yaml.interrupted.tmp");
+        write_synced(
+            &temp_path,
+            b"network:\n  version: 2\n  ethernets:\n    eth0: {dhc",
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DHCP_CONFIG);
+        let netplan = scripted_netplan(&path, &ScriptedRunner::default());
+        assert!(netplan.load_config().unwrap().get_ethernets()["eth0"].get_dhcp4());
+
+        // Failing to rename, here over a directory, removes the temporary file
+        let occupied = dir.path().join("02-occupied.yaml");
+        fs::create_dir(&occupied).unwrap();
+        fs::write(occupied.join("keep"), "").unwrap();
+        assert!(write_atomically(&occupied, DHCP_CONFIG.as_bytes()).is_err());
+        assert!(occupied.join("keep").exists());
+        let left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().starts_with(".02-occupied.yaml."))
+            .collect();
+        assert!(left.is_empty(), "{left:?}");
+    }
+
+    #[test]
+    fn loads_the_configuration() {
+        let runner = ScriptedRunner::default();
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        let network = netplan.load_config().unwrap();
+        let eth0 = &network.get_ethernets()["eth0"];
+        assert!(eth0.get_dhcp4());
+        assert!(!eth0.get_dhcp6());
+        assert_eq!(
+            runner.calls(),
+            [
+                "netplan status --format yaml --all",
+                "netplan status --diff-only --format yaml"
+            ]
+        );
+    }
+
+    #[test]
+    fn saved_configuration_loads_back() {
+        let (dir, netplan) = netplan_with_config(DHCP_CONFIG, &ScriptedRunner::default());
+        let network = Netplan::parse_config(
+            b"network:
+  version: 2
+  renderer: networkd
+  ethernets:
+    eth0:
+      addresses: [10.0.0.5/24]
+      routes:
+        - {to: default, via: 10.0.0.1}
+      nameservers: {addresses: [10.0.0.1], search: [example.com]}
+    eth1:
+      dhcp6: true
+      mtu: 9000
+",
+        )
+        .unwrap();
+        netplan.save_config(&network).unwrap();
+        let written = fs::read_to_string(dir.path().join("01-network-conf.yaml")).unwrap();
+        assert!(!written.contains("null"), "{written}");
+        assert_eq!(netplan.load_config().unwrap(), network);
+    }
+
+    #[test]
+    fn seeds_a_missing_configuration_from_the_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default()).with_seed_vars(
+            [
+                ("OPENSIGHT_NET_ETH0_ADDRESS", "10.0.0.5/24"),
+                ("OPENSIGHT_NET_ETH0_GATEWAY", "10.0.0.1"),
+                ("OPENSIGHT_NET_DNS", "10.0.0.1"),
+                ("OPENSIGHT_NET_SEARCH", "example.com"),
+                ("OPENSIGHT_NET_ETH0_MTU", "1400"),
+            ]
+            .map(|(variable, value)| (variable.to_string(), value.to_string()))
+            .to_vec(),
+        );
+
+        let network = netplan.load_config().unwrap();
+        assert_eq!(network.get_ethernets().len(), 1);
+        let written: serde_yml::Value =
+            serde_yml::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        let expected: serde_yml::Value = serde_yml::from_str(
+            "network:
+  version: 2
+  renderer: networkd
+  ethernets:
+    eth0:
+      dhcp4: false
+      dhcp6: false
+      mtu: 1400
+      routes:
+        - to: default
+          via: 10.0.0.1
+      addresses:
+        - 10.0.0.5/24
+      nameservers:
+        search:
+          - example.com
+        addresses:
+          - 10.0.0.1
+",
+        )
+        .unwrap();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn unreadable_configuration_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory can't be read as a file
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::create_dir(&config_path).unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default()).with_seed_vars(
+            vec![("OPENSIGHT_NET_ETH0_MODE".to_string(), "dhcp".to_string())],
+        );
+
+        let err = netplan.load_config().unwrap_err();
+        assert!(matches!(err, NetplanError::Io(_)), "{err:?}");
+        assert!(config_path.is_dir());
+        assert_eq!(netplan.list_backups().unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn malformed_configuration_is_a_parse_error() {
+        let (_dir, netplan) =
+            netplan_with_config("network:\n  ethernets: [eth0\n", &ScriptedRunner::default());
+        let err = netplan.load_config().unwrap_err();
+        assert!(matches!(err, NetplanError::ParseError { .. }), "{err:?}");
+        assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn unexpected_status_output_is_a_parse_error() {
+        let cases = [
+            ScriptedRunner::default().on("netplan status --format", 0, "[unclosed\n"),
+            ScriptedRunner::default().on("netplan status --format", 0, "- eth0\n"),
+            ScriptedRunner::default().on("netplan status --format", 0, "42: {type: ethernet}\n"),
+            ScriptedRunner::default().on(
+                "netplan status --diff-only",
+                0,
+                "netplan-global-state: {}\n",
+            ),
+            ScriptedRunner::default().on(
+                "netplan status --diff-only",
+                0,
+                "interfaces: {eth0: []}\n",
+            ),
+            ScriptedRunner::default().on(
+                "netplan status --diff-only",
+                0,
+                "interfaces: {eth0: {system_state: up}}\n",
+            ),
+        ];
+        for runner in cases {
+            let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+            let err = netplan.load_config().unwrap_err();
+            assert!(matches!(err, NetplanError::ParseError { .. }), "{err:?}");
+        }
+    }
+
+    /// A store over a `Netplan` backend managing `content`, whose sessions last `timeout`.
+    fn store_with_config(
+        content: &str,
+        runner: &ScriptedRunner,
+        timeout: Duration,
+    ) -> (tempfile::TempDir, NetplanStore) {
+        let (dir, netplan) = netplan_with_config(content, runner);
+        let store = NetplanStore::new(Box::new(netplan)).with_operation_timeout(timeout);
+        (dir, store)
+    }
+
+    #[actix_web::test]
+    async fn non_boolean_dhcp_flags_are_a_parse_error() {
+        let runner = ScriptedRunner::default().on(
+            "netplan status --diff-only",
+            0,
+            "interfaces:\n  eth0:\n    system_state:\n      missing_dhcp4_address: maybe\n",
+        );
+        let (_dir, store) = store_with_config(DHCP_CONFIG, &runner, DEFAULT_OPERATION_TIMEOUT);
+        let err = store
+            .lock()
+            .await
+            .apply_with_diff(NetworkRenderer::NetworkD)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, NetplanError::ParseError { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn labels_only_changes_are_metadata_mutations() {
+        let current =
+            Netplan::parse_config(b"{version: 2, ethernets: {eth0: {dhcp4: true}}}").unwrap();
+        assert!(Mutation::new(&current, current.clone()).is_none());
+
+        let mut labeled = current.clone();
+        let mut eth0 = labeled.get_ethernets()["eth0"].clone();
+        eth0.set_labels(HashSet::from(["wan".to_string()]));
+        labeled.add_ethernet(&eth0);
+        assert!(matches!(
+            Mutation::new(&current, labeled.clone()),
+            Some(Mutation::Metadata(_))
+        ));
+
+        // Along with a change to the configuration, the labels are saved with it
+        eth0.set_dhcp6(true);
+        labeled.add_ethernet(&eth0);
+        assert!(matches!(
+            Mutation::new(&current, labeled),
+            Some(Mutation::Config(_))
+        ));
+    }
+
+    #[test]
+    fn labels_bump_the_metadata_revision_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(&config_path, DHCP_CONFIG).unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_networkd_paths(dir.path().join("run"), dir.path().join("network"));
+        let etag = netplan.config_etag().unwrap();
+        let revision = netplan.metadata_revision().unwrap();
+
+        netplan.save_metadata(&labeled("wan")).unwrap();
+        assert_eq!(netplan.config_etag().unwrap(), etag);
+        let labeled_revision = netplan.metadata_revision().unwrap();
+        assert_ne!(labeled_revision, revision);
+
+        // `required-for-online` is kept with the metadata, but applied with the configuration
+        let mut network = labeled("wan");
+        let mut eth0 = network.get_ethernets()["eth0"].clone();
+        eth0.set_required_for_online(Some(RequiredForOnline::Degraded));
+        network.add_ethernet(&eth0);
+        netplan.save_config(&network).unwrap();
+        assert_ne!(netplan.config_etag().unwrap(), etag);
+        assert_ne!(netplan.metadata_revision().unwrap(), labeled_revision);
+    }
+
+    #[actix_web::test]
+    async fn one_deadline_covers_every_command_of_a_session() {
+        let step = Duration::from_millis(150);
+        let runner = ScriptedRunner::default()
+            .hanging("netplan status", step, "{}\n")
+            .hanging("netplan status --diff-only", step, "interfaces: {}\n");
+        let (_dir, store) = store_with_config(DHCP_CONFIG, &runner, Duration::from_millis(400));
+        let session = store.lock().await;
+        let started = std::time::Instant::now();
+        session.load_config().await.unwrap();
+        // Each command would fit in the timeout on its own, but not all of them
+        let err = session.load_config().await.unwrap_err();
+        assert!(
+            matches!(&err, NetplanError::Timeout { command, .. } if command.starts_with("netplan status")),
+            "{err:?}"
+        );
+        assert!(started.elapsed() < Duration::from_millis(550));
+        assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[actix_web::test]
+    async fn waiting_for_dhcp_leases_does_not_block_the_worker() {
+        let runner = ScriptedRunner::default().on(
+            "netplan status --diff-only",
+            0,
+            "interfaces:\n  eth0:\n    system_state:\n      missing_dhcp4_address: true\n",
+        );
+        let (_dir, store) = store_with_config(DHCP_CONFIG, &runner, Duration::from_millis(1500));
+        let session = store.lock().await;
+        let started = std::time::Instant::now();
+        let (applied, served) =
+            futures::join!(session.apply_with_diff(NetworkRenderer::NetworkD), async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                started.elapsed()
             });
-        Ok(result)
+        // The lease never comes, so the wait ends at the deadline rather than after 15 seconds
+        let err = applied.unwrap_err();
+        assert!(matches!(err, NetplanError::Timeout { .. }), "{err:?}");
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert!(served < Duration::from_millis(500), "{served:?}");
+    }
+
+    #[test]
+    fn finds_the_interfaces_missing_a_dhcp_address() {
+        let diff: HashMap<String, serde_yml::Mapping> = serde_yml::from_str(
+            "eth0: {missing_dhcp4_address: true}\n\
+            eth1: {missing_dhcp6_address: true}\n\
+            eth2: {missing_dhcp4_address: false}\n\
+            eth3: {mtu: 1500}\n",
+        )
+        .unwrap();
+        let mut interfaces = Netplan::interfaces_with_misssing_dhcp_address(&diff).unwrap();
+        interfaces.sort();
+        assert_eq!(interfaces, ["eth0", "eth1"]);
+    }
+
+    #[test]
+    fn only_dhcp6_with_router_advertisements_expects_an_address() {
+        let network = Netplan::parse_config(
+            b"{version: 2, renderer: networkd, ethernets: {\
+                eth0: {dhcp4: true},\
+                eth1: {dhcp6: true},\
+                eth2: {dhcp6: true, accept-ra: true},\
+                eth3: {dhcp6: true, accept-ra: false},\
+                eth4: {dhcp4: true, activation-mode: manual}}}",
+        )
+        .unwrap();
+        let mut interfaces = Netplan::interfaces_expecting_dhcp_address(&network);
+        interfaces.sort();
+        assert_eq!(interfaces, ["eth0", "eth2"]);
+    }
+
+    /// Commands run to manage the renderer service, besides querying it.
+    fn service_changes(runner: &ScriptedRunner) -> Vec<String> {
+        runner
+            .calls()
+            .into_iter()
+            .filter(|call| call.starts_with("systemctl") && !call.starts_with("systemctl is-"))
+            .collect()
+    }
+
+    #[test]
+    fn applies_when_the_renderer_service_is_active() {
+        let runner = ScriptedRunner::default();
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        netplan
+            .apply_or_roll_back(&NetworkRenderer::NetworkD)
+            .unwrap();
+        let calls = runner.calls();
+        assert_eq!(calls[0], "systemctl is-active systemd-networkd.service");
+        assert!(calls.contains(&"netplan apply".to_string()), "{calls:?}");
+    }
+
+    #[test]
+    fn inactive_renderer_service_fails_fast() {
+        let runner = ScriptedRunner::default()
+            .on("systemctl is-active", 3, "inactive\n")
+            .on("systemctl is-enabled", 1, "disabled\n");
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        let err = netplan
+            .apply_or_roll_back(&NetworkRenderer::NetworkManager)
+            .unwrap_err();
+        let NetplanError::RendererInactive {
+            unit,
+            active,
+            enabled,
+            remediation,
+        } = &err
+        else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(unit, "NetworkManager.service");
+        assert_eq!(active, "inactive");
+        assert_eq!(enabled, "disabled");
+        assert!(remediation.contains("systemctl enable --now NetworkManager.service"));
+        assert_eq!(err.code(), "renderer_service_inactive");
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("netplan")));
+        assert!(service_changes(&runner).is_empty());
+    }
+
+    #[test]
+    fn masked_renderer_service_is_never_unmasked() {
+        let runner = ScriptedRunner::default()
+            .on("systemctl is-active", 3, "inactive\n")
+            .on("systemctl is-enabled", 1, "masked\n");
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        let err = netplan.replace_config(&Network::new()).unwrap_err();
+        let NetplanError::RendererInactive { remediation, .. } = &err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert!(remediation.contains("systemctl unmask systemd-networkd.service"));
+        assert!(service_changes(&runner).is_empty());
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("netplan")));
+        // Nothing was saved either
+        assert_eq!(netplan.list_backups().unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn unknown_renderer_service_state_fails_closed() {
+        let runner = ScriptedRunner::default().missing("systemctl");
+        let (_dir, netplan) = netplan_with_config(DHCP_CONFIG, &runner);
+        let err = netplan
+            .apply_or_roll_back(&NetworkRenderer::NetworkD)
+            .unwrap_err();
+        assert!(
+            matches!(err, NetplanError::RendererUnknown { .. }),
+            "{err:?}"
+        );
+        assert_eq!(err.code(), "renderer_service_unknown");
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("netplan")));
+    }
+
+    #[actix_web::test]
+    async fn errors_are_served_as_json() {
+        let response = NetplanError::BackupNotFound(42).error_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"code": "backup_not_found", "message": "Backup 42 was not found."})
+        );
+    }
+
+    #[test]
+    fn required_for_online_is_set_through_a_networkd_drop_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(&config_path, DHCP_CONFIG).unwrap();
+        let networkd_path = dir.path().join("network");
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default())
+            .with_networkd_paths(dir.path().join("run"), networkd_path.clone());
+        let mut network = netplan.load_config().unwrap();
+        let mut eth0 = network.get_ethernets()["eth0"].clone();
+        eth0.set_required_for_online(Some(RequiredForOnline::Degraded));
+        network.add_ethernet(&eth0);
+
+        netplan.save_config(&network).unwrap();
+        // Netplan doesn't know about it, so it is kept with the metadata
+        assert!(!fs::read_to_string(&config_path)
+            .unwrap()
+            .contains("required-for-online"));
+        let drop_in = networkd_path.join("10-netplan-eth0.network.d/required-for-online.conf");
+        assert_eq!(
+            fs::read_to_string(&drop_in).unwrap(),
+            "[Link]\nRequiredForOnline=degraded\n"
+        );
+        assert_eq!(
+            fs::metadata(&drop_in).unwrap().permissions().mode() & 0o777,
+            0o644
+        );
+        assert_eq!(
+            netplan.load_config().unwrap().get_ethernets()["eth0"].required_for_online(),
+            Some(RequiredForOnline::Degraded)
+        );
+
+        // The drop-ins of the administrator are left alone
+        let own_drop_in = networkd_path.join("10-netplan-eth0.network.d/50-mtu.conf");
+        fs::write(&own_drop_in, "[Link]\nMTUBytes=1400\n").unwrap();
+        eth0.set_required_for_online(None);
+        network.add_ethernet(&eth0);
+        netplan.save_config(&network).unwrap();
+        assert!(!drop_in.exists());
+        assert!(own_drop_in.exists());
+        assert_eq!(
+            netplan.load_config().unwrap().get_ethernets()["eth0"].required_for_online(),
+            None
+        );
+        assert!(!config_path
+            .with_file_name("01-network-conf.yaml.meta")
+            .exists());
+    }
+
+    #[test]
+    fn changing_required_for_online_takes_an_apply() {
+        let current = Netplan::parse_config(DHCP_CONFIG.as_bytes()).unwrap();
+        let mut updated = current.clone();
+        let mut eth0 = updated.get_ethernets()["eth0"].clone();
+        eth0.set_required_for_online(Some(RequiredForOnline::No));
+        updated.add_ethernet(&eth0);
+        assert!(matches!(
+            Mutation::new(&current, updated),
+            Some(Mutation::Config(_))
+        ));
+    }
+
+    fn yaml_references_fixture(name: &str) -> String {
+        fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/yaml-references")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn yaml_references_are_expanded_like_netplan_does() {
+        for fixture in ["anchored-nameservers", "merge-key-routes"] {
+            let dir = tempfile::tempdir().unwrap();
+            let config_path = dir.path().join("01-network-conf.yaml");
+            fs::write(
+                &config_path,
+                yaml_references_fixture(&format!("{fixture}.yaml")),
+            )
+            .unwrap();
+            let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+            let expanded = Netplan::parse_config(
+                yaml_references_fixture(&format!("{fixture}.expanded.yaml")).as_bytes(),
+            )
+            .unwrap();
+            assert_eq!(netplan.load_stored_config().unwrap(), expanded, "{fixture}");
+        }
+    }
+
+    #[test]
+    fn changes_expanding_yaml_references_are_refused_in_strict_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let content = yaml_references_fixture("anchored-nameservers.yaml");
+        fs::write(&config_path, &content).unwrap();
+        let strict =
+            scripted_netplan(&config_path, &ScriptedRunner::default()).with_strict_yaml(true);
+        let current = strict.load_stored_config().unwrap();
+        let mut updated = current.clone();
+        let mut eth0 = updated.get_ethernets()["eth0"].clone();
+        eth0.set_dhcp4(true);
+        updated.add_ethernet(&eth0);
+        let Some(Mutation::Config(mutation)) = Mutation::new(&current, updated.clone()) else {
+            panic!("Changing the MTU is a change to the configuration");
+        };
+
+        let err = strict.commit(mutation, false).unwrap_err();
+        assert_eq!(err.code(), "yaml_references");
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+        assert!(err.to_string().contains("&office-dns, *office-dns"));
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), content);
+
+        // Otherwise, the file is normalized with the expanded references
+        let lenient = scripted_netplan(&config_path, &ScriptedRunner::default());
+        let Some(Mutation::Config(mutation)) = Mutation::new(&current, updated.clone()) else {
+            panic!("Changing the MTU is a change to the configuration");
+        };
+        lenient.commit(mutation, false).unwrap();
+        assert!(lenient.yaml_references().unwrap().is_empty());
+        assert_eq!(lenient.load_stored_config().unwrap(), updated);
+    }
+
+    #[test]
+    fn corrupt_metadata_is_rebuilt_from_the_readable_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {}, eth1: {}}}\n",
+        )
+        .unwrap();
+        let metadata_path = dir.path().join("01-network-conf.yaml.meta");
+        fs::write(
+            &metadata_path,
+            "labels: {eth0: [wan], eth1: {not: labels}}\n\
+            required_for_online: {eth0: routable}\n\
+            provenance: {eth0: api}\n",
+        )
+        .unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+        assert_eq!(netplan.corrupt_sidecars(), [Sidecar::Metadata]);
+        assert!(netplan.load_stored_config().is_err());
+
+        let reports = netplan.rebuild_state(&[Sidecar::Metadata]).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].outcome, RebuildOutcome::PartiallyRecovered);
+        assert_eq!(reports[0].recovered, 2);
+        assert_eq!(reports[0].lost, ["labels of eth1", "section provenance"]);
+        assert!(dir
+            .path()
+            .join("01-network-conf.yaml.meta.corrupt")
+            .exists());
+        assert!(netplan.corrupt_sidecars().is_empty());
+        let network = netplan.load_stored_config().unwrap();
+        let eth0 = &network.get_ethernets()["eth0"];
+        assert_eq!(eth0.labels(), HashSet::from(["wan".to_string()]));
+        assert_eq!(
+            eth0.required_for_online(),
+            Some(RequiredForOnline::Routable)
+        );
+
+        // Rebuilding again finds nothing to do
+        assert_eq!(
+            netplan.rebuild_state(&Sidecar::ALL).unwrap(),
+            [
+                RebuildReport::intact(Sidecar::Metadata),
+                RebuildReport::intact(Sidecar::PendingActions),
+            ]
+        );
+
+        fs::write(&metadata_path, "- not\n- metadata\n").unwrap();
+        let reports = netplan.rebuild_state(&[Sidecar::Metadata]).unwrap();
+        assert_eq!(reports[0].outcome, RebuildOutcome::Unrecoverable);
+        assert!(!metadata_path.exists());
+        assert!(
+            netplan.load_stored_config().unwrap().get_ethernets()["eth0"]
+                .labels()
+                .is_empty()
+        );
     }
 }
@@ -0,0 +1,525 @@
+//! Property tests of the round trip of the network through the netplan YAML the API writes, and
+//! of a corpus of hand written configurations.
+
+use crate::{
+    custom_types::IpNetwork,
+    models::{
+        device::{Device, MTU, MTUV6},
+        ethernet::Ethernet,
+        nameservers::Nameservers,
+        network::{Network, NetworkRenderer},
+        route::{Route, RouteScope, RouteType},
+        vlan::Vlan,
+    },
+    netplan::Netplan,
+};
+use proptest::{collection, prelude::*, sample::Index};
+use serde_yml::Value;
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    num::NonZeroU32,
+    path::Path,
+};
+
+/// Sections of a device stanza netplan reads as sequences.
+const SEQUENCE_KEYS: [&str; 6] = [
+    "addresses",
+    "routes",
+    "routing-policy",
+    "interfaces",
+    "link-local",
+    "search",
+];
+
+/// Sections of a device stanza netplan reads as mappings, along with the elements of `routes`.
+const MAPPING_KEYS: [&str; 9] = [
+    "nameservers",
+    "match",
+    "dhcp4-overrides",
+    "dhcp6-overrides",
+    "parameters",
+    "access-points",
+    "auth",
+    "path-cost",
+    "routes[]",
+];
+
+/// Sections of the configuration holding the devices, keyed by name.
+const DEVICE_SECTIONS: [&str; 8] = [
+    "ethernets",
+    "vlans",
+    "bridges",
+    "bonds",
+    "wifis",
+    "tunnels",
+    "dummy-devices",
+    "vrfs",
+];
+
+/// Checks the structure netplan expects from a configuration, independently of the models:
+/// a `network` root of version 2, device sections mapping names to stanzas, sequences and
+/// mappings where netplan reads them, and no null keys nor values.
+///
+/// Returns the path and a description of every violation.
+fn schema_violations(config: &Value) -> Vec<String> {
+    let mut result = vec![];
+    let Some(network) = config.get("network").and_then(Value::as_mapping) else {
+        return vec!["network: not a mapping".to_string()];
+    };
+    if network.get("version").and_then(Value::as_u64) != Some(2) {
+        result.push("network.version: not 2".to_string());
+    }
+    for (key, value) in network {
+        let Some(key) = key.as_str() else {
+            result.push(format!("network: {key:?} is not a string key"));
+            continue;
+        };
+        if !DEVICE_SECTIONS.contains(&key) {
+            check_values(&format!("network.{key}"), key, value, &mut result);
+            continue;
+        }
+        let Some(devices) = value.as_mapping() else {
+            result.push(format!("network.{key}: not a mapping"));
+            continue;
+        };
+        for (name, stanza) in devices {
+            let path = format!("network.{key}.{}", name.as_str().unwrap_or("?"));
+            if name.as_str().is_none_or(str::is_empty) {
+                result.push(format!("{path}: {name:?} is not an interface name"));
+            }
+            if !stanza.is_mapping() {
+                result.push(format!("{path}: not a mapping"));
+            }
+            check_values(&path, "", stanza, &mut result);
+        }
+    }
+    result
+}
+
+/// Checks `value`, found under `key` at `path`, and everything below it.
+fn check_values(path: &str, key: &str, value: &Value, result: &mut Vec<String>) {
+    if SEQUENCE_KEYS.contains(&key) && !value.is_sequence() {
+        result.push(format!("{path}: not a sequence"));
+    }
+    if MAPPING_KEYS.contains(&key) && !value.is_mapping() {
+        result.push(format!("{path}: not a mapping"));
+    }
+    match value {
+        Value::Null => result.push(format!("{path}: null")),
+        Value::Mapping(mapping) => {
+            for (child_key, child) in mapping {
+                match child_key.as_str() {
+                    Some(child_key) => {
+                        check_values(&format!("{path}.{child_key}"), child_key, child, result)
+                    }
+                    None => result.push(format!("{path}: {child_key:?} is not a string key")),
+                }
+            }
+        }
+        Value::Sequence(sequence) => {
+            for (index, child) in sequence.iter().enumerate() {
+                let child_key = format!("{key}[]");
+                check_values(&format!("{path}[{index}]"), &child_key, child, result);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Lists what of `original` is lost or altered in `written`, by path.
+///
+/// The elements of sequences are matched regardless of their order, and keys only present in
+/// `written`, like the defaults the API writes out, are ignored.
+fn lost_keys(path: &str, original: &Value, written: &Value, result: &mut Vec<String>) {
+    match (original, written) {
+        (Value::Mapping(original), Value::Mapping(written)) => {
+            for (key, value) in original {
+                let key_name = key.as_str().unwrap_or("?");
+                let child_path = match path {
+                    "" => key_name.to_string(),
+                    path => format!("{path}.{key_name}"),
+                };
+                match written.get(key) {
+                    Some(written_value) => lost_keys(&child_path, value, written_value, result),
+                    None => result.push(format!("{child_path}: lost")),
+                }
+            }
+        }
+        (Value::Sequence(original), Value::Sequence(written)) => {
+            for (index, value) in original.iter().enumerate() {
+                let found = written.iter().any(|written_value| {
+                    let mut differences = vec![];
+                    lost_keys("", value, written_value, &mut differences);
+                    differences.is_empty()
+                });
+                if !found {
+                    result.push(format!("{path}[{index}]: {value:?} lost or altered"));
+                }
+            }
+        }
+        (original, written) if original != written => {
+            result.push(format!("{path}: {original:?} altered to {written:?}"));
+        }
+        _ => (),
+    }
+}
+
+/// `network` as read back from its YAML, which knows of a single unspecified destination.
+///
+/// Default routes are written as `to: default`, whatever the version of their destination, and
+/// read back as `0.0.0.0`. The other fields netplan doesn't know about, like the system state,
+/// are already left out by the equality of the models.
+fn semantic(network: &Network) -> Network {
+    let mut result = network.clone();
+    for ethernet in network.get_ethernets().values() {
+        let mut ethernet = ethernet.clone();
+        normalize_routes(&mut ethernet);
+        result.add_ethernet(&ethernet);
+    }
+    for vlan in network.get_vlans().values() {
+        let mut vlan = vlan.clone();
+        normalize_routes(&mut vlan);
+        result.add_vlan(&vlan);
+    }
+    result
+}
+
+fn normalize_routes(device: &mut impl Device) {
+    let routes = device.get_routes();
+    device.delete_all_routes();
+    for mut route in routes.into_values() {
+        if route.to.is_unspecified() {
+            route.to = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        }
+        device.add_route(&route);
+    }
+}
+
+/// Settings shared by the generated devices.
+#[derive(Debug, Clone)]
+struct Settings {
+    dhcp4: bool,
+    dhcp6: bool,
+    mtu: Option<MTU>,
+    ipv6_mtu: Option<MTUV6>,
+    accept_ra: Option<bool>,
+    optional: Option<bool>,
+    ipv6_privacy: Option<bool>,
+    addresses: Vec<IpNetwork>,
+    routes: Vec<Route>,
+    nameservers: Nameservers,
+}
+
+impl Settings {
+    fn apply(&self, device: &mut impl Device) {
+        device.set_dhcp4(self.dhcp4);
+        device.set_dhcp6(self.dhcp6);
+        device.set_mtu(self.mtu);
+        device.set_ipv6_mtu(self.ipv6_mtu);
+        device.set_accept_ra(self.accept_ra);
+        device.set_optional(self.optional);
+        device.set_ipv6_privacy(self.ipv6_privacy);
+        for address in &self.addresses {
+            device.add_address(address);
+        }
+        for route in &self.routes {
+            device.add_route(route);
+        }
+        device.add_nameservers(self.nameservers.clone());
+    }
+}
+
+fn ip_address() -> impl Strategy<Value = IpAddr> {
+    prop_oneof![
+        any::<u32>().prop_map(|bits| IpAddr::V4(Ipv4Addr::from(bits))),
+        any::<u128>().prop_map(|bits| IpAddr::V6(Ipv6Addr::from(bits))),
+    ]
+}
+
+/// A host address, e.g. a gateway, which can't be unspecified.
+fn host_address() -> impl Strategy<Value = IpAddr> {
+    ip_address().prop_filter("unspecified", |address| !address.is_unspecified())
+}
+
+fn ip_network() -> impl Strategy<Value = IpNetwork> {
+    prop_oneof![
+        (any::<u32>(), 0u8..=32).prop_map(|(bits, prefix)| {
+            IpNetwork::new(IpAddr::V4(Ipv4Addr::from(bits)), prefix).unwrap()
+        }),
+        (any::<u128>(), 0u8..=128).prop_map(|(bits, prefix)| {
+            IpNetwork::new(IpAddr::V6(Ipv6Addr::from(bits)), prefix).unwrap()
+        }),
+    ]
+}
+
+/// A valid route, with any of its optional fields set.
+fn route() -> impl Strategy<Value = Route> {
+    let scope = prop_oneof![
+        Just(RouteScope::Global),
+        Just(RouteScope::Link),
+        Just(RouteScope::Host)
+    ];
+    let route_type = prop_oneof![
+        Just(RouteType::Unicast),
+        Just(RouteType::Blackhole),
+        Just(RouteType::Unreachable),
+        Just(RouteType::Prohibit),
+    ];
+    (
+        prop_oneof![
+            1 => Just(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            1 => Just(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+            8 => ip_address(),
+        ],
+        proptest::option::of(host_address()),
+        proptest::option::of(host_address()),
+        proptest::option::of(any::<u32>()),
+        proptest::option::of(any::<NonZeroU32>()),
+        proptest::option::of(scope),
+        proptest::option::of(route_type),
+    )
+        .prop_map(|(to, via, from, metric, table, scope, r#type)| {
+            let mut route = Route::new(to, via, from);
+            route.metric = metric;
+            route.table = table;
+            route.scope = scope;
+            route.r#type = r#type;
+            // Routes on the link or dropping the traffic have no gateway
+            if route.validate().is_err() {
+                route.via = None;
+            }
+            route
+        })
+}
+
+fn nameservers() -> impl Strategy<Value = Nameservers> {
+    (
+        collection::hash_set("[a-z]{1,8}\\.example", 0..3),
+        collection::hash_set(ip_address(), 0..3),
+    )
+        .prop_map(|(search, addresses)| Nameservers { search, addresses })
+}
+
+fn settings() -> impl Strategy<Value = Settings> {
+    (
+        (any::<bool>(), any::<bool>()),
+        proptest::option::of((68u32..=64000).prop_map(|mtu| MTU::new(mtu).unwrap())),
+        proptest::option::of((1280u32..=64000).prop_map(|mtu| MTUV6::new(mtu).unwrap())),
+        (
+            proptest::option::of(any::<bool>()),
+            proptest::option::of(any::<bool>()),
+            proptest::option::of(any::<bool>()),
+        ),
+        collection::vec(ip_network(), 0..4),
+        collection::vec(route(), 0..4),
+        nameservers(),
+    )
+        .prop_map(
+            |(
+                (dhcp4, dhcp6),
+                mtu,
+                ipv6_mtu,
+                (accept_ra, optional, ipv6_privacy),
+                addresses,
+                routes,
+                nameservers,
+            )| Settings {
+                dhcp4,
+                dhcp6,
+                mtu,
+                ipv6_mtu,
+                accept_ra,
+                optional,
+                ipv6_privacy,
+                addresses,
+                routes,
+                nameservers,
+            },
+        )
+}
+
+/// A valid network of Ethernets, and of VLANs on top of them.
+///
+/// Labels are left out, as they are kept in the metadata file rather than in the YAML.
+fn network() -> impl Strategy<Value = Network> {
+    (
+        prop_oneof![
+            Just(NetworkRenderer::NetworkD),
+            Just(NetworkRenderer::NetworkManager)
+        ],
+        collection::btree_map("en[a-z0-9]{1,6}", settings(), 0..4),
+        collection::btree_map(
+            "vlan[0-9]{1,4}",
+            (1u16..=4094, any::<Index>(), settings()),
+            0..3,
+        ),
+    )
+        .prop_map(|(renderer, ethernets, vlans)| {
+            let mut network = Network::new();
+            network.renderer = renderer;
+            let names: Vec<&String> = ethernets.keys().collect();
+            for (name, settings) in &ethernets {
+                let mut ethernet = Ethernet::new(name.clone());
+                settings.apply(&mut ethernet);
+                network.add_ethernet(&ethernet);
+            }
+            for (name, (id, link, settings)) in vlans {
+                let link = match names.is_empty() {
+                    true => "eth0".to_string(),
+                    false => names[link.index(names.len())].clone(),
+                };
+                let mut vlan = Vlan::new(name, id, link);
+                settings.apply(&mut vlan);
+                network.add_vlan(&vlan);
+            }
+            network
+        })
+}
+
+proptest! {
+    #[test]
+    fn networks_survive_a_round_trip(network in network()) {
+        let yaml = Netplan::to_netplan_yaml(&network);
+        let read = Netplan::parse_config(yaml.as_bytes()).unwrap();
+        prop_assert_eq!(semantic(&read), semantic(&network), "{}", yaml);
+    }
+
+    #[test]
+    fn networks_are_always_written_the_same_way(network in network()) {
+        let yaml = Netplan::to_netplan_yaml(&network);
+        prop_assert_eq!(&Netplan::to_netplan_yaml(&network.clone()), &yaml);
+        // The maps and sets read back are built anew, in another order
+        let read = Netplan::parse_config(yaml.as_bytes()).unwrap();
+        prop_assert_eq!(&Netplan::to_netplan_yaml(&read), &yaml);
+    }
+
+    #[test]
+    fn networks_are_written_as_netplan_expects(network in network()) {
+        let yaml = Netplan::to_netplan_yaml(&network);
+        let violations = schema_violations(&serde_yml::from_str(&yaml).unwrap());
+        prop_assert!(violations.is_empty(), "{:?}\n{}", violations, yaml);
+    }
+}
+
+#[test]
+fn the_schema_check_finds_misshapen_sections() {
+    let config: Value = serde_yml::from_str(
+        "network:
+  version: 2
+  ethernets:
+    eth0:
+      addresses: 10.0.0.5/24
+      routes: {to: default, via: 10.0.0.1}
+      nameservers: [10.0.0.1]
+      mtu: ~
+    eth1: []
+  vlans: []
+",
+    )
+    .unwrap();
+    assert_eq!(
+        schema_violations(&config),
+        [
+            "network.ethernets.eth0.addresses: not a sequence",
+            "network.ethernets.eth0.routes: not a sequence",
+            "network.ethernets.eth0.nameservers: not a mapping",
+            "network.ethernets.eth0.mtu: null",
+            "network.ethernets.eth1: not a mapping",
+            "network.vlans: not a mapping",
+        ]
+    );
+}
+
+#[test]
+fn the_corpus_survives_a_round_trip() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/netplan-corpus");
+    let mut paths: Vec<_> = fs::read_dir(&corpus)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "yaml")
+        })
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixture in {}", corpus.display());
+    let mut failures = vec![];
+    for path in paths {
+        let content = fs::read_to_string(&path).unwrap();
+        let original: Value = serde_yml::from_str(&content).unwrap();
+        let network = match Netplan::parse_config(content.as_bytes()) {
+            Ok(network) => network,
+            Err(err) => {
+                failures.push(format!("{}: {err}", path.display()));
+                continue;
+            }
+        };
+        let written: Value = serde_yml::from_str(&Netplan::to_netplan_yaml(&network)).unwrap();
+        let mut lost = vec![];
+        lost_keys("", &original, &written, &mut lost);
+        lost.extend(schema_violations(&written));
+        failures.extend(
+            lost.into_iter()
+                .map(|lost| format!("{}: {lost}", path.display())),
+        );
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn a_nested_device_survives_a_round_trip() {
+    let content = "network:
+  version: 2
+  renderer: networkd
+  ethernets:
+    eth0:
+      renderer: NetworkManager
+      addresses: [10.0.0.5/24]
+      nameservers:
+        addresses: [10.0.0.53, 2001:db8::53]
+        search: [example.com]
+      routes:
+        - to: default
+          via: 10.0.0.1
+        - to: 192.168.0.1
+          via: 10.0.0.254
+          metric: 200
+";
+    let network = Netplan::parse_config(content.as_bytes()).unwrap();
+    let eth0 = &network.get_ethernets()["eth0"];
+    assert_eq!(eth0.get_renderer(), Some(NetworkRenderer::NetworkManager));
+    assert!(eth0.get_nameservers().search.contains("example.com"));
+    assert_eq!(eth0.get_routes().len(), 2);
+
+    let yaml = Netplan::to_netplan_yaml(&network);
+    let written: Value = serde_yml::from_str(&yaml).unwrap();
+    let original: Value = serde_yml::from_str(content).unwrap();
+    let mut lost = vec![];
+    lost_keys("", &original, &written, &mut lost);
+    assert!(lost.is_empty(), "{lost:?}\n{yaml}");
+    // The renderer of the device stays under the device, and the network keeps its own
+    assert_eq!(
+        written["network"]["ethernets"]["eth0"]["renderer"],
+        "NetworkManager"
+    );
+    assert_eq!(written["network"]["renderer"], "networkd");
+    let read = Netplan::parse_config(yaml.as_bytes()).unwrap();
+    assert_eq!(semantic(&read), semantic(&network), "{yaml}");
+}
+
+#[test]
+fn renderers_are_named_as_netplan_names_them() {
+    for (renderer, name) in [
+        (NetworkRenderer::NetworkD, "networkd"),
+        (NetworkRenderer::NetworkManager, "NetworkManager"),
+    ] {
+        assert_eq!(serde_yml::to_string(&renderer).unwrap().trim(), name);
+        assert_eq!(
+            serde_yml::from_str::<NetworkRenderer>(name).unwrap(),
+            renderer
+        );
+    }
+    // netplan is case sensitive
+    assert!(serde_yml::from_str::<NetworkRenderer>("networkmanager").is_err());
+}
@@ -0,0 +1,239 @@
+use actix_web::{
+    get, post,
+    web::{Data, Query},
+    HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{input_models::RebuildStateQuery, sidecar::Sidecar},
+    netplan::NetplanStore,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_storage, get_resource_usage, rebuild_state))]
+/// API documentation for the administration of the service itself.
+pub struct AdminApi;
+
+/// Configures the Actix web service with the administration endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_storage)
+            .service(get_resource_usage)
+            .service(rebuild_state);
+    }
+}
+
+#[api_path(operation_id = "get-storage")]
+#[get("/storage")]
+/// Retrieves how much disk the service uses besides the configuration, against its budget.
+///
+/// The usage is reported by category, as counted on every write and corrected every five minutes
+/// from what is actually on disk. The budgets are set in bytes through the
+/// `OPENSIGHT_STORAGE_BUDGET` environment variable for the total, and
+/// `OPENSIGHT_STORAGE_BUDGET_BACKUPS` and `OPENSIGHT_STORAGE_BUDGET_METADATA` for the categories.
+/// The oldest backups are evicted to stay within budget, while metadata going over it is rejected
+/// with `507 Insufficient Storage`.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the usage by category, the budgets, the last
+///   evictions and the last reconciliation with the disk.
+/// - `HttpResponse::InternalServerError` if the disk usage could not be measured.
+pub async fn get_storage(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.storage_report().await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-resource-usage")]
+#[get("/resource-usage")]
+/// Retrieves how many entries the in-memory structures of the service hold.
+///
+/// Each structure is reported with its current count, the most it held since the service started
+/// and its cap, so leaks can be told apart in the field. The history of the storage evictions is
+/// capped, the oldest evictions being forgotten first, while the DHCP probes are bounded by the
+/// configured interfaces.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the usage of each structure, sorted by name.
+pub async fn get_resource_usage(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.resource_usage().await {
+        Ok(usage) => HttpResponse::Ok().json(usage),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "rebuild-state")]
+#[post("/rebuild-state")]
+/// Rebuilds the files kept next to the configuration which became corrupt.
+///
+/// The targets are `metadata` (the labels and `required-for-online` of the Ethernets) and
+/// `pending-actions` (the changes waiting for a reboot). A corrupt file is set aside with the
+/// `.corrupt` suffix, and rewritten with the entries which can still be read; nothing else
+/// records them, so the others are reported as lost. The files which can be read are left as
+/// is, so rebuilding again changes nothing. The service logs the corrupt files on startup.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `query`: A `Query<RebuildStateQuery>` instance with the targets to rebuild, all by default.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing, for each target, whether it was intact,
+///   rebuilt, partially recovered or unrecoverable, with what was lost.
+/// - `HttpResponse::BadRequest` if a target is unknown.
+/// - `HttpResponse::InternalServerError` if a file could not be read or written.
+pub async fn rebuild_state(
+    netplan_store: Data<NetplanStore>,
+    query: Query<RebuildStateQuery>,
+) -> impl Responder {
+    let targets = match &query.targets {
+        None => Sidecar::ALL.to_vec(),
+        Some(targets) => match targets.split(',').map(str::parse).collect() {
+            Ok(targets) => targets,
+            Err(err) => return HttpResponse::BadRequest().body(err),
+        },
+    };
+    let netplan = netplan_store.lock().await;
+    match netplan.rebuild_state(targets).await {
+        Ok(reports) => HttpResponse::Ok().json(reports),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::storage::{StorageBudget, StorageCategory};
+    use crate::test_support::{init_scope, scripted_netplan, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+    use std::collections::BTreeMap;
+
+    #[actix_web::test]
+    async fn reports_the_usage_against_the_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, "network: {version: 2}\n").unwrap();
+        std::fs::write(dir.path().join("01-network-conf.yaml.1.bak"), [0; 100]).unwrap();
+        std::fs::write(dir.path().join("01-network-conf.yaml.meta"), [0; 20]).unwrap();
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner).with_storage_budget(StorageBudget {
+            total: Some(1000),
+            categories: BTreeMap::from([(StorageCategory::Backups, 500)]),
+        });
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        let app = init_scope!("/admin", configure, store);
+
+        let req = test::TestRequest::get().uri("/admin/storage").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "used": 120,
+                "budget": 1000,
+                "categories": [
+                    {"category": "backups", "used": 100, "budget": 500, "policy": "evict_oldest"},
+                    {"category": "metadata", "used": 20, "budget": null, "policy": "reject"},
+                ],
+                "evictions": [],
+                "last_reconciliation": null,
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn reports_the_usage_of_the_in_memory_structures() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, "network: {version: 2}\n").unwrap();
+        for timestamp in 1..=3 {
+            std::fs::write(
+                dir.path()
+                    .join(format!("01-network-conf.yaml.{timestamp}.bak")),
+                [0; 100],
+            )
+            .unwrap();
+        }
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner).with_storage_budget(StorageBudget {
+            total: None,
+            categories: BTreeMap::from([(StorageCategory::Backups, 150)]),
+        });
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        // Going over the budget evicts the two oldest backups
+        store.lock().await.reconcile_storage().await.unwrap();
+        let app = init_scope!("/admin", configure, store);
+
+        let req = test::TestRequest::get()
+            .uri("/admin/resource-usage")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let usage: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            usage,
+            serde_json::json!([
+                {"name": "dhcp_probes", "count": 0, "high_water_mark": 0, "cap": null},
+                {"name": "storage_evictions", "count": 2, "high_water_mark": 2, "cap": 20},
+            ])
+        );
+    }
+
+    #[actix_web::test]
+    async fn rebuilds_the_requested_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, "network: {version: 2}\n").unwrap();
+        std::fs::write(dir.path().join("01-network-conf.yaml.pending"), "{[").unwrap();
+        let netplan = scripted_netplan(&config_path, &ScriptedRunner::default());
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        let app = init_scope!("/admin", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/rebuild-state?targets=pending-actions,provenance")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::post()
+            .uri("/admin/rebuild-state")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let reports: serde_json::Value = test::read_body_json(resp).await;
+        let set_aside = dir.path().join("01-network-conf.yaml.pending.corrupt");
+        assert_eq!(
+            reports,
+            serde_json::json!([
+                {"target": "metadata", "outcome": "intact", "recovered": 0, "lost": [], "set_aside": null},
+                {
+                    "target": "pending-actions",
+                    "outcome": "unrecoverable",
+                    "recovered": 0,
+                    "lost": ["the whole file"],
+                    "set_aside": set_aside.to_string_lossy(),
+                },
+            ])
+        );
+    }
+}
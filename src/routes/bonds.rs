@@ -0,0 +1,525 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        bond::Bond,
+        bond_parameters::BondParameters,
+        device::{validate_interface_name, Device},
+        input_models::{
+            ApplyQuery, CascadeQuery, ForceQuery, InputBond, InputBondPatch, InputMember,
+        },
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_bonds,
+    get_bond,
+    create_bond,
+    update_bond,
+    delete_bond,
+    update_bond_parameters,
+    add_bond_member,
+    delete_bond_member,
+))]
+/// API documentation for bond management.
+pub struct BondsApi;
+
+/// Configures the Actix web service with the bond endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_bonds)
+            .service(get_bond)
+            .service(create_bond)
+            .service(update_bond)
+            .service(delete_bond)
+            .service(update_bond_parameters)
+            .service(add_bond_member)
+            .service(delete_bond_member);
+    }
+}
+
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(bond: &Bond) -> Option<HttpResponse> {
+    if bond.get_ipv6_address_generation().is_some() && bond.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            bond.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-bonds")]
+#[get("")]
+/// Retrieves all bond entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the bond entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_bonds(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_bonds())
+}
+
+#[api_path(operation_id = "show-bond")]
+#[get("/{bond_name}")]
+/// Retrieves a specific bond entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `bond_name`: The name of the bond entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the bond entry if found, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_bond(
+    netplan_store: Data<NetplanStore>,
+    bond_name: Path<String>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_bonds().get(&bond_name) {
+        Some(bond) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(bond),
+        None => HttpResponse::NotFound().body(format!("Bond {bond_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-bond")]
+#[post("/{bond_name}")]
+/// Creates a new bond entry, aggregating the given Ethernets.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bond_name`: The name of the bond interface to be created.
+/// - `bond`: The member Ethernets of the bond, along with its settings.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created bond entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, or an IPv6 address generation
+///   is set while router advertisements are not accepted.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured Ethernet, is already a member of
+///   another device, or has addresses or DHCP of its own, listing every offending member and its
+///   conflicting settings.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_bond(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bond_name: Path<String>,
+    bond: Json<InputBond>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    if let Err(err) = validate_interface_name(&bond_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let bond = Bond::from_input_bond(&bond_name, &bond);
+    if let Some(rejection) = check_ipv6_address_generation(&bond) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&bond_name) {
+        return HttpResponse::Conflict().body(format!("{bond_name} is already configured."));
+    }
+    if let Err(err) = network.validate_bond_members(&bond_name, &bond.interfaces()) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    network.add_bond(&bond);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(bond);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bond>(&network, &bond_name) {
+            Ok(bond) => HttpResponse::Created().json(bond),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-bond")]
+#[patch("/{bond_name}")]
+/// Updates an existing bond entry.
+///
+/// The member Ethernets are only changed if present in the body, and are then replaced as a
+/// whole, the primary one being unset if left out. The other settings are replaced the same way
+/// as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bond_name`: The name of the bond entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bond entry if successful.
+/// - `HttpResponse::BadRequest` if an IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured Ethernet, is already a member of
+///   another device, or has addresses or DHCP of its own, listing every offending member and its
+///   conflicting settings.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_bond(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bond_name: Path<String>,
+    patch: Json<InputBondPatch>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bond) = network.get_bonds().get(&bond_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bond {bond_name} was not found."));
+    };
+    if let Some(interfaces) = &patch.interfaces {
+        if let Err(err) = network.validate_bond_members(&bond_name, interfaces) {
+            return HttpResponse::UnprocessableEntity().body(err);
+        }
+        bond.set_interfaces(interfaces.clone());
+    }
+    patch.device.update(&mut bond);
+    if let Some(rejection) = check_ipv6_address_generation(&bond) {
+        return rejection;
+    }
+    network.add_bond(&bond);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bond);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bond>(&network, &bond_name) {
+            Ok(bond) => HttpResponse::Ok().json(bond),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-bond")]
+#[delete("/{bond_name}")]
+/// Removes a bond entry from the network configuration, releasing its member Ethernets.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `bond_name`: The name of the bond entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the bond entry was removed.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_bond(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    bond_name: Path<String>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_bond(&bond_name).is_none() {
+        return HttpResponse::NotFound().body(format!("Bond {bond_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &bond_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[api_path(operation_id = "update-bond-parameters")]
+#[patch("/{bond_name}/parameters")]
+/// Updates the aggregation and monitoring parameters of a bond.
+///
+/// The parameters present in the body are overlaid on the current ones. Parameters never set are
+/// left out of the configuration, so netplan's defaults apply.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bond_name`: The name of the bond entry whose parameters will be updated.
+/// - `parameters`: The parameters to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bond entry if successful.
+/// - `HttpResponse::BadRequest` if the resulting parameters are inconsistent, e.g. a LACP rate is set
+///   outside of 802.3ad mode.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if the primary interface is not a member of the bond.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_bond_parameters(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bond_name: Path<String>,
+    parameters: Json<BondParameters>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bond) = network.get_bonds().get(&bond_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bond {bond_name} was not found."));
+    };
+    if let Some(primary) = &parameters.primary {
+        if !bond.interfaces().contains(primary) {
+            return HttpResponse::UnprocessableEntity().body(format!(
+                "The primary interface {primary} is not a member of {bond_name}."
+            ));
+        }
+    }
+    let mut merged = bond.parameters();
+    merged.merge(&parameters);
+    if let Err(err) = merged.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    bond.set_parameters(merged);
+    network.add_bond(&bond);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bond);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bond>(&network, &bond_name) {
+            Ok(bond) => HttpResponse::Ok().json(bond),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "add-bond-member")]
+#[post("/{bond_name}/interfaces")]
+/// Enslaves an Ethernet to a bond, without resending the whole bond.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bond_name`: The name of the bond entry the Ethernet will be added to.
+/// - `member`: The Ethernet to be added.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bond entry if successful.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found.
+/// - `HttpResponse::Conflict` if the Ethernet is already a member of the bond, in which case nothing is applied.
+/// - `HttpResponse::UnprocessableEntity` if the interface is not a configured Ethernet, is already a member of
+///   another device, or has addresses or DHCP of its own.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_bond_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bond_name: Path<String>,
+    member: Json<InputMember>,
+) -> impl Responder {
+    let bond_name = bond_name.into_inner();
+    let member = member.into_inner().interface;
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bond) = network.get_bonds().get(&bond_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bond {bond_name} was not found."));
+    };
+    let mut interfaces = bond.interfaces();
+    if interfaces.contains(&member) {
+        return HttpResponse::Conflict()
+            .body(format!("{member} is already a member of {bond_name}."));
+    }
+    if let Err(err) = network.validate_bond_members(&bond_name, std::slice::from_ref(&member)) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    interfaces.push(member);
+    bond.set_interfaces(interfaces);
+    network.add_bond(&bond);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bond);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bond>(&network, &bond_name) {
+            Ok(bond) => HttpResponse::Ok().json(bond),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-bond-member")]
+#[delete("/{bond_name}/interfaces/{member}")]
+/// Releases an Ethernet from a bond.
+///
+/// Removing the primary member is refused unless forced, in which case the primary is unset.
+/// Removing the last member is allowed, although the bond then carries no traffic.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, the primary member may be removed.
+/// - `bond_name`: The name of the bond entry the Ethernet will be removed from.
+/// - `member`: The name of the Ethernet to be removed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bond entry if successful, with a `Warning`
+///   header if the bond has no member left.
+/// - `HttpResponse::NotFound` if the specified bond entry is not found, or the Ethernet is not a member of it.
+/// - `HttpResponse::Conflict` if the Ethernet is the primary member of the bond and `force` is not set.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_bond_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    query: Query<ForceQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (bond_name, member) = path.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bond) = network.get_bonds().get(&bond_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bond {bond_name} was not found."));
+    };
+    let mut interfaces = bond.interfaces();
+    if !interfaces.contains(&member) {
+        return HttpResponse::NotFound().body(format!("{member} is not a member of {bond_name}."));
+    }
+    if bond.parameters().primary.as_ref() == Some(&member) && !query.force {
+        return HttpResponse::Conflict().body(format!(
+            "{member} is the primary member of {bond_name}. \
+            Use force=true to remove it and unset the primary anyway."
+        ));
+    }
+    interfaces.retain(|interface| *interface != member);
+    bond.set_interfaces(interfaces);
+    network.add_bond(&bond);
+    let mut response = HttpResponse::Ok();
+    if bond.interfaces().is_empty() {
+        response.insert_header((
+            header::WARNING,
+            format!("199 - \"{bond_name} has no member left, so it carries no traffic.\""),
+        ));
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return response.json(bond);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bond>(&network, &bond_name) {
+            Ok(bond) => response.json(bond),
+            Err(err) => err,
+        },
+    }
+}
@@ -0,0 +1,582 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        bridge::Bridge,
+        bridge_parameters::BridgeParameters,
+        device::{validate_interface_name, Device},
+        input_models::{ApplyQuery, CascadeQuery, InputBridge, InputBridgePatch, InputMember},
+        openvswitch::OpenVSwitch,
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_bridges,
+    get_bridge,
+    create_bridge,
+    update_bridge,
+    delete_bridge,
+    update_bridge_parameters,
+    update_bridge_openvswitch,
+    add_bridge_member,
+    delete_bridge_member,
+))]
+/// API documentation for bridge management.
+pub struct BridgesApi;
+
+/// Configures the Actix web service with the bridge endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_bridges)
+            .service(get_bridge)
+            .service(create_bridge)
+            .service(update_bridge)
+            .service(delete_bridge)
+            .service(update_bridge_parameters)
+            .service(update_bridge_openvswitch)
+            .service(add_bridge_member)
+            .service(delete_bridge_member);
+    }
+}
+
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(bridge: &Bridge) -> Option<HttpResponse> {
+    if bridge.get_ipv6_address_generation().is_some() && bridge.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            bridge.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-bridges")]
+#[get("")]
+/// Retrieves all bridge entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the bridge entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_bridges(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_bridges())
+}
+
+#[api_path(operation_id = "show-bridge")]
+#[get("/{bridge_name}")]
+/// Retrieves a specific bridge entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `bridge_name`: The name of the bridge entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the bridge entry if found, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_bridge(
+    netplan_store: Data<NetplanStore>,
+    bridge_name: Path<String>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_bridges().get(&bridge_name) {
+        Some(bridge) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(bridge),
+        None => HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-bridge")]
+#[post("/{bridge_name}")]
+/// Creates a new bridge entry, enslaving the given interfaces to it.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge interface to be created.
+/// - `bridge`: The member interfaces of the bridge, along with its settings.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created bridge entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, or an IPv6 address generation
+///   is set while router advertisements are not accepted.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured Ethernet, VLAN or bond, or is already a
+///   member of another device, listing every offending member.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_bridge(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bridge_name: Path<String>,
+    bridge: Json<InputBridge>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    if let Err(err) = validate_interface_name(&bridge_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let bridge = Bridge::from_input_bridge(&bridge_name, &bridge);
+    if let Some(rejection) = check_ipv6_address_generation(&bridge) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&bridge_name) {
+        return HttpResponse::Conflict().body(format!("{bridge_name} is already configured."));
+    }
+    if let Err(err) = network.validate_members(&bridge_name, &bridge.interfaces()) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    network.add_bridge(&bridge);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => HttpResponse::Created().json(bridge),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-bridge")]
+#[patch("/{bridge_name}")]
+/// Updates an existing bridge entry.
+///
+/// The member interfaces are only changed if present in the body, and are then replaced as a
+/// whole. The other settings are replaced the same way as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bridge entry if successful.
+/// - `HttpResponse::BadRequest` if an IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured Ethernet, VLAN or bond, or is already a
+///   member of another device, listing every offending member.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_bridge(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bridge_name: Path<String>,
+    patch: Json<InputBridgePatch>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bridge) = network.get_bridges().get(&bridge_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    };
+    if let Some(interfaces) = &patch.interfaces {
+        if let Err(err) = network.validate_members(&bridge_name, interfaces) {
+            return HttpResponse::UnprocessableEntity().body(err);
+        }
+        bridge.set_interfaces(interfaces.clone());
+    }
+    patch.device.update(&mut bridge);
+    if let Some(rejection) = check_ipv6_address_generation(&bridge) {
+        return rejection;
+    }
+    network.add_bridge(&bridge);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => HttpResponse::Ok().json(bridge),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-bridge")]
+#[delete("/{bridge_name}")]
+/// Removes a bridge entry from the network configuration, releasing its member interfaces.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `bridge_name`: The name of the bridge entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the bridge entry was removed.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_bridge(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    bridge_name: Path<String>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_bridge(&bridge_name).is_none() {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &bridge_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[api_path(operation_id = "update-bridge-parameters")]
+#[patch("/{bridge_name}/parameters")]
+/// Updates the spanning tree parameters of a bridge.
+///
+/// The parameters present in the body are overlaid on the current ones, and path costs are merged
+/// per port. Parameters never set are left out of the configuration, so netplan's defaults apply.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge entry whose parameters will be updated.
+/// - `parameters`: The parameters to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bridge entry if successful.
+/// - `HttpResponse::BadRequest` if a parameter is out of range, e.g. the priority is above 65535.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if a path cost is set for an interface which is not a member of the bridge.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_bridge_parameters(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bridge_name: Path<String>,
+    parameters: Json<BridgeParameters>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    if let Err(err) = parameters.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bridge) = network.get_bridges().get(&bridge_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    };
+    let mut ports: Vec<String> = parameters
+        .path_cost
+        .keys()
+        .filter(|port| !bridge.interfaces().contains(port))
+        .cloned()
+        .collect();
+    if !ports.is_empty() {
+        ports.sort();
+        return HttpResponse::UnprocessableEntity().body(format!(
+            "Path costs can only be set for the members of {bridge_name}, unlike {}.",
+            ports.join(", ")
+        ));
+    }
+    let mut merged = bridge.parameters();
+    merged.merge(&parameters);
+    bridge.set_parameters(merged);
+    network.add_bridge(&bridge);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => HttpResponse::Ok().json(bridge),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-bridge-openvswitch")]
+#[patch("/{bridge_name}/openvswitch")]
+/// Updates the Open vSwitch settings of a bridge.
+///
+/// The settings present in the body are overlaid on the current ones, and an empty `protocols` or
+/// `controller.addresses` list removes the setting. Once any setting is present, netplan creates
+/// the bridge through Open vSwitch. The configuration is saved even if Open vSwitch is not
+/// installed, but applying it then fails with netplan's error message.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge entry whose Open vSwitch settings will be updated.
+/// - `openvswitch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bridge entry if successful.
+/// - `HttpResponse::BadRequest` if a controller address does not start with a known connection method.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration,
+///   e.g. with netplan's error as body when Open vSwitch is not installed.
+pub async fn update_bridge_openvswitch(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bridge_name: Path<String>,
+    openvswitch: Json<OpenVSwitch>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    if let Err(err) = openvswitch.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bridge) = network.get_bridges().get(&bridge_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    };
+    let mut merged = bridge.openvswitch().unwrap_or_default();
+    merged.merge(&openvswitch);
+    bridge.set_openvswitch(merged);
+    network.add_bridge(&bridge);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => HttpResponse::Ok().json(bridge),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "add-bridge-member")]
+#[post("/{bridge_name}/interfaces")]
+/// Enslaves an interface to a bridge, without resending the whole bridge.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge entry the interface will be added to.
+/// - `member`: The interface to be added.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bridge entry if successful.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found.
+/// - `HttpResponse::Conflict` if the interface is already a member of the bridge, in which case nothing is applied.
+/// - `HttpResponse::UnprocessableEntity` if the interface is not a configured Ethernet, VLAN or bond, or is already
+///   a member of another device.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_bridge_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    bridge_name: Path<String>,
+    member: Json<InputMember>,
+) -> impl Responder {
+    let bridge_name = bridge_name.into_inner();
+    let member = member.into_inner().interface;
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bridge) = network.get_bridges().get(&bridge_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    };
+    let mut interfaces = bridge.interfaces();
+    if interfaces.contains(&member) {
+        return HttpResponse::Conflict()
+            .body(format!("{member} is already a member of {bridge_name}."));
+    }
+    if let Err(err) = network.validate_members(&bridge_name, std::slice::from_ref(&member)) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    interfaces.push(member);
+    bridge.set_interfaces(interfaces);
+    network.add_bridge(&bridge);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => HttpResponse::Ok().json(bridge),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-bridge-member")]
+#[delete("/{bridge_name}/interfaces/{member}")]
+/// Releases an interface from a bridge.
+///
+/// Removing the last member is allowed, although the bridge then carries no traffic.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `bridge_name`: The name of the bridge entry the interface will be removed from.
+/// - `member`: The name of the interface to be removed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated bridge entry if successful.
+///   If the bridge has no member left, a `Warning` header is included.
+/// - `HttpResponse::NotFound` if the specified bridge entry is not found, or the interface is not one of its members.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_bridge_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (bridge_name, member) = path.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut bridge) = network.get_bridges().get(&bridge_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Bridge {bridge_name} was not found."));
+    };
+    let mut interfaces = bridge.interfaces();
+    if !interfaces.contains(&member) {
+        return HttpResponse::NotFound()
+            .body(format!("{member} is not a member of {bridge_name}."));
+    }
+    interfaces.retain(|interface| *interface != member);
+    bridge.set_interfaces(interfaces);
+    network.add_bridge(&bridge);
+    let mut response = HttpResponse::Ok();
+    if bridge.interfaces().is_empty() {
+        response.insert_header((
+            header::WARNING,
+            format!("199 - \"{bridge_name} has no member left, so it carries no traffic.\""),
+        ));
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return response.json(bridge);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Bridge>(&network, &bridge_name) {
+            Ok(bridge) => response.json(bridge),
+            Err(err) => err,
+        },
+    }
+}
@@ -0,0 +1,230 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    web::{Data, Path},
+    HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::capture::{CaptureStatus, CaptureStore},
+    netplan::NetplanError,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_capture, delete_capture,))]
+/// API documentation for the packet captures, started with `capture-ethernet`.
+pub struct CapturesApi;
+
+/// Configures the Actix web service with the endpoints of the packet captures.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<CaptureStore>` instance that holds the packet captures.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<CaptureStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_capture)
+            .service(delete_capture);
+    }
+}
+
+/// Rejects the requests on captures while they are disabled on this server.
+pub(crate) fn reject_disabled(store: &CaptureStore) -> Option<HttpResponse> {
+    (!store.is_enabled())
+        .then(|| HttpResponse::Forbidden().body("Packet capture is disabled on this server."))
+}
+
+#[api_path(operation_id = "get-capture")]
+#[get("/{capture_id}")]
+/// Retrieves a packet capture, and downloads it once finished.
+///
+/// While the capture runs, or if it failed, its status is returned. Once finished, the pcap file
+/// is returned instead, until it is deleted at the end of the retention window.
+///
+/// # Arguments
+/// - `store`: A `Data<CaptureStore>` instance that holds the packet captures.
+/// - `capture_id`: The ID of the capture, as returned by `capture-ethernet`.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the status of the capture while it runs or if it
+///   failed, or with the pcap file as an attachment once it finished.
+/// - `HttpResponse::Forbidden` if packet capture is disabled on this server.
+/// - `HttpResponse::NotFound` if there is no such capture, or if it was deleted.
+/// - `HttpResponse::InternalServerError` if the file of the capture could not be read.
+pub async fn get_capture(store: Data<CaptureStore>, capture_id: Path<String>) -> impl Responder {
+    if let Some(rejection) = reject_disabled(&store) {
+        return rejection;
+    }
+    let Some(capture) = store.get(&capture_id) else {
+        return HttpResponse::NotFound().body(format!("Capture {capture_id} was not found."));
+    };
+    if capture.status != CaptureStatus::Finished {
+        return HttpResponse::Ok().json(capture);
+    }
+    match store.read_file(&capture.id) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("application/vnd.tcpdump.pcap")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}-{}.pcap\"",
+                    capture.interface, capture.id
+                ),
+            ))
+            .body(content),
+        Err(err) => NetplanError::from(err).error_response(),
+    }
+}
+
+#[api_path(operation_id = "delete-capture")]
+#[delete("/{capture_id}")]
+/// Cancels a running packet capture, or deletes a finished one.
+///
+/// The file of the capture is deleted in both cases.
+///
+/// # Arguments
+/// - `store`: A `Data<CaptureStore>` instance that holds the packet captures.
+/// - `capture_id`: The ID of the capture, as returned by `capture-ethernet`.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the capture was cancelled or deleted.
+/// - `HttpResponse::Forbidden` if packet capture is disabled on this server.
+/// - `HttpResponse::NotFound` if there is no such capture.
+pub async fn delete_capture(store: Data<CaptureStore>, capture_id: Path<String>) -> impl Responder {
+    if let Some(rejection) = reject_disabled(&store) {
+        return rejection;
+    }
+    if store.cancel(&capture_id) {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body(format!("Capture {capture_id} was not found."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        routes::ethernet,
+        test_support::{mock_store, ScriptedRunner},
+    };
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use utoipa_actix_web::AppExt;
+
+    /// A test service capturing into `spool` with tcpdump standing in as scripted by `runner`, like
+    /// `main` serves it: started from the ethernets, followed from the captures.
+    macro_rules! init_captures {
+        ($spool:expr, $runner:expr, $enabled:expr) => {{
+            let store = Data::new(
+                CaptureStore::new($spool.path().to_path_buf())
+                    .with_runner(Box::new($runner.clone()))
+                    .with_enabled($enabled),
+            );
+            test::init_service(
+                App::new()
+                    .into_utoipa_app()
+                    .map(|app| app.app_data(store.clone()))
+                    .service(
+                        utoipa_actix_web::scope("/ethernets")
+                            .configure(ethernet::configure(mock_store(&["lo"]))),
+                    )
+                    .service(utoipa_actix_web::scope("/captures").configure(configure(store)))
+                    .into_app(),
+            )
+            .await
+        }};
+    }
+
+    #[actix_web::test]
+    async fn captures_are_refused_while_disabled() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default();
+        let app = init_captures!(spool, runner, false);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/lo/capture")
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let req = test::TestRequest::get().uri("/captures/any").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(runner.calls().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn a_capture_is_started_followed_and_cancelled() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("tcpdump", Duration::from_secs(30), "");
+        let app = init_captures!(spool, runner, true);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/lo/capture")
+            .set_json(json!({"duration": 10, "filter": {"protocol": "udp", "port": 53}}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let location = resp.headers().get(header::LOCATION).unwrap().clone();
+        let capture: Value = test::read_body_json(resp).await;
+        assert_eq!(capture["status"], json!("running"));
+        assert_eq!(
+            location.to_str().unwrap(),
+            format!("/captures/{}", capture["id"].as_str().unwrap())
+        );
+        assert!(runner.calls()[0].ends_with("udp and port 53"));
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/lo/capture")
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::get()
+            .uri(location.to_str().unwrap())
+            .to_request();
+        let status: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(status["id"], capture["id"]);
+
+        let req = test::TestRequest::delete()
+            .uri(location.to_str().unwrap())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[actix_web::test]
+    async fn captures_out_of_bounds_are_rejected() {
+        let spool = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default();
+        let app = init_captures!(spool, runner, true);
+
+        for body in [
+            json!({"duration": 3600}),
+            json!({"filter": {"protocol": "icmp", "port": 53}}),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/ethernets/lo/capture")
+                .set_json(&body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{body}");
+        }
+        let req = test::TestRequest::post()
+            .uri("/ethernets/does-not-exist/capture")
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(runner.calls().is_empty());
+    }
+}
@@ -0,0 +1,754 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
+
+use actix_web::{
+    http::header::{self, HeaderValue},
+    HttpRequest, HttpResponse, ResponseError,
+};
+use serde::Serialize;
+
+use crate::{
+    custom_types::IpNetwork,
+    models::{
+        address_conflict::{find_address_conflicts, AddressConflict},
+        annotated_address::annotate_addresses,
+        device::Device,
+        input_models::InputRoutePatch,
+        nameservers::Nameservers,
+        network::{DeviceMap, Network},
+        reachability::{reachable_via, AnnotatedNameservers, Reachability},
+        route::Route,
+    },
+    netplan::{Mutation, NetplanSession, NetplanStore},
+};
+
+/// Checks that no other device references the interface `name`, which is being removed from
+/// `network`.
+///
+/// With `cascade`, the references are removed from `network` instead, see
+/// `Network::remove_dependents`. Otherwise, a 409 response listing the dependents is returned.
+pub(crate) fn check_dependents(
+    network: &mut Network,
+    name: &str,
+    cascade: bool,
+) -> Option<HttpResponse> {
+    let dependents = network.find_dependents(name);
+    if dependents.is_empty() {
+        return None;
+    }
+    if cascade {
+        network.remove_dependents(name);
+        return None;
+    }
+    let dependents: Vec<String> = dependents
+        .iter()
+        .map(|(kind, dependent)| format!("{kind} {dependent}"))
+        .collect();
+    Some(HttpResponse::Conflict().body(format!(
+        "{name} is still used by {}. Use cascade=true to remove the VLANs and tunnels built on it, \
+        and release it from its bridges, bonds and VRFs.",
+        dependents.join(", ")
+    )))
+}
+
+/// Rejects L3 configuration (addresses or DHCP) on the interface `name` if it is enslaved to a
+/// bridge or bond, which owns it instead.
+pub(crate) fn reject_enslaved(network: &Network, name: &str) -> Option<HttpResponse> {
+    let master = network.enslavement_of(name)?;
+    Some(HttpResponse::UnprocessableEntity().body(format!(
+        "{name} is a member of {} {}, which owns its L3 configuration. \
+        Configure addresses and DHCP on {} instead.",
+        master.kind, master.name, master.name
+    )))
+}
+
+/// Checks the `If-Match` header of a request against the current configuration.
+///
+/// Requests without the header are always allowed. Otherwise, a 412 response is returned
+/// unless one of the listed entity tags, or `*`, matches the current one.
+pub(crate) async fn check_if_match(
+    req: &HttpRequest,
+    netplan: &NetplanSession,
+) -> Option<HttpResponse> {
+    let if_match = req.headers().get(header::IF_MATCH)?;
+    match netplan.config_etag().await {
+        Ok(current) => precondition(if_match, current, "configuration"),
+        Err(err) => Some(err.error_response()),
+    }
+}
+
+/// Checks the `If-Match` header of a request against the current revision of the metadata, like
+/// `check_if_match` does against the configuration.
+pub(crate) async fn check_metadata_if_match(
+    req: &HttpRequest,
+    netplan: &NetplanSession,
+) -> Option<HttpResponse> {
+    let if_match = req.headers().get(header::IF_MATCH)?;
+    match netplan.metadata_revision().await {
+        Ok(current) => precondition(if_match, current, "metadata"),
+        Err(err) => Some(err.error_response()),
+    }
+}
+
+/// A 412 response naming `current`, unless one of the entity tags listed in `if_match`, or `*`,
+/// matches it.
+fn precondition(if_match: &HeaderValue, current: String, what: &str) -> Option<HttpResponse> {
+    let matches = if_match
+        .to_str()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .any(|etag| etag == "*" || etag == current);
+    if matches {
+        None
+    } else {
+        Some(
+            HttpResponse::PreconditionFailed()
+                .insert_header((header::ETAG, current))
+                .body(format!("The {what} was changed since it was read.")),
+        )
+    }
+}
+
+/// Parses addresses in CIDR notation, reporting every invalid one, one per line.
+pub(crate) fn parse_addresses(addresses: &[String]) -> Result<HashSet<IpNetwork>, HttpResponse> {
+    let mut result = HashSet::new();
+    let mut errors = vec![];
+    for address in addresses.iter() {
+        match address.parse::<IpNetwork>() {
+            Ok(address) => {
+                result.insert(address);
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(HttpResponse::BadRequest().body(errors.join("\n")));
+    }
+    Ok(result)
+}
+
+/// Parses nameserver addresses, reporting every invalid one, one per line.
+pub(crate) fn parse_nameserver_addresses(
+    addresses: &[String],
+) -> Result<Vec<IpAddr>, HttpResponse> {
+    let mut result = vec![];
+    let mut errors = vec![];
+    for address in addresses.iter() {
+        match address.parse::<IpAddr>() {
+            Ok(address) => result.push(address),
+            Err(err) => errors.push(format!("Invalid nameserver '{address}': {err}")),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(HttpResponse::BadRequest().body(errors.join("\n")));
+    }
+    Ok(result)
+}
+
+pub(crate) fn not_found<D>(name: &str) -> HttpResponse
+where
+    Network: DeviceMap<D>,
+{
+    HttpResponse::NotFound().body(format!(
+        "{} {name} was not found.",
+        <Network as DeviceMap<D>>::KIND
+    ))
+}
+
+/// The device `name` as committed in `network`.
+///
+/// The file may have been edited behind the back of the API in the meantime, so a 404 response is
+/// returned if the device is gone.
+pub(crate) fn committed<D>(network: &Network, name: &str) -> Result<D, HttpResponse>
+where
+    D: Clone,
+    Network: DeviceMap<D>,
+{
+    network
+        .get_devices()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| not_found::<D>(name))
+}
+
+/// Loads the configuration, and the device `name` from it.
+async fn load_device<D>(
+    netplan_store: &NetplanStore,
+    name: &str,
+) -> Result<(Network, D), HttpResponse>
+where
+    D: Clone,
+    Network: DeviceMap<D>,
+{
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return Err(err.error_response()),
+        Ok(network) => network,
+    };
+    match network.get_devices().get(name).cloned() {
+        Some(device) => Ok((network, device)),
+        None => Err(not_found::<D>(name)),
+    }
+}
+
+/// Modifies the device `name` with `modify`, then saves the configuration, and applies it unless
+/// `apply` is false.
+///
+/// `default` stands for the device when it is not configured yet; without it, a 404 response is
+/// returned. The configuration is left untouched if `modify` fails, or doesn't change anything.
+/// Returns the device as committed.
+async fn modify_device<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    default: Option<D>,
+    modify: impl FnOnce(&Network, &mut D) -> Result<(), HttpResponse>,
+) -> Result<D, HttpResponse>
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(req, &netplan).await {
+        return Err(rejection);
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return Err(err.error_response()),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut device) = network.get_devices().get(name).cloned().or(default) else {
+        return Err(not_found::<D>(name));
+    };
+    modify(&network, &mut device)?;
+    network.add_device(&device);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return Ok(device);
+    };
+    let network = netplan
+        .commit(mutation, apply)
+        .await
+        .map_err(|err| err.error_response())?;
+    committed(&network, name)
+}
+
+fn json_or_error<T: Serialize>(result: Result<T, HttpResponse>) -> HttpResponse {
+    match result {
+        Ok(value) => HttpResponse::Ok().json(value),
+        Err(err) => err,
+    }
+}
+
+fn no_content_or_error<T>(result: Result<T, HttpResponse>) -> HttpResponse {
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(err) => err,
+    }
+}
+
+/// Lists the static addresses of the device, or all its addresses annotated with their source.
+pub(crate) async fn get_addresses<D>(
+    netplan_store: &NetplanStore,
+    name: &str,
+    detailed: bool,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    match load_device::<D>(netplan_store, name).await {
+        Ok((_, device)) if detailed => HttpResponse::Ok().json(annotate_addresses(&device)),
+        Ok((_, device)) => HttpResponse::Ok().json(device.get_addresses()),
+        Err(err) => err,
+    }
+}
+
+/// Lists the dynamic addresses of the device, as reported by `netplan status`.
+pub(crate) async fn get_dynamic_addresses<D>(
+    netplan_store: &NetplanStore,
+    name: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        load_device::<D>(netplan_store, name)
+            .await
+            .map(|(_, device)| device.get_dynamic_addresses()),
+    )
+}
+
+/// Adds a static address to the device, unless it conflicts with other interfaces and `force` is false.
+pub(crate) async fn add_address<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    force: bool,
+    to_add: IpNetwork,
+    default: Option<D>,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        modify_device(
+            req,
+            netplan_store,
+            apply,
+            name,
+            default,
+            |network, device| {
+                if let Some(rejection) = reject_enslaved(network, name) {
+                    return Err(rejection);
+                }
+                if device.get_addresses().contains(&to_add) {
+                    return Err(HttpResponse::Conflict().body(format!(
+                        "Address {to_add} is already configured on {} {name}.",
+                        <Network as DeviceMap<D>>::KIND
+                    )));
+                }
+                if !force {
+                    let conflicts = find_address_conflicts(network, name, &to_add);
+                    if !conflicts.is_empty() {
+                        return Err(HttpResponse::UnprocessableEntity().json(conflicts));
+                    }
+                }
+                device.add_address(&to_add);
+                Ok(())
+            },
+        )
+        .await,
+    )
+}
+
+/// Replaces the static addresses of the device, unless they conflict with other interfaces and
+/// `force` is false.
+pub(crate) async fn replace_addresses<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    force: bool,
+    addresses: HashSet<IpNetwork>,
+    default: Option<D>,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        modify_device(
+            req,
+            netplan_store,
+            apply,
+            name,
+            default,
+            |network, device| {
+                if !addresses.is_empty() {
+                    if let Some(rejection) = reject_enslaved(network, name) {
+                        return Err(rejection);
+                    }
+                }
+                if !force {
+                    let conflicts: Vec<AddressConflict> = addresses
+                        .iter()
+                        .flat_map(|address| find_address_conflicts(network, name, address))
+                        .collect();
+                    if !conflicts.is_empty() {
+                        return Err(HttpResponse::UnprocessableEntity().json(conflicts));
+                    }
+                }
+                device.set_addresses(addresses);
+                Ok(())
+            },
+        )
+        .await,
+    )
+}
+
+/// Removes a static address from the device.
+pub(crate) async fn delete_address<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    to_delete: IpNetwork,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.delete_address(&to_delete);
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Returns the nameservers of the device, each address annotated with how it can be reached.
+pub(crate) async fn get_nameservers<D>(netplan_store: &NetplanStore, name: &str) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        load_device::<D>(netplan_store, name)
+            .await
+            .map(|(network, device)| {
+                AnnotatedNameservers::new(&network, name, &device.get_nameservers())
+            }),
+    )
+}
+
+/// Adds a search domain to the nameservers of the device.
+pub(crate) async fn add_nameservers_search<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    search: &str,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    let result = modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+        if device.get_nameservers().contains_search(search) {
+            return Err(HttpResponse::Conflict().body(format!(
+                "Search domain {search} is already configured on {} {name}.",
+                <Network as DeviceMap<D>>::KIND
+            )));
+        }
+        device.add_nameservers_search(search);
+        Ok(())
+    })
+    .await;
+    match result {
+        Ok(device) => HttpResponse::Created().json(device),
+        Err(err) => err,
+    }
+}
+
+/// Replaces the search domains and/or the addresses of the nameservers of the device.
+///
+/// Whatever is `None` is kept as it is.
+pub(crate) async fn replace_nameservers<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    search: Option<Vec<String>>,
+    addresses: Option<Vec<IpAddr>>,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            let current = device.get_nameservers();
+            let mut result = Nameservers::new();
+            match &search {
+                Some(search) => search.iter().for_each(|search| result.add_search(search)),
+                None => result.search = current.search,
+            }
+            match &addresses {
+                Some(addresses) => addresses
+                    .iter()
+                    .for_each(|address| result.add_address(address)),
+                None => result.addresses = current.addresses,
+            }
+            device.add_nameservers(result);
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Removes a search domain from the nameservers of the device.
+pub(crate) async fn delete_nameservers_search<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    search: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.delete_nameservers_search(search);
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Adds an address to the nameservers of the device.
+///
+/// A `Warning` header is included if no configured subnet or route covers the address.
+pub(crate) async fn add_nameservers_address<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    address: IpAddr,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    let mut unreachable = false;
+    let result = modify_device::<D>(req, netplan_store, apply, name, None, |network, device| {
+        if device.get_nameservers().contains_address(&address) {
+            return Err(HttpResponse::Conflict().body(format!(
+                "Nameserver {address} is already configured on {} {name}.",
+                <Network as DeviceMap<D>>::KIND
+            )));
+        }
+        device.add_nameservers_address(&address);
+        unreachable = reachable_via(network, name, &address) == Reachability::NoKnownRoute;
+        Ok(())
+    })
+    .await;
+    let device = match result {
+        Ok(device) => device,
+        Err(err) => return err,
+    };
+    let mut response = HttpResponse::Ok();
+    if unreachable {
+        response.insert_header((
+            header::WARNING,
+            format!(
+                "199 - \"Nameserver {address} is not covered by any configured subnet or route. \
+                It may still be reachable through routes learned via DHCP.\""
+            ),
+        ));
+    }
+    response.json(device)
+}
+
+/// Removes an address from the nameservers of the device.
+pub(crate) async fn delete_nameservers_address<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    address: IpAddr,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.delete_nameservers_address(&address);
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Removes all the nameservers of the device, both search domains and addresses.
+pub(crate) async fn delete_nameservers<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.clear_nameservers();
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Returns the routes of the device, by id.
+pub(crate) async fn get_routes<D>(netplan_store: &NetplanStore, name: &str) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    json_or_error(
+        load_device::<D>(netplan_store, name)
+            .await
+            .map(|(_, device)| device.get_routes()),
+    )
+}
+
+/// Returns the route `route_id` of the device.
+pub(crate) async fn get_route<D>(
+    netplan_store: &NetplanStore,
+    name: &str,
+    route_id: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    let device = match load_device::<D>(netplan_store, name).await {
+        Ok((_, device)) => device,
+        Err(err) => return err,
+    };
+    match device
+        .get_routes()
+        .into_values()
+        .find(|route| route.id() == route_id)
+    {
+        Some(route) => HttpResponse::Ok().json(route),
+        None => HttpResponse::NotFound().body(format!(
+            "Route {route_id} was not found on {} {name}.",
+            <Network as DeviceMap<D>>::KIND
+        )),
+    }
+}
+
+/// Adds a route to the device. A route with the same id is replaced only if `replace` is true,
+/// otherwise it is returned with a 409 response.
+///
+/// A `Warning` header is included if the device is bound to a VRF whose table the route escapes.
+pub(crate) async fn add_route<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    replace: bool,
+    route: Route,
+) -> HttpResponse
+where
+    D: Device + Clone + Serialize,
+    Network: DeviceMap<D>,
+{
+    let mut warning = None;
+    let result = modify_device::<D>(req, netplan_store, apply, name, None, |network, device| {
+        if !replace {
+            if let Some(existing) = device.get_routes().get(&route.id()) {
+                return Err(HttpResponse::Conflict().json(existing));
+            }
+        }
+        device.add_route(&route);
+        warning = network.check_vrf_table(name, &route);
+        Ok(())
+    })
+    .await;
+    let device = match result {
+        Ok(device) => device,
+        Err(err) => return err,
+    };
+    let mut response = HttpResponse::Ok();
+    if let Some(warning) = warning {
+        response.insert_header((header::WARNING, format!("199 - \"{warning}\"")));
+    }
+    response.json(device)
+}
+
+/// Updates the given fields of the route `route_id` of the device, which is re-keyed if its id changes.
+///
+/// Returns the updated route, keyed by its new id, with a `Warning` header if the device is bound
+/// to a VRF whose table the route escapes.
+pub(crate) async fn update_route<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    route_id: &str,
+    patch: &InputRoutePatch,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    let mut result: Option<Route> = None;
+    let mut warning = None;
+    let modified = modify_device::<D>(req, netplan_store, apply, name, None, |network, device| {
+        let routes = device.get_routes();
+        let Some((old_id, route)) = routes.iter().find(|(_, route)| route.id() == route_id) else {
+            return Err(HttpResponse::NotFound().body(format!(
+                "Route {route_id} was not found on {} {name}.",
+                <Network as DeviceMap<D>>::KIND
+            )));
+        };
+        let updated = route
+            .patched(patch)
+            .map_err(|err| HttpResponse::BadRequest().body(err))?;
+        let new_id = updated.id();
+        if new_id != route_id {
+            if let Some(existing) = routes.values().find(|route| route.id() == new_id) {
+                return Err(HttpResponse::Conflict().json(existing));
+            }
+        }
+        device.delete_route(old_id);
+        device.add_route(&updated);
+        warning = network.check_vrf_table(name, &updated);
+        result = Some(updated);
+        Ok(())
+    })
+    .await;
+    if let Err(err) = modified {
+        return err;
+    }
+    let updated = result.expect("The route is updated whenever the device is");
+    let mut response = HttpResponse::Ok();
+    if let Some(warning) = warning {
+        response.insert_header((header::WARNING, format!("199 - \"{warning}\"")));
+    }
+    response.json(HashMap::from([(updated.id(), updated)]))
+}
+
+/// Removes the route `route_id` from the device.
+pub(crate) async fn delete_route<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+    route_id: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.delete_route(route_id);
+            Ok(())
+        })
+        .await,
+    )
+}
+
+/// Removes all the routes of the device.
+pub(crate) async fn delete_routes<D>(
+    req: &HttpRequest,
+    netplan_store: &NetplanStore,
+    apply: bool,
+    name: &str,
+) -> HttpResponse
+where
+    D: Device + Clone,
+    Network: DeviceMap<D>,
+{
+    no_content_or_error(
+        modify_device::<D>(req, netplan_store, apply, name, None, |_, device| {
+            device.delete_all_routes();
+            Ok(())
+        })
+        .await,
+    )
+}
@@ -0,0 +1,88 @@
+use actix_web::{
+    get,
+    http::header,
+    web::{Data, Query},
+    HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        device::Device,
+        device_listing::list_devices,
+        input_models::{DeviceScopeQuery, EthernetScope},
+    },
+    netplan::NetplanStore,
+    system::System,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_all_devices,))]
+/// API documentation for the listing of devices of every kind.
+pub struct DevicesApi;
+
+/// Configures the Actix web service with the device listing endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store).service(get_all_devices);
+    }
+}
+
+#[api_path(operation_id = "show-all-devices", params(DeviceScopeQuery))]
+#[get("")]
+/// Retrieves the devices of every kind in one list.
+///
+/// The devices are grouped by kind, Ethernets first, and sorted by name within each group. Each
+/// one comes with a summary of its static addresses, DHCP flags, and the bridge, bond or VRF it
+/// belongs to. With `scope=all`, the physical interfaces of the system which are not configured,
+/// neither under their own name nor through `set-name`, are appended with the `unmanaged` kind.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `scope`: A `Query<DeviceScopeQuery>` instance that specifies the scope of the listing.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the listed devices, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::BadRequest` if a query parameter has an unknown value.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration or listing
+///   the interfaces of the system.
+pub async fn get_all_devices(
+    netplan_store: Data<NetplanStore>,
+    scope: Query<DeviceScopeQuery>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    let mut unmanaged = vec![];
+    if scope.scope == EthernetScope::All {
+        unmanaged = match System::physical_interfaces() {
+            Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+            Ok(interfaces) => interfaces,
+        };
+        let renamed: Vec<String> = network
+            .get_ethernets()
+            .values()
+            .filter_map(|ethernet| ethernet.get_set_name())
+            .collect();
+        unmanaged.retain(|name| !network.is_configured(name) && !renamed.contains(name));
+    }
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(list_devices(&network, &unmanaged))
+}
@@ -0,0 +1,293 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        device::{validate_interface_name, Device},
+        dummy_device::DummyDevice,
+        input_models::{ApplyQuery, CascadeQuery, InputDevice},
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_dummy_devices,
+    get_dummy_device,
+    create_dummy_device,
+    update_dummy_device,
+    delete_dummy_device,
+))]
+/// API documentation for dummy device management.
+pub struct DummyDevicesApi;
+
+/// Configures the Actix web service with the dummy device endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_dummy_devices)
+            .service(get_dummy_device)
+            .service(create_dummy_device)
+            .service(update_dummy_device)
+            .service(delete_dummy_device);
+    }
+}
+
+/// Rejects DHCP on a dummy device, which has no link to obtain a lease on.
+fn check_no_dhcp(dummy: &DummyDevice) -> Option<HttpResponse> {
+    if dummy.get_dhcp4() || dummy.get_dhcp6() {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "{} is a dummy device, which has no link to obtain a DHCP lease on. \
+            Configure its addresses statically instead.",
+            dummy.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-dummy-devices")]
+#[get("")]
+/// Retrieves all dummy device entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the dummy device entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_dummy_devices(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_dummy_devices())
+}
+
+#[api_path(operation_id = "show-dummy-device")]
+#[get("/{dummy_name}")]
+/// Retrieves a specific dummy device entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `dummy_name`: The name of the dummy device entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the dummy device entry if found, and an `ETag`
+///   header identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified dummy device entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_dummy_device(
+    netplan_store: Data<NetplanStore>,
+    dummy_name: Path<String>,
+) -> impl Responder {
+    let dummy_name = dummy_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_dummy_devices().get(&dummy_name) {
+        Some(dummy) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(dummy),
+        None => HttpResponse::NotFound().body(format!("Dummy device {dummy_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-dummy-device")]
+#[post("/{dummy_name}")]
+/// Creates a new dummy device entry, e.g. to hold anycast service addresses.
+///
+/// Unlike Ethernets, dummy devices only exist in the system once applied, so any valid interface
+/// name can be created.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `dummy_name`: The name of the dummy interface to be created.
+/// - `dummy`: The settings of the dummy device, mainly its addresses and routes.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created dummy device entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, or DHCP is enabled.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_dummy_device(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    dummy_name: Path<String>,
+    dummy: Json<InputDevice>,
+) -> impl Responder {
+    let dummy_name = dummy_name.into_inner();
+    if let Err(err) = validate_interface_name(&dummy_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let dummy = DummyDevice::from_input_device(&dummy_name, &dummy);
+    if let Some(rejection) = check_no_dhcp(&dummy) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&dummy_name) {
+        return HttpResponse::Conflict().body(format!("{dummy_name} is already configured."));
+    }
+    network.add_dummy_device(&dummy);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(dummy);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<DummyDevice>(&network, &dummy_name) {
+            Ok(dummy) => HttpResponse::Created().json(dummy),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-dummy-device")]
+#[patch("/{dummy_name}")]
+/// Updates an existing dummy device entry.
+///
+/// The settings are replaced the same way as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `dummy_name`: The name of the dummy device entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated dummy device entry if successful.
+/// - `HttpResponse::BadRequest` if DHCP is enabled.
+/// - `HttpResponse::NotFound` if the specified dummy device entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_dummy_device(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    dummy_name: Path<String>,
+    patch: Json<InputDevice>,
+) -> impl Responder {
+    let dummy_name = dummy_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut dummy) = network.get_dummy_devices().get(&dummy_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Dummy device {dummy_name} was not found."));
+    };
+    patch.update(&mut dummy);
+    if let Some(rejection) = check_no_dhcp(&dummy) {
+        return rejection;
+    }
+    network.add_dummy_device(&dummy);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(dummy);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<DummyDevice>(&network, &dummy_name) {
+            Ok(dummy) => HttpResponse::Ok().json(dummy),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-dummy-device")]
+#[delete("/{dummy_name}")]
+/// Removes a dummy device entry from the network configuration.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `dummy_name`: The name of the dummy device entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the dummy device entry was removed.
+/// - `HttpResponse::NotFound` if the specified dummy device entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_dummy_device(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    dummy_name: Path<String>,
+) -> impl Responder {
+    let dummy_name = dummy_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_dummy_device(&dummy_name).is_none() {
+        return HttpResponse::NotFound().body(format!("Dummy device {dummy_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &dummy_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
@@ -1,18 +1,47 @@
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
 
 use crate::{
+    custom_types::IpNetwork,
+    misc::yaml_mapping_to_json_compatible,
     models::{
-        device::Device,
-        ethernet::Ethernet,
-        input_models::{InputDevice, InputRoute, ScopeQuery},
-        route::Route,
+        address_conflict::{find_address_conflicts, AddressConflict},
+        capture::{CaptureError, CaptureSettings, CaptureStore},
+        change_set::{ChangeSet, EthernetChanges, PendingChanges},
+        device::{validate_interface_name, validate_virtual_function_count, Device},
+        ethernet::{validate_labels, Ethernet},
+        input_models::{
+            ApplyQuery, CascadeQuery, DetailedQuery, DryRunQuery, EthernetScope, EthernetSpec,
+            EthernetView, ForceQuery, GatewayQuery, InputCapture, InputDevice, InputGateway,
+            InputLink, InputNameserversReplacement, InputRename, InputReset, InputRoute,
+            InputRoutePatch, InputRoutingPolicy, LabelQuery, LinkState, ReplaceQuery, ScopeQuery,
+            TryQuery,
+        },
+        interface_state::InterfaceState,
+        loopback::{is_loopback, list_loopback_interfaces, validate_loopback_address},
+        network::Network,
+        route::{AddressFamily, Gateways, Route},
+        routing_policy::RoutingPolicy,
+    },
+    netplan::{
+        restore_tried, Mutation, NetplanError, NetplanSession, NetplanStore, PendingTry, Snapshot,
     },
-    netplan::NetplanStore,
+    routes::{
+        captures,
+        device::{
+            self, check_dependents, check_if_match, check_metadata_if_match, reject_enslaved,
+        },
+    },
+    system::System,
 };
 use actix_web::{
-    delete, get, patch, post,
-    web::{Data, Json, Query},
-    HttpResponse, Responder,
+    delete, get,
+    http::header,
+    patch, post, put,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
 };
 use utoipa::{path as api_path, OpenApi};
 use utoipa_actix_web::service_config::ServiceConfig;
@@ -20,20 +49,47 @@ use utoipa_actix_web::service_config::ServiceConfig;
 #[derive(OpenApi)]
 #[openapi(paths(
     get_all_ethernets,
+    apply_ethernets,
+    get_pending_ethernets,
     update_ethernet,
+    update_ethernets,
+    try_ethernet,
+    replace_ethernet,
+    reset_ethernet,
+    rename_ethernet,
+    delete_ethernet,
     get_ethernet,
     get_ethernet_ip_addresses,
+    get_ethernet_dynamic_addresses,
     add_ethernet_ip_address,
+    replace_ethernet_ip_addresses,
+    get_ethernet_labels,
+    replace_ethernet_labels,
     delete_ethernet_ip_address,
     get_ethernet_nameservers,
     add_ethernet_nameservers_search,
+    replace_ethernet_nameservers,
     delete_ethernet_nameservers_search,
     add_ethernet_nameservers_address,
     delete_ethernet_nameservers_address,
+    delete_ethernet_nameservers,
     get_ethernet_routes,
+    get_ethernet_route,
     add_ethernet_route,
+    update_ethernet_route,
     delete_ethernet_route,
     delete_ethernet_routes,
+    get_ethernet_gateway,
+    set_ethernet_gateway,
+    delete_ethernet_gateway,
+    get_ethernet_routing_policy,
+    add_ethernet_routing_policy,
+    delete_ethernet_routing_policy,
+    probe_ethernet_dhcp_server,
+    capture_ethernet,
+    get_ethernet_state,
+    get_ethernet_diff,
+    set_ethernet_link,
 ))]
 /// API documentation for Ethernet management.
 ///
@@ -43,6 +99,33 @@ use utoipa_actix_web::service_config::ServiceConfig;
 /// accept_ra, nameservers, and routes.
 pub struct EthernetsApi;
 
+/// Rejects configuration operations on loopback interfaces.
+///
+/// Loopback interfaces only allow extra host (VIP) addresses to be added or removed;
+/// every other operation on them returns a 422 response.
+fn reject_loopback(ethernet_name: &str) -> Option<HttpResponse> {
+    if is_loopback(ethernet_name) {
+        Some(HttpResponse::UnprocessableEntity().body(format!(
+            "Ethernet {ethernet_name} is a loopback interface. \
+            Only extra host addresses can be configured on it."
+        )))
+    } else {
+        None
+    }
+}
+
+/// Whether the update enables DHCP, which an enslaved interface cannot have.
+fn enables_dhcp(update: &InputDevice) -> bool {
+    update.dhcp4 == Some(true) || update.dhcp6 == Some(true)
+}
+
+/// Returns a copy of the Ethernet as shown in responses, along with the device it is enslaved to.
+fn with_enslavement(network: &Network, ethernet: &Ethernet) -> Ethernet {
+    let mut result = ethernet.clone();
+    result.set_enslaved_to(network.enslavement_of(&ethernet.name()));
+    result
+}
+
 /// Configures the service with the provided NetplanStore.
 ///
 /// This function sets up the service configuration by adding the necessary
@@ -62,62 +145,288 @@ pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
     |config: &mut ServiceConfig| {
         config
             .app_data(store)
+            // Registered before `/{ethernet_name}`, which would shadow them
+            .service(apply_ethernets)
+            .service(get_pending_ethernets)
             .service(add_ethernet_ip_address)
+            .service(replace_ethernet_ip_addresses)
+            .service(get_ethernet_labels)
+            .service(replace_ethernet_labels)
             .service(add_ethernet_nameservers_address)
             .service(add_ethernet_nameservers_search)
+            .service(replace_ethernet_nameservers)
             .service(update_ethernet)
+            .service(update_ethernets)
+            .service(try_ethernet)
+            .service(replace_ethernet)
+            .service(reset_ethernet)
+            .service(rename_ethernet)
+            .service(delete_ethernet)
             .service(delete_ethernet_ip_address)
             .service(delete_ethernet_nameservers_address)
+            .service(delete_ethernet_nameservers)
             .service(delete_ethernet_nameservers_search)
             .service(get_ethernet)
             .service(get_ethernet_ip_addresses)
+            .service(get_ethernet_dynamic_addresses)
             .service(get_ethernet_nameservers)
             .service(get_ethernet_routes)
-            .service(get_all_ethernets);
+            .service(get_ethernet_route)
+            .service(add_ethernet_route)
+            .service(update_ethernet_route)
+            .service(delete_ethernet_route)
+            .service(delete_ethernet_routes)
+            .service(get_ethernet_gateway)
+            .service(set_ethernet_gateway)
+            .service(delete_ethernet_gateway)
+            .service(get_ethernet_routing_policy)
+            .service(add_ethernet_routing_policy)
+            .service(delete_ethernet_routing_policy)
+            .service(get_all_ethernets)
+            .service(probe_ethernet_dhcp_server)
+            .service(capture_ethernet)
+            .service(get_ethernet_state)
+            .service(get_ethernet_diff)
+            .service(set_ethernet_link);
+    }
+}
+
+/// Names of the interfaces within `scope` which are not part of the configuration.
+///
+/// With `EthernetScope::All`, these are the interfaces present in the system, except the ones
+/// configured under their own name or renamed to it through `set-name`.
+async fn unconfigured_interfaces(
+    netplan: &NetplanSession,
+    network: &Network,
+    scope: EthernetScope,
+    include_loopback: bool,
+) -> Result<Vec<String>, HttpResponse> {
+    if scope == EthernetScope::Configured {
+        return Ok(vec![]);
+    }
+    let system_interfaces = netplan
+        .list_system_interfaces()
+        .await
+        .map_err(|err| err.error_response())?;
+    Ok(unconfigured_among(
+        system_interfaces,
+        network,
+        scope,
+        include_loopback,
+    ))
+}
+
+/// Like `unconfigured_interfaces`, for the interfaces of the system already listed.
+fn unconfigured_among(
+    mut all_ethernets: Vec<String>,
+    network: &Network,
+    scope: EthernetScope,
+    include_loopback: bool,
+) -> Vec<String> {
+    if scope == EthernetScope::Configured {
+        return vec![];
+    }
+    let renamed: Vec<String> = network
+        .get_ethernets()
+        .values()
+        .filter_map(|ethernet| ethernet.get_set_name())
+        .collect();
+    if include_loopback {
+        all_ethernets.extend(list_loopback_interfaces());
+    }
+    all_ethernets
+        .retain(|eth| !network.get_ethernets().contains_key(eth) && !renamed.contains(eth));
+    all_ethernets
+}
+
+/// The Ethernet entries listed for `query`, by name, along with the `unconfigured` interfaces.
+fn listed_ethernets(
+    network: &Network,
+    unconfigured: Vec<String>,
+    query: &ScopeQuery,
+) -> HashMap<String, Ethernet> {
+    // Renamed interfaces are listed under the name they are given
+    let mut ethernets: HashMap<String, Ethernet> = network
+        .get_ethernets()
+        .iter()
+        .map(|(name, ethernet)| {
+            (
+                ethernet.get_set_name().unwrap_or(name.clone()),
+                with_enslavement(network, ethernet),
+            )
+        })
+        .collect();
+    for eth in unconfigured {
+        ethernets.insert(eth.clone(), Ethernet::new(eth));
+    }
+    if !query.include_loopback {
+        ethernets.retain(|eth, _| !is_loopback(eth));
+    }
+    ethernets.retain(|_, ethernet| {
+        query
+            .dhcp4
+            .is_none_or(|dhcp4| ethernet.get_dhcp4() == dhcp4)
+            && query
+                .dhcp6
+                .is_none_or(|dhcp6| ethernet.get_dhcp6() == dhcp6)
+            && query
+                .has_addresses
+                .is_none_or(|has_addresses| ethernet.get_addresses().is_empty() != has_addresses)
+            && query
+                .label
+                .as_ref()
+                .is_none_or(|label| ethernet.labels().contains(label))
+    });
+    ethernets
+}
+
+/// The summaries of the Ethernet entries of `snapshot` listed for `query`, sorted by name.
+fn summary_response(snapshot: &Snapshot, query: &ScopeQuery) -> HttpResponse {
+    let unconfigured = unconfigured_among(
+        snapshot.system_interfaces.clone(),
+        &snapshot.network,
+        query.scope,
+        query.include_loopback,
+    );
+    let ethernets = listed_ethernets(&snapshot.network, unconfigured, query);
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, snapshot.etag.clone()))
+        .json(snapshot.network.ethernet_summaries(ethernets.values()))
+}
+
+/// Checks the settings of an `InputDevice` given for an Ethernet which can be checked on their own.
+fn check_input_device(input: &InputDevice) -> Result<(), String> {
+    if let Some(Some(set_name)) = &input.set_name {
+        validate_interface_name(set_name)?;
+    }
+    if let Some(Some(count)) = input.virtual_function_count {
+        validate_virtual_function_count(count)?;
+    }
+    if let Some(labels) = &input.labels {
+        validate_labels(labels)?;
+    }
+    if let Some(Some(required_for_online)) = input.required_for_online {
+        required_for_online.validate_setting()?;
     }
+    Ok(())
 }
 
-#[api_path(operation_id = "show-all-ethernets")]
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(ethernet: &Ethernet) -> Option<HttpResponse> {
+    if ethernet.get_ipv6_address_generation().is_some() && ethernet.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            ethernet.name()
+        )));
+    }
+    None
+}
+
+/// Merges `input` into the configured entry of `name`, or builds a new entry from it. Absent
+/// settings are kept, and the labels and `required-for-online` are only replaced when given.
+fn merged_ethernet(network: &Network, name: &str, input: &InputDevice) -> Ethernet {
+    let Some(network_ethernet) = network.get_ethernets().get(name) else {
+        return Ethernet::from_input_device(name, input);
+    };
+    let mut updated = network_ethernet.clone();
+    input.update(&mut updated);
+    if let Some(labels) = &input.labels {
+        updated.set_labels(labels.clone());
+    }
+    if let Some(required_for_online) = input.required_for_online {
+        updated.set_required_for_online(required_for_online);
+    }
+    updated
+}
+
+/// Responds with the Ethernet entry `name` of the network resulting from a change, or with the
+/// error of netplan.
+fn respond_with_network(result: Result<Network, NetplanError>, name: &str) -> HttpResponse {
+    match result {
+        Err(err) => err.error_response(),
+        // The file may have been edited behind the back of the API in the meantime
+        Ok(network) => match network.get_ethernets().get(name) {
+            Some(ethernet) => HttpResponse::Ok().json(ethernet),
+            None => device::not_found::<Ethernet>(name),
+        },
+    }
+}
+
+#[api_path(operation_id = "show-all-ethernets", params(ScopeQuery))]
 #[get("")]
 /// Retrieves all Ethernet entries.
 ///
 /// This function loads the network configuration using Netplan and retrieves all Ethernet entries.
 /// If the `scope` query parameter is set to "all", it includes all Ethernet entries from the Netplan store,
-/// even those not currently in the network configuration. The resulting list of Ethernet entries is returned
-/// as a JSON response. If there is an error loading the configuration or retrieving the Ethernet entries,
+/// even those not currently in the network configuration. Without it, only the configured entries are listed,
+/// and unknown values are rejected. Loopback interfaces are excluded unless the
+/// `include_loopback` query parameter is set. The resulting list of Ethernet entries is returned
+/// as a JSON response, or as a list of summaries sorted by name if the `view` query parameter is
+/// set to "summary". The `dhcp4`, `dhcp6`, `has_addresses` and `label` query parameters filter the entries,
+/// all of them having to match. If there is an error loading the configuration or retrieving the Ethernet entries,
 /// an appropriate HTTP response is returned.
 ///
+/// Summaries are computed from the configuration as last loaded, without waiting for other requests, as long as
+/// nothing was changed through the API since. If the configuration can't be loaded, they are computed from the
+/// last one loaded, if any.
+///
 /// # Arguments
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
 /// - `scope`: A `Query<ScopeQuery>` instance that specifies the scope of the query.
 ///
 /// # Returns
-/// - `HttpResponse::Ok` with a JSON body containing the Ethernet entries if successful.
+/// - `HttpResponse::Ok` with a JSON body containing the Ethernet entries if successful, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::BadRequest` if a query parameter has an unknown value.
 /// - `HttpResponse::InternalServerError` if there is an issue loading the configuration or retrieving the Ethernet entries.
 pub async fn get_all_ethernets(
     netplan_store: Data<NetplanStore>,
     scope: Query<ScopeQuery>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    if scope.scope == "all" {
-        match netplan.get_all_ethernets() {
-            Ok(all_ethernets) => {
-                all_ethernets
-                    .iter()
-                    .filter(|&eth| !network.get_ethernets().contains_key(eth))
-                    .for_each(|eth| {
-                        ethernets.insert(eth.clone(), Ethernet::new(eth.clone()));
-                    });
+    let summary = scope.view == EthernetView::Summary;
+    // Summaries are served from the last snapshot while nothing changed, without waiting for the
+    // backend nor running netplan
+    if summary {
+        if let Some(snapshot) = netplan_store.snapshot() {
+            return summary_response(&snapshot, &scope);
+        }
+    }
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => {
+            if let Some(snapshot) = netplan_store.last_known_good().filter(|_| summary) {
+                log::warn!("Serving the summaries of the last known configuration: {err}");
+                return summary_response(&snapshot, &scope);
             }
-            Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+            return err.error_response();
         }
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    let unconfigured = match unconfigured_interfaces(
+        &netplan,
+        &network,
+        scope.scope,
+        scope.include_loopback,
+    )
+    .await
+    {
+        Ok(unconfigured) => unconfigured,
+        Err(err) => return err,
+    };
+    let ethernets = listed_ethernets(&network, unconfigured, &scope);
+    if summary {
+        return HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(network.ethernet_summaries(ethernets.values()));
     }
-    HttpResponse::Ok().json(ethernets)
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(ethernets)
 }
 
 #[api_path(operation_id = "update-ethernet")]
@@ -129,35 +438,52 @@ pub async fn get_all_ethernets(
 /// an appropriate HTTP response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the new Ethernet entry to be created.
 ///
 /// # Returns
 /// - `HttpResponse::Ok` with a JSON body containing the created Ethernet entry if successful.
+///   If a DHCP identifier is set while DHCP is disabled, a `Warning` header is included, as well as
+///   if the applied virtual function count is not realized by the interface yet.
+/// - `HttpResponse::BadRequest` if the body is invalid, e.g. it contains a malformed MAC address, `set-name` or label,
+///   or an out of range virtual function count, or if an IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::UnprocessableEntity` if DHCP is enabled on an interface enslaved to a bridge or bond,
+///   which owns its L3 configuration.
 /// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn update_ethernet(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
     ethernet: Json<InputDevice>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    if let Err(err) = check_input_device(&ethernet) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
-    let mut present_ethernets = network.get_ethernets().clone();
-    match netplan.get_all_ethernets() {
-        Ok(all_ethernets) => {
-            all_ethernets
-                .iter()
-                .filter(|&eth| !network.get_ethernets().contains_key(eth))
-                .for_each(|eth| {
-                    present_ethernets.insert(eth.clone(), Ethernet::new(eth.clone()));
-                });
-        }
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-    }
-    if !present_ethernets.contains_key(&ethernet_name) {
+    let original = network.clone();
+    let unconfigured =
+        match unconfigured_interfaces(&netplan, &network, EthernetScope::All, false).await {
+            Ok(unconfigured) => unconfigured,
+            Err(err) => return err,
+        };
+    if !network.get_ethernets().contains_key(&ethernet_name)
+        && !unconfigured.contains(&ethernet_name)
+    {
         return HttpResponse::NotFound().body(format!(
             "Ethernet '{}' not found. \
             Please make sure that the interface exists in the system.",
@@ -165,21 +491,670 @@ pub async fn update_ethernet(
         ));
     }
 
-    let new_ethernet = Ethernet::from_input_device(&ethernet_name, &ethernet.into_inner());
-    let result = if let Some(network_ethernet) = network.get_ethernets().get(&ethernet_name) {
-        let mut updated = network_ethernet.clone();
-        updated.update_from_device(&new_ethernet);
-        updated
-    } else {
-        new_ethernet
-    };
+    if enables_dhcp(&ethernet) {
+        if let Some(rejection) = reject_enslaved(&network, &ethernet_name) {
+            return rejection;
+        }
+    }
+    let result = merged_ethernet(&network, &ethernet_name, &ethernet);
+    if let Some(rejection) = check_ipv6_address_generation(&result) {
+        return rejection;
+    }
     network.add_ethernet(&result);
-    match netplan.save_and_apply(&network) {
-        Err(err) => err,
+    let mut response = HttpResponse::Ok();
+    if result.get_dhcp_identifier().is_some() && !result.get_dhcp4() && !result.get_dhcp6() {
+        response.insert_header((
+            header::WARNING,
+            format!(
+                "199 - \"The DHCP identifier of {ethernet_name} has no effect while DHCP is disabled.\""
+            ),
+        ));
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return response.json(with_enslavement(&original, &result));
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
         Ok(network) => {
-            HttpResponse::Ok().json(network.get_ethernets().get(&ethernet_name).unwrap())
+            let Some(ethernet) = network.get_ethernets().get(&ethernet_name) else {
+                return device::not_found::<Ethernet>(&ethernet_name);
+            };
+            // The driver may need a reload before the virtual functions show up
+            if let Some(count) = ethernet.get_virtual_function_count() {
+                let interface = ethernet.get_set_name().unwrap_or(ethernet_name.clone());
+                let current = System::virtual_function_count(&interface).unwrap_or(0);
+                if apply && current != count {
+                    response.insert_header((
+                        header::WARNING,
+                        format!(
+                            "199 - \"{interface} has {current} virtual functions instead of {count} yet. \
+                            Reloading the driver or rebooting may be needed.\""
+                        ),
+                    ));
+                }
+            }
+            response.json(with_enslavement(&network, ethernet))
+        }
+    }
+}
+
+#[api_path(
+    operation_id = "update-ethernets",
+    params(LabelQuery),
+    request_body(
+        content = HashMap<String, InputDevice>,
+        description = "The settings to be merged, by name of the Ethernet entry. With `label`, \
+            the settings to be merged into every labeled entry, as a single `InputDevice`."
+    )
+)]
+#[patch("")]
+/// Updates several Ethernet entries at once.
+///
+/// This function merges the given settings into each named Ethernet entry like `update-ethernet`,
+/// and saves and applies all the changes at once. Nothing is modified unless every update is valid.
+/// With the `label` query parameter, the body holds one set of settings, merged into every
+/// configured Ethernet entry with that label.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `group`: A `Query<LabelQuery>` instance that selects the labeled Ethernet entries to update, if any.
+/// - `ethernets`: The settings to be merged, by name of the Ethernet entry, or for the whole group.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entries, by name, if successful.
+/// - `HttpResponse::BadRequest` if the body doesn't match the `label` query parameter, or any of the updates
+///   is invalid, e.g. it contains a malformed `set-name` or label, if an IPv6 address generation is set while
+///   router advertisements are not accepted, or if a `set-name` is given to a whole group.
+/// - `HttpResponse::NotFound` listing every interface that exists neither in the configuration nor in the system,
+///   or if no Ethernet entry has the given label.
+/// - `HttpResponse::UnprocessableEntity` if DHCP is enabled on an interface enslaved to a bridge or bond,
+///   or if any of the updated interfaces, named or labeled, is a loopback.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn update_ethernets(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    group: Query<LabelQuery>,
+    ethernets: Json<serde_yml::Value>,
+) -> impl Responder {
+    // The shape of the body depends on whether a group is updated
+    let (group_settings, ethernets) = match &group.label {
+        Some(label) => {
+            let settings: InputDevice = match serde_yml::from_value(ethernets.into_inner()) {
+                Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+                Ok(settings) => settings,
+            };
+            if let Err(err) = check_input_device(&settings) {
+                return HttpResponse::BadRequest().body(format!("{label}: {err}"));
+            }
+            if settings.set_name.is_some() {
+                return HttpResponse::BadRequest().body(format!(
+                    "The Ethernets labeled {label} can't all be given the same set-name."
+                ));
+            }
+            (Some(settings), HashMap::new())
+        }
+        None => match serde_yml::from_value::<HashMap<String, InputDevice>>(ethernets.into_inner())
+        {
+            Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+            Ok(ethernets) => (None, ethernets),
+        },
+    };
+    let mut names: Vec<&String> = ethernets.keys().collect();
+    names.sort();
+    for &name in names.iter() {
+        if let Some(rejection) = reject_loopback(name) {
+            return rejection;
+        }
+        if let Err(err) = check_input_device(&ethernets[name]) {
+            return HttpResponse::BadRequest().body(format!("{name}: {err}"));
+        }
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let names: Vec<String> = match &group.label {
+        Some(label) => {
+            let mut labeled: Vec<String> = network
+                .get_ethernets()
+                .iter()
+                .filter(|(_, ethernet)| ethernet.labels().contains(label))
+                .map(|(name, _)| name.clone())
+                .collect();
+            if labeled.is_empty() {
+                return HttpResponse::NotFound()
+                    .body(format!("No Ethernet entry is labeled {label}."));
+            }
+            labeled.sort();
+            labeled
+        }
+        None => names.into_iter().cloned().collect(),
+    };
+    // A labeled loopback must not be modified along with the rest of its group
+    for name in names.iter() {
+        if let Some(rejection) = reject_loopback(name) {
+            return rejection;
+        }
+    }
+    let settings_of = |name: &str| match &group_settings {
+        Some(settings) => settings,
+        None => &ethernets[name],
+    };
+    let unconfigured =
+        match unconfigured_interfaces(&netplan, &network, EthernetScope::All, false).await {
+            Ok(unconfigured) => unconfigured,
+            Err(err) => return err,
+        };
+    let missing: Vec<&str> = names
+        .iter()
+        .filter(|name| !network.get_ethernets().contains_key(*name) && !unconfigured.contains(name))
+        .map(|name| name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return HttpResponse::NotFound().body(format!(
+            "Ethernets not found: {}. \
+            Please make sure that the interfaces exist in the system.",
+            missing.join(", ")
+        ));
+    }
+
+    let mut results: HashMap<String, Ethernet> = HashMap::new();
+    for name in names.iter() {
+        if enables_dhcp(settings_of(name)) {
+            if let Some(rejection) = reject_enslaved(&network, name) {
+                return rejection;
+            }
+        }
+        let result = merged_ethernet(&network, name, settings_of(name));
+        if result.get_ipv6_address_generation().is_some() && result.get_accept_ra() == Some(false) {
+            return HttpResponse::BadRequest().body(format!(
+                "The IPv6 address generation of {name} only applies to addresses autoconfigured \
+                from router advertisements, which are disabled by 'accept-ra: false'."
+            ));
+        }
+        results.insert(name.clone(), result);
+    }
+    for result in results.values() {
+        network.add_ethernet(result);
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(results);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => HttpResponse::Ok().json(
+            names
+                .iter()
+                .filter_map(|name| {
+                    let ethernet = network.get_ethernets().get(name)?;
+                    Some((name, ethernet))
+                })
+                .collect::<HashMap<_, _>>(),
+        ),
+    }
+}
+
+#[api_path(operation_id = "try-ethernet")]
+#[post("/{ethernet_name}/try")]
+/// Tries an update of an Ethernet entry, reverting it unless confirmed in time.
+///
+/// This function merges the given settings into the Ethernet entry like `update-ethernet`, saves the
+/// resulting configuration and runs `netplan try` over it. The try has to be confirmed with
+/// `POST /netplan/confirm` before the timeout, or the previous configuration is restored.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry to be updated.
+/// - `query`: A `Query<TryQuery>` instance with the timeout of the try, in seconds (120 by default).
+/// - `ethernet`: The settings to be merged into the Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Accepted` with a JSON body containing the tried Ethernet entry if the try was started.
+/// - `HttpResponse::Ok` with a JSON body containing the Ethernet entry if its configuration stays the same,
+///   in which case nothing is tried. Changed labels are saved nonetheless.
+/// - `HttpResponse::BadRequest` if the body is invalid, or if an IPv6 address generation is set while router
+///   advertisements are not accepted.
+/// - `HttpResponse::NotFound` if the interface exists neither in the configuration nor in the system.
+/// - `HttpResponse::Conflict` if another try is already pending, or the interface is defined in another
+///   netplan file, naming that file.
+/// - `HttpResponse::UnprocessableEntity` if DHCP is enabled on an interface enslaved to a bridge or bond.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration, or starting the try.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn try_ethernet(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+    query: Query<TryQuery>,
+    ethernet: Json<InputDevice>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    if let Err(err) = check_input_device(&ethernet) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    if netplan_store
+        .pending_try
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(PendingTry::is_running)
+    {
+        return HttpResponse::Conflict().body("Another netplan try is already pending.");
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let unconfigured =
+        match unconfigured_interfaces(&netplan, &network, EthernetScope::All, false).await {
+            Ok(unconfigured) => unconfigured,
+            Err(err) => return err,
+        };
+    if !network.get_ethernets().contains_key(&ethernet_name)
+        && !unconfigured.contains(&ethernet_name)
+    {
+        return HttpResponse::NotFound().body(format!(
+            "Ethernet '{ethernet_name}' not found. \
+            Please make sure that the interface exists in the system."
+        ));
+    }
+    if enables_dhcp(&ethernet) {
+        if let Some(rejection) = reject_enslaved(&network, &ethernet_name) {
+            return rejection;
+        }
+    }
+    let original = network.clone();
+    let result = merged_ethernet(&network, &ethernet_name, &ethernet);
+    if let Some(rejection) = check_ipv6_address_generation(&result) {
+        return rejection;
+    }
+    network.add_ethernet(&result);
+    // The change is staged like with `apply=false`, and tried instead of applied. There is
+    // nothing to try if the configuration stays the same
+    let mutation = Mutation::new(&original, network);
+    let tried = matches!(mutation, Some(Mutation::Config(_)));
+    if let Some(mutation) = mutation {
+        if let Err(err) = netplan.commit(mutation, false).await {
+            return err.error_response();
+        }
+    }
+    if !tried {
+        return HttpResponse::Ok().json(result);
+    }
+    let timeout = query.timeout;
+    match netplan.run(move |netplan| netplan.tryout(timeout)).await {
+        Ok(started) => {
+            // No other try could start meanwhile, as the session is still open
+            *netplan_store.pending_try.lock().unwrap() = Some(started.with_previous(original));
+            HttpResponse::Accepted().json(result)
+        }
+        Err(err) => {
+            // The staged change was never tried, so it is not left in the file, labels included
+            if let Err(restore_err) = netplan
+                .run(move |netplan| restore_tried(netplan, Some(&original)))
+                .await
+            {
+                log::error!("Could not undo the change of {ethernet_name} which failed to be tried: {restore_err}");
+            }
+            err.error_response()
+        }
+    }
+}
+
+#[api_path(operation_id = "replace-ethernet")]
+#[put("/{ethernet_name}")]
+/// Replaces the whole configuration of an Ethernet entry with the given desired state.
+///
+/// This function compares the desired state with the current configuration of the Ethernet entry,
+/// computes the minimal set of changes between them, and applies it with a single save and apply.
+/// Settings that are absent from the body are unset, and absent collections are treated as empty, removing
+/// everything configured in them. The same checks as for updates apply. If nothing changes, the configuration is not applied at all. With the `dry_run` query parameter,
+/// the changes are only reported. The labels of the entry are kept, as they are not part of its state.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry to be replaced.
+/// - `query`: A `Query<DryRunQuery>` instance that specifies whether the changes should only be reported.
+/// - `force`: A `Query<ForceQuery>` instance; with `force=true`, conflicts with other interfaces are ignored,
+///   and the address this API is served on may be removed.
+/// - `spec`: The complete desired state of the Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the resulting Ethernet entry and the applied changes.
+/// - `HttpResponse::BadRequest` if any setting, address, route or rule of the desired state is invalid.
+/// - `HttpResponse::NotFound` if the interface exists neither in the configuration nor in the system.
+/// - `HttpResponse::Conflict` if the address this API is served on would be removed, or the interface is
+///   defined in another netplan file, naming that file.
+/// - `HttpResponse::UnprocessableEntity` if DHCP or addresses are configured on an interface enslaved to a
+///   bridge or bond, or with a JSON body listing the conflicts if an added address or its subnet is already
+///   configured on other interfaces.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn replace_ethernet(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    query: Query<DryRunQuery>,
+    force: Query<ForceQuery>,
+    spec: Json<EthernetSpec>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let mut desired = match Ethernet::from_spec(&ethernet_name, &spec) {
+        Ok(desired) => desired,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let current = match network.get_ethernets().get(&ethernet_name) {
+        Some(current) => current.clone(),
+        None => match netplan.list_system_interfaces().await {
+            Ok(all_ethernets) if all_ethernets.contains(&ethernet_name) => {
+                Ethernet::new(ethernet_name.clone())
+            }
+            Ok(_) => {
+                return HttpResponse::NotFound().body(format!(
+                    "Ethernet '{ethernet_name}' not found. \
+                    Please make sure that the interface exists in the system."
+                ))
+            }
+            Err(err) => return err.error_response(),
+        },
+    };
+    desired.set_labels(current.labels());
+    if desired.get_dhcp4() || desired.get_dhcp6() || !desired.get_addresses().is_empty() {
+        if let Some(rejection) = reject_enslaved(&network, &ethernet_name) {
+            return rejection;
         }
     }
+    if !force.force {
+        let current_addresses = current.get_addresses();
+        let conflicts: Vec<AddressConflict> = desired
+            .get_addresses()
+            .difference(&current_addresses)
+            .flat_map(|address| find_address_conflicts(&network, &ethernet_name, address))
+            .collect();
+        if !conflicts.is_empty() {
+            return HttpResponse::UnprocessableEntity().json(conflicts);
+        }
+        if let Some(rejection) = reject_lockout(served_address(&req), &current, &desired) {
+            return rejection;
+        }
+    }
+    let changes = ChangeSet::between(&current, &desired);
+    network.add_ethernet(&desired);
+    if let Err(rejection) = original.keep_foreign_devices(&mut network) {
+        return NetplanError::ForeignDevice(rejection).error_response();
+    }
+    if query.dry_run {
+        return HttpResponse::Ok().json(EthernetChanges {
+            ethernet: desired,
+            changes,
+        });
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(EthernetChanges {
+            ethernet: current,
+            changes,
+        });
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match network.get_ethernets().get(&ethernet_name) {
+            Some(ethernet) => HttpResponse::Ok().json(EthernetChanges {
+                ethernet: ethernet.clone(),
+                changes,
+            }),
+            None => device::not_found::<Ethernet>(&ethernet_name),
+        },
+    }
+}
+
+/// Refuses to drop from an Ethernet the static address `served`, which this API is being served
+/// on, as it would lock the client out.
+fn reject_lockout(
+    served: Option<IpAddr>,
+    current: &Ethernet,
+    desired: &Ethernet,
+) -> Option<HttpResponse> {
+    let served = served?;
+    let kept = |ethernet: &Ethernet| {
+        ethernet
+            .get_addresses()
+            .iter()
+            .any(|address| address.ip() == served)
+    };
+    if kept(current) && !kept(desired) {
+        Some(HttpResponse::Conflict().body(format!(
+            "This API is served on {served}, which would be removed from {}. \
+            Use force=true to remove it anyway.",
+            current.name()
+        )))
+    } else {
+        None
+    }
+}
+
+#[api_path(operation_id = "reset-ethernet")]
+#[post("/{ethernet_name}/reset")]
+/// Resets an Ethernet entry to its defaults.
+///
+/// This function replaces the configuration of the Ethernet entry with a pristine one, dropping all its
+/// addresses, routes, nameservers and settings, and applies it. DHCPv4 can optionally be enabled on it.
+/// Its labels are kept.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry to be reset.
+/// - `reset`: Optional settings of the reset Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the reset Ethernet entry if successful.
+/// - `HttpResponse::NotFound` if the Ethernet entry is not part of the configuration.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn reset_ethernet(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    reset: Option<Json<InputReset>>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let reset = reset.map(Json::into_inner).unwrap_or_default();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let Some(labels) = network
+        .get_ethernets()
+        .get(&ethernet_name)
+        .map(Ethernet::labels)
+    else {
+        return HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."));
+    };
+    let original = network.clone();
+    let mut ethernet = Ethernet::new(ethernet_name.clone());
+    ethernet.set_dhcp4(reset.dhcp4);
+    ethernet.set_labels(labels);
+    network.add_ethernet(&ethernet);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(ethernet);
+    };
+    let apply = apply.apply;
+    respond_with_network(netplan.commit(mutation, apply).await, &ethernet_name)
+}
+
+#[api_path(operation_id = "rename-ethernet")]
+#[post("/{ethernet_name}/rename")]
+/// Moves an Ethernet entry to a new name, e.g. when migrating from `eth0` to `mgmt0`.
+///
+/// Every reference to the old name is rewritten along: the `link` of the VLANs and tunnels built on
+/// the Ethernet, and its membership in bridges, bonds and VRFs, including the primary member of a
+/// bond. The whole change is saved and applied at once.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry to be renamed.
+/// - `rename`: The new name of the Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the renamed Ethernet entry if successful.
+/// - `HttpResponse::BadRequest` if the new name is not a valid interface name.
+/// - `HttpResponse::NotFound` if the Ethernet entry is not part of the configuration.
+/// - `HttpResponse::Conflict` if an interface is already configured under the new name.
+/// - `HttpResponse::UnprocessableEntity` if either name is a loopback interface.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn rename_ethernet(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    rename: Json<InputRename>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    let new_name = rename.into_inner().new_name;
+    if let Some(rejection) = reject_loopback(&ethernet_name).or_else(|| reject_loopback(&new_name))
+    {
+        return rejection;
+    }
+    if let Err(err) = validate_interface_name(&new_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if !network.get_ethernets().contains_key(&ethernet_name) {
+        return HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."));
+    }
+    if network.is_configured(&new_name) {
+        return HttpResponse::Conflict().body(format!("{new_name} is already configured."));
+    }
+    network.rename_ethernet(&ethernet_name, &new_name);
+    let renamed = network.get_ethernets()[&new_name].clone();
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(renamed);
+    };
+    let apply = apply.apply;
+    respond_with_network(netplan.commit(mutation, apply).await, &new_name)
+}
+
+#[api_path(operation_id = "delete-ethernet")]
+#[delete("/{ethernet_name}")]
+/// Removes an Ethernet entry from the network configuration.
+///
+/// This function loads the network configuration using Netplan, removes the specified Ethernet entry,
+/// saves the updated configuration, and applies the changes. Interfaces which exist in the system but
+/// are not part of the configuration are left untouched. The removal is refused while VLANs or tunnels
+/// are built on the Ethernet, or it is a member of a bridge, bond or VRF, unless `cascade` is set.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the VLANs and tunnels built on the
+///   Ethernet are removed as well, and the bridges, bonds and VRFs it is a member of release it.
+/// - `ethernet_name`: The name of the Ethernet entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the Ethernet entry was removed, or if the interface is not managed.
+/// - `HttpResponse::NotFound` if the interface is neither configured nor present in the system.
+/// - `HttpResponse::Conflict` if other devices still reference the Ethernet and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::UnprocessableEntity` if the interface is a loopback interface.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_ethernet(&ethernet_name).is_none() {
+        return match netplan.list_system_interfaces().await {
+            Ok(all_ethernets) if all_ethernets.contains(&ethernet_name) => {
+                HttpResponse::NoContent().finish()
+            }
+            Ok(_) => {
+                HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
+            }
+            Err(err) => err.error_response(),
+        };
+    }
+    if let Some(rejection) = check_dependents(&mut network, &ethernet_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
 }
 
 #[api_path(operation_id = "show-ethernet")]
@@ -195,20 +1170,29 @@ pub async fn update_ethernet(
 /// - `ethernet_name`: The name of the Ethernet entry to be retrieved.
 ///
 /// # Returns
-/// - `HttpResponse::Ok` with a JSON body containing the Ethernet entry if found.
+/// - `HttpResponse::Ok` with a JSON body containing the Ethernet entry if found, along with the bridge or bond
+///   it is `enslaved-to`, if any, and an `ETag` header identifying the current configuration, to be sent back
+///   in `If-Match` by updates.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
 /// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
 pub async fn get_ethernet(
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    ethernet_name: Path<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    let ethernet_name = ethernet_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
     if let Some(ethernet) = network.get_ethernets().get(&ethernet_name) {
-        HttpResponse::Ok().json(ethernet)
+        HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(with_enslavement(&network, ethernet))
     } else {
         HttpResponse::NotFound().body(format!(
             "Ethernet {ethernet_name} was not found in the current \
@@ -224,49 +1208,61 @@ pub async fn get_ethernet(
 /// This function parses the provided IP address, loads the network configuration,
 /// and adds the IP address to the specified Ethernet entry. If the Ethernet entry
 /// is found, the IP address is added, and the updated configuration is saved and applied.
-/// If the Ethernet entry is not found, a 404 response is returned.
+/// If the Ethernet entry is not found, a 404 response is returned. Loopback interfaces only accept
+/// extra host addresses, and are added to the configuration if they are not yet part of it.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the Ethernet entry to which the IP address will be added.
-/// - `ip_address`: The IP address to be added to the Ethernet entry.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, conflicts with other interfaces are ignored.
+/// - `ip_address`: The IP address to be added to the Ethernet entry, in CIDR notation (`10.0.0.5/24`, `fd00::1/64`).
 ///
 /// # Returns
 /// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
 /// - `HttpResponse::BadRequest` if the provided IP address is invalid.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::Conflict` if the address is already configured, in which case nothing is applied.
+/// - `HttpResponse::UnprocessableEntity` if the address is not allowed on a loopback interface, or the interface
+///   is enslaved to a bridge or bond, which owns its L3 configuration, or with a JSON body listing the conflicts
+///   if the address or its subnet is already configured on other interfaces.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn add_ethernet_ip_address(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    query: Query<ForceQuery>,
     ip_address: Json<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let to_add = match ip_address.parse::<SocketAddr>() {
-        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    let ethernet_name = ethernet_name.into_inner();
+    let to_add = match ip_address.parse::<IpNetwork>() {
+        Err(err) => return HttpResponse::BadRequest().body(err),
         Ok(ip) => ip,
     };
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.add_address(&to_add);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Err(err) => err,
-            Ok(network) => {
-                HttpResponse::Ok().json(network.get_ethernets().get(&ethernet_name).unwrap())
-            }
+    let loopback = is_loopback(&ethernet_name);
+    if loopback {
+        if let Err(err) = validate_loopback_address(&to_add) {
+            return HttpResponse::UnprocessableEntity().body(err);
         }
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
     }
+    // Loopback interfaces are added to the configuration along with their first extra address
+    let default = loopback.then(|| Ethernet::new(ethernet_name.clone()));
+    device::add_address(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        query.force,
+        to_add,
+        default,
+    )
+    .await
 }
 
-#[api_path(operation_id = "get-ethernet-ip-addresses")]
+#[api_path(operation_id = "get-ethernet-ip-addresses", params(DetailedQuery))]
 #[get("/{ethernet_name}/addresses")]
 /// Retrieves the IP addresses associated with a specific Ethernet entry.
 ///
@@ -277,6 +1273,8 @@ pub async fn add_ethernet_ip_address(
 /// # Arguments
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
 /// - `ethernet_name`: The name of the Ethernet entry whose IP addresses are to be retrieved.
+/// - `query`: A `Query<DetailedQuery>` instance; with `detailed=true`, the dynamic addresses are listed as well,
+///   as objects annotated with their source (`static`, `dhcp4`, `dhcp6`, `ra` or `link-local`).
 ///
 /// # Returns
 /// - `HttpResponse::Ok` with a JSON body containing the IP addresses if the Ethernet entry is found.
@@ -284,66 +1282,233 @@ pub async fn add_ethernet_ip_address(
 /// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
 pub async fn get_ethernet_ip_addresses(
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    ethernet_name: Path<String>,
+    query: Query<DetailedQuery>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(n) => n,
-    };
-    let ethernet = network.get_ethernets().get(&ethernet_name);
-    if let Some(ethernet) = ethernet {
-        HttpResponse::Ok().json(ethernet.get_addresses())
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
-    }
+    device::get_addresses::<Ethernet>(&netplan_store, &ethernet_name, query.detailed).await
 }
 
-#[api_path(operation_id = "delete-ethernet-ip-address")]
-#[delete("/{ethernet_name}/addresses/{ip_address}")]
-/// Deletes an IP address from a specific Ethernet entry.
+#[api_path(operation_id = "get-ethernet-dynamic-addresses")]
+#[get("/{ethernet_name}/addresses/dynamic")]
+/// Retrieves the dynamic IP addresses of a specific Ethernet entry.
 ///
-/// This function parses the provided IP address, loads the network configuration,
-/// and removes the IP address from the specified Ethernet entry. If the Ethernet entry
-/// is found, the IP address is removed, and the updated configuration is saved and applied.
-/// If the Ethernet entry is not found, a 404 response is returned.
+/// These are the addresses obtained through DHCP or router advertisements, as reported by
+/// `netplan status`, annotated with their flags, e.g. `10.0.0.5/24 (dhcp)`.
 ///
 /// # Arguments
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
-/// - `ethernet_name`: The name of the Ethernet entry from which the IP address will be removed.
-/// - `ip_address`: The IP address to be removed from the Ethernet entry.
+/// - `ethernet_name`: The name of the Ethernet entry whose dynamic IP addresses are to be retrieved.
 ///
 /// # Returns
-/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
-/// - `HttpResponse::BadRequest` if the provided IP address is invalid.
-/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
+/// - `HttpResponse::Ok` with a JSON body containing the dynamic IP addresses if the Ethernet entry is found.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
-pub async fn delete_ethernet_ip_address(
+/// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
+pub async fn get_ethernet_dynamic_addresses(
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
-    ip_address: String,
+    ethernet_name: Path<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let to_delete = match ip_address.parse::<SocketAddr>() {
-        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
-        Ok(ip) => ip,
+    device::get_dynamic_addresses::<Ethernet>(&netplan_store, &ethernet_name).await
+}
+
+#[api_path(operation_id = "replace-ethernet-ip-addresses")]
+#[put("/{ethernet_name}/addresses")]
+/// Replaces all the static IP addresses of a specific Ethernet entry.
+///
+/// Every address is parsed before anything is changed, and the new set of addresses is applied
+/// with a single save and apply. An empty list removes all the static addresses.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose IP addresses will be replaced.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, conflicts with other interfaces are ignored.
+/// - `ip_addresses`: The new IP addresses of the Ethernet entry, in CIDR notation.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+/// - `HttpResponse::BadRequest` listing every invalid address, one per line.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if an address is not allowed on a loopback interface, or the interface
+///   is enslaved to a bridge or bond, which owns its L3 configuration, or with a JSON body listing the conflicts
+///   if addresses or their subnets are already configured on other interfaces.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn replace_ethernet_ip_addresses(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    query: Query<ForceQuery>,
+    ip_addresses: Json<Vec<String>>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    let addresses = match device::parse_addresses(&ip_addresses) {
+        Ok(addresses) => addresses,
+        Err(err) => return err,
     };
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    let loopback = is_loopback(&ethernet_name);
+    if loopback {
+        if let Some(err) = addresses
+            .iter()
+            .find_map(|address| validate_loopback_address(address).err())
+        {
+            return HttpResponse::UnprocessableEntity().body(err);
+        }
+    }
+    let default = loopback.then(|| Ethernet::new(ethernet_name.clone()));
+    device::replace_addresses(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        query.force,
+        addresses,
+        default,
+    )
+    .await
+}
+
+#[api_path(operation_id = "get-ethernet-labels")]
+#[get("/{ethernet_name}/labels")]
+/// Retrieves the labels of a specific Ethernet entry.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry whose labels are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the labels of the Ethernet entry, sorted, and an `ETag`
+///   header identifying the current metadata, to be sent back in `If-Match` by `replace-ethernet-labels`.
+/// - `HttpResponse::NotFound` if the Ethernet entry is not configured.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration or its metadata.
+pub async fn get_ethernet_labels(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_stored_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.delete_address(&to_delete);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Err(err) => err,
-            Ok(_) => HttpResponse::NoContent().finish(),
+    let revision = match netplan.metadata_revision().await {
+        Err(err) => return err.error_response(),
+        Ok(revision) => revision,
+    };
+    let Some(ethernet) = network.get_ethernets().get(&ethernet_name) else {
+        return HttpResponse::NotFound().body(format!("Ethernet '{ethernet_name}' not found."));
+    };
+    let mut labels: Vec<String> = ethernet.labels().into_iter().collect();
+    labels.sort();
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, revision))
+        .json(labels)
+}
+
+#[api_path(operation_id = "replace-ethernet-labels")]
+#[put("/{ethernet_name}/labels")]
+/// Replaces the labels of a specific Ethernet entry.
+///
+/// Labels are not part of the netplan configuration, so they are saved next to it without running netplan,
+/// the system being left as is. An empty list removes all the labels. The metadata has its own revision, so
+/// changing the labels leaves the entity tag of the configuration as is.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current metadata.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry whose labels will be replaced.
+/// - `labels`: The new labels of the Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the labels of the Ethernet entry, sorted, and an `ETag`
+///   header identifying the updated metadata.
+/// - `HttpResponse::BadRequest` if a label is invalid.
+/// - `HttpResponse::NotFound` if the Ethernet entry is not configured.
+/// - `HttpResponse::Conflict` if the interface is defined in another netplan file, naming that file.
+/// - `HttpResponse::PreconditionFailed` if the metadata changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
+pub async fn replace_ethernet_labels(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+    labels: Json<HashSet<String>>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Err(err) = validate_labels(&labels) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_metadata_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_stored_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut ethernet) = network.get_ethernets().get(&ethernet_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Ethernet '{ethernet_name}' not found."));
+    };
+    ethernet.set_labels(labels.into_inner());
+    network.add_ethernet(&ethernet);
+    if let Some(mutation) = Mutation::new(&original, network) {
+        if let Err(err) = netplan.commit(mutation, false).await {
+            return err.error_response();
+        }
+    }
+    let revision = match netplan.metadata_revision().await {
+        Err(err) => return err.error_response(),
+        Ok(revision) => revision,
+    };
+    let mut labels: Vec<String> = ethernet.labels().into_iter().collect();
+    labels.sort();
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, revision))
+        .json(labels)
+}
+
+#[api_path(operation_id = "delete-ethernet-ip-address")]
+#[delete("/{ethernet_name}/addresses/{ip_address}")]
+/// Deletes an IP address from a specific Ethernet entry.
+///
+/// This function parses the provided IP address, loads the network configuration,
+/// and removes the IP address from the specified Ethernet entry. If the Ethernet entry
+/// is found, the IP address is removed, and the updated configuration is saved and applied.
+/// If the Ethernet entry is not found, a 404 response is returned.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry from which the IP address will be removed.
+/// - `ip_address`: The IP address to be removed from the Ethernet entry, in CIDR notation with the
+///   slash percent-encoded (`10.0.0.5%2F24`).
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+/// - `HttpResponse::BadRequest` if the provided IP address is invalid.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_ip_address(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (ethernet_name, ip_address) = path.into_inner();
+    let to_delete = match ip_address.parse::<IpNetwork>() {
+        Err(err) => return HttpResponse::BadRequest().body(err),
+        Ok(ip) => ip,
+    };
+    if is_loopback(&ethernet_name) {
+        if let Err(err) = validate_loopback_address(&to_delete) {
+            return HttpResponse::UnprocessableEntity().body(err);
         }
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
     }
+    device::delete_address::<Ethernet>(&req, &netplan_store, apply.apply, &ethernet_name, to_delete)
+        .await
 }
 
 #[api_path(operation_id = "get-ethernet-nameservers")]
@@ -360,23 +1525,15 @@ pub async fn delete_ethernet_ip_address(
 ///
 /// # Returns
 /// - `HttpResponse::Ok` with a JSON body containing the nameservers if the Ethernet entry is found.
+///   Each nameserver address is annotated with `reachable_via`, telling whether it is reachable
+///   through this interface, through another one, or through no configured subnet or route.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
 /// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
 pub async fn get_ethernet_nameservers(
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    ethernet_name: Path<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(n) => n,
-    };
-    let ethernet = network.get_ethernets().get(&ethernet_name);
-    if let Some(ethernet) = ethernet {
-        HttpResponse::Ok().json(ethernet.get_nameservers())
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
-    }
+    device::get_nameservers::<Ethernet>(&netplan_store, &ethernet_name).await
 }
 
 #[api_path(operation_id = "add-ethernet-nameservers-search")]
@@ -388,7 +1545,9 @@ pub async fn get_ethernet_nameservers(
 /// is added, and the updated configuration is saved and applied. If the Ethernet entry is not found, a 404 response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the Ethernet entry to which the search domain will be added.
 /// - `search`: The search domain to be added to the Ethernet entry's nameservers.
 ///
@@ -396,31 +1555,78 @@ pub async fn get_ethernet_nameservers(
 /// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::Conflict` if the search domain is already configured, in which case nothing is applied.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn add_ethernet_nameservers_search(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
     search: Json<String>,
 ) -> impl Responder {
-    let search = search.into_inner();
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.add_nameservers_search(&search);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Err(err) => err,
-            Ok(network) => {
-                HttpResponse::Created().json(network.get_ethernets().get(&ethernet_name).unwrap())
-            }
-        }
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    device::add_nameservers_search::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        &search,
+    )
+    .await
+}
+
+#[api_path(operation_id = "replace-ethernet-nameservers")]
+#[put("/{ethernet_name}/nameservers")]
+/// Replaces the nameservers of a specific Ethernet entry.
+///
+/// Every address is parsed before anything is changed, and the new nameservers are applied with
+/// a single save and apply. When only `search` or only `addresses` is given, the other half of the
+/// nameservers is kept as is.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose nameservers will be replaced.
+/// - `nameservers`: The new search domains and nameserver addresses of the Ethernet entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+/// - `HttpResponse::BadRequest` listing every invalid nameserver address, one per line.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn replace_ethernet_nameservers(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    nameservers: Json<InputNameserversReplacement>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
     }
+    let nameservers = nameservers.into_inner();
+    let addresses = match &nameservers.addresses {
+        None => None,
+        Some(addresses) => match device::parse_nameserver_addresses(addresses) {
+            Ok(addresses) => Some(addresses),
+            Err(err) => return err,
+        },
+    };
+    device::replace_nameservers::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        nameservers.search,
+        addresses,
+    )
+    .await
 }
 
 #[api_path(operation_id = "delete-ethernet-nameservers-search")]
@@ -432,7 +1638,9 @@ pub async fn add_ethernet_nameservers_search(
 /// is removed, and the updated configuration is saved and applied. If the Ethernet entry is not found, a 404 response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the Ethernet entry from which the search domain will be removed.
 /// - `search`: The search domain to be removed from the Ethernet entry's nameservers.
 ///
@@ -440,28 +1648,25 @@ pub async fn add_ethernet_nameservers_search(
 /// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn delete_ethernet_nameservers_search(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
-    search: String,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.delete_nameservers_search(&search);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Err(err) => err,
-            Ok(_) => HttpResponse::NoContent().finish(),
-        }
-    } else {
-        HttpResponse::NotFound().finish()
+    let (ethernet_name, search) = path.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
     }
+    device::delete_nameservers_search::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        &search,
+    )
+    .await
 }
 
 #[api_path(operation_id = "add-ethernet-nameservers-address")]
@@ -474,47 +1679,47 @@ pub async fn delete_ethernet_nameservers_search(
 /// If the Ethernet entry is not found, a 404 response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the Ethernet entry to which the nameserver address will be added.
 /// - `address`: The nameserver address to be added to the Ethernet entry.
 ///
 /// # Returns
 /// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+///   If no configured subnet or route covers the address, a `Warning` header is included.
 /// - `HttpResponse::BadRequest` if the provided nameserver address is invalid.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::Conflict` if the nameserver address is already configured, in which case nothing is applied.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn add_ethernet_nameservers_address(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
     address: Json<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
     let address: IpAddr = match address.parse() {
         Err(_) => return HttpResponse::BadRequest().finish(),
         Ok(address) => address,
     };
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.add_nameservers_address(&address);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Ok(network) => {
-                HttpResponse::Ok().json(network.get_ethernets().get(&ethernet_name).unwrap())
-            }
-            Err(err) => err,
-        }
-    } else {
-        HttpResponse::NotFound().finish()
-    }
+    device::add_nameservers_address::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        address,
+    )
+    .await
 }
 
 #[api_path(operation_id = "delete-ethernet-nameservers-address")]
-#[delete("/{ethernet_name}/nameservers/address")]
+#[delete("/{ethernet_name}/nameservers/address/{address}")]
 /// Deletes a nameserver address from a specific Ethernet entry.
 ///
 /// This function parses the provided nameserver address, loads the network configuration,
@@ -523,41 +1728,68 @@ pub async fn add_ethernet_nameservers_address(
 /// If the Ethernet entry is not found, a 404 response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
-/// - `ethernet_name`: The name of the Ethernet entry from which the nameserver address will be removed.
-/// - `address`: The nameserver address to be removed from the Ethernet entry.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `path`: The name of the Ethernet entry from which the nameserver address will be removed, and the
+///   nameserver address to be removed from it.
 ///
 /// # Returns
-/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+/// - `HttpResponse::NoContent` if the nameserver address was removed, or was not configured.
 /// - `HttpResponse::BadRequest` if the provided nameserver address is invalid.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn delete_ethernet_nameservers_address(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
-    address: String,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
+    let (ethernet_name, address) = path.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
     let address: IpAddr = match address.parse() {
         Err(_) => return HttpResponse::BadRequest().finish(),
         Ok(address) => address,
     };
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(network) => network,
-    };
-    let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.delete_nameservers_address(&address);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Ok(_) => HttpResponse::NoContent().finish(),
-            Err(err) => err,
-        }
-    } else {
-        HttpResponse::NotFound().finish()
+    device::delete_nameservers_address::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        address,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-ethernet-nameservers")]
+#[delete("/{ethernet_name}/nameservers")]
+/// Deletes all the nameservers of a specific Ethernet entry, both search domains and addresses.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose nameservers will be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the nameservers were removed, or there were none.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_nameservers(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
     }
+    device::delete_nameservers::<Ethernet>(&req, &netplan_store, apply.apply, &ethernet_name).await
 }
 
 #[api_path(operation_id = "get-ethernet-routes")]
@@ -578,119 +1810,1854 @@ pub async fn delete_ethernet_nameservers_address(
 /// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
 pub async fn get_ethernet_routes(
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    ethernet_name: Path<String>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
-        Ok(n) => n,
-    };
-    let ethernet = network.get_ethernets().get(&ethernet_name);
-    if let Some(ethernet) = ethernet {
-        HttpResponse::Ok().json(ethernet.get_routes())
-    } else {
-        HttpResponse::NotFound().finish()
-    }
+    device::get_routes::<Ethernet>(&netplan_store, &ethernet_name).await
+}
+
+#[api_path(operation_id = "get-ethernet-route")]
+#[get("/{ethernet_name}/routes/{route_id}")]
+/// Retrieves a single route of a specific Ethernet entry.
+///
+/// The route is looked up by its identifier, as returned as key by the routes listing.
+/// Identifiers of IPv6 routes contain colons, which may be sent as is or percent-encoded (`%3A`).
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry the route belongs to.
+/// - `route_id`: The identifier of the route, e.g. `from-fe80::1-via`.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the route if it is found.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry or route is not found.
+/// - `HttpResponse::InternalServerError` with an error message if there is an issue loading the configuration.
+pub async fn get_ethernet_route(
+    netplan_store: Data<NetplanStore>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (ethernet_name, route_id) = path.into_inner();
+    device::get_route::<Ethernet>(&netplan_store, &ethernet_name, &route_id).await
 }
 
 #[api_path(operation_id = "add-ethernet-route")]
 #[post("/{ethernet_name}/routes")]
 /// Adds a route to an existing Ethernet entry.
 ///
-/// This function parses the provided `to`, `via`, and `from` IP addresses, loads the network configuration,
-/// and adds the route to the specified Ethernet entry. If the Ethernet entry is found, the route is added,
+/// This function parses the provided `to`, `via`, and `from` IP addresses and the optional `metric`,
+/// loads the network configuration, and adds the route to the specified Ethernet entry. If the Ethernet entry is found, the route is added,
 /// and the updated configuration is saved and applied. If the Ethernet entry is not found, a 404 response is returned.
 ///
 /// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
 /// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
 /// - `ethernet_name`: The name of the Ethernet entry to which the route will be added.
 /// - `to`: The destination IP address for the route.
 /// - `via`: The gateway IP address for the route (optional).
 /// - `from`: The source IP address for the route (optional).
+/// - `metric`: The metric of the route, lower values being preferred (optional).
+/// - `table`: The routing table to install the route into, for policy routing (optional, non-zero).
+/// - `scope`: The scope of the route, `global`, `link` or `host` (optional). Link scoped routes cannot have a `via`.
+/// - `type`: The type of the route, `unicast`, `blackhole`, `unreachable` or `prohibit` (optional).
+///   Only unicast routes can have a `via`.
+/// - `query`: A `Query<ReplaceQuery>` instance; with `replace=true`, a route with the same identifier is overwritten.
 ///
 /// # Returns
-/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
-/// - `HttpResponse::BadRequest` if the provided IP addresses are invalid.
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful, with a `Warning`
+///   header if the Ethernet is bound to a VRF and the route targets another table than the VRF's.
+/// - `HttpResponse::BadRequest` if the provided IP addresses are invalid, or a link scoped or non-unicast route
+///   has a gateway.
 /// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
 /// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::Conflict` with a JSON body containing the existing route if a route with the same
+///   identifier already exists and `replace` is not set.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
 pub async fn add_ethernet_route(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    query: Query<ReplaceQuery>,
     input_route: Json<InputRoute>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
     let route = match Route::from_input_route(&input_route.into_inner()) {
         Ok(route) => route,
-        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+        Err(err) => return HttpResponse::BadRequest().body(err),
     };
+    device::add_route::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        query.replace,
+        route,
+    )
+    .await
+}
+
+#[api_path(operation_id = "update-ethernet-route")]
+#[patch("/{ethernet_name}/routes/{route_id}")]
+/// Updates an existing route of a specific Ethernet entry in place.
+///
+/// The fields present in the body are overlaid on the route, which is then stored under its new
+/// identifier, replacing the old one, with a single save and apply.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry the route belongs to.
+/// - `route_id`: The identifier of the route to be updated.
+/// - `patch`: The fields of the route to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body mapping the (possibly new) identifier of the route to the updated route,
+///   with a `Warning` header if the Ethernet is bound to a VRF and the route targets another table than the VRF's.
+/// - `HttpResponse::BadRequest` if an address is invalid, or the updated route is inconsistent.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry or route is not found.
+/// - `HttpResponse::Conflict` if the updated route collides with another existing route.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn update_ethernet_route(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+    patch: Json<InputRoutePatch>,
+) -> impl Responder {
+    let (ethernet_name, route_id) = path.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    device::update_route::<Ethernet>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &ethernet_name,
+        &route_id,
+        &patch,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-ethernet-route")]
+#[delete("/{ethernet_name}/routes/{route_id}")]
+/// Deletes a route from a specific Ethernet entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry from which the route will be removed.
+/// - `route_id`: The identifier of the route to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the route was removed, or did not exist.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_route(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (ethernet_name, route_id) = path.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    device::delete_route::<Ethernet>(&req, &netplan_store, apply.apply, &ethernet_name, &route_id)
+        .await
+}
+
+#[api_path(operation_id = "delete-ethernet-routes")]
+#[delete("/{ethernet_name}/routes")]
+/// Deletes all the routes of a specific Ethernet entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose routes will be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the routes were removed.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_routes(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    device::delete_routes::<Ethernet>(&req, &netplan_store, apply.apply, &ethernet_name).await
+}
 
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+#[api_path(operation_id = "get-ethernet-gateway")]
+#[get("/{ethernet_name}/gateway")]
+/// Retrieves the default gateways of a specific Ethernet entry.
+///
+/// The default gateways are the routes of the Ethernet entry whose destination is unspecified,
+/// one for IPv4 and one for IPv6 at most.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry whose gateways are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the `ipv4` and `ipv6` default routes, if any.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_ethernet_gateway(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
-    let mut ethernets = network.get_ethernets().clone();
-    if let Some(mut ethernet) = ethernets.remove(&ethernet_name) {
-        ethernet.add_route(&route);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Ok(network) => {
-                HttpResponse::Ok().json(network.get_ethernets().get(&ethernet_name).unwrap())
-            }
-            Err(err) => err,
-        }
+    if let Some(ethernet) = network.get_ethernets().get(&ethernet_name) {
+        HttpResponse::Ok().json(Gateways::from_routes(ethernet.get_routes().values()))
     } else {
         HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
     }
 }
 
-// Delete Ethernet Routes
-#[api_path(operation_id = "delete-ethernet-route")]
-#[delete("/ethernet/{ethernet_name}/route/{route_id}")]
-pub async fn delete_ethernet_route(
+#[api_path(operation_id = "set-ethernet-gateway")]
+#[put("/{ethernet_name}/gateway")]
+/// Sets the default gateway of a specific Ethernet entry.
+///
+/// Any default route of the same IP version as the gateway is replaced by a default route
+/// through the gateway, so an IPv4 and an IPv6 gateway can be configured at the same time.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose gateway is to be set.
+/// - `via`: The IP address of the gateway.
+/// - `metric`: The metric of the default route, lower values being preferred (optional).
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the resulting default gateways if successful.
+/// - `HttpResponse::BadRequest` if the gateway is not a valid IP address.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn set_ethernet_gateway(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
-    route_id: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    gateway: Json<InputGateway>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let via = match gateway.via.parse::<IpAddr>() {
+        Ok(via) if !via.is_unspecified() => via,
+        _ => {
+            return HttpResponse::BadRequest()
+                .body(format!("Invalid gateway address '{}'.", gateway.via))
+        }
+    };
+    let to = match AddressFamily::of(&via) {
+        AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let mut route = Route::new(to, Some(via), None);
+    route.metric = gateway.metric;
+
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
+    let original = network.clone();
     let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.remove(&ethernet_name);
-    if let Some(mut ethernet) = ethernet {
-        ethernet.delete_route(&route_id);
-        network.add_ethernet(&ethernet);
-        match netplan.save_and_apply(&network) {
-            Ok(_) => HttpResponse::NoContent().finish(),
-            Err(err) => err,
+    let Some(mut ethernet) = ethernets.remove(&ethernet_name) else {
+        return HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."));
+    };
+    for (route_id, existing) in ethernet.get_routes() {
+        if existing.is_default() && existing.family() == route.family() {
+            ethernet.delete_route(&route_id);
         }
-    } else {
-        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
+    }
+    ethernet.add_route(&route);
+    network.add_ethernet(&ethernet);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(Gateways::from_routes(ethernet.get_routes().values()));
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Ok(network) => match network.get_ethernets().get(&ethernet_name) {
+            Some(ethernet) => {
+                HttpResponse::Ok().json(Gateways::from_routes(ethernet.get_routes().values()))
+            }
+            None => device::not_found::<Ethernet>(&ethernet_name),
+        },
+        Err(err) => err.error_response(),
     }
 }
 
-#[api_path(operation_id = "delete-ethernet-routes")]
-#[delete("/ethernet/{ethernet_name}/route")]
-pub async fn delete_ethernet_routes(
+#[api_path(operation_id = "delete-ethernet-gateway")]
+#[delete("/{ethernet_name}/gateway")]
+/// Removes the default gateways of a specific Ethernet entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry whose gateways are to be removed.
+/// - `query`: A `Query<GatewayQuery>` instance restricting the removal to the `ipv4` or `ipv6` gateway.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the gateways were removed, or there were none.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_gateway(
+    req: HttpRequest,
     netplan_store: Data<NetplanStore>,
-    ethernet_name: String,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    query: Query<GatewayQuery>,
 ) -> impl Responder {
-    let netplan = netplan_store.netplan.lock().unwrap();
-    let mut network = match netplan.load_config() {
-        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
         Ok(network) => network,
     };
+    let original = network.clone();
     let mut ethernets = network.get_ethernets().clone();
-    let ethernet = ethernets.get_mut(&ethernet_name);
-    if let Some(ethernet) = ethernet {
-        ethernet.delete_all_routes();
-        network.add_ethernet(ethernet);
-        match netplan.save_and_apply(&network) {
-            Ok(_) => HttpResponse::NoContent().finish(),
-            Err(err) => err,
+    let Some(mut ethernet) = ethernets.remove(&ethernet_name) else {
+        return HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."));
+    };
+    for (route_id, route) in ethernet.get_routes() {
+        if route.is_default() && query.family.is_none_or(|family| family == route.family()) {
+            ethernet.delete_route(&route_id);
         }
+    }
+    network.add_ethernet(&ethernet);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-ethernet-routing-policy")]
+#[get("/{ethernet_name}/routing-policy")]
+/// Retrieves the routing policy rules of a specific Ethernet entry.
+///
+/// This function loads the network configuration using Netplan, searches for the specified Ethernet entry,
+/// and returns its routing policy rules, keyed by their id, as a JSON response.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry whose routing policy rules are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the routing policy rules if the Ethernet entry is found.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_ethernet_routing_policy(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(n) => n,
+    };
+    let ethernet = network.get_ethernets().get(&ethernet_name);
+    if let Some(ethernet) = ethernet {
+        HttpResponse::Ok().json(ethernet.get_routing_policy())
     } else {
         HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
     }
 }
+
+#[api_path(operation_id = "add-ethernet-routing-policy")]
+#[post("/{ethernet_name}/routing-policy")]
+/// Adds a routing policy rule to an existing Ethernet entry.
+///
+/// This function parses the provided rule, loads the network configuration, and adds the rule to the
+/// specified Ethernet entry. The updated configuration is then saved and applied.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry to which the rule will be added.
+/// - `input_routing_policy`: The rule, with its `from` and `to` networks in CIDR notation, and the
+///   optional `table`, `priority` and `mark`. At least one of `from` and `to` must be set.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated Ethernet entry if successful.
+/// - `HttpResponse::BadRequest` if the provided rule is invalid.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn add_ethernet_routing_policy(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    input_routing_policy: Json<InputRoutingPolicy>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let routing_policy = match RoutingPolicy::from_input_routing_policy(&input_routing_policy) {
+        Ok(routing_policy) => routing_policy,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let mut ethernets = network.get_ethernets().clone();
+    if let Some(mut ethernet) = ethernets.remove(&ethernet_name) {
+        ethernet.add_routing_policy(&routing_policy);
+        network.add_ethernet(&ethernet);
+        let Some(mutation) = Mutation::new(&original, network) else {
+            return HttpResponse::Ok().json(ethernet);
+        };
+        let apply = apply.apply;
+        respond_with_network(netplan.commit(mutation, apply).await, &ethernet_name)
+    } else {
+        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
+    }
+}
+
+#[api_path(operation_id = "delete-ethernet-routing-policy")]
+#[delete("/{ethernet_name}/routing-policy")]
+/// Deletes a routing policy rule from an existing Ethernet entry.
+///
+/// This function parses the provided rule, loads the network configuration, and removes the matching
+/// rule from the specified Ethernet entry. The updated configuration is then saved and applied.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /apply`.
+/// - `ethernet_name`: The name of the Ethernet entry from which the rule will be removed.
+/// - `input_routing_policy`: The rule to be removed, as it was added.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if successful.
+/// - `HttpResponse::BadRequest` if the provided rule is invalid.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or saving the configuration.
+/// - `HttpResponse::NotFound` if the specified Ethernet entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+pub async fn delete_ethernet_routing_policy(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    ethernet_name: Path<String>,
+    input_routing_policy: Json<InputRoutingPolicy>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if let Some(rejection) = reject_loopback(&ethernet_name) {
+        return rejection;
+    }
+    let routing_policy = match RoutingPolicy::from_input_routing_policy(&input_routing_policy) {
+        Ok(routing_policy) => routing_policy,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let mut ethernets = network.get_ethernets().clone();
+    if let Some(mut ethernet) = ethernets.remove(&ethernet_name) {
+        ethernet.delete_routing_policy(&routing_policy.id());
+        network.add_ethernet(&ethernet);
+        let Some(mutation) = Mutation::new(&original, network) else {
+            return HttpResponse::NoContent().finish();
+        };
+        let apply = apply.apply;
+        match netplan.commit(mutation, apply).await {
+            Err(err) => err.error_response(),
+            Ok(_) => HttpResponse::NoContent().finish(),
+        }
+    } else {
+        HttpResponse::NotFound().body(format!("Ethernet {ethernet_name} was not found."))
+    }
+}
+
+#[api_path(operation_id = "probe-ethernet-dhcp-server")]
+#[post("/{ethernet_name}/dhcp-probe")]
+/// Looks for a DHCP server on the segment of a specific Ethernet entry.
+///
+/// This function sends a DHCPDISCOVER, a router solicitation and a DHCPv6 information request from
+/// the interface and listens for answers, which explains whether an interface is stuck waiting for a
+/// lease because there is no server at all, or because the lease is not completing. Without the
+/// privileges to do so (`CAP_NET_RAW` and `CAP_NET_BIND_SERVICE`), the lease state reported by
+/// `networkctl` is checked instead, as told by the `method` of the result.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the Ethernet entry whose segment should be probed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the probe result.
+/// - `HttpResponse::NotFound` if the interface does not exist in the system.
+/// - `HttpResponse::InternalServerError` if the segment could not be probed.
+pub async fn probe_ethernet_dhcp_server(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.probe_dhcp(ethernet_name.into_inner()).await {
+        Ok(probe) => HttpResponse::Ok().json(probe),
+        Err(NetplanError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            HttpResponse::NotFound().body(err.to_string())
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "capture-ethernet")]
+#[post("/{ethernet_name}/capture")]
+/// Starts a bounded packet capture on a specific interface.
+///
+/// The capture runs tcpdump for a limited time, keeping a limited number of packets of a limited size,
+/// optionally filtered by protocol, port and host. Its status is retrieved, and its pcap file downloaded
+/// once finished, with `get-capture`. Each interface is captured once at a time. Packet capture has to be
+/// enabled by the administrator with the `OPENSIGHT_PACKET_CAPTURE` environment variable.
+///
+/// # Arguments
+/// - `capture_store`: A `Data<CaptureStore>` instance that holds the packet captures.
+/// - `ethernet_name`: The name of the interface whose packets are to be captured.
+/// - `capture`: The bounds and the filter of the capture, their defaults being used for what is left out.
+///
+/// # Returns
+/// - `HttpResponse::Accepted` with a JSON body containing the started capture, with its `id`, and a
+///   `Location` header pointing to it.
+/// - `HttpResponse::BadRequest` if a bound is out of range, or the fields of the filter can't be combined.
+/// - `HttpResponse::Forbidden` if packet capture is disabled on this server.
+/// - `HttpResponse::NotFound` if the interface does not exist in the system.
+/// - `HttpResponse::Conflict` if the interface is already being captured.
+/// - `HttpResponse::InternalServerError` if tcpdump is not installed, or could not be started.
+pub async fn capture_ethernet(
+    capture_store: Data<CaptureStore>,
+    ethernet_name: Path<String>,
+    capture: Json<InputCapture>,
+) -> impl Responder {
+    if let Some(rejection) = captures::reject_disabled(&capture_store) {
+        return rejection;
+    }
+    let settings = match CaptureSettings::from_input(&capture) {
+        Ok(settings) => settings,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    if !System::interface_exists(&ethernet_name) {
+        return HttpResponse::NotFound().body(format!(
+            "Interface {ethernet_name} does not exist in the system."
+        ));
+    }
+    match capture_store.start(&ethernet_name, settings) {
+        Ok(capture) => HttpResponse::Accepted()
+            .insert_header((header::LOCATION, format!("/captures/{}", capture.id)))
+            .json(capture),
+        Err(err @ CaptureError::Disabled) => HttpResponse::Forbidden().body(err.to_string()),
+        Err(err @ CaptureError::Busy { .. }) => HttpResponse::Conflict().body(err.to_string()),
+        Err(CaptureError::Netplan(err)) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-ethernet-state")]
+#[get("/{ethernet_name}/state")]
+/// Retrieves the operational state of a specific Ethernet entry.
+///
+/// This function reads the state of the interface as seen by the kernel: its operational state,
+/// carrier, negotiated speed, current MAC address and MTU. Attributes that are not available for
+/// the interface are left out. If the interface was still waiting for its DHCP lease when the
+/// configuration was last applied, the probe of its segment is included as `dhcp_probe`.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the interface whose state is to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the state of the interface.
+/// - `HttpResponse::NotFound` if the interface does not exist in the system.
+/// - `HttpResponse::InternalServerError` if the state could not be read.
+pub async fn get_ethernet_state(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    match InterfaceState::read(&ethernet_name) {
+        Ok(mut state) => {
+            state.dhcp_probe = netplan_store.dhcp_probe(&ethernet_name);
+            HttpResponse::Ok().json(state)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            HttpResponse::NotFound().body(err.to_string())
+        }
+        Err(err) => NetplanError::from(err).error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-ethernet-diff")]
+#[get("/{ethernet_name}/diff")]
+/// Retrieves the differences between the configuration of a specific Ethernet entry and the system.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `ethernet_name`: The name of the interface whose differences are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the `system_state` differences of the interface.
+/// - `HttpResponse::NotFound` if the interface has no differences.
+/// - `HttpResponse::InternalServerError` if the state of the system could not be retrieved.
+pub async fn get_ethernet_diff(
+    netplan_store: Data<NetplanStore>,
+    ethernet_name: Path<String>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let diff = match netplan.get_diff().await {
+        Ok(diff) => diff,
+        Err(err) => return err.error_response(),
+    };
+    match diff.get(ethernet_name.as_str()) {
+        Some(system_state) => {
+            HttpResponse::Ok().json(yaml_mapping_to_json_compatible(system_state))
+        }
+        None => HttpResponse::NotFound().body(format!(
+            "Ethernet '{}' has no differences with the system.",
+            ethernet_name
+        )),
+    }
+}
+
+/// Address of this server the request was received on, if it can be told.
+///
+/// The address is the local end of the connection, recorded by the server when accepting it.
+fn served_address(req: &HttpRequest) -> Option<IpAddr> {
+    req.conn_data::<SocketAddr>().map(|address| address.ip())
+}
+
+#[api_path(operation_id = "set-ethernet-link")]
+#[post("/{ethernet_name}/link")]
+/// Administratively brings a specific interface up or down.
+///
+/// The state of the link is changed directly, without touching the netplan configuration, so it is
+/// not persisted. Bringing down the interface this API is being served on is refused unless forced.
+///
+/// # Arguments
+/// - `req`: The request, used to find out the interface it was received on.
+/// - `ethernet_name`: The name of the interface to be brought up or down.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, the interface serving the API may be brought down.
+/// - `link`: The desired state of the link, `up` or `down`.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the state of the link was changed.
+/// - `HttpResponse::NotFound` if the interface does not exist in the system.
+/// - `HttpResponse::Conflict` if the interface serving the API would be brought down, or if the address
+///   the API is served on could not be told.
+/// - `HttpResponse::BadGateway` with the command line, exit status and error output of `ip` if the state
+///   could not be changed.
+/// - `HttpResponse::InternalServerError` if `ip` could not be run.
+pub async fn set_ethernet_link(
+    req: HttpRequest,
+    ethernet_name: Path<String>,
+    query: Query<ForceQuery>,
+    link: Json<InputLink>,
+) -> impl Responder {
+    let ethernet_name = ethernet_name.into_inner();
+    if !System::interface_exists(&ethernet_name) {
+        return HttpResponse::NotFound().body(format!(
+            "Interface {ethernet_name} does not exist in the system."
+        ));
+    }
+    if link.state == LinkState::Down && !query.force {
+        // Without the address, bringing the interface down could as well lock the client out
+        let Some(served) = served_address(&req) else {
+            return HttpResponse::Conflict().body(format!(
+                "The address this API is served on could not be told, so it may belong to \
+                {ethernet_name}. Use force=true to bring it down anyway."
+            ));
+        };
+        match System::addresses(&ethernet_name) {
+            Ok(addresses) if addresses.contains(&served) => {
+                return HttpResponse::Conflict().body(format!(
+                    "This API is served on {served}, which belongs to {ethernet_name}. \
+                    Use force=true to bring it down anyway."
+                ))
+            }
+            Ok(_) => {}
+            Err(err) => return err.error_response(),
+        }
+    }
+    match System::set_link(&ethernet_name, link.state == LinkState::Up) {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "apply-ethernets")]
+#[post("/apply")]
+/// Applies the staged configuration.
+///
+/// This function applies the configuration file as it is, including the changes saved by the
+/// mutating endpoints with `apply=false`, so that several changes only need a single apply.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the resulting network configuration if successful.
+/// - `HttpResponse::ServiceUnavailable` if the renderer service is not running.
+/// - `HttpResponse::GatewayTimeout` if netplan did not finish in time.
+/// - `HttpResponse::InternalServerError` if there is an issue loading or applying the configuration.
+pub async fn apply_ethernets(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    match netplan.apply_with_diff(network.renderer).await {
+        Ok(network) => HttpResponse::Ok().json(network),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-pending-ethernets")]
+#[get("/pending")]
+/// Tells whether there are staged changes which are not applied yet.
+///
+/// This function compares the configuration file with the state of the system, as reported
+/// by `netplan status --diff`.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body listing the interfaces whose state differs from the configuration.
+/// - `HttpResponse::BadGateway` if netplan failed to report the state of the system.
+/// - `HttpResponse::InternalServerError` if the state of the system could not be retrieved.
+pub async fn get_pending_ethernets(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.pending_interfaces().await {
+        Ok(interfaces) => HttpResponse::Ok().json(PendingChanges {
+            pending: !interfaces.is_empty(),
+            interfaces,
+        }),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::dhcp_probe::LeaseDiagnosis;
+    use crate::netplan::Netplan;
+    use crate::test_support::{
+        init_scope, mock_store, scripted_netplan, scripted_store, ScriptedRunner,
+    };
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    const SUMMARY_CONFIG: &str = "network: {version: 2, renderer: networkd, ethernets: {\
+        enp1s0: {dhcp4: true, routes: [{to: 10.1.0.0, via: 10.0.0.1}], nameservers: {addresses: [10.0.0.53]}},\
+        enp2s0: {addresses: [192.168.1.2/24, 192.168.1.3/24]},\
+        enp3s0: {dhcp6: true, addresses: [fd00::2/64]}}}";
+
+    /// `netplan status` reporting `enp1s0` still waiting for its lease.
+    const SUMMARY_DIFF: &str =
+        "interfaces:\n  enp1s0:\n    system_state:\n      missing_dhcp4_address: true\n";
+
+    #[actix_web::test]
+    async fn summaries_are_served_from_the_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default().on("netplan status --diff-only", 0, SUMMARY_DIFF);
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let golden = json!([
+            {
+                "name": "enp1s0", "managed": true, "oper_state": null,
+                "addressing_mode": "dhcp", "address_count": 0, "route_count": 1,
+                "has_nameservers": true, "dhcp4": true, "dhcp6": false, "drift": true
+            },
+            {
+                "name": "enp2s0", "managed": true, "oper_state": null,
+                "addressing_mode": "static", "address_count": 2, "route_count": 0,
+                "has_nameservers": false, "dhcp4": false, "dhcp6": false, "drift": false
+            },
+            {
+                "name": "enp3s0", "managed": true, "oper_state": null,
+                "addressing_mode": "dhcp+static", "address_count": 1, "route_count": 0,
+                "has_nameservers": false, "dhcp4": false, "dhcp6": true, "drift": false
+            }
+        ]);
+
+        // The first listing loads the configuration
+        let req = test::TestRequest::get()
+            .uri("/ethernets?view=summary")
+            .to_request();
+        let summaries: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(summaries, golden);
+        let calls = runner.calls().len();
+        assert!(calls > 0);
+
+        // The next ones are served from the snapshot
+        let req = test::TestRequest::get()
+            .uri("/ethernets?view=summary")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().contains_key(header::ETAG));
+        let summaries: Value = test::read_body_json(resp).await;
+        assert_eq!(summaries, golden);
+        assert_eq!(runner.calls().len(), calls);
+    }
+
+    #[actix_web::test]
+    async fn stale_entity_tags_are_refused_by_every_mutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let rule = json!({"from": "192.168.1.0/24", "table": 100});
+
+        let requests = [
+            test::TestRequest::post()
+                .uri("/ethernets/enp2s0/try")
+                .set_json(json!({"dhcp4": true})),
+            test::TestRequest::post().uri("/ethernets/enp2s0/reset"),
+            test::TestRequest::delete().uri("/ethernets/enp2s0"),
+            test::TestRequest::put()
+                .uri("/ethernets/enp2s0/gateway")
+                .set_json(json!({"via": "192.168.1.1"})),
+            test::TestRequest::delete().uri("/ethernets/enp1s0/gateway"),
+            test::TestRequest::post()
+                .uri("/ethernets/enp2s0/routing-policy")
+                .set_json(&rule),
+            test::TestRequest::delete()
+                .uri("/ethernets/enp2s0/routing-policy")
+                .set_json(&rule),
+        ];
+        for request in requests {
+            let req = request
+                .insert_header((header::IF_MATCH, "\"stale\""))
+                .to_request();
+            let uri = req.uri().to_string();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED, "{uri}");
+        }
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+        assert!(!runner.calls().iter().any(|call| call == "netplan apply"));
+
+        // The current entity tag lets the change through
+        let req = test::TestRequest::get()
+            .uri("/ethernets/enp2s0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/enp2s0?apply=false")
+            .insert_header((header::IF_MATCH, etag))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[actix_web::test]
+    async fn an_identical_replacement_is_not_applied_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let spec = json!({"addresses": ["192.168.1.2/24"], "mtu": 1400, "optional": true});
+        let applies = || {
+            runner
+                .calls()
+                .iter()
+                .filter(|call| *call == "netplan apply")
+                .count()
+        };
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0")
+            .set_json(&spec)
+            .to_request();
+        let replaced: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(replaced["changes"]["fields"], json!(["mtu", "optional"]));
+        assert_eq!(
+            replaced["changes"]["addresses"],
+            json!({"added": [], "removed": ["192.168.1.3/24"]})
+        );
+        assert_eq!(applies(), 1);
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0")
+            .set_json(&spec)
+            .to_request();
+        let replaced: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(replaced["changes"]["fields"], json!([]));
+        assert_eq!(replaced["ethernet"]["addresses"], json!(["192.168.1.2/24"]));
+        assert_eq!(applies(), 1);
+    }
+
+    #[actix_web::test]
+    async fn replacements_are_checked_like_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {\
+            enp1s0: {addresses: [10.0.0.2/24]}, enp2s0: {addresses: [127.0.0.1/8]}}}",
+        )
+        .unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp1s0")
+            .set_json(json!({"set_name": "a/b", "virtual_function_count": 1000}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // The subnet of enp1s0 is taken
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0?dry_run=true")
+            .set_json(json!({"addresses": ["127.0.0.1/8", "10.0.0.3/24"]}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0?dry_run=true&force=true")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        let replaced: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(replaced["changes"]["fields"], json!(["dhcp4"]));
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            "network: {version: 2, ethernets: {\
+            enp1s0: {addresses: [10.0.0.2/24]}, enp2s0: {addresses: [127.0.0.1/8]}}}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn the_served_address_is_not_dropped() {
+        let mut current = Ethernet::new("enp2s0".to_string());
+        current.add_address(&"127.0.0.1/8".parse().unwrap());
+        let mut desired = Ethernet::new("enp2s0".to_string());
+        desired.set_dhcp4(true);
+        let served = Some(IpAddr::from([127, 0, 0, 1]));
+
+        let rejection = reject_lockout(served, &current, &desired).unwrap();
+        assert_eq!(rejection.status(), StatusCode::CONFLICT);
+        assert!(reject_lockout(served, &current, &current).is_none());
+        assert!(reject_lockout(Some(IpAddr::from([10, 0, 0, 2])), &current, &desired).is_none());
+    }
+
+    #[actix_web::test]
+    async fn labels_are_saved_without_running_netplan() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0/labels")
+            .set_json(json!(["wan", "uplink"]))
+            .to_request();
+        let labels: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(labels, json!(["uplink", "wan"]));
+        assert_eq!(runner.calls(), Vec::<String>::new());
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/ethernets?label=wan")
+            .to_request();
+        let ethernets: Value = test::call_and_read_body_json(&app, req).await;
+        let names: Vec<&String> = ethernets.as_object().unwrap().keys().collect();
+        assert_eq!(names, ["enp2s0"]);
+    }
+
+    #[actix_web::test]
+    async fn labels_have_their_own_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let etag_of = |resp: &actix_web::dev::ServiceResponse| {
+            resp.headers()
+                .get(header::ETAG)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let req = test::TestRequest::get()
+            .uri("/ethernets/enp2s0")
+            .to_request();
+        let config_etag = etag_of(&test::call_service(&app, req).await);
+        let req = test::TestRequest::get()
+            .uri("/ethernets/enp2s0/labels")
+            .to_request();
+        let revision = etag_of(&test::call_service(&app, req).await);
+
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0/labels")
+            .insert_header((header::IF_MATCH, revision.clone()))
+            .set_json(json!(["wan"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_ne!(etag_of(&resp), revision);
+        // The labels were read before, so they are stale now
+        let req = test::TestRequest::put()
+            .uri("/ethernets/enp2s0/labels")
+            .insert_header((header::IF_MATCH, revision))
+            .set_json(json!(["lan"]))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        // The configuration is the same, so updating it still matches
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/enp2s0?apply=false")
+            .insert_header((header::IF_MATCH, config_etag))
+            .set_json(json!({"mtu": 1400}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn metadata_only_updates_are_not_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/enp2s0")
+            .set_json(json!({"labels": ["wan"]}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["labels"], json!(["wan"]));
+        // Trying the same configuration has nothing to try
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try")
+            .set_json(json!({"labels": ["lan"]}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // netplan was only asked about the state of the system
+        let calls = runner.calls();
+        assert!(
+            calls.iter().all(|call| call.starts_with("netplan status")),
+            "{calls:?}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+        let req = test::TestRequest::get()
+            .uri("/ethernets/enp2s0")
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["labels"], json!(["lan"]));
+    }
+
+    #[actix_web::test]
+    async fn summaries_follow_the_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let address_count = |summaries: &Value| summaries[1]["address_count"].clone();
+
+        let req = test::TestRequest::get()
+            .uri("/ethernets?view=summary")
+            .to_request();
+        let summaries: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(address_count(&summaries), json!(2));
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/enp2s0/addresses/192.168.1.3%2F24?apply=false")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let req = test::TestRequest::get()
+            .uri("/ethernets?view=summary")
+            .to_request();
+        let summaries: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(address_count(&summaries), json!(1));
+    }
+
+    #[actix_web::test]
+    async fn adds_and_deletes_an_address() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/eth0/addresses")
+            .set_json("10.0.0.5/24")
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["addresses"], json!(["10.0.0.5/24"]));
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth0/addresses/10.0.0.5%2F24")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get().uri("/ethernets/eth0").to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(ethernet["addresses"]
+            .as_array()
+            .is_none_or(|addresses| addresses.is_empty()));
+    }
+
+    #[actix_web::test]
+    async fn deletes_a_nameserver_address_named_in_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/enp1s0/nameservers/address/10.0.0.300")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/enp1s0/nameservers/address/10.0.0.53")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get()
+            .uri("/ethernets/enp1s0/nameservers")
+            .to_request();
+        let nameservers: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(nameservers["addresses"]
+            .as_array()
+            .is_none_or(|addresses| addresses.is_empty()));
+    }
+
+    #[actix_web::test]
+    async fn updates_keep_absent_settings_and_clear_null_ones() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({
+                "dhcp4": true,
+                "optional": true,
+                "macaddress": "52:54:00:12:34:56",
+                "renderer": "networkd",
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"mtu": 1400}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["mtu"], json!(1400));
+        assert_eq!(ethernet["dhcp4"], json!(true));
+        assert_eq!(ethernet["optional"], json!(true));
+        assert_eq!(ethernet["macaddress"], json!("52:54:00:12:34:56"));
+        assert_eq!(ethernet["renderer"], json!("networkd"));
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"macaddress": null, "mtu": null}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(ethernet["macaddress"].is_null());
+        assert!(ethernet["mtu"].is_null());
+        assert_eq!(ethernet["optional"], json!(true));
+        assert_eq!(ethernet["renderer"], json!("networkd"));
+    }
+
+    #[actix_web::test]
+    async fn probes_the_segment_of_an_interface() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default()
+            .on(
+                "networkctl status --no-pager enp1s0",
+                0,
+                "Address: 192.168.1.23 (DHCP4 via 192.168.1.1)\n",
+            )
+            .failing(
+                "networkctl status --no-pager eth9",
+                1,
+                "Interface \"eth9\" not found.",
+            );
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp1s0/dhcp-probe")
+            .to_request();
+        let probe: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(probe["server_detected"], json!(true));
+        assert_eq!(probe["server"], json!("192.168.1.1"));
+        assert_eq!(probe["method"], json!("networkctl"));
+        assert!(probe["diagnosis"].is_null());
+        assert!(runner
+            .calls()
+            .contains(&"networkctl status --no-pager enp1s0".to_string()));
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/eth9/dhcp-probe")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn interfaces_waiting_for_their_lease_are_diagnosed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default().on("netplan status --diff-only", 0, SUMMARY_DIFF);
+        let store = Data::new(
+            NetplanStore::new(Box::new(scripted_netplan(&config_path, &runner)))
+                .with_lease_wait(Duration::ZERO),
+        );
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/enp1s0")
+            .set_json(json!({"mtu": 1400}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["mtu"], json!(1400));
+        assert_eq!(ethernet["dhcp-probe"]["server_detected"], json!(false));
+        assert_eq!(ethernet["dhcp-probe"]["diagnosis"], json!("no_server"));
+        let probe = store.dhcp_probe("enp1s0").unwrap();
+        assert_eq!(probe.diagnosis, Some(LeaseDiagnosis::NoServer));
+        assert!(store.dhcp_probe("enp2s0").is_none());
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        assert!(!config.contains("dhcp-probe"));
+    }
+
+    #[actix_web::test]
+    async fn loopbacks_only_take_extra_host_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/lo")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        for address in ["10.255.0.0/24", "127.0.0.2/32"] {
+            let req = test::TestRequest::post()
+                .uri("/ethernets/lo/addresses")
+                .set_json(address)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY, "{address}");
+        }
+        assert!(!std::fs::read_to_string(&config_path)
+            .unwrap()
+            .contains("lo:"));
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/lo/addresses")
+            .set_json("10.255.0.1/32")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let config: serde_yml::Value =
+            serde_yml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(
+            config["network"]["ethernets"]["lo"]["addresses"],
+            serde_yml::from_str::<serde_yml::Value>("[10.255.0.1/32]").unwrap()
+        );
+        let req = test::TestRequest::get()
+            .uri("/ethernets/lo/addresses")
+            .to_request();
+        let addresses: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(addresses, json!(["10.255.0.1/32"]));
+    }
+
+    #[actix_web::test]
+    async fn deletes_an_ethernet_from_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/enp2s0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let config: serde_yml::Value =
+            serde_yml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        let ethernets = config["network"]["ethernets"].as_mapping().unwrap();
+        assert!(!ethernets.contains_key("enp2s0"));
+        assert!(ethernets.contains_key("enp1s0") && ethernets.contains_key("enp3s0"));
+        assert!(runner.calls().contains(&"netplan apply".to_string()));
+    }
+
+    #[actix_web::test]
+    async fn listing_survives_a_failing_netplan() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default().failing("netplan", 1, "netplan exploded");
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let req = test::TestRequest::get().uri("/ethernets").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "command_failed");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("failed (exit status: 1): netplan exploded"));
+    }
+
+    #[actix_web::test]
+    async fn deleting_a_referenced_ethernet_needs_a_cascade() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {}, eth1: {}}, \
+            vlans: {vlan10: {id: 10, link: eth0}, vlan20: {id: 20, link: vlan10}}, \
+            bonds: {bond0: {interfaces: [eth0, eth1]}}}",
+        )
+        .unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let read_config = || -> serde_yml::Value {
+            serde_yml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap()
+        };
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth0")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(
+            body.starts_with("eth0 is still used by VLAN vlan10, bond bond0."),
+            "{body}"
+        );
+        assert!(!read_config()["network"]["ethernets"]["eth0"].is_null());
+        assert!(!runner.calls().contains(&"netplan apply".to_string()));
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth0?cascade=true")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let config = read_config();
+        assert!(config["network"]["ethernets"]["eth0"].is_null());
+        assert!(config["network"]["vlans"].is_null());
+        assert_eq!(
+            config["network"]["bonds"]["bond0"]["interfaces"],
+            serde_yml::from_str::<serde_yml::Value>("[eth1]").unwrap()
+        );
+        let applies = runner
+            .calls()
+            .into_iter()
+            .filter(|call| call == "netplan apply")
+            .count();
+        assert_eq!(applies, 1);
+    }
+
+    #[actix_web::test]
+    async fn deleting_an_unmanaged_ethernet_changes_nothing() {
+        let store = mock_store(&["eth0", "eth1"]);
+        let app = init_scope!("/ethernets", configure, store);
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let req = test::TestRequest::get().uri("/ethernets").to_request();
+        let before = test::call_service(&app, req).await;
+        let etag = before.headers().get(header::ETAG).unwrap().clone();
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth9")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/ethernets").to_request();
+        let after = test::call_service(&app, req).await;
+        assert_eq!(after.headers().get(header::ETAG), Some(&etag));
+    }
+
+    #[actix_web::test]
+    async fn adds_routes_with_a_metric() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"addresses": ["192.168.1.2/24"]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/eth0/routes")
+            .set_json(json!({"to": "10.1.0.0", "via": "192.168.1.1", "metric": 100}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["routes"][0]["metric"], json!(100));
+        let req = test::TestRequest::post()
+            .uri("/ethernets/eth0/routes")
+            .set_json(json!({"to": "10.2.0.0", "via": "192.168.1.1"}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        let route = ethernet["routes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|route| route["to"] == json!("10.2.0.0"))
+            .unwrap();
+        assert!(route.get("metric").is_none());
+    }
+
+    #[actix_web::test]
+    async fn updates_the_dhcp6_overrides() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"dhcp6": true, "dhcp6_overrides": {"use-dns": false}}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["dhcp6-overrides"], json!({"use-dns": false}));
+        assert!(ethernet.get("dhcp4-overrides").is_none());
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"dhcp6_overrides": null}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(ethernet.get("dhcp6-overrides").is_none());
+        assert_eq!(ethernet["dhcp6"], json!(true));
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_malformed_mac_address() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"macaddress": "52:54:00:12:34"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8_lossy(&body).contains("Invalid MAC address '52:54:00:12:34'"));
+    }
+
+    #[actix_web::test]
+    async fn toggles_ipv6_privacy() {
+        let api = serde_json::to_value(EthernetsApi::openapi()).unwrap();
+        assert_eq!(
+            api["components"]["schemas"]["InputDevice"]["properties"]["ipv6_privacy"]["type"],
+            json!(["boolean", "null"])
+        );
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"dhcp6": true}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(ethernet.get("ipv6-privacy").is_none());
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"ipv6_privacy": false}))
+            .to_request();
+        let ethernet: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(ethernet["ipv6-privacy"], json!(false));
+    }
+
+    #[actix_web::test]
+    async fn gets_a_route_by_its_ipv6_id() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"addresses": ["fd00::2/64"]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        for route in [
+            json!({"to": "fe80::1"}),
+            json!({"to": "2001:db8::", "via": "fe80::1"}),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/ethernets/eth0/routes")
+                .set_json(route)
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+
+        for (uri, to) in [
+            ("/ethernets/eth0/routes/from-fe80::1-via", "fe80::1"),
+            ("/ethernets/eth0/routes/from-fe80%3A%3A1-via", "fe80::1"),
+            (
+                "/ethernets/eth0/routes/from-2001:db8::-fe80::1",
+                "2001:db8::",
+            ),
+            (
+                "/ethernets/eth0/routes/from-2001%3Adb8%3A%3A-fe80%3A%3A1",
+                "2001:db8::",
+            ),
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+            let route: Value = test::read_body_json(resp).await;
+            assert_eq!(route["to"], json!(to), "{uri}");
+        }
+        for uri in [
+            "/ethernets/eth0/routes/from-fe80::2-via",
+            "/ethernets/eth1/routes/from-fe80::1-via",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn route_deletions_are_mounted_under_routes() {
+        #[derive(OpenApi)]
+        #[openapi(nest((path = "/ethernets", api = EthernetsApi)))]
+        struct ApiDoc;
+        let api = ApiDoc::openapi();
+        let paths = &api.paths.paths;
+        assert!(paths["/ethernets/{ethernet_name}/routes/{route_id}"]
+            .delete
+            .is_some());
+        assert!(paths["/ethernets/{ethernet_name}/routes"].delete.is_some());
+        assert!(!paths
+            .keys()
+            .any(|path| path.starts_with("/ethernets/ethernet/") || path.contains("/route/")));
+
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth0")
+            .set_json(json!({"addresses": ["192.168.1.2/24"]}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        for to in ["10.1.0.0", "10.2.0.0"] {
+            let req = test::TestRequest::post()
+                .uri("/ethernets/eth0/routes")
+                .set_json(json!({"to": to, "via": "192.168.1.1"}))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth0/routes/from-10.1.0.0-192.168.1.1")
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get()
+            .uri("/ethernets/eth0/routes")
+            .to_request();
+        let routes: Value = test::call_and_read_body_json(&app, req).await;
+        let ids: Vec<&String> = routes.as_object().unwrap().keys().collect();
+        assert_eq!(ids, ["from-10.2.0.0-192.168.1.1"]);
+        let req = test::TestRequest::delete()
+            .uri("/ethernets/eth0/routes")
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get()
+            .uri("/ethernets/eth0/routes")
+            .to_request();
+        let routes: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(routes, json!({}));
+    }
+
+    #[actix_web::test]
+    async fn rejects_an_invalid_address() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/eth0/addresses")
+            .set_json("10.0.0.300/24")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn unknown_interfaces_are_not_found() {
+        let store = mock_store(&["eth0"]);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/eth9")
+            .set_json(json!({}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let req = test::TestRequest::get().uri("/ethernets/eth9").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn an_ethernet_gone_after_the_change_is_not_found() {
+        let resp = respond_with_network(Ok(Network::new()), "eth0");
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn changes_after_a_timed_out_try_leave_the_tried_change_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        // netplan exits right away, like at the end of the timeout
+        let store = scripted_store(&config_path, &ScriptedRunner::default());
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try?timeout=1")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        while store
+            .pending_try
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .is_running()
+        {
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let req = test::TestRequest::patch()
+            .uri("/ethernets/enp1s0")
+            .set_json(json!({"dhcp6": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let network = Netplan::parse_config(&std::fs::read(&config_path).unwrap()).unwrap();
+        assert!(network.get_ethernets()["enp1s0"].get_dhcp6());
+        assert!(!network.get_ethernets()["enp2s0"].get_dhcp4());
+    }
+
+    #[actix_web::test]
+    async fn a_change_failing_to_be_tried_is_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default().missing("netplan try");
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try")
+            .set_json(json!({"dhcp4": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_server_error());
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["code"], "not_installed");
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+        assert!(store.pending_try.lock().unwrap().is_none());
+    }
+
+    /// The labels of enp2s0 and their revision, as served by `$app`.
+    macro_rules! labels_and_revision {
+        ($app:expr) => {{
+            let req = test::TestRequest::get()
+                .uri("/ethernets/enp2s0/labels")
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            let revision = resp.headers().get(header::ETAG).unwrap().clone();
+            let labels: Value = test::read_body_json(resp).await;
+            (labels, revision)
+        }};
+    }
+
+    #[actix_web::test]
+    async fn a_try_is_checked_like_an_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try")
+            .set_json(json!({"ipv6_address_generation": "eui64", "accept_ra": false}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("netplan try")));
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+    }
+
+    #[actix_web::test]
+    async fn the_labels_of_a_change_failing_to_be_tried_are_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        let runner = ScriptedRunner::default().missing("netplan try");
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/ethernets", configure, store);
+        let before = labels_and_revision!(app);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try")
+            .set_json(json!({"dhcp4": true, "labels": ["wan"]}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_server_error());
+        assert_eq!(labels_and_revision!(app), before);
+    }
+
+    #[actix_web::test]
+    async fn the_labels_of_a_timed_out_try_are_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, SUMMARY_CONFIG).unwrap();
+        // netplan exits right away, like at the end of the timeout
+        let store = scripted_store(&config_path, &ScriptedRunner::default());
+        let app = init_scope!("/ethernets", configure, store);
+        let before = labels_and_revision!(app);
+
+        let req = test::TestRequest::post()
+            .uri("/ethernets/enp2s0/try?timeout=1")
+            .set_json(json!({"dhcp4": true, "labels": ["wan"]}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        while store
+            .pending_try
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .is_running()
+        {
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(labels_and_revision!(app), before);
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            SUMMARY_CONFIG
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use actix_web::{get, web::Data, HttpResponse, Responder};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{models::health::Health, netplan::NetplanStore};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_health,))]
+/// API documentation for the health check of the service.
+pub struct HealthApi;
+
+/// Configures the Actix web service with the health check endpoint.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store).service(get_health);
+    }
+}
+
+#[api_path(operation_id = "get-health")]
+#[get("")]
+/// Tells whether the service is up.
+///
+/// The status is `ok` whenever the service answers. Changes to the configuration waiting for a
+/// reboot are reported besides, for information only, as they don't make the service unhealthy.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the status, and whether changes are waiting
+///   for a reboot. If they can't be listed, none are reported.
+pub async fn get_health(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let pending_actions = netplan.pending_actions().await.unwrap_or_else(|err| {
+        log::warn!("Could not list the changes waiting for a reboot: {err}");
+        vec![]
+    });
+    HttpResponse::Ok().json(Health::new(&pending_actions))
+}
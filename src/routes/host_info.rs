@@ -1,21 +1,32 @@
 use actix_web::{
     get, patch,
     web::{Data, Json},
-    HttpResponse, Responder,
+    HttpResponse, Responder, ResponseError,
 };
 use serde::{Deserialize, Serialize};
 use utoipa::{path as api_path, OpenApi, ToSchema};
 use utoipa_actix_web::service_config::ServiceConfig;
 
-use crate::models::host_info::HostInfoStore;
+use crate::{
+    models::{
+        device::Device,
+        host_info::{default_route_interface, hostname_domain, DnsConsistency, HostInfoStore},
+    },
+    netplan::{Mutation, NetplanStore},
+};
 
 #[derive(OpenApi)]
-#[openapi(paths(get_host_info, update_host_info,))]
+#[openapi(paths(get_host_info, update_host_info, get_dns_consistency,))]
 pub struct HostInfoApi;
 
 #[derive(Serialize, Deserialize, ToSchema)]
 struct InputHostInfo {
     pub hostname: Option<String>,
+    /// Adds the domain of the new hostname to the search domains of an interface.
+    #[serde(default)]
+    pub align_search_domains: bool,
+    /// Interface whose search domains are aligned. Defaults to the one carrying the default route.
+    pub interface: Option<String>,
 }
 
 /// Configures the Actix web service with the provided `HostInfoStore`.
@@ -26,16 +37,23 @@ struct InputHostInfo {
 /// # Arguments
 ///
 /// * `store` - A `Data` instance containing the `HostInfoStore`.
+/// * `netplan_store` - A `Data` instance containing the `NetplanStore`, used to keep
+///   the search domains consistent with the hostname.
 ///
 /// # Returns
 ///
 /// A closure that takes a mutable reference to `ServiceConfig` and configures
-/// it with the provided `HostInfoStore` and services.
-pub fn configure(store: Data<HostInfoStore>) -> impl FnOnce(&mut ServiceConfig) {
+/// it with the provided stores and services.
+pub fn configure(
+    store: Data<HostInfoStore>,
+    netplan_store: Data<NetplanStore>,
+) -> impl FnOnce(&mut ServiceConfig) {
     move |cfg: &mut ServiceConfig| {
         cfg.app_data(store)
+            .app_data(netplan_store)
             .service(get_host_info)
-            .service(update_host_info);
+            .service(update_host_info)
+            .service(get_dns_consistency);
     }
 }
 
@@ -60,17 +78,263 @@ pub async fn get_host_info(store: Data<HostInfoStore>) -> impl Responder {
 
 #[api_path(operation_id = "update-host-information")]
 #[patch("")]
+/// Updates the host information.
+///
+/// If `align_search_domains` is set and the new hostname is a FQDN, its domain is added to the
+/// search domains of `interface` (or of the interface carrying the default route). The hostname
+/// is changed first; if applying the network configuration fails, the previous hostname is restored.
+///
+/// # Arguments
+///
+/// * `store` - A `Data` instance containing the `HostInfoStore`.
+/// * `netplan_store` - A `Data` instance containing the `NetplanStore`.
+/// * `new_host_info` - The new host information.
+///
+/// # Returns
+///
+/// - `HttpResponse::Ok` with the new hostname if successful.
+/// - `HttpResponse::BadRequest` if the hostname is invalid.
+/// - `HttpResponse::NotFound` if no hostname was given, or no interface can be aligned.
+/// - `HttpResponse::InternalServerError` if the hostname or the network configuration could not be changed.
 pub async fn update_host_info(
     store: Data<HostInfoStore>,
+    netplan_store: Data<NetplanStore>,
     new_host_info: Json<InputHostInfo>,
 ) -> HttpResponse {
     let new_host_info: InputHostInfo = new_host_info.into_inner();
-    let store = store.host_info.lock().unwrap();
-    if let Some(hostname) = new_host_info.hostname {
-        match store.set_hostname(&hostname) {
-            Ok(_) => return HttpResponse::Ok().json(hostname),
-            Err(err) => return HttpResponse::InternalServerError().json(err.to_string()),
+    let Some(hostname) = new_host_info.hostname else {
+        return HttpResponse::NotFound().json("Hostname not found");
+    };
+    let domain = match hostname_domain(&hostname) {
+        Ok(domain) => domain,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let domain = match domain {
+        Some(domain) if new_host_info.align_search_domains => domain,
+        _ => {
+            return match store.set_hostname(&hostname) {
+                Ok(_) => HttpResponse::Ok().json(hostname),
+                Err(err) => HttpResponse::InternalServerError().json(err.to_string()),
+            }
+        }
+    };
+    // Everything that can fail on the network side is resolved before touching the hostname
+    let netplan = netplan_store.lock().await;
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let interface = match new_host_info
+        .interface
+        .or_else(|| default_route_interface(&network))
+    {
+        Some(interface) => interface,
+        None => {
+            return HttpResponse::NotFound()
+                .json("No interface to align the search domains on was found")
         }
+    };
+    let Some(mut ethernet) = network.get_ethernets().get(&interface).cloned() else {
+        return HttpResponse::NotFound().json(format!("Ethernet {interface} was not found."));
+    };
+    let previous_hostname = match store.get_hostname() {
+        Ok(previous_hostname) => previous_hostname.trim().to_string(),
+        Err(err) => return HttpResponse::InternalServerError().json(err.to_string()),
+    };
+    if let Err(err) = store.set_hostname(&hostname) {
+        return HttpResponse::InternalServerError().json(err.to_string());
+    }
+    ethernet.add_nameservers_search(domain);
+    network.add_ethernet(&ethernet);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(hostname);
+    };
+    match netplan.commit(mutation, true).await {
+        Ok(_) => HttpResponse::Ok().json(hostname),
+        Err(err) => {
+            if let Err(rollback_err) = store.set_hostname(&previous_hostname) {
+                log::error!(
+                    "Could not restore hostname {previous_hostname} after a failed apply: {rollback_err}"
+                );
+            }
+            err.error_response()
+        }
+    }
+}
+
+#[api_path(operation_id = "get-dns-consistency")]
+#[get("/dns-consistency")]
+/// Checks whether the domain of the hostname is part of the configured search domains.
+///
+/// Hosts whose domain isn't searched fail to resolve short names of their neighbours,
+/// which usually shows up as inconsistent behaviour between otherwise identical appliances.
+///
+/// # Arguments
+///
+/// * `store` - A `Data` instance containing the `HostInfoStore`.
+/// * `netplan_store` - A `Data` instance containing the `NetplanStore`.
+///
+/// # Returns
+///
+/// - `HttpResponse::Ok` with the FQDN, the search domains of all interfaces, and whether the domain is covered.
+/// - `HttpResponse::InternalServerError` if the hostname or the network configuration could not be read.
+pub async fn get_dns_consistency(
+    store: Data<HostInfoStore>,
+    netplan_store: Data<NetplanStore>,
+) -> HttpResponse {
+    let fqdn = match store.get_hostname() {
+        Ok(fqdn) => fqdn,
+        Err(err) => return HttpResponse::InternalServerError().json(err.to_string()),
+    };
+    let network = match netplan_store.lock().await.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    HttpResponse::Ok().json(DnsConsistency::new(&fqdn, &network))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scripted_store, ScriptedRunner};
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::{json, Value};
+    use utoipa_actix_web::AppExt;
+
+    const CONFIG: &str = "network: {version: 2, renderer: networkd, ethernets: {\
+        enp1s0: {addresses: [10.0.0.2/24], routes: [{to: 0.0.0.0, via: 10.0.0.1}]},\
+        enp2s0: {addresses: [192.168.1.2/24], nameservers: {search: [corp.example]}}}}";
+
+    /// A test service of the host information, whose `hostnamectl` and `netplan` run through
+    /// `runner`, with the configuration at `config_path`.
+    macro_rules! init_host_info {
+        ($config_path:expr, $runner:expr) => {{
+            let store = Data::new(HostInfoStore::with_runner(Box::new($runner.clone())));
+            let netplan_store = scripted_store($config_path, &$runner);
+            test::init_service(
+                App::new()
+                    .into_utoipa_app()
+                    .service(
+                        utoipa_actix_web::scope("/host-info")
+                            .configure(configure(store, netplan_store)),
+                    )
+                    .into_app(),
+            )
+            .await
+        }};
+    }
+
+    fn search_domains(config_path: &std::path::Path, interface: &str) -> Value {
+        let config: Value =
+            serde_yml::from_str(&std::fs::read_to_string(config_path).unwrap()).unwrap();
+        config["network"]["ethernets"][interface]["nameservers"]["search"].clone()
+    }
+
+    #[actix_web::test]
+    async fn aligns_the_search_domains_of_the_default_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, CONFIG).unwrap();
+        let runner =
+            ScriptedRunner::default().on("hostnamectl hostname", 0, "scanner1.lab.example\n");
+        let app = init_host_info!(&config_path, runner);
+
+        let req = test::TestRequest::get()
+            .uri("/host-info/dns-consistency")
+            .to_request();
+        let consistency: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(consistency["domain"], json!("lab.example"));
+        assert_eq!(consistency["search_domains"], json!(["corp.example"]));
+        assert_eq!(consistency["covered"], json!(false));
+
+        let req = test::TestRequest::patch()
+            .uri("/host-info")
+            .set_json(json!({"hostname": "scanner1.lab.example", "align_search_domains": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            search_domains(&config_path, "enp1s0"),
+            json!(["lab.example"])
+        );
+        let calls = runner.calls();
+        let set = calls
+            .iter()
+            .position(|call| call == "hostnamectl hostname scanner1.lab.example")
+            .unwrap();
+        let apply = calls
+            .iter()
+            .position(|call| call == "netplan apply")
+            .unwrap();
+        assert!(set < apply);
+
+        let req = test::TestRequest::get()
+            .uri("/host-info/dns-consistency")
+            .to_request();
+        let consistency: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(consistency["covered"], json!(true));
+    }
+
+    #[actix_web::test]
+    async fn restores_the_hostname_if_the_network_is_not_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, CONFIG).unwrap();
+        let runner = ScriptedRunner::default()
+            .on("hostnamectl hostname", 0, "scanner1.old.example\n")
+            .failing("netplan apply", 1, "Invalid search domain");
+        let app = init_host_info!(&config_path, runner);
+
+        let req = test::TestRequest::patch()
+            .uri("/host-info")
+            .set_json(json!({
+                "hostname": "scanner1.lab.example",
+                "align_search_domains": true,
+                "interface": "enp2s0",
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_server_error());
+        assert_eq!(
+            search_domains(&config_path, "enp2s0"),
+            json!(["corp.example"])
+        );
+        let calls = runner.calls();
+        let set = calls
+            .iter()
+            .position(|call| call == "hostnamectl hostname scanner1.lab.example")
+            .unwrap();
+        let restore = calls
+            .iter()
+            .position(|call| call == "hostnamectl hostname scanner1.old.example")
+            .unwrap();
+        assert!(set < restore);
+    }
+
+    #[actix_web::test]
+    async fn hostnames_without_a_domain_leave_the_network_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, CONFIG).unwrap();
+        let runner = ScriptedRunner::default();
+        let app = init_host_info!(&config_path, runner);
+
+        let req = test::TestRequest::patch()
+            .uri("/host-info")
+            .set_json(json!({"hostname": "scanner1", "align_search_domains": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(runner.calls(), ["hostnamectl hostname scanner1"]);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), CONFIG);
+
+        let req = test::TestRequest::patch()
+            .uri("/host-info")
+            .set_json(json!({"hostname": "-scanner1", "align_search_domains": true}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(runner.calls().len(), 1);
     }
-    HttpResponse::NotFound().json("Hostname not found")
 }
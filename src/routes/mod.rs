@@ -1,2 +1,16 @@
+pub mod admin;
+pub mod bonds;
+pub mod bridges;
+pub mod captures;
+pub mod device;
+pub mod devices;
+pub mod dummy_devices;
 pub mod ethernet;
+pub mod health;
 pub mod host_info;
+pub mod netplan;
+pub mod network;
+pub mod tunnels;
+pub mod vlans;
+pub mod vrfs;
+pub mod wifis;
@@ -0,0 +1,784 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    get,
+    http::header,
+    post, put,
+    web::{Bytes, Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    misc::yaml_mapping_to_json_compatible,
+    models::{
+        device::Device,
+        input_models::{CascadeQuery, ForceQuery, TryQuery},
+        network::Network,
+        validation::{validate_network, ValidationIssue},
+    },
+    netplan::{restore_tried, Netplan, NetplanError, NetplanStore, PendingTry},
+    routes::device::check_dependents,
+    system::System,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_netplan_config,
+    replace_netplan_config,
+    get_netplan_diff,
+    validate_netplan,
+    try_netplan,
+    confirm_netplan,
+    rollback_netplan,
+    get_netplan_backups,
+    restore_netplan_backup,
+))]
+/// API documentation for the configuration-wide netplan operations.
+pub struct NetplanApi;
+
+/// Configures the Actix web service with the configuration-wide netplan endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_netplan_config)
+            .service(replace_netplan_config)
+            .service(get_netplan_diff)
+            .service(validate_netplan)
+            .service(try_netplan)
+            .service(confirm_netplan)
+            .service(rollback_netplan)
+            .service(get_netplan_backups)
+            .service(restore_netplan_backup);
+    }
+}
+
+#[api_path(operation_id = "get-netplan-config")]
+#[get("/config")]
+/// Exports the active configuration.
+///
+/// The configuration is returned as netplan YAML, the way the configuration file is written under
+/// its `network` root, or as the JSON network model if the client accepts `application/json`.
+/// The YAML leaves out the devices of other configuration files and the fields netplan doesn't
+/// know about, like labels. Secrets are left out in both cases. The configuration file is created
+/// if it is missing.
+///
+/// If the file uses YAML anchors, aliases or merge keys, they are returned expanded, with a
+/// `Warning` header listing them, as the next change saved normalizes the file that way. In strict
+/// mode (`OPENSIGHT_NETPLAN_STRICT_YAML`), such changes are refused with a conflict instead.
+///
+/// # Arguments
+/// - `req`: The `HttpRequest`, whose `Accept` header chooses the format.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with the configuration, as `application/yaml` or `application/json`.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_netplan_config(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network.redacted(),
+    };
+    let references = match netplan.yaml_references().await {
+        Err(err) => return err.error_response(),
+        Ok(references) => references,
+    };
+    let mut response = HttpResponse::Ok();
+    if !references.is_empty() {
+        let warning = format!(
+            "The configuration file uses YAML anchors, aliases or merge keys ({}), which are \
+            expanded here and when the next change is saved.",
+            references.join(", ")
+        );
+        response.insert_header((header::WARNING, format!("199 - \"{warning}\"")));
+    }
+    let accepts_json = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+    if accepts_json {
+        return response.json(network);
+    }
+    response
+        .content_type("application/yaml")
+        .body(Netplan::to_netplan_yaml(&network))
+}
+
+#[api_path(operation_id = "replace-netplan-config", request_body = String)]
+#[put("/config")]
+/// Replaces the whole configuration, and applies it.
+///
+/// The body is either the JSON network model (`application/json`) or netplan YAML (`application/yaml`).
+/// Every configured interface must exist in the system, unless it is selected through `match`. The
+/// configuration is validated as with `POST /netplan/validate`, and if netplan fails to apply it, the
+/// previous configuration is restored. Interfaces left out of the new configuration may not be
+/// referenced by the devices kept in it, unless `cascade` is set. The devices defined in other netplan
+/// files have to be kept as they are, since only the file of the API is written.
+///
+/// # Arguments
+/// - `req`: The `HttpRequest`, whose `Content-Type` header tells the format of the body.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on a removed
+///   interface are removed as well, and the devices it was a member of release it.
+/// - `body`: The complete network configuration.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the applied network configuration.
+/// - `HttpResponse::BadRequest` if the body is not a network configuration.
+/// - `HttpResponse::Conflict` if a netplan try is pending, a removed interface is still referenced by
+///   other devices and `cascade` is not set, or a device defined in another netplan file is changed,
+///   naming that file.
+/// - `HttpResponse::UnsupportedMediaType` if the body is neither JSON nor YAML.
+/// - `HttpResponse::UnprocessableEntity` with a JSON body containing the validation report, if there are errors.
+/// - `HttpResponse::BadGateway` with the error of netplan if the configuration could not be applied.
+/// - `HttpResponse::ServiceUnavailable` if the renderer's service is not running.
+/// - `HttpResponse::GatewayTimeout` if netplan did not finish in time, after which the previous
+///   configuration is restored.
+/// - `HttpResponse::InternalServerError` if there is an issue saving the configuration.
+pub async fn replace_netplan_config(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    cascade: Query<CascadeQuery>,
+    body: Bytes,
+) -> impl Responder {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default();
+    if !content_type.contains("json") && !content_type.contains("yaml") {
+        return HttpResponse::UnsupportedMediaType()
+            .body("The configuration must be sent as application/json or application/yaml.");
+    }
+    let mut network = match Netplan::parse_config(&body) {
+        Ok(network) => network,
+        Err(err) => return HttpResponse::BadRequest().body(err.to_string()),
+    };
+    let netplan = netplan_store.lock().await;
+    let current = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(current) => current,
+    };
+    if let Err(err) = current.keep_foreign_devices(&mut network) {
+        return HttpResponse::Conflict().body(err);
+    }
+    let mut removed: Vec<String> = current
+        .interfaces()
+        .into_keys()
+        .filter(|name| !network.is_configured(name))
+        .collect();
+    removed.sort();
+    for name in removed {
+        if let Some(rejection) = check_dependents(&mut network, &name, cascade.cascade) {
+            return rejection;
+        }
+    }
+    let mut report = validate_network(&network);
+    let mut names: Vec<&String> = network.get_ethernets().keys().collect();
+    names.sort();
+    for name in names {
+        let ethernet = &network.get_ethernets()[name];
+        if ethernet.get_match().is_none() && !System::interface_exists(name) {
+            report.errors.push(ValidationIssue {
+                interface: Some(name.clone()),
+                field: "name".to_string(),
+                message: format!("Interface {name} does not exist in the system."),
+            });
+        }
+    }
+    if !report.is_valid() {
+        return HttpResponse::UnprocessableEntity().json(report);
+    }
+    if netplan_store
+        .pending_try
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(PendingTry::is_running)
+    {
+        return HttpResponse::Conflict().body("A netplan try is pending.");
+    }
+    match netplan.replace_config(network).await {
+        Ok(network) => HttpResponse::Ok().json(network),
+        // netplan rejecting a whole configuration sent by the client is reported as a bad gateway
+        Err(NetplanError::ApplyFailed(message)) => HttpResponse::BadGateway().body(message),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-netplan-diff")]
+#[get("/diff")]
+/// Retrieves the differences between the configuration file and the state of the system.
+///
+/// This function reports the `system_state` differences of `netplan status --diff`, e.g. addresses
+/// or routes present in the system but not configured, or vice versa.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body mapping each interface with differences to its `system_state`.
+/// - `HttpResponse::BadGateway` if netplan failed to report the state of the system.
+/// - `HttpResponse::InternalServerError` if the state of the system could not be retrieved.
+pub async fn get_netplan_diff(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.get_diff().await {
+        Ok(diff) => HttpResponse::Ok().json(
+            diff.iter()
+                .map(|(interface, system_state)| {
+                    (interface, yaml_mapping_to_json_compatible(system_state))
+                })
+                .collect::<HashMap<_, _>>(),
+        ),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "validate-netplan", request_body = Object)]
+#[post("/validate")]
+/// Validates a complete network configuration, without saving or applying it.
+///
+/// This function checks the consistency of the configuration (e.g. conflicting addresses, or route
+/// gateways outside of the interface's subnets), and then runs `netplan generate` over it in a scratch
+/// root directory. Nothing is written to `/etc/netplan`.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `network`: The complete network configuration to be validated.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the validation report, whose errors are empty.
+/// - `HttpResponse::BadRequest` if the body is not a network configuration.
+/// - `HttpResponse::UnprocessableEntity` with a JSON body containing the validation report, if there are errors.
+/// - `HttpResponse::InternalServerError` if `netplan generate` could not be run.
+pub async fn validate_netplan(
+    netplan_store: Data<NetplanStore>,
+    network: Json<Network>,
+) -> impl Responder {
+    let mut report = validate_network(&network);
+    // Structural errors would be reported again, less clearly, by netplan
+    if report.is_valid() {
+        let netplan = netplan_store.lock().await;
+        let candidate = network.clone();
+        match netplan
+            .run(move |netplan| Ok(netplan.generate(&candidate)?))
+            .await
+        {
+            Ok(()) => (),
+            Err(NetplanError::Io(err)) if err.kind() == std::io::ErrorKind::InvalidData => {
+                report.errors.push(ValidationIssue {
+                    interface: None,
+                    field: "netplan generate".to_string(),
+                    message: err.to_string(),
+                });
+            }
+            Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+        }
+    }
+    if report.is_valid() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::UnprocessableEntity().json(report)
+    }
+}
+
+#[api_path(operation_id = "try-netplan")]
+#[post("/try")]
+/// Tries the saved configuration, reverting it unless confirmed in time.
+///
+/// This function runs `netplan try` over the configuration file as it is, e.g. with the changes staged
+/// through `apply=false`. The try has to be confirmed with `POST /netplan/confirm` before the timeout,
+/// or the previous configuration is restored, so a bad configuration can't lock the operator out.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `query`: A `Query<TryQuery>` instance with the timeout of the try, in seconds (120 by default).
+///
+/// # Returns
+/// - `HttpResponse::Accepted` if the try was started.
+/// - `HttpResponse::Conflict` if another try is already pending.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration or starting the try.
+pub async fn try_netplan(
+    netplan_store: Data<NetplanStore>,
+    query: Query<TryQuery>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    if netplan_store
+        .pending_try
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(PendingTry::is_running)
+    {
+        return HttpResponse::Conflict().body("Another netplan try is already pending.");
+    }
+    if let Err(err) = netplan.load_config().await {
+        return err.error_response();
+    }
+    let timeout = query.timeout;
+    match netplan.run(move |netplan| netplan.tryout(timeout)).await {
+        Ok(started) => {
+            // No other try could start meanwhile, as the session is still open
+            *netplan_store.pending_try.lock().unwrap() = Some(started);
+            HttpResponse::Accepted().body(format!(
+                "Trying the configuration for {} seconds. Confirm it with POST /netplan/confirm.",
+                query.timeout
+            ))
+        }
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "confirm-netplan")]
+#[post("/confirm")]
+/// Confirms the pending try, keeping the tried configuration.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the tried configuration was kept.
+/// - `HttpResponse::Conflict` if no try is pending, e.g. as it timed out, in which case the previous
+///   configuration was restored.
+/// - `HttpResponse::InternalServerError` if the try could not be confirmed.
+pub async fn confirm_netplan(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let Some(mut pending_try) = netplan_store.pending_try.lock().unwrap().take() else {
+        return HttpResponse::Conflict().body("No netplan try is pending.");
+    };
+    if !pending_try.is_running() {
+        // It timed out since the session was opened. netplan already reverted the system, so
+        // the file follows
+        let previous = pending_try.previous();
+        if let Err(err) = netplan
+            .run(move |netplan| restore_tried(netplan, previous.as_ref()))
+            .await
+        {
+            log::error!(
+                "The netplan try timed out, but the previous configuration could not be restored: {err}"
+            );
+            return err.error_response();
+        }
+        return HttpResponse::Conflict()
+            .body("The netplan try timed out, and the previous configuration was restored.");
+    }
+    match pending_try.confirm() {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(err) => NetplanError::from(err).error_response(),
+    }
+}
+
+#[api_path(operation_id = "rollback-netplan")]
+#[post("/rollback")]
+/// Rolls the pending try back, restoring and applying the previous configuration.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the previous configuration was restored.
+/// - `HttpResponse::Conflict` if no try is pending.
+/// - `HttpResponse::InternalServerError` if the try could not be stopped, or the previous configuration applied.
+pub async fn rollback_netplan(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let Some(pending_try) = netplan_store.pending_try.lock().unwrap().take() else {
+        return HttpResponse::Conflict().body("No netplan try is pending.");
+    };
+    let previous = pending_try.previous();
+    if let Err(err) = pending_try.cancel() {
+        return NetplanError::from(err).error_response();
+    }
+    let result = netplan
+        .run(move |netplan| {
+            restore_tried(netplan, previous.as_ref())?;
+            netplan.apply()
+        })
+        .await;
+    match result {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-netplan-backups")]
+#[get("/backups")]
+/// Lists the backups of the configuration, one being taken before every save.
+///
+/// Only the latest ones are kept, ten unless configured otherwise through the
+/// `OPENSIGHT_NETPLAN_BACKUPS` environment variable.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the timestamps of the backups, in milliseconds since
+///   the Unix epoch, oldest first.
+/// - `HttpResponse::InternalServerError` if the backups could not be listed.
+pub async fn get_netplan_backups(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.run(|netplan| Ok(netplan.list_backups()?)).await {
+        Ok(timestamps) => HttpResponse::Ok().json(timestamps),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "restore-netplan-backup")]
+#[post("/backups/{timestamp}/restore")]
+/// Restores a backup of the configuration, and applies it.
+///
+/// The current configuration is backed up first, so the restore can be undone the same way. If
+/// netplan fails to apply the backup, the current configuration is restored.
+///
+/// The backup imported at startup from the single backup kept before the upgrade is only restored
+/// with `force=true`, as how it relates to the current configuration is unknown.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `timestamp`: The timestamp of the backup to restore, as listed by `GET /netplan/backups`.
+/// - `force`: A `Query<ForceQuery>` instance; `force=true` is required to restore the backup
+///   imported from before the upgrade.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the applied network configuration.
+/// - `HttpResponse::NotFound` if there is no backup with this timestamp.
+/// - `HttpResponse::Conflict` if a netplan try is pending, or if the backup was imported from before
+///   the upgrade and `force` is not set.
+/// - `HttpResponse::ServiceUnavailable` if the renderer's service is not running.
+/// - `HttpResponse::GatewayTimeout` if netplan did not finish in time, after which the previous
+///   configuration is restored.
+/// - `HttpResponse::InternalServerError` if there is an issue restoring or applying the backup, with the
+///   error of netplan and the outcome of the rollback.
+pub async fn restore_netplan_backup(
+    netplan_store: Data<NetplanStore>,
+    timestamp: Path<u64>,
+    force: Query<ForceQuery>,
+) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    if netplan_store
+        .pending_try
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(PendingTry::is_running)
+    {
+        return HttpResponse::Conflict().body("A netplan try is pending.");
+    }
+    let timestamp = timestamp.into_inner();
+    match netplan.apply_backup(timestamp, force.force).await {
+        Ok(network) => HttpResponse::Ok().json(network),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netplan::NetplanBackend;
+    use crate::test_support::{init_scope, scripted_netplan, scripted_store, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn replacing_the_config_keeps_the_referenced_interfaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let config = "network: {version: 2, ethernets: {eth0: {}, eth1: {}}, \
+            vlans: {vlan10: {id: 10, link: eth0}}, bridges: {br0: {interfaces: [eth1]}}}\n";
+        std::fs::write(&config_path, config).unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::put()
+            .uri("/netplan/config")
+            .insert_header((header::CONTENT_TYPE, "application/yaml"))
+            .set_payload(
+                "network: {version: 2, vlans: {vlan10: {id: 10, link: eth0}}, \
+                bridges: {br0: {interfaces: [eth1]}}}",
+            )
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(
+            body.starts_with("eth0 is still used by VLAN vlan10."),
+            "{body}"
+        );
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config);
+        assert!(!runner.calls().contains(&"netplan apply".to_string()));
+    }
+
+    #[actix_web::test]
+    async fn the_pre_upgrade_backup_is_only_restored_by_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let config = "network: {version: 2, ethernets: {eth0: {dhcp4: true}}}\n";
+        std::fs::write(&config_path, config).unwrap();
+        let legacy = "version: 2\nethernets: {eth1: {dhcp4: true}}\n";
+        std::fs::write(dir.path().join("01-network-conf.yaml.bak"), legacy).unwrap();
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner);
+        let timestamp = netplan.migrate_legacy_backup().unwrap().unwrap();
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        let app = init_scope!("/netplan", configure, store);
+
+        let uri = format!("/netplan/backups/{timestamp}/restore");
+        let req = test::TestRequest::post().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), config);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("{uri}?force=true"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let network: serde_json::Value = test::read_body_json(resp).await;
+        assert!(network["ethernets"].get("eth1").is_some());
+    }
+
+    #[actix_web::test]
+    async fn failing_to_list_the_backups_is_reported_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        // The directory of the configuration doesn't exist, so it can't be read
+        let config_path = dir.path().join("missing/01-network-conf.yaml");
+        let store = scripted_store(&config_path, &ScriptedRunner::default());
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::get()
+            .uri("/netplan/backups")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let error: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(error["code"], "io");
+    }
+
+    #[actix_web::test]
+    async fn the_exported_yaml_is_the_configuration_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let config =
+            "network:\n  version: 2\n  renderer: networkd\n  ethernets:\n    eth0:\n      \
+            dhcp4: false\n      addresses: [10.0.0.2/24]\n      \
+            nameservers: {addresses: [10.0.0.53], search: [example.com]}\n      \
+            routes: [{to: default, via: 10.0.0.1}]\n  vlans:\n    vlan10: {id: 10, link: eth0}\n";
+        std::fs::write(&config_path, config).unwrap();
+        // Devices of other files are theirs to export
+        let other_config = "network: {version: 2, ethernets: {eth9: {dhcp4: true}}}\n";
+        std::fs::write(dir.path().join("50-cloud-init.yaml"), other_config).unwrap();
+        let store = scripted_store(&config_path, &ScriptedRunner::default());
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::get().uri("/netplan/config").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/yaml"
+        );
+        let body = test::read_body(resp).await;
+        let exported: serde_yml::Value = serde_yml::from_slice(&body).unwrap();
+        let on_disk: serde_yml::Value = serde_yml::from_str(config).unwrap();
+        assert_eq!(
+            Netplan::parse_config(&body).unwrap(),
+            Netplan::parse_config(config.as_bytes()).unwrap()
+        );
+        assert_eq!(
+            exported["network"]["ethernets"]
+                .as_mapping()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            on_disk["network"]["ethernets"]
+                .as_mapping()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+        for field in [
+            "managed-by",
+            "pending-reboot",
+            "pending-reboot-reasons",
+            "labels",
+        ] {
+            assert!(exported["network"]["ethernets"]["eth0"]
+                .get(field)
+                .is_none());
+        }
+    }
+
+    const PREVIOUS_CONFIG: &str = "network: {version: 2, ethernets: {eth0: {dhcp4: true}}}\n";
+
+    /// A store whose configuration was saved over `PREVIOUS_CONFIG`, ready to be tried.
+    fn tried_store(
+        dir: &std::path::Path,
+        runner: &ScriptedRunner,
+    ) -> (std::path::PathBuf, Data<NetplanStore>) {
+        let config_path = dir.join("01-network-conf.yaml");
+        std::fs::write(&config_path, PREVIOUS_CONFIG).unwrap();
+        let netplan = scripted_netplan(&config_path, runner);
+        let candidate = Netplan::parse_config(b"{version: 2, ethernets: {eth0: {}}}").unwrap();
+        netplan.save_config(&candidate).unwrap();
+        (config_path, Data::new(NetplanStore::new(Box::new(netplan))))
+    }
+
+    #[actix_web::test]
+    async fn a_timed_out_try_restores_the_previous_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        // netplan exits right away, like at the end of the timeout
+        let runner = ScriptedRunner::default();
+        let (config_path, store) = tried_store(dir.path(), &runner);
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::post()
+            .uri("/netplan/try?timeout=1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let try_command = format!(
+            "netplan try --timeout 1 --config-file {}",
+            config_path.display()
+        );
+        assert!(runner.calls().contains(&try_command));
+        while store
+            .pending_try
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .is_running()
+        {
+            actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Whatever comes next works on the previous configuration
+        let req = test::TestRequest::get().uri("/netplan/config").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            PREVIOUS_CONFIG
+        );
+        let req = test::TestRequest::post()
+            .uri("/netplan/confirm")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn a_confirmed_try_keeps_the_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("netplan try", Duration::from_secs(60), "");
+        let (config_path, store) = tried_store(dir.path(), &runner);
+        let tried = std::fs::read_to_string(&config_path).unwrap();
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::post().uri("/netplan/try").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let req = test::TestRequest::post().uri("/netplan/try").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/netplan/confirm")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(store.pending_try.lock().unwrap().is_none());
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), tried);
+    }
+
+    #[actix_web::test]
+    async fn a_rolled_back_try_restores_the_previous_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().hanging("netplan try", Duration::from_secs(60), "");
+        let (config_path, store) = tried_store(dir.path(), &runner);
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::post().uri("/netplan/try").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let req = test::TestRequest::post()
+            .uri("/netplan/rollback")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            std::fs::read_to_string(&config_path).unwrap(),
+            PREVIOUS_CONFIG
+        );
+        assert!(runner.calls().contains(&"netplan apply".to_string()));
+    }
+
+    #[actix_web::test]
+    async fn a_try_failing_to_start_is_reported_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = ScriptedRunner::default().missing("netplan try");
+        let (_config_path, store) = tried_store(dir.path(), &runner);
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::post().uri("/netplan/try").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_server_error());
+        let error: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(error["code"], "not_installed");
+    }
+
+    #[actix_web::test]
+    async fn exporting_yaml_references_warns_that_they_are_expanded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/yaml-references");
+        std::fs::copy(fixtures.join("anchored-nameservers.yaml"), &config_path).unwrap();
+        let store = scripted_store(&config_path, &ScriptedRunner::default());
+        let app = init_scope!("/netplan", configure, store);
+
+        let req = test::TestRequest::get()
+            .uri("/netplan/config")
+            .insert_header((header::ACCEPT, "application/json"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let warning = resp
+            .headers()
+            .get(header::WARNING)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(warning.starts_with("199 - \""));
+        assert!(warning.contains("(&office-dns, *office-dns)"));
+        let network: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            network["ethernets"]["eth1"]["nameservers"],
+            network["ethernets"]["eth0"]["nameservers"]
+        );
+
+        // Without references, there is nothing to warn about
+        std::fs::copy(
+            fixtures.join("anchored-nameservers.expanded.yaml"),
+            &config_path,
+        )
+        .unwrap();
+        let req = test::TestRequest::get().uri("/netplan/config").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(header::WARNING).is_none());
+    }
+}
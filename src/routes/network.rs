@@ -0,0 +1,217 @@
+use actix_web::{get, web::Data, HttpResponse, Responder, ResponseError};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::netplan::NetplanStore;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_wait_online, get_pending_actions))]
+/// API documentation for the system-wide network state.
+pub struct NetworkApi;
+
+/// Configures the Actix web service with the system-wide network endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_wait_online)
+            .service(get_pending_actions);
+    }
+}
+
+#[api_path(operation_id = "get-wait-online")]
+#[get("/wait-online")]
+/// Retrieves which interfaces are gating boot through systemd-networkd-wait-online.
+///
+/// This function reads the effective `RequiredForOnline=` setting of each interface from the
+/// configuration rendered by netplan and its drop-ins, together with the state of the wait-online
+/// service. A warning is included when no interface is required for online.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the wait-online report.
+/// - `HttpResponse::InternalServerError` if the rendered configuration could not be read.
+pub async fn get_wait_online(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.wait_online().await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[api_path(operation_id = "get-pending-actions")]
+#[get("/pending-actions")]
+/// Retrieves the changes to the configuration which only take full effect after a reboot.
+///
+/// Renaming interfaces through `set-name`, changing their `match`, switching renderers, and
+/// changing the MTU of interfaces whose driver only takes it when loaded are saved and applied,
+/// but the system keeps its current state until the next boot. Each change is listed with the
+/// revision of the configuration which introduced it, i.e. its entity tag, and the interfaces it
+/// affects are marked with `pending-reboot`. After a reboot, the changes the system shows are
+/// cleared, and the others are kept.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the changes waiting for a reboot, oldest first.
+/// - `HttpResponse::InternalServerError` if the changes or the id of the boot could not be read.
+pub async fn get_pending_actions(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    match netplan.pending_actions().await {
+        Ok(actions) => HttpResponse::Ok().json(actions),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::device::Device;
+    use crate::models::wait_online::RequiredForOnline;
+    use crate::netplan::{Mutation, Netplan, NetplanBackend};
+    use crate::test_support::{init_scope, scripted_netplan, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+
+    #[actix_web::test]
+    async fn lists_the_changes_waiting_for_a_reboot() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {}, eth1: {}}}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("boot_id"), "first\n").unwrap();
+        let runner = ScriptedRunner::default();
+        let netplan = scripted_netplan(&config_path, &runner)
+            .with_system_paths(dir.path().join("boot_id"), dir.path().join("net"));
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        let session = store.lock().await;
+        let current = session.load_config().await.unwrap();
+        let mut updated = current.clone();
+        let mut eth0 = updated.get_ethernets()["eth0"].clone();
+        eth0.set_set_name(Some("lan0".to_string()));
+        updated.add_ethernet(&eth0);
+        session
+            .commit(Mutation::new(&current, updated).unwrap(), false)
+            .await
+            .unwrap();
+        let marked = session.load_config().await.unwrap();
+        assert_eq!(
+            marked.get_ethernets()["eth0"]
+                .pending_reboot_reasons()
+                .len(),
+            1
+        );
+        assert!(marked.get_ethernets()["eth1"]
+            .pending_reboot_reasons()
+            .is_empty());
+        drop(session);
+        let app = init_scope!("/network", configure, store);
+
+        let req = test::TestRequest::get()
+            .uri("/network/pending-actions")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let actions: serde_json::Value = test::read_body_json(resp).await;
+        let etag = Netplan::with_config_path(config_path)
+            .config_etag()
+            .unwrap();
+        assert_eq!(
+            actions,
+            serde_json::json!([{
+                "interface": "eth0",
+                "field": "set-name",
+                "from": null,
+                "to": "lan0",
+                "reason": "Interfaces are only renamed when their device appears, so set-name takes effect at the next boot.",
+                "revision": etag,
+                "boot_id": "first",
+                "expected": {"kind": "interface", "name": "lan0"},
+            }])
+        );
+    }
+
+    #[actix_web::test]
+    async fn reports_the_interfaces_boot_waits_for() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {dhcp4: true}, eth1: {optional: true}}}\n",
+        )
+        .unwrap();
+        let run_path = dir.path().join("run");
+        std::fs::create_dir(&run_path).unwrap();
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/networkd");
+        for file in ["10-netplan-eth0.network", "10-netplan-eth1.network"] {
+            std::fs::copy(fixtures.join(file), run_path.join(file)).unwrap();
+        }
+        let runner = ScriptedRunner::default().on(
+            "systemctl is-active systemd-networkd-wait-online.service",
+            0,
+            "active\n",
+        );
+        let netplan = scripted_netplan(&config_path, &runner)
+            .with_networkd_paths(run_path, dir.path().join("network"));
+        let store = Data::new(NetplanStore::new(Box::new(netplan)));
+        let app = init_scope!("/network", configure, store.clone());
+
+        let req = test::TestRequest::get()
+            .uri("/network/wait-online")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "service_state": "active",
+                "interfaces": [
+                    {"interface": "eth0", "required_for_online": "yes"},
+                    {"interface": "eth1", "required_for_online": "no"},
+                ],
+                "warnings": [],
+            })
+        );
+
+        // Once eth0 isn't required either, boot never waits for the network
+        let session = store.lock().await;
+        let current = session.load_config().await.unwrap();
+        let mut updated = current.clone();
+        let mut eth0 = updated.get_ethernets()["eth0"].clone();
+        eth0.set_required_for_online(Some(RequiredForOnline::No));
+        updated.add_ethernet(&eth0);
+        session
+            .commit(Mutation::new(&current, updated).unwrap(), false)
+            .await
+            .unwrap();
+        drop(session);
+        let req = test::TestRequest::get()
+            .uri("/network/wait-online")
+            .to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["interfaces"][0]["required_for_online"], "no");
+        assert_eq!(
+            report["warnings"],
+            serde_json::json!([
+                "No interface is required for online: boot will never wait for the network."
+            ])
+        );
+        assert!(runner
+            .calls()
+            .contains(&"systemctl is-active systemd-networkd-wait-online.service".to_string()));
+    }
+}
@@ -0,0 +1,459 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        device::{validate_interface_name, Device},
+        input_models::{ApplyQuery, CascadeQuery, InputTunnel, InputTunnelPatch},
+        network::Network,
+        tunnel::{remote_or_group, Tunnel},
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_tunnels,
+    get_tunnel,
+    create_tunnel,
+    update_tunnel,
+    delete_tunnel,
+))]
+/// API documentation for tunnel management.
+pub struct TunnelsApi;
+
+/// Configures the Actix web service with the tunnel endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_tunnels)
+            .service(get_tunnel)
+            .service(create_tunnel)
+            .service(update_tunnel)
+            .service(delete_tunnel);
+    }
+}
+
+/// Checks a tunnel against the rest of the configuration.
+///
+/// Its remote end may not be one of the host's own addresses, which would loop back, and the
+/// link of a VXLAN has to be a configured interface (422 otherwise).
+fn check_tunnel_references(network: &Network, tunnel: &Tunnel) -> Option<HttpResponse> {
+    if let Some(link) = tunnel.link().filter(|link| !network.is_configured(link)) {
+        return Some(HttpResponse::UnprocessableEntity().body(format!(
+            "{link} is not configured, so {} cannot be linked to it.",
+            tunnel.name()
+        )));
+    }
+    let remote = tunnel.remote()?;
+    let owner = network.owner_of(&remote)?;
+    Some(HttpResponse::UnprocessableEntity().body(format!(
+        "The remote address {remote} of {} is configured on {owner} of this host.",
+        tunnel.name()
+    )))
+}
+
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(tunnel: &Tunnel) -> Option<HttpResponse> {
+    if tunnel.get_ipv6_address_generation().is_some() && tunnel.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            tunnel.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-tunnels")]
+#[get("")]
+/// Retrieves all tunnel entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the tunnel entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_tunnels(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_tunnels())
+}
+
+#[api_path(operation_id = "show-tunnel")]
+#[get("/{tunnel_name}")]
+/// Retrieves a specific tunnel entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `tunnel_name`: The name of the tunnel entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the tunnel entry if found, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified tunnel entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_tunnel(
+    netplan_store: Data<NetplanStore>,
+    tunnel_name: Path<String>,
+) -> impl Responder {
+    let tunnel_name = tunnel_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_tunnels().get(&tunnel_name) {
+        Some(tunnel) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(tunnel),
+        None => HttpResponse::NotFound().body(format!("Tunnel {tunnel_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-tunnel")]
+#[post("/{tunnel_name}")]
+/// Creates a new tunnel entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `tunnel_name`: The name of the tunnel interface to be created.
+/// - `tunnel`: The mode and endpoints of the tunnel, along with its settings.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created tunnel entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, a setting the mode requires is
+///   missing or one it does not support is set (e.g. a key outside of GRE, an id outside of VXLAN), an
+///   endpoint is not of the address family the mode runs over, both a remote and a group are set, or an
+///   IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured.
+/// - `HttpResponse::UnprocessableEntity` if the remote address is configured on one of the host's interfaces,
+///   or the link is not a configured interface.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_tunnel(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    tunnel_name: Path<String>,
+    tunnel: Json<InputTunnel>,
+) -> impl Responder {
+    let tunnel_name = tunnel_name.into_inner();
+    if let Err(err) = validate_interface_name(&tunnel_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    if let Err(err) = remote_or_group(tunnel.remote, tunnel.group) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let tunnel = Tunnel::from_input_tunnel(&tunnel_name, &tunnel);
+    if let Err(err) = tunnel.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    if let Some(rejection) = check_ipv6_address_generation(&tunnel) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&tunnel_name) {
+        return HttpResponse::Conflict().body(format!("{tunnel_name} is already configured."));
+    }
+    if let Some(rejection) = check_tunnel_references(&network, &tunnel) {
+        return rejection;
+    }
+    network.add_tunnel(&tunnel);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(tunnel);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Tunnel>(&network, &tunnel_name) {
+            Ok(tunnel) => HttpResponse::Created().json(tunnel),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-tunnel")]
+#[patch("/{tunnel_name}")]
+/// Updates an existing tunnel entry.
+///
+/// The mode, endpoints and key are only changed if present in the body, and the resulting tunnel
+/// is validated as a whole. The other settings are replaced the same way as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `tunnel_name`: The name of the tunnel entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated tunnel entry if successful.
+/// - `HttpResponse::BadRequest` if the resulting tunnel lacks a setting its mode requires or sets one it
+///   does not support, an endpoint is not of the address family the mode runs over, both a remote and a
+///   group are sent, or an IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::NotFound` if the specified tunnel entry is not found.
+/// - `HttpResponse::UnprocessableEntity` if the remote address is configured on one of the host's interfaces,
+///   or the link is not a configured interface.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_tunnel(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    tunnel_name: Path<String>,
+    patch: Json<InputTunnelPatch>,
+) -> impl Responder {
+    let tunnel_name = tunnel_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut tunnel) = network.get_tunnels().get(&tunnel_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("Tunnel {tunnel_name} was not found."));
+    };
+    if let Some(mode) = patch.mode {
+        tunnel.set_mode(mode);
+    }
+    if let Some(local) = patch.local {
+        tunnel.set_local(Some(local));
+    }
+    match remote_or_group(patch.remote, patch.group) {
+        Err(err) => return HttpResponse::BadRequest().body(err),
+        Ok(Some(remote)) => tunnel.set_remote(Some(remote)),
+        Ok(None) => {}
+    }
+    if let Some(key) = patch.key {
+        tunnel.set_key(Some(key));
+    }
+    if let Some(id) = patch.id {
+        tunnel.set_id(Some(id));
+    }
+    if let Some(link) = &patch.link {
+        tunnel.set_link(Some(link.clone()));
+    }
+    if let Some(port) = patch.port {
+        tunnel.set_port(Some(port));
+    }
+    if let Err(err) = tunnel.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    patch.device.update(&mut tunnel);
+    if let Some(rejection) = check_ipv6_address_generation(&tunnel) {
+        return rejection;
+    }
+    if let Some(rejection) = check_tunnel_references(&network, &tunnel) {
+        return rejection;
+    }
+    network.add_tunnel(&tunnel);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(tunnel);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Tunnel>(&network, &tunnel_name) {
+            Ok(tunnel) => HttpResponse::Ok().json(tunnel),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-tunnel")]
+#[delete("/{tunnel_name}")]
+/// Removes a tunnel entry from the network configuration.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `tunnel_name`: The name of the tunnel entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the tunnel entry was removed.
+/// - `HttpResponse::NotFound` if the specified tunnel entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_tunnel(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    tunnel_name: Path<String>,
+) -> impl Responder {
+    let tunnel_name = tunnel_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_tunnel(&tunnel_name).is_none() {
+        return HttpResponse::NotFound().body(format!("Tunnel {tunnel_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &tunnel_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_scope, scripted_store, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn vxlans_are_managed_through_the_tunnels() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {addresses: [10.0.0.1/24]}}}",
+        )
+        .unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/tunnels", configure, store);
+        let read_tunnel = || {
+            let config: serde_yml::Value =
+                serde_yml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+            config["network"]["tunnels"]["vxlan42"].clone()
+        };
+
+        let req = test::TestRequest::post()
+            .uri("/tunnels/vxlan42")
+            .set_json(json!({"mode": "vxlan", "id": 42, "link": "eth0", "group": "239.1.1.1"}))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(created["id"], 42);
+        assert_eq!(created["remote"], "239.1.1.1");
+        let tunnel = read_tunnel();
+        assert_eq!(tunnel["mode"].as_str(), Some("vxlan"));
+        assert_eq!(tunnel["id"].as_u64(), Some(42));
+        assert_eq!(tunnel["link"].as_str(), Some("eth0"));
+        assert_eq!(tunnel["remote"].as_str(), Some("239.1.1.1"));
+
+        let req = test::TestRequest::get()
+            .uri("/tunnels/vxlan42")
+            .to_request();
+        let read: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(read, created);
+
+        let req = test::TestRequest::patch()
+            .uri("/tunnels/vxlan42")
+            .set_json(json!({"id": 4242, "port": 4789}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let tunnel = read_tunnel();
+        assert_eq!(tunnel["id"].as_u64(), Some(4242));
+        assert_eq!(tunnel["port"].as_u64(), Some(4789));
+
+        let req = test::TestRequest::delete()
+            .uri("/tunnels/vxlan42")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(read_tunnel().is_null());
+    }
+
+    #[actix_web::test]
+    async fn invalid_vxlans_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, "network: {version: 2, ethernets: {eth0: {}}}").unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/tunnels", configure, store);
+
+        for (body, status) in [
+            (json!({"mode": "vxlan", "id": 0}), StatusCode::BAD_REQUEST),
+            (
+                json!({"mode": "vxlan", "id": 16777216}),
+                StatusCode::BAD_REQUEST,
+            ),
+            (json!({"mode": "vxlan"}), StatusCode::BAD_REQUEST),
+            (
+                json!({"mode": "vxlan", "id": 1, "remote": "10.0.0.2", "group": "239.1.1.1"}),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                json!({"mode": "vxlan", "id": 1, "remote": "239.1.1.1", "link": "eth0"}),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                json!({"mode": "vxlan", "id": 1, "group": "239.1.1.1", "link": "eth9"}),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/tunnels/vxlan1")
+                .set_json(&body)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), status, "{body}");
+        }
+        assert!(!runner.calls().contains(&"netplan apply".to_string()));
+    }
+}
@@ -0,0 +1,988 @@
+use std::net::IpAddr;
+
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post, put,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    custom_types::IpNetwork,
+    models::{
+        device::{validate_interface_name, Device},
+        input_models::{
+            ApplyQuery, CascadeQuery, DetailedQuery, ForceQuery, InputNameserversReplacement,
+            InputRoute, InputRoutePatch, InputVlan, InputVlanPatch, ReplaceQuery,
+        },
+        network::Network,
+        route::Route,
+        vlan::{validate_vlan_id, Vlan},
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_vlans,
+    get_vlan,
+    create_vlan,
+    update_vlan,
+    delete_vlan,
+    get_vlan_ip_addresses,
+    get_vlan_dynamic_addresses,
+    add_vlan_ip_address,
+    replace_vlan_ip_addresses,
+    delete_vlan_ip_address,
+    get_vlan_nameservers,
+    add_vlan_nameservers_search,
+    replace_vlan_nameservers,
+    delete_vlan_nameservers_search,
+    add_vlan_nameservers_address,
+    delete_vlan_nameservers_address,
+    delete_vlan_nameservers,
+    get_vlan_routes,
+    get_vlan_route,
+    add_vlan_route,
+    update_vlan_route,
+    delete_vlan_route,
+    delete_vlan_routes,
+))]
+/// API documentation for VLAN management.
+///
+/// Besides the VLAN entries themselves, it documents their addresses, nameservers and routes,
+/// which behave exactly as those of the Ethernet entries.
+pub struct VlansApi;
+
+/// Configures the Actix web service with the VLAN endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_vlans)
+            .service(get_vlan)
+            .service(create_vlan)
+            .service(update_vlan)
+            .service(delete_vlan)
+            .service(get_vlan_ip_addresses)
+            .service(get_vlan_dynamic_addresses)
+            .service(add_vlan_ip_address)
+            .service(replace_vlan_ip_addresses)
+            .service(delete_vlan_ip_address)
+            .service(get_vlan_nameservers)
+            .service(add_vlan_nameservers_search)
+            .service(replace_vlan_nameservers)
+            .service(delete_vlan_nameservers_search)
+            .service(add_vlan_nameservers_address)
+            .service(delete_vlan_nameservers_address)
+            .service(delete_vlan_nameservers)
+            .service(get_vlan_routes)
+            .service(get_vlan_route)
+            .service(add_vlan_route)
+            .service(update_vlan_route)
+            .service(delete_vlan_route)
+            .service(delete_vlan_routes);
+    }
+}
+
+/// Checks a VLAN against the rest of the configuration it is about to be added to.
+///
+/// Its parent interface has to be a configured Ethernet (422 otherwise), and no other VLAN may
+/// use the same id on it (409 otherwise).
+fn check_vlan_link(network: &Network, vlan: &Vlan) -> Option<HttpResponse> {
+    if !network.get_ethernets().contains_key(&vlan.link()) {
+        return Some(HttpResponse::UnprocessableEntity().body(format!(
+            "Ethernet {} is not configured, so VLAN {} cannot be created on it.",
+            vlan.link(),
+            vlan.name()
+        )));
+    }
+    let duplicate = network.get_vlans().values().find(|other| {
+        other.name() != vlan.name() && other.link() == vlan.link() && other.id() == vlan.id()
+    })?;
+    Some(HttpResponse::Conflict().body(format!(
+        "VLAN {} already uses the id {} on {}.",
+        duplicate.name(),
+        vlan.id(),
+        vlan.link()
+    )))
+}
+
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(vlan: &Vlan) -> Option<HttpResponse> {
+    if vlan.get_ipv6_address_generation().is_some() && vlan.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            vlan.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-vlans")]
+#[get("")]
+/// Retrieves all VLAN entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the VLAN entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_vlans(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_vlans())
+}
+
+#[api_path(operation_id = "show-vlan")]
+#[get("/{vlan_name}")]
+/// Retrieves a specific VLAN entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the VLAN entry if found, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan(
+    netplan_store: Data<NetplanStore>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    let vlan_name = vlan_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_vlans().get(&vlan_name) {
+        Some(vlan) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(vlan),
+        None => HttpResponse::NotFound().body(format!("VLAN {vlan_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-vlan")]
+#[post("/{vlan_name}")]
+/// Creates a new VLAN entry on a configured Ethernet.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN interface to be created.
+/// - `vlan`: The id and parent interface of the VLAN, along with its settings.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created VLAN entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, the id is out of range,
+///   or an IPv6 address generation is set while router advertisements are not accepted.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured, or another VLAN
+///   uses the same id on the parent interface.
+/// - `HttpResponse::UnprocessableEntity` if the parent interface (`link`) is not a configured Ethernet.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_vlan(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    vlan: Json<InputVlan>,
+) -> impl Responder {
+    let vlan_name = vlan_name.into_inner();
+    if let Err(err) = validate_interface_name(&vlan_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    if let Err(err) = validate_vlan_id(vlan.id) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let vlan = Vlan::from_input_vlan(&vlan_name, &vlan);
+    if let Some(rejection) = check_ipv6_address_generation(&vlan) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&vlan_name) {
+        return HttpResponse::Conflict().body(format!("{vlan_name} is already configured."));
+    }
+    if let Some(rejection) = check_vlan_link(&network, &vlan) {
+        return rejection;
+    }
+    network.add_vlan(&vlan);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(vlan);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vlan>(&network, &vlan_name) {
+            Ok(vlan) => HttpResponse::Created().json(vlan),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-vlan")]
+#[patch("/{vlan_name}")]
+/// Updates an existing VLAN entry.
+///
+/// The id and parent interface are only changed if present in the body. The other settings are
+/// replaced the same way as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::BadRequest` if the id is out of range, or an IPv6 address generation is set while
+///   router advertisements are not accepted.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` if another VLAN uses the same id on the parent interface.
+/// - `HttpResponse::UnprocessableEntity` if the parent interface (`link`) is not a configured Ethernet.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_vlan(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    patch: Json<InputVlanPatch>,
+) -> impl Responder {
+    let vlan_name = vlan_name.into_inner();
+    if let Some(id) = patch.id {
+        if let Err(err) = validate_vlan_id(id) {
+            return HttpResponse::BadRequest().body(err);
+        }
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut vlan) = network.get_vlans().get(&vlan_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("VLAN {vlan_name} was not found."));
+    };
+    if let Some(id) = patch.id {
+        vlan.set_id(id);
+    }
+    if let Some(link) = &patch.link {
+        vlan.set_link(link.clone());
+    }
+    patch.device.update(&mut vlan);
+    if let Some(rejection) = check_ipv6_address_generation(&vlan) {
+        return rejection;
+    }
+    if let Some(rejection) = check_vlan_link(&network, &vlan) {
+        return rejection;
+    }
+    network.add_vlan(&vlan);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(vlan);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vlan>(&network, &vlan_name) {
+            Ok(vlan) => HttpResponse::Ok().json(vlan),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-vlan")]
+#[delete("/{vlan_name}")]
+/// Removes a VLAN entry from the network configuration.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `vlan_name`: The name of the VLAN entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the VLAN entry was removed.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    let vlan_name = vlan_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_vlan(&vlan_name).is_none() {
+        return HttpResponse::NotFound().body(format!("VLAN {vlan_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &vlan_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[api_path(operation_id = "get-vlan-ip-addresses", params(DetailedQuery))]
+#[get("/{vlan_name}/addresses")]
+/// Retrieves the IP addresses of a specific VLAN entry.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry whose IP addresses are to be retrieved.
+/// - `query`: A `Query<DetailedQuery>` instance; with `detailed=true`, the dynamic addresses are listed as well,
+///   as objects annotated with their source.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the IP addresses if the VLAN entry is found.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan_ip_addresses(
+    netplan_store: Data<NetplanStore>,
+    vlan_name: Path<String>,
+    query: Query<DetailedQuery>,
+) -> impl Responder {
+    device::get_addresses::<Vlan>(&netplan_store, &vlan_name, query.detailed).await
+}
+
+#[api_path(operation_id = "get-vlan-dynamic-addresses")]
+#[get("/{vlan_name}/addresses/dynamic")]
+/// Retrieves the IP addresses a specific VLAN entry obtained through DHCP or router advertisements.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry whose dynamic IP addresses are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the dynamic IP addresses if the VLAN entry is found.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan_dynamic_addresses(
+    netplan_store: Data<NetplanStore>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    device::get_dynamic_addresses::<Vlan>(&netplan_store, &vlan_name).await
+}
+
+#[api_path(operation_id = "add-vlan-address")]
+#[post("/{vlan_name}/addresses")]
+/// Adds an IP address to a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry to which the IP address will be added.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, conflicts with other interfaces are ignored.
+/// - `ip_address`: The IP address to be added, in CIDR notation (`10.0.0.5/24`, `fd00::1/64`).
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::BadRequest` if the provided IP address is invalid.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` if the address is already configured, in which case nothing is applied.
+/// - `HttpResponse::UnprocessableEntity` with a JSON body listing the conflicts if the address or its subnet
+///   is already configured on other interfaces.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_vlan_ip_address(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    query: Query<ForceQuery>,
+    ip_address: Json<String>,
+) -> impl Responder {
+    let to_add = match ip_address.parse::<IpNetwork>() {
+        Err(err) => return HttpResponse::BadRequest().body(err),
+        Ok(ip) => ip,
+    };
+    device::add_address::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        query.force,
+        to_add,
+        None,
+    )
+    .await
+}
+
+#[api_path(operation_id = "replace-vlan-ip-addresses")]
+#[put("/{vlan_name}/addresses")]
+/// Replaces all the static IP addresses of a specific VLAN entry, with a single save and apply.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry whose IP addresses will be replaced.
+/// - `query`: A `Query<ForceQuery>` instance; with `force=true`, conflicts with other interfaces are ignored.
+/// - `ip_addresses`: The new IP addresses of the VLAN entry, in CIDR notation.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::BadRequest` listing every invalid address, one per line.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::UnprocessableEntity` with a JSON body listing the conflicts if addresses or their subnets
+///   are already configured on other interfaces.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn replace_vlan_ip_addresses(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    query: Query<ForceQuery>,
+    ip_addresses: Json<Vec<String>>,
+) -> impl Responder {
+    let addresses = match device::parse_addresses(&ip_addresses) {
+        Ok(addresses) => addresses,
+        Err(err) => return err,
+    };
+    device::replace_addresses::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        query.force,
+        addresses,
+        None,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-vlan-ip-address")]
+#[delete("/{vlan_name}/addresses/{ip_address}")]
+/// Deletes an IP address from a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry from which the IP address will be removed.
+/// - `ip_address`: The IP address to be removed, in CIDR notation with the slash percent-encoded (`10.0.0.5%2F24`).
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::BadRequest` if the provided IP address is invalid.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_ip_address(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vlan_name, ip_address) = path.into_inner();
+    let to_delete = match ip_address.parse::<IpNetwork>() {
+        Err(err) => return HttpResponse::BadRequest().body(err),
+        Ok(ip) => ip,
+    };
+    device::delete_address::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name, to_delete).await
+}
+
+#[api_path(operation_id = "get-vlan-nameservers")]
+#[get("/{vlan_name}/nameservers")]
+/// Retrieves the nameservers of a specific VLAN entry.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry whose nameservers are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the nameservers if the VLAN entry is found,
+///   each address annotated with `reachable_via`.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan_nameservers(
+    netplan_store: Data<NetplanStore>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    device::get_nameservers::<Vlan>(&netplan_store, &vlan_name).await
+}
+
+#[api_path(operation_id = "add-vlan-nameservers-search")]
+#[post("/{vlan_name}/nameservers")]
+/// Adds a search domain to the nameservers of a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry to which the search domain will be added.
+/// - `search`: The search domain to be added.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` if the search domain is already configured, in which case nothing is applied.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_vlan_nameservers_search(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    search: Json<String>,
+) -> impl Responder {
+    device::add_nameservers_search::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name, &search)
+        .await
+}
+
+#[api_path(operation_id = "replace-vlan-nameservers")]
+#[put("/{vlan_name}/nameservers")]
+/// Replaces the nameservers of a specific VLAN entry.
+///
+/// When only `search` or only `addresses` is given, the other half of the nameservers is kept as is.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry whose nameservers will be replaced.
+/// - `nameservers`: The new search domains and nameserver addresses of the VLAN entry.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::BadRequest` listing every invalid nameserver address, one per line.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn replace_vlan_nameservers(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    nameservers: Json<InputNameserversReplacement>,
+) -> impl Responder {
+    let nameservers = nameservers.into_inner();
+    let addresses = match &nameservers.addresses {
+        None => None,
+        Some(addresses) => match device::parse_nameserver_addresses(addresses) {
+            Ok(addresses) => Some(addresses),
+            Err(err) => return err,
+        },
+    };
+    device::replace_nameservers::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        nameservers.search,
+        addresses,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-vlan-nameservers-search")]
+#[delete("/{vlan_name}/nameservers/search/{search}")]
+/// Deletes a search domain from the nameservers of a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry from which the search domain will be removed.
+/// - `search`: The search domain to be removed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_nameservers_search(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vlan_name, search) = path.into_inner();
+    device::delete_nameservers_search::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        &search,
+    )
+    .await
+}
+
+#[api_path(operation_id = "add-vlan-nameservers-address")]
+#[post("/{vlan_name}/nameservers/address")]
+/// Adds a nameserver address to a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry to which the nameserver address will be added.
+/// - `address`: The nameserver address to be added.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful.
+///   If no configured subnet or route covers the address, a `Warning` header is included.
+/// - `HttpResponse::BadRequest` if the provided nameserver address is invalid.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` if the nameserver address is already configured, in which case nothing is applied.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_vlan_nameservers_address(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    address: Json<String>,
+) -> impl Responder {
+    let address: IpAddr = match address.parse() {
+        Err(_) => return HttpResponse::BadRequest().finish(),
+        Ok(address) => address,
+    };
+    device::add_nameservers_address::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name, address)
+        .await
+}
+
+#[api_path(operation_id = "delete-vlan-nameservers-address")]
+#[delete("/{vlan_name}/nameservers/address/{address}")]
+/// Deletes a nameserver address from a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `path`: The name of the VLAN entry from which the nameserver address will be removed, and the
+///   nameserver address to be removed from it.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the nameserver address was removed, or was not configured.
+/// - `HttpResponse::BadRequest` if the provided nameserver address is invalid.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_nameservers_address(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vlan_name, address) = path.into_inner();
+    let address: IpAddr = match address.parse() {
+        Err(_) => return HttpResponse::BadRequest().finish(),
+        Ok(address) => address,
+    };
+    device::delete_nameservers_address::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        address,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-vlan-nameservers")]
+#[delete("/{vlan_name}/nameservers")]
+/// Deletes all the nameservers of a specific VLAN entry, both search domains and addresses.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry whose nameservers will be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the nameservers were removed, or there were none.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_nameservers(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    device::delete_nameservers::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name).await
+}
+
+#[api_path(operation_id = "get-vlan-routes")]
+#[get("/{vlan_name}/routes")]
+/// Retrieves the routes of a specific VLAN entry, keyed by their identifier.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry whose routes are to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the routes if the VLAN entry is found.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan_routes(
+    netplan_store: Data<NetplanStore>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    device::get_routes::<Vlan>(&netplan_store, &vlan_name).await
+}
+
+#[api_path(operation_id = "get-vlan-route")]
+#[get("/{vlan_name}/routes/{route_id}")]
+/// Retrieves a single route of a specific VLAN entry by its identifier.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vlan_name`: The name of the VLAN entry the route belongs to.
+/// - `route_id`: The identifier of the route, as returned as key by the routes listing.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the route if it is found.
+/// - `HttpResponse::NotFound` if the specified VLAN entry or route is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vlan_route(
+    netplan_store: Data<NetplanStore>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vlan_name, route_id) = path.into_inner();
+    device::get_route::<Vlan>(&netplan_store, &vlan_name, &route_id).await
+}
+
+#[api_path(operation_id = "add-vlan-route")]
+#[post("/{vlan_name}/routes")]
+/// Adds a route to a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry to which the route will be added.
+/// - `query`: A `Query<ReplaceQuery>` instance; with `replace=true`, a route with the same identifier is overwritten.
+/// - `input_route`: The route to be added, as for `add-ethernet-route`.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VLAN entry if successful, with a `Warning`
+///   header if the VLAN is bound to a VRF and the route targets another table than the VRF's.
+/// - `HttpResponse::BadRequest` if the provided IP addresses are invalid, or a link scoped or non-unicast route
+///   has a gateway.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::Conflict` with a JSON body containing the existing route if a route with the same
+///   identifier already exists and `replace` is not set.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_vlan_route(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+    query: Query<ReplaceQuery>,
+    input_route: Json<InputRoute>,
+) -> impl Responder {
+    let route = match Route::from_input_route(&input_route.into_inner()) {
+        Ok(route) => route,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    device::add_route::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        query.replace,
+        route,
+    )
+    .await
+}
+
+#[api_path(operation_id = "update-vlan-route")]
+#[patch("/{vlan_name}/routes/{route_id}")]
+/// Updates an existing route of a specific VLAN entry in place.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry the route belongs to.
+/// - `route_id`: The identifier of the route to be updated.
+/// - `patch`: The fields of the route to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body mapping the (possibly new) identifier of the route to the updated route,
+///   with a `Warning` header if the VLAN is bound to a VRF and the route targets another table than the VRF's.
+/// - `HttpResponse::BadRequest` if an address is invalid, or the updated route is inconsistent.
+/// - `HttpResponse::NotFound` if the specified VLAN entry or route is not found.
+/// - `HttpResponse::Conflict` if the updated route collides with another existing route.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_vlan_route(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+    patch: Json<InputRoutePatch>,
+) -> impl Responder {
+    let (vlan_name, route_id) = path.into_inner();
+    device::update_route::<Vlan>(
+        &req,
+        &netplan_store,
+        apply.apply,
+        &vlan_name,
+        &route_id,
+        &patch,
+    )
+    .await
+}
+
+#[api_path(operation_id = "delete-vlan-route")]
+#[delete("/{vlan_name}/routes/{route_id}")]
+/// Deletes a route from a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry from which the route will be removed.
+/// - `route_id`: The identifier of the route to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the route was removed, or did not exist.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_route(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vlan_name, route_id) = path.into_inner();
+    device::delete_route::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name, &route_id).await
+}
+
+#[api_path(operation_id = "delete-vlan-routes")]
+#[delete("/{vlan_name}/routes")]
+/// Deletes all the routes of a specific VLAN entry.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vlan_name`: The name of the VLAN entry whose routes will be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the routes were removed.
+/// - `HttpResponse::NotFound` if the specified VLAN entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vlan_routes(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vlan_name: Path<String>,
+) -> impl Responder {
+    device::delete_routes::<Vlan>(&req, &netplan_store, apply.apply, &vlan_name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_scope, scripted_store, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn deletes_a_nameserver_address_named_in_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(
+            &config_path,
+            "network: {version: 2, ethernets: {eth0: {}}, vlans: {vlan10: \
+            {id: 10, link: eth0, nameservers: {addresses: [10.0.0.53, \"fd00::53\"]}}}}",
+        )
+        .unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/vlans", configure, store);
+
+        let req = test::TestRequest::delete()
+            .uri("/vlans/vlan10/nameservers/address/fd00::5g")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::delete()
+            .uri("/vlans/vlan10/nameservers/address/fd00::53")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let req = test::TestRequest::get().uri("/vlans/vlan10").to_request();
+        let vlan: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(vlan["nameservers"]["addresses"], json!(["10.0.0.53"]));
+    }
+
+    #[actix_web::test]
+    async fn a_vlan_gone_after_the_change_is_not_found() {
+        let resp = device::committed::<Vlan>(&Network::new(), "vlan10").unwrap_err();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
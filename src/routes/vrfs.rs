@@ -0,0 +1,436 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        device::validate_interface_name,
+        input_models::{ApplyQuery, InputMember, InputVrf, InputVrfPatch},
+        network::Network,
+        vrf::{parse_vrf_routes, parse_vrf_routing_policy, Vrf},
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_vrfs,
+    get_vrf,
+    create_vrf,
+    update_vrf,
+    delete_vrf,
+    add_vrf_member,
+    delete_vrf_member,
+))]
+/// API documentation for VRF management.
+pub struct VrfsApi;
+
+/// Configures the Actix web service with the VRF endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_vrfs)
+            .service(get_vrf)
+            .service(create_vrf)
+            .service(update_vrf)
+            .service(delete_vrf)
+            .service(add_vrf_member)
+            .service(delete_vrf_member);
+    }
+}
+
+/// Rejects a table already used by another VRF than `vrf`.
+fn check_table(network: &Network, vrf: &Vrf) -> Option<HttpResponse> {
+    network
+        .vrf_using_table(&vrf.name(), vrf.table())
+        .map(|other| {
+            HttpResponse::Conflict().body(format!(
+                "The table {} is already used by the VRF {other}.",
+                vrf.table()
+            ))
+        })
+}
+
+#[api_path(operation_id = "show-all-vrfs")]
+#[get("")]
+/// Retrieves all VRF entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the VRF entries, and an `ETag` header
+///   identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_vrfs(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.get_vrfs())
+}
+
+#[api_path(operation_id = "show-vrf")]
+#[get("/{vrf_name}")]
+/// Retrieves a specific VRF entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `vrf_name`: The name of the VRF entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the VRF entry if found, and an `ETag`
+///   header identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified VRF entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_vrf(netplan_store: Data<NetplanStore>, vrf_name: Path<String>) -> impl Responder {
+    let vrf_name = vrf_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_vrfs().get(&vrf_name) {
+        Some(vrf) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(vrf),
+        None => HttpResponse::NotFound().body(format!("VRF {vrf_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-vrf")]
+#[post("/{vrf_name}")]
+/// Creates a new VRF entry, binding the given interfaces to its routing table.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vrf_name`: The name of the VRF interface to be created.
+/// - `vrf`: The table and member interfaces of the VRF, along with its routes and routing policy rules.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created VRF entry if successful.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, or a route or rule is invalid.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured, or the table is
+///   already used by another VRF.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured interface, or is already a member
+///   of a bridge, a bond or another VRF, listing every offending member.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_vrf(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vrf_name: Path<String>,
+    vrf: Json<InputVrf>,
+) -> impl Responder {
+    let vrf_name = vrf_name.into_inner();
+    if let Err(err) = validate_interface_name(&vrf_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let vrf = match Vrf::from_input_vrf(&vrf_name, &vrf) {
+        Ok(vrf) => vrf,
+        Err(err) => return HttpResponse::BadRequest().body(err),
+    };
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&vrf_name) {
+        return HttpResponse::Conflict().body(format!("{vrf_name} is already configured."));
+    }
+    if let Some(rejection) = check_table(&network, &vrf) {
+        return rejection;
+    }
+    if let Err(err) = network.validate_vrf_members(&vrf_name, &vrf.interfaces()) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    network.add_vrf(&vrf);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(vrf);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vrf>(&network, &vrf_name) {
+            Ok(vrf) => HttpResponse::Created().json(vrf),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-vrf")]
+#[patch("/{vrf_name}")]
+/// Updates an existing VRF entry.
+///
+/// The member interfaces, routes and routing policy rules are only changed if present in the
+/// body, and are then replaced as a whole.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vrf_name`: The name of the VRF entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VRF entry if successful.
+/// - `HttpResponse::BadRequest` if a route or rule is invalid.
+/// - `HttpResponse::NotFound` if the specified VRF entry is not found.
+/// - `HttpResponse::Conflict` if the table is already used by another VRF.
+/// - `HttpResponse::UnprocessableEntity` if a member is not a configured interface, or is already a member
+///   of a bridge, a bond or another VRF, listing every offending member.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_vrf(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vrf_name: Path<String>,
+    patch: Json<InputVrfPatch>,
+) -> impl Responder {
+    let vrf_name = vrf_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut vrf) = network.get_vrfs().get(&vrf_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("VRF {vrf_name} was not found."));
+    };
+    if let Some(routes) = &patch.routes {
+        match parse_vrf_routes(routes) {
+            Ok(routes) => vrf.set_routes(routes),
+            Err(err) => return HttpResponse::BadRequest().body(err),
+        }
+    }
+    if let Some(routing_policy) = &patch.routing_policy {
+        match parse_vrf_routing_policy(routing_policy) {
+            Ok(routing_policy) => vrf.set_routing_policy(routing_policy),
+            Err(err) => return HttpResponse::BadRequest().body(err),
+        }
+    }
+    if let Some(table) = patch.table {
+        vrf.set_table(table);
+        if let Some(rejection) = check_table(&network, &vrf) {
+            return rejection;
+        }
+    }
+    if let Some(interfaces) = &patch.interfaces {
+        if let Err(err) = network.validate_vrf_members(&vrf_name, interfaces) {
+            return HttpResponse::UnprocessableEntity().body(err);
+        }
+        vrf.set_interfaces(interfaces.clone());
+    }
+    network.add_vrf(&vrf);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(vrf);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vrf>(&network, &vrf_name) {
+            Ok(vrf) => HttpResponse::Ok().json(vrf),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-vrf")]
+#[delete("/{vrf_name}")]
+/// Removes a VRF entry from the network configuration, releasing its member interfaces.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vrf_name`: The name of the VRF entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the VRF entry was removed.
+/// - `HttpResponse::NotFound` if the specified VRF entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vrf(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vrf_name: Path<String>,
+) -> impl Responder {
+    let vrf_name = vrf_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_vrf(&vrf_name).is_none() {
+        return HttpResponse::NotFound().body(format!("VRF {vrf_name} was not found."));
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[api_path(operation_id = "add-vrf-member")]
+#[post("/{vrf_name}/interfaces")]
+/// Binds an interface to a VRF, without resending the whole VRF.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vrf_name`: The name of the VRF entry the interface will be bound to.
+/// - `member`: The interface to be bound.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VRF entry if successful.
+/// - `HttpResponse::NotFound` if the specified VRF entry is not found.
+/// - `HttpResponse::Conflict` if the interface is already a member of the VRF, in which case nothing is applied.
+/// - `HttpResponse::UnprocessableEntity` if the interface is not configured, or is already a member of a
+///   bridge, a bond or another VRF.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn add_vrf_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    vrf_name: Path<String>,
+    member: Json<InputMember>,
+) -> impl Responder {
+    let vrf_name = vrf_name.into_inner();
+    let member = member.into_inner().interface;
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut vrf) = network.get_vrfs().get(&vrf_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("VRF {vrf_name} was not found."));
+    };
+    let mut interfaces = vrf.interfaces();
+    if interfaces.contains(&member) {
+        return HttpResponse::Conflict()
+            .body(format!("{member} is already a member of {vrf_name}."));
+    }
+    if let Err(err) = network.validate_vrf_members(&vrf_name, std::slice::from_ref(&member)) {
+        return HttpResponse::UnprocessableEntity().body(err);
+    }
+    interfaces.push(member);
+    vrf.set_interfaces(interfaces);
+    network.add_vrf(&vrf);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(vrf);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vrf>(&network, &vrf_name) {
+            Ok(vrf) => HttpResponse::Ok().json(vrf),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-vrf-member")]
+#[delete("/{vrf_name}/interfaces/{member}")]
+/// Releases an interface from a VRF, back to the main routing table.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `vrf_name`: The name of the VRF entry the interface will be released from.
+/// - `member`: The name of the interface to be released.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated VRF entry if successful.
+/// - `HttpResponse::NotFound` if the specified VRF entry is not found, or the interface is not a member of it.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_vrf_member(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+) -> impl Responder {
+    let (vrf_name, member) = path.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut vrf) = network.get_vrfs().get(&vrf_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("VRF {vrf_name} was not found."));
+    };
+    let mut interfaces = vrf.interfaces();
+    if !interfaces.contains(&member) {
+        return HttpResponse::NotFound().body(format!("{member} is not a member of {vrf_name}."));
+    }
+    interfaces.retain(|interface| *interface != member);
+    vrf.set_interfaces(interfaces);
+    network.add_vrf(&vrf);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(vrf);
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Vrf>(&network, &vrf_name) {
+            Ok(vrf) => HttpResponse::Ok().json(vrf),
+            Err(err) => err,
+        },
+    }
+}
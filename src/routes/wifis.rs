@@ -0,0 +1,481 @@
+use actix_web::{
+    delete, get,
+    http::header,
+    patch, post, put,
+    web::{Data, Json, Path, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use utoipa::{path as api_path, OpenApi};
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use crate::{
+    models::{
+        access_point::AccessPoint,
+        device::{validate_interface_name, Device},
+        input_models::{ApplyQuery, CascadeQuery, InputWifi, InputWifiPatch},
+        wifi::Wifi,
+    },
+    netplan::{Mutation, NetplanStore},
+    routes::device::{self, check_dependents, check_if_match},
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_all_wifis,
+    get_wifi,
+    create_wifi,
+    update_wifi,
+    delete_wifi,
+    set_wifi_access_point,
+))]
+/// API documentation for wifi management.
+pub struct WifisApi;
+
+/// Configures the Actix web service with the wifi endpoints.
+///
+/// # Arguments
+///
+/// * `store` - A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+///
+/// A closure that takes a mutable reference to `ServiceConfig` and registers the services.
+pub fn configure(store: Data<NetplanStore>) -> impl FnOnce(&mut ServiceConfig) {
+    |cfg: &mut ServiceConfig| {
+        cfg.app_data(store)
+            .service(get_all_wifis)
+            .service(get_wifi)
+            .service(create_wifi)
+            .service(update_wifi)
+            .service(delete_wifi)
+            .service(set_wifi_access_point);
+    }
+}
+
+/// Rejects an IPv6 address generation set while router advertisements are not accepted.
+fn check_ipv6_address_generation(wifi: &Wifi) -> Option<HttpResponse> {
+    if wifi.get_ipv6_address_generation().is_some() && wifi.get_accept_ra() == Some(false) {
+        return Some(HttpResponse::BadRequest().body(format!(
+            "The IPv6 address generation of {} only applies to addresses autoconfigured \
+            from router advertisements, which are disabled by 'accept-ra: false'.",
+            wifi.name()
+        )));
+    }
+    None
+}
+
+#[api_path(operation_id = "show-all-wifis")]
+#[get("")]
+/// Retrieves all wifi entries, keyed by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the wifi entries, their passwords being masked, and
+///   an `ETag` header identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_all_wifis(netplan_store: Data<NetplanStore>) -> impl Responder {
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(network.redacted().get_wifis())
+}
+
+#[api_path(operation_id = "show-wifi")]
+#[get("/{wifi_name}")]
+/// Retrieves a specific wifi entry by name.
+///
+/// # Arguments
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `wifi_name`: The name of the wifi entry to be retrieved.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the wifi entry if found, its passwords being masked,
+///   and an `ETag` header identifying the current configuration, to be sent back in `If-Match` by updates.
+/// - `HttpResponse::NotFound` if the specified wifi entry is not found.
+/// - `HttpResponse::InternalServerError` if there is an issue loading the configuration.
+pub async fn get_wifi(
+    netplan_store: Data<NetplanStore>,
+    wifi_name: Path<String>,
+) -> impl Responder {
+    let wifi_name = wifi_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    let network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let etag = match netplan.config_etag().await {
+        Err(err) => return err.error_response(),
+        Ok(etag) => etag,
+    };
+    match network.get_wifis().get(&wifi_name) {
+        Some(wifi) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(wifi.redacted()),
+        None => HttpResponse::NotFound().body(format!("WiFi {wifi_name} was not found.")),
+    }
+}
+
+#[api_path(operation_id = "create-wifi")]
+#[post("/{wifi_name}")]
+/// Creates a new wifi entry, connecting to the given access points.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `wifi_name`: The name of the wifi interface to be created.
+/// - `wifi`: The access points of the wifi, along with its settings.
+///
+/// # Returns
+/// - `HttpResponse::Created` with a JSON body containing the created wifi entry if successful, its
+///   passwords being masked.
+/// - `HttpResponse::BadRequest` if the name is not a valid interface name, an access point sets a channel
+///   without a band or lacks the identity PEAP and TTLS require, or an IPv6 address generation is set
+///   while router advertisements are not accepted.
+/// - `HttpResponse::Conflict` if an interface with this name is already configured.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn create_wifi(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    wifi_name: Path<String>,
+    wifi: Json<InputWifi>,
+) -> impl Responder {
+    let wifi_name = wifi_name.into_inner();
+    if let Err(err) = validate_interface_name(&wifi_name) {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let wifi = Wifi::from_input_wifi(&wifi_name, &wifi);
+    if let Err(err) = wifi.validate_access_points() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    if let Some(rejection) = check_ipv6_address_generation(&wifi) {
+        return rejection;
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.is_configured(&wifi_name) {
+        return HttpResponse::Conflict().body(format!("{wifi_name} is already configured."));
+    }
+    network.add_wifi(&wifi);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Created().json(wifi.redacted());
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Wifi>(&network, &wifi_name) {
+            Ok(wifi) => HttpResponse::Created().json(wifi.redacted()),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "update-wifi")]
+#[patch("/{wifi_name}")]
+/// Updates an existing wifi entry.
+///
+/// The access points are only changed if present in the body, and are then replaced as a whole.
+/// Passwords sent back masked as `***` keep their current value. The
+/// other settings are replaced the same way as by `update-ethernet`.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `wifi_name`: The name of the wifi entry to be updated.
+/// - `patch`: The settings to be changed.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated wifi entry if successful, its passwords
+///   being masked.
+/// - `HttpResponse::BadRequest` if an access point sets a channel without a band or lacks the identity
+///   PEAP and TTLS require, or an IPv6 address generation is set while router advertisements are not
+///   accepted.
+/// - `HttpResponse::NotFound` if the specified wifi entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn update_wifi(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    wifi_name: Path<String>,
+    patch: Json<InputWifiPatch>,
+) -> impl Responder {
+    let wifi_name = wifi_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut wifi) = network.get_wifis().get(&wifi_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("WiFi {wifi_name} was not found."));
+    };
+    if let Some(access_points) = &patch.access_points {
+        wifi.set_access_points(access_points.clone());
+        if let Err(err) = wifi.validate_access_points() {
+            return HttpResponse::BadRequest().body(err);
+        }
+    }
+    patch.device.update(&mut wifi);
+    if let Some(rejection) = check_ipv6_address_generation(&wifi) {
+        return rejection;
+    }
+    network.add_wifi(&wifi);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(wifi.redacted());
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Wifi>(&network, &wifi_name) {
+            Ok(wifi) => HttpResponse::Ok().json(wifi.redacted()),
+            Err(err) => err,
+        },
+    }
+}
+
+#[api_path(operation_id = "delete-wifi")]
+#[delete("/{wifi_name}")]
+/// Removes a wifi entry from the network configuration.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `cascade`: A `Query<CascadeQuery>` instance; with `cascade=true`, the devices built on the interface are
+///   removed as well, and the devices it is a member of release it.
+/// - `wifi_name`: The name of the wifi entry to be removed.
+///
+/// # Returns
+/// - `HttpResponse::NoContent` if the wifi entry was removed.
+/// - `HttpResponse::NotFound` if the specified wifi entry is not found.
+/// - `HttpResponse::Conflict` if other devices still reference the interface and `cascade` is not set,
+///   listing them.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn delete_wifi(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    cascade: Query<CascadeQuery>,
+    wifi_name: Path<String>,
+) -> impl Responder {
+    let wifi_name = wifi_name.into_inner();
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    if network.remove_wifi(&wifi_name).is_none() {
+        return HttpResponse::NotFound().body(format!("WiFi {wifi_name} was not found."));
+    }
+    if let Some(rejection) = check_dependents(&mut network, &wifi_name, cascade.cascade) {
+        return rejection;
+    }
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::NoContent().finish();
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+    }
+}
+
+#[api_path(operation_id = "set-wifi-access-point")]
+#[put("/{wifi_name}/access-points/{ssid}")]
+/// Adds the access point of a wireless network to a wifi, or replaces it.
+///
+/// Passwords sent back masked as `***`, including those of the `auth` settings, keep their current
+/// value.
+///
+/// # Arguments
+/// - `req`: The request, whose `If-Match` header is checked against the current configuration.
+/// - `netplan_store`: A `Data<NetplanStore>` instance that holds the Netplan configuration store.
+/// - `apply`: A `Query<ApplyQuery>` instance; with `apply=false`, the changes are only saved until `POST /ethernets/apply`.
+/// - `wifi_name`: The name of the wifi entry the access point belongs to.
+/// - `ssid`: The SSID of the wireless network.
+/// - `access_point`: The settings of the access point.
+///
+/// # Returns
+/// - `HttpResponse::Ok` with a JSON body containing the updated wifi entry if successful, its passwords
+///   being masked.
+/// - `HttpResponse::BadRequest` if a channel is set without a band, or the identity PEAP and TTLS
+///   require is missing.
+/// - `HttpResponse::NotFound` if the specified wifi entry is not found.
+/// - `HttpResponse::PreconditionFailed` if the configuration changed since the entity tag in `If-Match` was read.
+/// - `HttpResponse::InternalServerError` if there is an issue loading, saving, or applying the configuration.
+pub async fn set_wifi_access_point(
+    req: HttpRequest,
+    netplan_store: Data<NetplanStore>,
+    apply: Query<ApplyQuery>,
+    path: Path<(String, String)>,
+    access_point: Json<AccessPoint>,
+) -> impl Responder {
+    let (wifi_name, ssid) = path.into_inner();
+    if let Err(err) = access_point.validate() {
+        return HttpResponse::BadRequest().body(err);
+    }
+    let netplan = netplan_store.lock().await;
+    if let Some(rejection) = check_if_match(&req, &netplan).await {
+        return rejection;
+    }
+    let mut network = match netplan.load_config().await {
+        Err(err) => return err.error_response(),
+        Ok(network) => network,
+    };
+    let original = network.clone();
+    let Some(mut wifi) = network.get_wifis().get(&wifi_name).cloned() else {
+        return HttpResponse::NotFound().body(format!("WiFi {wifi_name} was not found."));
+    };
+    wifi.set_access_point(&ssid, access_point.into_inner());
+    network.add_wifi(&wifi);
+    let Some(mutation) = Mutation::new(&original, network) else {
+        return HttpResponse::Ok().json(wifi.redacted());
+    };
+    let apply = apply.apply;
+    match netplan.commit(mutation, apply).await {
+        Err(err) => err.error_response(),
+        Ok(network) => match device::committed::<Wifi>(&network, &wifi_name) {
+            Ok(wifi) => HttpResponse::Ok().json(wifi.redacted()),
+            Err(err) => err,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::access_point::MASKED_PASSWORD;
+    use crate::test_support::{init_scope, mock_store, scripted_store, ScriptedRunner};
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    fn enterprise_access_point() -> Value {
+        json!({"auth": {
+            "key-management": "eap", "method": "ttls", "identity": "capture@example.com",
+            "password": "hunter2", "client-key": "/etc/wpa/client.key",
+            "client-key-password": "key-secret"
+        }})
+    }
+
+    #[actix_web::test]
+    async fn enterprise_auth_is_written_under_the_access_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("01-network-conf.yaml");
+        std::fs::write(&config_path, "network: {version: 2, wifis: {wlan0: {}}}").unwrap();
+        let runner = ScriptedRunner::default();
+        let store = scripted_store(&config_path, &runner);
+        let app = init_scope!("/wifis", configure, store);
+
+        let req = test::TestRequest::put()
+            .uri("/wifis/wlan0/access-points/corp")
+            .set_json(enterprise_access_point())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let config: serde_yml::Value =
+            serde_yml::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+        let auth = &config["network"]["wifis"]["wlan0"]["access-points"]["corp"]["auth"];
+        assert_eq!(auth["key-management"].as_str(), Some("eap"));
+        assert_eq!(auth["method"].as_str(), Some("ttls"));
+        assert_eq!(auth["identity"].as_str(), Some("capture@example.com"));
+        assert_eq!(auth["password"].as_str(), Some("hunter2"));
+        assert_eq!(auth["client-key-password"].as_str(), Some("key-secret"));
+    }
+
+    #[actix_web::test]
+    async fn auth_secrets_are_masked_and_kept_when_sent_back() {
+        let store = mock_store(&["wlan0"]);
+        let app = init_scope!("/wifis", configure, store);
+        let req = test::TestRequest::post()
+            .uri("/wifis/wlan0")
+            .set_json(json!({"access-points": {"corp": enterprise_access_point()}}))
+            .to_request();
+        let created: Value = test::call_and_read_body_json(&app, req).await;
+        let auth = &created["access-points"]["corp"]["auth"];
+        assert_eq!(auth["password"], MASKED_PASSWORD);
+        assert_eq!(auth["client-key-password"], MASKED_PASSWORD);
+        assert_eq!(auth["identity"], "capture@example.com");
+
+        let req = test::TestRequest::get().uri("/wifis/wlan0").to_request();
+        let wifi: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(wifi, created);
+
+        let req = test::TestRequest::put()
+            .uri("/wifis/wlan0/access-points/corp")
+            .set_json(json!({"auth": auth}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        let netplan = store.lock().await;
+        let network = netplan.load_config().await.unwrap();
+        let access_points = network.get_wifis()["wlan0"].access_points();
+        let kept = access_points["corp"].auth.as_ref().unwrap();
+        assert_eq!(kept.password.as_deref(), Some("hunter2"));
+        assert_eq!(kept.client_key_password.as_deref(), Some("key-secret"));
+
+        let openapi = WifisApi::openapi();
+        let schemas = &serde_json::to_value(&openapi.components.unwrap().schemas).unwrap();
+        assert_eq!(schemas["Auth"]["properties"]["password"]["writeOnly"], true);
+        assert_eq!(
+            schemas["Auth"]["properties"]["client-key-password"]["writeOnly"],
+            true
+        );
+        assert!(schemas["Auth"]["properties"]["password"]
+            .get("example")
+            .is_none());
+    }
+
+    #[actix_web::test]
+    async fn peap_and_ttls_access_points_require_an_identity() {
+        let store = mock_store(&["wlan0"]);
+        let app = init_scope!("/wifis", configure, store);
+        let req = test::TestRequest::post()
+            .uri("/wifis/wlan0")
+            .set_json(json!({"access-points": {"corp": {"auth": {"method": "peap"}}}}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post()
+            .uri("/wifis/wlan0")
+            .set_json(json!({}))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::CREATED
+        );
+        let req = test::TestRequest::put()
+            .uri("/wifis/wlan0/access-points/corp")
+            .set_json(json!({"auth": {"method": "ttls", "password": "hunter2"}}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    command::{command_line, CommandRunner, Deadline, SystemRunner},
+    netplan::NetplanError,
+};
+use std::{fs, io, net::IpAddr, path::Path};
+
+/// Where the kernel exposes the network interfaces of the system.
+pub(crate) const SYS_CLASS_NET_PATH: &str = "/sys/class/net";
+
+/// Wrapper around the `ip` tool, for the operations on interfaces not going through netplan.
+pub struct System;
+
+impl System {
+    fn run_command(args: &[&str]) -> Result<String, NetplanError> {
+        Self::run_command_with(&SystemRunner, args)
+    }
+
+    /// Runs `ip` with the given arguments through `runner`, returning its output.
+    ///
+    /// A non-zero exit status is reported as `CommandFailed`, with the stderr of `ip`.
+    fn run_command_with(runner: &dyn CommandRunner, args: &[&str]) -> Result<String, NetplanError> {
+        let output = runner.output("ip", args, Deadline::current())?;
+        if !output.status.success() {
+            let err = NetplanError::CommandFailed {
+                command: command_line("ip", args),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            };
+            log::error!("{err}");
+            return Err(err);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Whether the interface exists in the system.
+    pub fn interface_exists(interface: &str) -> bool {
+        Path::new(SYS_CLASS_NET_PATH).join(interface).exists()
+    }
+
+    /// Names of the physical interfaces present in the system, i.e. those backed by a device,
+    /// sorted.
+    pub fn physical_interfaces() -> io::Result<Vec<String>> {
+        Self::physical_interfaces_in(Path::new(SYS_CLASS_NET_PATH))
+    }
+
+    /// Names of the physical interfaces listed in `sys_class_net`, a directory laid out like
+    /// `/sys/class/net`. Virtual interfaces have no `device` link, and the loopback is skipped.
+    pub fn physical_interfaces_in(sys_class_net: &Path) -> io::Result<Vec<String>> {
+        let mut result = vec![];
+        for entry in fs::read_dir(sys_class_net)? {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name != "lo" && entry.path().join("device").exists() {
+                result.push(name);
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    /// Number of SR-IOV virtual functions currently enabled on the interface, if it supports them.
+    pub fn virtual_function_count(interface: &str) -> Option<u16> {
+        fs::read_to_string(
+            Path::new(SYS_CLASS_NET_PATH)
+                .join(interface)
+                .join("device/sriov_numvfs"),
+        )
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+    }
+
+    /// Administratively brings the interface up or down.
+    pub fn set_link(interface: &str, up: bool) -> Result<(), NetplanError> {
+        let state = if up { "up" } else { "down" };
+        Self::run_command(&["link", "set", "dev", interface, state])?;
+        Ok(())
+    }
+
+    /// Returns the addresses currently assigned to the interface.
+    pub fn addresses(interface: &str) -> Result<Vec<IpAddr>, NetplanError> {
+        let output = Self::run_command(&["-o", "addr", "show", "dev", interface])?;
+        Ok(Self::parse_addresses(&output))
+    }
+
+    /// Parses the addresses listed by `ip -o addr show`.
+    fn parse_addresses(output: &str) -> Vec<IpAddr> {
+        // e.g. `2: eth0    inet 10.0.0.5/24 brd 10.0.0.255 scope global eth0`
+        output
+            .lines()
+            .filter_map(|line| {
+                let address = line.split_whitespace().nth(3)?;
+                let (address, _) = address.split_once('/').unwrap_or((address, ""));
+                address.parse().ok()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ScriptedRunner;
+
+    #[test]
+    fn lists_the_physical_interfaces_of_a_sysfs_root() {
+        let root = tempfile::tempdir().unwrap();
+        let devices = root.path().join("devices");
+        for name in ["lo", "enp2s0", "enp1s0", "docker0", "vlan10", "wlp3s0"] {
+            fs::create_dir(root.path().join(name)).unwrap();
+        }
+        for name in ["enp1s0", "enp2s0", "wlp3s0"] {
+            fs::create_dir_all(devices.join(name)).unwrap();
+            std::os::unix::fs::symlink(devices.join(name), root.path().join(name).join("device"))
+                .unwrap();
+        }
+        // The loopback is skipped even if it were backed by a device
+        fs::create_dir(root.path().join("lo/device")).unwrap();
+        // A dangling link has no device behind it
+        std::os::unix::fs::symlink(root.path().join("gone"), root.path().join("docker0/device"))
+            .unwrap();
+
+        assert_eq!(
+            System::physical_interfaces_in(root.path()).unwrap(),
+            ["enp1s0", "enp2s0", "wlp3s0"]
+        );
+        let missing = System::physical_interfaces_in(&root.path().join("missing")).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn failing_commands_are_reported_with_their_stderr() {
+        let runner =
+            ScriptedRunner::default().failing("ip link set", 1, "Cannot find device \"eth9\"\n");
+        let err =
+            System::run_command_with(&runner, &["link", "set", "dev", "eth9", "up"]).unwrap_err();
+        let NetplanError::CommandFailed {
+            command,
+            status,
+            stderr,
+        } = err
+        else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(command, "ip link set dev eth9 up");
+        assert_eq!(status.code(), Some(1));
+        assert_eq!(stderr, "Cannot find device \"eth9\"");
+    }
+
+    #[test]
+    fn parses_the_addresses_of_an_interface() {
+        let output = "2: eth0    inet 10.0.0.5/24 brd 10.0.0.255 scope global eth0\\       valid_lft forever preferred_lft forever\n\
+            2: eth0    inet6 fd00::5/64 scope global \\       valid_lft forever preferred_lft forever\n";
+        assert_eq!(
+            System::parse_addresses(output),
+            [
+                "10.0.0.5".parse::<IpAddr>().unwrap(),
+                "fd00::5".parse().unwrap()
+            ]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Helpers shared by the tests of the routes.
+
+use crate::{
+    command::{command_line, CommandRunner, Deadline},
+    mock_netplan::MockNetplan,
+    models::dhcp_probe::Privileges,
+    netplan::{Netplan, NetplanError, NetplanStore},
+};
+use actix_web::web::Data;
+use std::{
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process::{Child, Command, ExitStatus, Output},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Initializes a test service serving the routes configured by `$configure` under `$path`, like
+/// `main` does, e.g. `init_scope!("/ethernets", ethernet::configure, store)`.
+macro_rules! init_scope {
+    ($path:literal, $configure:path, $store:expr) => {{
+        use utoipa_actix_web::AppExt;
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .into_utoipa_app()
+                .service(utoipa_actix_web::scope($path).configure($configure($store.clone())))
+                .into_app(),
+        )
+        .await
+    }};
+}
+pub(crate) use init_scope;
+
+/// A store keeping the configuration in memory, for a system with the given interfaces.
+pub fn mock_store(interfaces: &[&str]) -> Data<NetplanStore> {
+    let interfaces = interfaces.iter().map(|name| name.to_string()).collect();
+    Data::new(NetplanStore::new(Box::new(MockNetplan::new(interfaces))))
+}
+
+/// How a scripted command behaves.
+enum Answer {
+    /// Exits with the code, after writing to its standard output and error.
+    Exit {
+        code: i32,
+        stdout: String,
+        stderr: String,
+    },
+    /// Its program is not installed.
+    Missing,
+    /// Takes `duration` to exit successfully with `stdout`, unless killed at the deadline.
+    Hang { duration: Duration, stdout: String },
+}
+
+/// Runs no command, answering with scripted outputs instead, and records the command lines.
+///
+/// Clones share the script and the record, so a test can keep one while the backend owns the
+/// other. By default, the answers are those of an idle system where everything succeeds.
+#[derive(Clone)]
+pub struct ScriptedRunner {
+    /// The answers by prefix of the command line, the first matching one winning.
+    script: Arc<Mutex<Vec<(String, Answer)>>>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for ScriptedRunner {
+    fn default() -> Self {
+        let runner = Self {
+            script: Arc::default(),
+            calls: Arc::default(),
+        };
+        runner
+            .on("netplan status", 0, "{}\n")
+            .on("netplan status --diff-only", 0, "interfaces: {}\n")
+            .on("systemctl is-active", 0, "active\n")
+            .on("systemctl is-enabled", 0, "enabled\n")
+    }
+}
+
+impl ScriptedRunner {
+    /// Answers the commands starting with `prefix` with `stdout` and the exit `code`.
+    pub fn on(self, prefix: &str, code: i32, stdout: &str) -> Self {
+        self.answer(
+            prefix,
+            Answer::Exit {
+                code,
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            },
+        )
+    }
+
+    /// Fails the commands starting with `prefix` with `stderr` and the exit `code`.
+    pub fn failing(self, prefix: &str, code: i32, stderr: &str) -> Self {
+        self.answer(
+            prefix,
+            Answer::Exit {
+                code,
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            },
+        )
+    }
+
+    /// Makes the program of the commands starting with `prefix` missing.
+    pub fn missing(self, prefix: &str) -> Self {
+        self.answer(prefix, Answer::Missing)
+    }
+
+    /// Makes the commands starting with `prefix` take `duration` to print `stdout` and succeed.
+    pub fn hanging(self, prefix: &str, duration: Duration, stdout: &str) -> Self {
+        self.answer(
+            prefix,
+            Answer::Hang {
+                duration,
+                stdout: stdout.to_string(),
+            },
+        )
+    }
+
+    /// Takes precedence over the answers scripted before.
+    fn answer(self, prefix: &str, answer: Answer) -> Self {
+        self.script
+            .lock()
+            .unwrap()
+            .insert(0, (prefix.to_string(), answer));
+        self
+    }
+
+    /// The command lines run so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl CommandRunner for ScriptedRunner {
+    fn output(
+        &self,
+        program: &str,
+        args: &[&str],
+        deadline: Deadline,
+    ) -> Result<Output, NetplanError> {
+        let command = command_line(program, args);
+        if deadline.is_past() {
+            return Err(deadline.timeout(&command));
+        }
+        self.calls.lock().unwrap().push(command.clone());
+        let script = self.script.lock().unwrap();
+        let (code, stdout, stderr) = match answer_to(&script, &command) {
+            None => (0, "", ""),
+            Some(Answer::Exit {
+                code,
+                stdout,
+                stderr,
+            }) => (*code, stdout.as_str(), stderr.as_str()),
+            Some(Answer::Missing) => return Err(NetplanError::NotInstalled(program.to_string())),
+            Some(Answer::Hang { duration, stdout }) => {
+                thread::sleep((*duration).min(deadline.remaining()));
+                if deadline.is_past() {
+                    return Err(deadline.timeout(&command));
+                }
+                (0, stdout.as_str(), "")
+            }
+        };
+        Ok(Output {
+            // The exit code is in the second byte of the wait status
+            status: ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        })
+    }
+
+    /// Starts a stand-in process instead, which runs for as long as the scripted command would.
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, NetplanError> {
+        let command = command_line(program, args);
+        self.calls.lock().unwrap().push(command.clone());
+        let script = self.script.lock().unwrap();
+        let stand_in = match answer_to(&script, &command) {
+            None => Command::new("true").spawn(),
+            Some(Answer::Exit { code, .. }) => Command::new("sh")
+                .args(["-c", &format!("exit {code}")])
+                .spawn(),
+            Some(Answer::Missing) => return Err(NetplanError::NotInstalled(program.to_string())),
+            Some(Answer::Hang { duration, .. }) => Command::new("sleep")
+                .arg(duration.as_secs_f64().to_string())
+                .spawn(),
+        };
+        stand_in.map_err(|err| NetplanError::spawn(program, err))
+    }
+}
+
+/// The answer scripted for `command`.
+fn answer_to<'a>(script: &'a [(String, Answer)], command: &str) -> Option<&'a Answer> {
+    script
+        .iter()
+        .find(|(prefix, _)| command.starts_with(prefix.as_str()))
+        .map(|(_, answer)| answer)
+}
+
+/// A `Netplan` backend managing `config_path`, running its commands through `runner`.
+///
+/// DHCP servers are probed through `networkctl`, as if the tests lacked the privileges to send
+/// packets from the interfaces.
+pub fn scripted_netplan(config_path: &Path, runner: &ScriptedRunner) -> Netplan {
+    Netplan::with_config_path(config_path.to_path_buf())
+        .with_runner(Box::new(runner.clone()))
+        .with_privileges(Privileges::default())
+}
+
+/// A store over a `Netplan` backend managing `config_path`, running its commands through `runner`.
+pub fn scripted_store(config_path: &Path, runner: &ScriptedRunner) -> Data<NetplanStore> {
+    Data::new(NetplanStore::new(Box::new(scripted_netplan(
+        config_path,
+        runner,
+    ))))
+}